@@ -0,0 +1,82 @@
+// Generated by WireDeck. Everything happens in the browser: the access code never leaves this page.
+(function () {
+  'use strict';
+
+  var PBKDF2_ITERATIONS = {{PBKDF2_ITERATIONS}};
+  var FILE_ID_PREFIX = '{{FILE_ID_PREFIX}}';
+  var TEXT = {{TEXT}};
+
+  var encoder = new TextEncoder();
+  var status = document.getElementById('status');
+
+  function fromBase64(text) {
+    return Uint8Array.from(atob(text), function (c) { return c.charCodeAt(0); });
+  }
+
+  function toHex(buffer) {
+    return Array.from(new Uint8Array(buffer), function (b) { return b.toString(16).padStart(2, '0'); }).join('');
+  }
+
+  function normalize(code) {
+    return code.replace(/[\s-]/g, '').toUpperCase();
+  }
+
+  function fail(message) {
+    status.textContent = message;
+    status.className = 'error';
+  }
+
+  async function verify(file) {
+    var signingKey = document.querySelector('meta[name="wiredeck-signing-key"]').content;
+    var key = await crypto.subtle.importKey('raw', fromBase64(signingKey), { name: 'Ed25519' }, false, ['verify']);
+    var signed = encoder.encode(file.salt + '.' + file.iv + '.' + file.ciphertext);
+    return crypto.subtle.verify({ name: 'Ed25519' }, key, fromBase64(file.signature), signed);
+  }
+
+  async function decrypt(file, code) {
+    var material = await crypto.subtle.importKey('raw', encoder.encode(code), 'PBKDF2', false, ['deriveKey']);
+    var key = await crypto.subtle.deriveKey(
+      { name: 'PBKDF2', hash: 'SHA-256', salt: fromBase64(file.salt), iterations: PBKDF2_ITERATIONS },
+      material,
+      { name: 'AES-GCM', length: 256 },
+      false,
+      ['decrypt']
+    );
+    var plain = await crypto.subtle.decrypt({ name: 'AES-GCM', iv: fromBase64(file.iv) }, key, fromBase64(file.ciphertext));
+    return JSON.parse(new TextDecoder().decode(plain));
+  }
+
+  function show(payload) {
+    document.getElementById('name').textContent = payload.name;
+    document.getElementById('config').textContent = payload.config;
+    document.getElementById('qr').innerHTML = payload.qr_svg || '';
+    var link = document.getElementById('download');
+    link.href = URL.createObjectURL(new Blob([payload.config], { type: 'text/plain' }));
+    link.download = payload.file_name;
+    document.getElementById('result').hidden = false;
+    status.textContent = '';
+    status.className = '';
+  }
+
+  document.getElementById('unlock').addEventListener('submit', async function (event) {
+    event.preventDefault();
+    var code = normalize(document.getElementById('code').value);
+    status.textContent = TEXT.checking;
+    status.className = '';
+
+    try {
+      var id = toHex(await crypto.subtle.digest('SHA-256', encoder.encode(FILE_ID_PREFIX + code))).slice(0, 32);
+      var response = await fetch('data/' + id + '.json', { cache: 'no-store' });
+      if (!response.ok) {
+        return fail(TEXT.invalid);
+      }
+      var file = await response.json();
+      if (!(await verify(file))) {
+        return fail(TEXT.unsigned);
+      }
+      show(await decrypt(file, code));
+    } catch (e) {
+      fail(TEXT.failed + ' ' + e.message);
+    }
+  });
+})();
@@ -0,0 +1,8 @@
+//! Print the API JSON Schema; `schema/wiredeck.schema.json` is this program's output.
+//!
+//!     cargo run -p wiredeck-core --example api_schema > crates/wiredeck-core/schema/wiredeck.schema.json
+
+fn main() {
+    let schema = wiredeck_core::schema::api_schema();
+    println!("{}", serde_json::to_string_pretty(&schema).expect("schema serializes"));
+}
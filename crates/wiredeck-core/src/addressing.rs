@@ -0,0 +1,495 @@
+use rand::RngCore;
+use ring::digest;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::cidr::Cidr;
+use crate::server::tunnel_subnet;
+use crate::store;
+use crate::wireguard::{
+    config_file_exists, generate_keypair, get_public_key, get_wireguard_dir, list_configs, parse_config, save_config,
+    validate_config_name, Comments, Interface, WgConfig, WgError,
+};
+
+const ADDRESSING_FILE: &str = "addressing.json";
+
+/// Seconds between the NTP epoch (1900) and the Unix epoch
+const NTP_UNIX_OFFSET: u64 = 2_208_988_800;
+
+/// Where IPv4 tunnel subnets are picked from: 10.8.0.0/24, 10.9.0.0/24, ... (clear of the 10.0.x.x home LANs)
+const IPV4_SECOND_OCTETS: std::ops::RangeInclusive<u8> = 8..=254;
+
+/// Addressing state of the workspace: one ULA /48 per site, a /64 per tunnel carved from it
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct AddressingState {
+    ula_prefix: Option<Cidr>,
+}
+
+/// Whose address an entry of the address map is
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AddressOwner {
+    Interface,
+    Peer { public_key: String, name: Option<String> },
+}
+
+/// One address handed out in some config
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AddressAssignment {
+    pub config: String,
+    pub address: Cidr,
+    pub owner: AddressOwner,
+}
+
+/// The addresses that fall inside one subnet
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubnetUsage {
+    pub subnet: Cidr,
+    /// Configs whose interface sits in this subnet; empty for addresses outside every tunnel subnet
+    pub configs: Vec<String>,
+    pub assignments: Vec<AddressAssignment>,
+}
+
+/// An address given to more than one device
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AddressConflict {
+    pub address: Cidr,
+    pub message: String,
+    pub assignments: Vec<AddressAssignment>,
+}
+
+/// A config entry covering a queried address or subnet
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AddressMatch {
+    pub config: String,
+    pub owner: AddressOwner,
+    /// The Address or AllowedIPs entry that matched
+    pub matched: Cidr,
+}
+
+/// Every address assigned across all configs, grouped by subnet
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AddressMap {
+    pub subnets: Vec<SubnetUsage>,
+    pub conflicts: Vec<AddressConflict>,
+}
+
+/// Subnets for a new tunnel that don't collide with any existing config
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AddressPlan {
+    pub ipv4: Cidr,
+    pub ipv6: Cidr,
+    /// The site /48 `ipv6` was taken from
+    pub ula_prefix: Cidr,
+    /// Interface Address for the tunnel's first host, e.g. ["10.8.0.1/24", "fd12:3456:789a::1/64"]
+    pub interface_address: Vec<String>,
+}
+
+/// 64-bit NTP timestamp of now: seconds since 1900 and a 32-bit binary fraction
+fn ntp_time() -> u64 {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+    let seconds = now.as_secs() + NTP_UNIX_OFFSET;
+    let fraction = (u64::from(now.subsec_nanos()) << 32) / 1_000_000_000;
+    (seconds << 32) | fraction
+}
+
+fn parse_mac(text: &str) -> Option<[u8; 6]> {
+    let bytes: Vec<u8> = text.trim().split(':').filter_map(|b| u8::from_str_radix(b, 16).ok()).collect();
+    let mac: [u8; 6] = bytes.try_into().ok()?;
+    (mac != [0; 6]).then_some(mac)
+}
+
+/// A hardware address of this machine, for the EUI-64 input of RFC 4193
+fn machine_mac() -> Option<[u8; 6]> {
+    if cfg!(target_os = "macos") {
+        let output = Command::new("ifconfig").arg("en0").output().ok()?;
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        return stdout
+            .lines()
+            .find_map(|l| l.trim().strip_prefix("ether "))
+            .and_then(parse_mac);
+    }
+
+    let mut interfaces: Vec<_> = fs::read_dir("/sys/class/net").ok()?.flatten().map(|e| e.path()).collect();
+    interfaces.sort();
+    interfaces
+        .iter()
+        .filter(|dir| dir.file_name().is_some_and(|n| n != "lo"))
+        .find_map(|dir| fs::read_to_string(dir.join("address")).ok().and_then(|a| parse_mac(&a)))
+}
+
+/// EUI-64 from a MAC: ff:fe in the middle and the universal/local bit flipped
+fn eui64(mac: [u8; 6]) -> [u8; 8] {
+    [mac[0] ^ 0x02, mac[1], mac[2], 0xff, 0xfe, mac[3], mac[4], mac[5]]
+}
+
+/// A fresh `fdXX:XXXX:XXXX::/48` following RFC 4193 section 3.2.2: SHA-1 over the NTP time
+/// and an EUI-64, keeping the low 40 bits as the Global ID. Without a MAC, random bytes stand in.
+pub fn generate_ula_prefix() -> Cidr {
+    let identifier = match machine_mac() {
+        Some(mac) => eui64(mac),
+        None => {
+            let mut bytes = [0u8; 8];
+            rand::thread_rng().fill_bytes(&mut bytes);
+            bytes
+        }
+    };
+
+    let mut input = ntp_time().to_be_bytes().to_vec();
+    input.extend_from_slice(&identifier);
+    let hash = digest::digest(&digest::SHA1_FOR_LEGACY_USE_ONLY, &input);
+    let global_id = &hash.as_ref()[15..20];
+
+    let mut octets = [0u8; 16];
+    octets[0] = 0xfd;
+    octets[1..6].copy_from_slice(global_id);
+    Cidr {
+        addr: IpAddr::V6(Ipv6Addr::from(octets)),
+        prefix: 48,
+    }
+}
+
+fn is_ula(cidr: &Cidr) -> bool {
+    matches!(cidr.addr, IpAddr::V6(v6) if v6.octets()[0] == 0xfd)
+}
+
+/// Every prefix the workspace's configs already use, interface addresses and peer AllowedIPs alike
+fn used_prefixes() -> Vec<Cidr> {
+    let mut used = Vec::new();
+    for name in list_configs().unwrap_or_default() {
+        let Ok(config) = parse_config(&name) else {
+            continue;
+        };
+        used.extend(Cidr::parse_all(&config.interface.address).unwrap_or_default().iter().map(Cidr::network));
+        for peer in &config.peers {
+            // Default routes overlap everything and say nothing about which subnets are taken
+            let routes = Cidr::parse_list(&peer.allowed_ips).unwrap_or_default();
+            used.extend(routes.into_iter().filter(|c| c.prefix > 0));
+        }
+    }
+    used
+}
+
+/// The workspace's ULA /48: the one recorded earlier, else one already in use by a config, else a new one
+pub fn site_ula_prefix() -> Result<Cidr, WgError> {
+    let mut state: AddressingState = store::load(ADDRESSING_FILE)?;
+    if let Some(prefix) = state.ula_prefix {
+        return Ok(prefix);
+    }
+
+    let existing = used_prefixes().into_iter().find(is_ula).map(|c| Cidr { addr: c.addr, prefix: 48 }.network());
+    let prefix = existing.unwrap_or_else(generate_ula_prefix);
+    state.ula_prefix = Some(prefix);
+    store::save(ADDRESSING_FILE, &state)?;
+    Ok(prefix)
+}
+
+/// First host of a subnet, with the subnet's prefix length
+fn first_host(subnet: &Cidr) -> Cidr {
+    let addr = match subnet.addr {
+        IpAddr::V4(v4) => IpAddr::V4(Ipv4Addr::from(u32::from(v4) + 1)),
+        IpAddr::V6(v6) => IpAddr::V6(Ipv6Addr::from(u128::from(v6) + 1)),
+    };
+    Cidr { addr, prefix: subnet.prefix }
+}
+
+fn free_ipv4_in(used: &[Cidr]) -> Result<Cidr, WgError> {
+    IPV4_SECOND_OCTETS
+        .map(|octet| Cidr {
+            addr: IpAddr::V4(Ipv4Addr::new(10, octet, 0, 0)),
+            prefix: 24,
+        })
+        .find(|candidate| !used.iter().any(|u| u.overlaps(candidate)))
+        .ok_or_else(|| WgError::CommandFailed("No free 10.x.0.0/24 subnet left".to_string()))
+}
+
+/// An IPv4 /24 no config uses yet
+pub fn free_ipv4_subnet() -> Result<Cidr, WgError> {
+    free_ipv4_in(&used_prefixes())
+}
+
+/// Pick a free IPv4 /24 and a free /64 of the site ULA prefix for a new tunnel
+pub fn plan_tunnel_addresses() -> Result<AddressPlan, WgError> {
+    let used = used_prefixes();
+    let free = |candidate: &Cidr| !used.iter().any(|u| u.overlaps(candidate));
+
+    let ipv4 = free_ipv4_in(&used)?;
+
+    let ula_prefix = site_ula_prefix()?;
+    let IpAddr::V6(base) = ula_prefix.addr else {
+        return Err(WgError::Parse("ULA prefix is not IPv6".to_string()));
+    };
+    let ipv6 = (0..=u16::MAX)
+        .map(|subnet_id| Cidr {
+            addr: IpAddr::V6(Ipv6Addr::from(u128::from(base) | (u128::from(subnet_id) << 64))),
+            prefix: 64,
+        })
+        .find(free)
+        .ok_or_else(|| WgError::CommandFailed(format!("No free /64 left in {}", ula_prefix)))?;
+
+    Ok(AddressPlan {
+        interface_address: vec![first_host(&ipv4).to_string(), first_host(&ipv6).to_string()],
+        ipv4,
+        ipv6,
+        ula_prefix,
+    })
+}
+
+/// The lowest host of the config's IPv4 tunnel subnet that no interface address or peer uses yet
+pub fn allocate_peer_address(config: &WgConfig) -> Result<Cidr, WgError> {
+    let subnet = tunnel_subnet(config)
+        .ok_or_else(|| WgError::Parse(format!("{} has no IPv4 Address to allocate peer addresses from", config.name)))?;
+    let IpAddr::V4(base) = subnet.addr else {
+        return Err(WgError::Parse("Tunnel subnet is not IPv4".to_string()));
+    };
+
+    // The interface Address names the subnet, so only its own host is taken
+    let mut taken: Vec<Cidr> = Cidr::parse_all(&config.interface.address)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|a| Cidr {
+            addr: a.addr,
+            prefix: if a.addr.is_ipv4() { 32 } else { 128 },
+        })
+        .collect();
+    for peer in &config.peers {
+        taken.extend(Cidr::parse_list(&peer.allowed_ips).unwrap_or_default());
+    }
+
+    // Skip the network and broadcast addresses
+    let size = 1u64 << (32 - u32::from(subnet.prefix));
+    (1..size.saturating_sub(1))
+        .map(|offset| Cidr {
+            addr: IpAddr::V4(Ipv4Addr::from(u32::from(base) + offset as u32)),
+            prefix: 32,
+        })
+        .find(|candidate| !taken.iter().any(|t| t.overlaps(candidate)))
+        .ok_or_else(|| WgError::Refused(format!("No free address left in {}", subnet)))
+}
+
+/// Create a new dual-stack config with a fresh key and planned addresses
+pub fn create_wireguard_config(name: &str, listen_port: Option<u16>) -> Result<WgConfig, WgError> {
+    validate_config_name(name)?;
+    let path = get_wireguard_dir().join(format!("{}.conf", name));
+    if config_file_exists(&path) {
+        return Err(WgError::Refused(format!("{} already exists", name)));
+    }
+
+    let plan = plan_tunnel_addresses()?;
+    let (private_key, _) = generate_keypair()?;
+    let config = WgConfig {
+        name: name.to_string(),
+        path,
+        interface: Interface {
+            private_key: Some(private_key),
+            has_private_key: true,
+            address: plan.interface_address,
+            listen_port,
+            dns: Vec::new(),
+            dns_search: Vec::new(),
+            mtu: None,
+            table: None,
+            fw_mark: None,
+            pre_up: Vec::new(),
+            post_up: Vec::new(),
+            pre_down: Vec::new(),
+            post_down: Vec::new(),
+            save_config: false,
+            extra: Vec::new(),
+            comments: Comments::default(),
+        },
+        peers: Vec::new(),
+        managed_by: None,
+        content_hash: None,
+    };
+    save_config(&config)?;
+    Ok(config)
+}
+
+fn host(cidr: &Cidr) -> Cidr {
+    Cidr {
+        addr: cidr.addr,
+        prefix: if cidr.addr.is_ipv4() { 32 } else { 128 },
+    }
+}
+
+/// Orders IPv4 before IPv6, then numerically
+fn sort_key(cidr: &Cidr) -> (bool, u128, u8) {
+    match cidr.addr {
+        IpAddr::V4(v4) => (false, u128::from(u32::from(v4)), cidr.prefix),
+        IpAddr::V6(v6) => (true, u128::from(v6), cidr.prefix),
+    }
+}
+
+/// Every interface address and peer host address (/32, /128 AllowedIPs) across all configs, grouped
+/// by tunnel subnet. Addresses used by more than one device are reported as conflicts; a peer entry
+/// for one of our own interfaces (same public key) is the same device and doesn't count.
+pub fn get_address_map() -> Result<AddressMap, WgError> {
+    let mut assignments = Vec::new();
+    let mut subnets: BTreeMap<(bool, u128, u8), (Cidr, BTreeSet<String>)> = BTreeMap::new();
+    // Who each assignment is, by public key where one is known
+    let mut identities = Vec::new();
+
+    for name in list_configs()? {
+        let Ok(config) = parse_config(&name) else {
+            continue;
+        };
+        let own_key = config
+            .interface
+            .private_key
+            .as_deref()
+            .filter(|_| config.interface.has_private_key)
+            .and_then(|k| get_public_key(k).ok());
+
+        for address in Cidr::parse_all(&config.interface.address).unwrap_or_default() {
+            let network = address.network();
+            subnets
+                .entry(sort_key(&network))
+                .or_insert_with(|| (network, BTreeSet::new()))
+                .1
+                .insert(name.clone());
+            identities.push(own_key.clone().unwrap_or_else(|| format!("interface:{}", name)));
+            assignments.push(AddressAssignment {
+                config: name.clone(),
+                address: host(&address),
+                owner: AddressOwner::Interface,
+            });
+        }
+        for peer in &config.peers {
+            for address in Cidr::parse_list(&peer.allowed_ips).unwrap_or_default() {
+                if !address.is_host() {
+                    continue;
+                }
+                identities.push(peer.public_key.clone());
+                assignments.push(AddressAssignment {
+                    config: name.clone(),
+                    address,
+                    owner: AddressOwner::Peer {
+                        public_key: peer.public_key.clone(),
+                        name: peer.name.clone(),
+                    },
+                });
+            }
+        }
+    }
+
+    let mut by_address: BTreeMap<(bool, u128, u8), Vec<usize>> = BTreeMap::new();
+    for (index, assignment) in assignments.iter().enumerate() {
+        by_address.entry(sort_key(&assignment.address)).or_default().push(index);
+    }
+    let conflicts = by_address
+        .values()
+        .filter_map(|indices| {
+            let devices: BTreeSet<&String> = indices.iter().map(|&i| &identities[i]).collect();
+            let address = assignments[indices[0]].address;
+            (devices.len() > 1).then(|| AddressConflict {
+                address,
+                message: format!("{} is assigned to {} different devices", address.addr, devices.len()),
+                assignments: indices.iter().map(|&i| assignments[i].clone()).collect(),
+            })
+        })
+        .collect();
+
+    // The most specific tunnel subnet holding an address, else a /24 or /64 of its own
+    let mut groups: BTreeMap<(bool, u128, u8), SubnetUsage> = BTreeMap::new();
+    for assignment in assignments {
+        let subnet = subnets
+            .values()
+            .filter(|(subnet, _)| subnet.contains(&assignment.address.addr))
+            .max_by_key(|(subnet, _)| subnet.prefix)
+            .map(|(subnet, _)| *subnet)
+            .unwrap_or_else(|| {
+                Cidr {
+                    addr: assignment.address.addr,
+                    prefix: if assignment.address.addr.is_ipv4() { 24 } else { 64 },
+                }
+                .network()
+            });
+        let configs = subnets
+            .get(&sort_key(&subnet))
+            .map(|(_, c)| c.iter().cloned().collect())
+            .unwrap_or_default();
+        groups
+            .entry(sort_key(&subnet))
+            .or_insert_with(|| SubnetUsage {
+                subnet,
+                configs,
+                assignments: Vec::new(),
+            })
+            .assignments
+            .push(assignment);
+    }
+    for usage in groups.values_mut() {
+        usage.assignments.sort_by_key(|a| sort_key(&a.address));
+    }
+
+    Ok(AddressMap {
+        subnets: groups.into_values().collect(),
+        conflicts,
+    })
+}
+
+/// Interface addresses and peer AllowedIPs of every config, default routes left out
+fn address_entries() -> Result<Vec<AddressMatch>, WgError> {
+    let mut entries = Vec::new();
+    for name in list_configs()? {
+        let Ok(config) = parse_config(&name) else {
+            continue;
+        };
+        for address in Cidr::parse_all(&config.interface.address).unwrap_or_default() {
+            entries.push(AddressMatch {
+                config: name.clone(),
+                owner: AddressOwner::Interface,
+                matched: address,
+            });
+        }
+        for peer in &config.peers {
+            for route in Cidr::parse_list(&peer.allowed_ips).unwrap_or_default() {
+                if route.prefix == 0 {
+                    continue;
+                }
+                entries.push(AddressMatch {
+                    config: name.clone(),
+                    owner: AddressOwner::Peer {
+                        public_key: peer.public_key.clone(),
+                        name: peer.name.clone(),
+                    },
+                    matched: route,
+                });
+            }
+        }
+    }
+    Ok(entries)
+}
+
+/// Which interfaces and peers an IP belongs to across all configs: an interface with exactly that
+/// address, or a peer whose AllowedIPs cover it. Most specific match first.
+pub fn who_has_ip(ip: IpAddr) -> Result<Vec<AddressMatch>, WgError> {
+    let mut matches: Vec<AddressMatch> = address_entries()?
+        .into_iter()
+        .filter(|entry| match entry.owner {
+            AddressOwner::Interface => entry.matched.addr == ip,
+            AddressOwner::Peer { .. } => entry.matched.contains(&ip),
+        })
+        .collect();
+    matches.sort_by_key(|m| std::cmp::Reverse(m.matched.prefix));
+    Ok(matches)
+}
+
+/// Peers across all configs with an AllowedIPs entry inside `subnet`, in address order
+pub fn list_peers_in_subnet(subnet: Cidr) -> Result<Vec<AddressMatch>, WgError> {
+    let mut matches: Vec<AddressMatch> = address_entries()?
+        .into_iter()
+        .filter(|entry| {
+            matches!(entry.owner, AddressOwner::Peer { .. })
+                && entry.matched.prefix >= subnet.prefix
+                && subnet.contains(&entry.matched.addr)
+        })
+        .collect();
+    matches.sort_by_key(|m| sort_key(&m.matched));
+    Ok(matches)
+}
@@ -0,0 +1,303 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use crate::cidr::Cidr;
+use crate::endpoint::Endpoint;
+use crate::keys::validate_key_format;
+use crate::wireguard::{generate_preshared_key, parse_config, serialize_config, Comments, WgError};
+
+/// TEST-NET-1, -2 and -3 (RFC 5737); further blocks come out of 198.18.0.0/15 (RFC 2544)
+const FAKE_V4_BLOCKS: [[u8; 3]; 3] = [[192, 0, 2], [198, 51, 100], [203, 0, 113]];
+
+/// A config with nothing in it that identifies its owner, ready to post
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnonymizedConfig {
+    pub content: String,
+    /// Distinct keys replaced by random ones
+    pub keys: usize,
+    /// Distinct public addresses moved into documentation ranges
+    pub addresses: usize,
+    /// Distinct host and domain names replaced by names under `.example`
+    pub hostnames: usize,
+}
+
+fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => {
+            let [a, b, ..] = v4.octets();
+            !(v4.is_private()
+                || v4.is_loopback()
+                || v4.is_link_local()
+                || v4.is_unspecified()
+                || v4.is_broadcast()
+                || v4.is_multicast()
+                || v4.is_documentation()
+                // Carrier-grade NAT, 100.64.0.0/10
+                || (a == 100 && (64..128).contains(&b)))
+        }
+        IpAddr::V6(v6) => {
+            let first = v6.segments()[0];
+            !(v6.is_loopback()
+                || v6.is_unspecified()
+                || v6.is_multicast()
+                // Unique local fc00::/7, link-local fe80::/10 and documentation 2001:db8::/32
+                || (first & 0xfe00) == 0xfc00
+                || (first & 0xffc0) == 0xfe80
+                || (first == 0x2001 && v6.segments()[1] == 0x0db8))
+        }
+    }
+}
+
+/// Consistent replacements, so a value showing up twice is replaced the same way both times.
+/// Public addresses keep their last octet (IPv4) or their last 80 bits (IPv6) and get a fake
+/// /24 or /48 per real one, which keeps hosts of one network together.
+#[derive(Default)]
+struct Anonymizer {
+    keys: HashMap<String, String>,
+    v4_blocks: HashMap<[u8; 3], [u8; 3]>,
+    v6_blocks: HashMap<[u16; 3], [u16; 3]>,
+    addresses: HashSet<IpAddr>,
+    /// Registrable domains (the last two labels) to `domainN.example`
+    domains: HashMap<String, String>,
+    hosts: HashMap<String, String>,
+}
+
+impl Anonymizer {
+    fn key(&mut self, key: &str) -> Result<String, WgError> {
+        if let Some(fake) = self.keys.get(key) {
+            return Ok(fake.clone());
+        }
+        let fake = generate_preshared_key()?;
+        self.keys.insert(key.to_string(), fake.clone());
+        Ok(fake)
+    }
+
+    fn ip(&mut self, ip: IpAddr) -> IpAddr {
+        if !is_public(ip) {
+            return ip;
+        }
+        self.addresses.insert(ip);
+        match ip {
+            IpAddr::V4(v4) => {
+                let [a, b, c, d] = v4.octets();
+                let next = self.v4_blocks.len();
+                let [x, y, z] = *self.v4_blocks.entry([a, b, c]).or_insert_with(|| match next {
+                    0..=2 => FAKE_V4_BLOCKS[next],
+                    _ => [198, 18 + ((next - 3) / 256 % 2) as u8, ((next - 3) % 256) as u8],
+                });
+                IpAddr::V4(Ipv4Addr::new(x, y, z, d))
+            }
+            IpAddr::V6(v6) => {
+                let mut segments = v6.segments();
+                let next = self.v6_blocks.len();
+                let fake = *self
+                    .v6_blocks
+                    .entry([segments[0], segments[1], segments[2]])
+                    .or_insert_with(|| [0x2001, 0x0db8, (next + 1) as u16]);
+                segments[..3].copy_from_slice(&fake);
+                IpAddr::V6(Ipv6Addr::from(segments))
+            }
+        }
+    }
+
+    /// Wide public ranges such as the halves of a split default route say nothing about anyone
+    /// and are kept. Other prefixes shorter than a fake block stay aligned but only contain the
+    /// hosts of their first /24 or /48 afterwards.
+    fn cidr(&mut self, cidr: Cidr) -> Cidr {
+        let wide = match cidr.addr {
+            IpAddr::V4(_) => cidr.prefix <= 8,
+            IpAddr::V6(_) => cidr.prefix <= 16,
+        };
+        if wide || !is_public(cidr.addr) {
+            return cidr;
+        }
+        let mapped = Cidr {
+            addr: self.ip(cidr.addr),
+            prefix: cidr.prefix,
+        };
+        // A network stays aligned; an address with its prefix, as in Address, keeps its host part
+        if cidr.addr == cidr.network().addr {
+            mapped.network()
+        } else {
+            mapped
+        }
+    }
+
+    fn host(&mut self, host: &str) -> String {
+        if let Ok(ip) = host.parse::<IpAddr>() {
+            return self.ip(ip).to_string();
+        }
+        let host = host.trim_end_matches('.').to_ascii_lowercase();
+        if let Some(fake) = self.hosts.get(&host) {
+            return fake.clone();
+        }
+
+        let labels: Vec<&str> = host.split('.').collect();
+        let registrable = labels[labels.len().saturating_sub(2)..].join(".");
+        let next = self.domains.len() + 1;
+        let domain = self
+            .domains
+            .entry(registrable.clone())
+            .or_insert_with(|| format!("domain{}.example", next))
+            .clone();
+        let fake = if host == registrable {
+            domain
+        } else {
+            format!("host{}.{}", self.hosts.len() + 1, domain)
+        };
+        self.hosts.insert(host, fake.clone());
+        fake
+    }
+
+    fn endpoint(&mut self, value: &str) -> String {
+        match Endpoint::parse(value) {
+            Ok(endpoint) => Endpoint {
+                host: self.host(&endpoint.host),
+                port: endpoint.port,
+            }
+            .to_string(),
+            Err(_) => self.text(value),
+        }
+    }
+
+    /// One word of free text: a key, endpoint, prefix or address, or a name seen elsewhere in the
+    /// config. Anything else is kept.
+    fn word(&mut self, word: &str) -> Result<String, WgError> {
+        if self.keys.contains_key(word) || validate_key_format(word).is_ok() {
+            return self.key(word);
+        }
+        if let Ok(endpoint) = Endpoint::parse(word) {
+            let known = self.hosts.contains_key(&endpoint.host.to_ascii_lowercase());
+            if endpoint.ip().is_some() || known {
+                return Ok(self.endpoint(word));
+            }
+        }
+        if word.contains('/') {
+            if let Ok(cidr) = Cidr::parse(word) {
+                return Ok(self.cidr(cidr).to_string());
+            }
+        }
+        if let Ok(ip) = word.parse::<IpAddr>() {
+            return Ok(self.ip(ip).to_string());
+        }
+        let name = word.trim_end_matches('.').to_ascii_lowercase();
+        if self.hosts.contains_key(&name) || self.domains.contains_key(&name) {
+            return Ok(self.host(&name));
+        }
+        Ok(word.to_string())
+    }
+
+    /// Hook commands and unknown keys: every word is looked at on its own, the rest is kept as is
+    fn text(&mut self, text: &str) -> String {
+        let is_word = |c: char| c.is_ascii_alphanumeric() || ".:/+[]-_%=".contains(c);
+        let mut output = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(start) = rest.find(is_word) {
+            output.push_str(&rest[..start]);
+            let run = &rest[start..];
+            let end = run.find(|c: char| !is_word(c)).unwrap_or(run.len());
+            let run = &run[..end];
+            // `=` ends a key, but in `--to=1.2.3.4` it separates an option from its value
+            let replaced = match self.word(run) {
+                Ok(word) if word != run => word,
+                _ => {
+                    let parts: Vec<String> =
+                        run.split('=').map(|part| self.word(part).unwrap_or_else(|_| part.to_string())).collect();
+                    parts.join("=")
+                }
+            };
+            output.push_str(&replaced);
+            rest = &rest[start + end..];
+        }
+        output.push_str(rest);
+        output
+    }
+}
+
+/// A copy of a config that is safe to post when asking for help. Keys become random keys,
+/// public addresses move into documentation ranges and host names under `.example`.
+/// Replacements are consistent throughout the file, so which peer routes to which network and
+/// which endpoint shares a host with which still shows. Private ranges stay as written, peer
+/// names become `Peer 1`, `Peer 2`, ... and comments are dropped.
+pub fn anonymize_config(config_name: &str) -> Result<AnonymizedConfig, WgError> {
+    let mut config = parse_config(config_name)?;
+    let mut anonymizer = Anonymizer::default();
+
+    // Names first, so free text mentioning them is replaced too
+    for peer in &config.peers {
+        if let Some(ref endpoint) = peer.endpoint {
+            if let Ok(endpoint) = Endpoint::parse(endpoint) {
+                anonymizer.host(&endpoint.host);
+            }
+        }
+    }
+    for domain in &config.interface.dns_search {
+        anonymizer.host(domain);
+    }
+
+    let interface = &mut config.interface;
+    if interface.has_private_key {
+        if let Some(ref key) = interface.private_key {
+            interface.private_key = Some(anonymizer.key(key)?);
+        }
+    }
+    for address in interface.address.iter_mut() {
+        *address = match Cidr::parse(address) {
+            Ok(cidr) => anonymizer.cidr(cidr).to_string(),
+            Err(_) => anonymizer.text(address),
+        };
+    }
+    for server in interface.dns.iter_mut() {
+        *server = anonymizer.host(server);
+    }
+    for domain in interface.dns_search.iter_mut() {
+        *domain = anonymizer.host(domain);
+    }
+    for hook in interface
+        .pre_up
+        .iter_mut()
+        .chain(interface.post_up.iter_mut())
+        .chain(interface.pre_down.iter_mut())
+        .chain(interface.post_down.iter_mut())
+    {
+        *hook = anonymizer.text(hook);
+    }
+    for (_, value) in interface.extra.iter_mut() {
+        *value = anonymizer.text(value);
+    }
+    interface.comments = Comments::default();
+
+    for (index, peer) in config.peers.iter_mut().enumerate() {
+        if !peer.public_key.is_empty() {
+            peer.public_key = anonymizer.key(&peer.public_key)?;
+        }
+        if let Some(ref key) = peer.preshared_key {
+            peer.preshared_key = Some(anonymizer.key(key)?);
+        }
+        let allowed: Vec<String> = peer
+            .allowed_ips
+            .split(',')
+            .map(str::trim)
+            .filter(|a| !a.is_empty())
+            .map(|a| match Cidr::parse(a) {
+                Ok(cidr) => anonymizer.cidr(cidr).to_string(),
+                Err(_) => anonymizer.text(a),
+            })
+            .collect();
+        peer.allowed_ips = allowed.join(", ");
+        peer.endpoint = peer.endpoint.as_deref().map(|e| anonymizer.endpoint(e));
+        for (_, value) in peer.extra.iter_mut() {
+            *value = anonymizer.text(value);
+        }
+        peer.name = Some(format!("Peer {}", index + 1));
+        peer.comments = Comments::default();
+    }
+
+    Ok(AnonymizedConfig {
+        content: serialize_config(&config),
+        keys: anonymizer.keys.len(),
+        addresses: anonymizer.addresses.len(),
+        hostnames: anonymizer.hosts.len(),
+    })
+}
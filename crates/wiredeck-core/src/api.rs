@@ -0,0 +1,171 @@
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::store::{self, now_secs};
+use crate::wireguard::WgError;
+
+const TOKENS_FILE: &str = "api_tokens.json";
+
+/// Prefix that makes WireDeck tokens recognizable in scripts and secret scanners
+const TOKEN_PREFIX: &str = "wdk_";
+
+/// What a remote API token may do; each scope includes the ones before it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenScope {
+    /// Status, verdicts and non-secret settings
+    ReadOnly,
+    /// Read-only plus adding, editing and removing peers
+    PeerManagement,
+    /// Everything, including tunnel up/down and raw config access
+    Full,
+}
+
+/// Endpoints below `Full`; anything not listed needs a full token
+const ENDPOINT_SCOPES: &[(&str, TokenScope)] = &[
+    ("list_wireguard_configs", TokenScope::ReadOnly),
+    ("get_wireguard_status", TokenScope::ReadOnly),
+    ("check_interface_status", TokenScope::ReadOnly),
+    ("get_tunnel_state", TokenScope::ReadOnly),
+    ("verify_tunnel", TokenScope::ReadOnly),
+    ("get_external_ip_via", TokenScope::ReadOnly),
+    ("get_tune_report", TokenScope::ReadOnly),
+    ("get_peer_timeline", TokenScope::ReadOnly),
+    ("get_peer_metadata", TokenScope::ReadOnly),
+    ("check_peer_keys", TokenScope::ReadOnly),
+    ("check_forwarding", TokenScope::ReadOnly),
+    ("get_tunnel_settings", TokenScope::ReadOnly),
+    ("list_peer_rate_limits", TokenScope::ReadOnly),
+    ("list_remote_subscriptions", TokenScope::ReadOnly),
+    ("list_profiles", TokenScope::ReadOnly),
+    ("list_snippets", TokenScope::ReadOnly),
+    ("list_workspaces", TokenScope::ReadOnly),
+    ("get_active_workspace", TokenScope::ReadOnly),
+    ("add_peer", TokenScope::PeerManagement),
+    ("update_peer", TokenScope::PeerManagement),
+    ("delete_peer", TokenScope::PeerManagement),
+    ("generate_wireguard_keypair", TokenScope::PeerManagement),
+    ("set_peer_endpoint_family", TokenScope::PeerManagement),
+    ("set_peer_rate_limit", TokenScope::PeerManagement),
+    ("clear_peer_rate_limit", TokenScope::PeerManagement),
+];
+
+/// An issued token; only a hash of the secret is stored
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiToken {
+    pub id: String,
+    /// Label chosen at issuance, e.g. "grafana"
+    pub name: String,
+    pub scope: TokenScope,
+    pub created_at: u64,
+    pub last_used: Option<u64>,
+    /// Only set inside the token store; cleared (and omitted) in anything handed out
+    #[serde(skip_serializing_if = "String::is_empty", default)]
+    secret_hash: String,
+}
+
+/// A freshly issued token; the secret is shown once and can't be recovered later
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IssuedToken {
+    pub token: ApiToken,
+    pub secret: String,
+}
+
+fn random_hex(len: usize) -> String {
+    let mut bytes = vec![0u8; len];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn hash_secret(secret: &str) -> String {
+    Sha256::digest(secret.as_bytes()).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn load_tokens() -> Result<Vec<ApiToken>, WgError> {
+    store::load_global(TOKENS_FILE)
+}
+
+fn save_tokens(tokens: &[ApiToken]) -> Result<(), WgError> {
+    store::save_global(TOKENS_FILE, &tokens)
+}
+
+/// Scope an endpoint needs; unknown endpoints need `Full`
+pub fn required_scope(endpoint: &str) -> TokenScope {
+    ENDPOINT_SCOPES
+        .iter()
+        .find(|(name, _)| *name == endpoint)
+        .map(|(_, scope)| *scope)
+        .unwrap_or(TokenScope::Full)
+}
+
+/// Issued tokens, without their secrets
+pub fn list_tokens() -> Result<Vec<ApiToken>, WgError> {
+    let mut tokens = load_tokens()?;
+    for token in &mut tokens {
+        token.secret_hash.clear();
+    }
+    Ok(tokens)
+}
+
+/// Issue a new token with the given scope
+pub fn issue_token(name: &str, scope: TokenScope) -> Result<IssuedToken, WgError> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(WgError::Parse("Token name is required".to_string()));
+    }
+
+    let secret = format!("{}{}", TOKEN_PREFIX, random_hex(32));
+    let token = ApiToken {
+        id: random_hex(8),
+        name: name.to_string(),
+        scope,
+        created_at: now_secs(),
+        last_used: None,
+        secret_hash: hash_secret(&secret),
+    };
+
+    let mut tokens = load_tokens()?;
+    tokens.push(token.clone());
+    save_tokens(&tokens)?;
+
+    let mut token = token;
+    token.secret_hash.clear();
+    Ok(IssuedToken { token, secret })
+}
+
+/// Revoke a token so it stops working immediately
+pub fn revoke_token(id: &str) -> Result<(), WgError> {
+    let mut tokens = load_tokens()?;
+    let before = tokens.len();
+    tokens.retain(|t| t.id != id);
+    if tokens.len() == before {
+        return Err(WgError::NotFound(format!("API token {}", id)));
+    }
+    save_tokens(&tokens)
+}
+
+/// Check that `secret` is a live token allowed to call `endpoint`, recording its use
+pub fn authorize(secret: &str, endpoint: &str) -> Result<ApiToken, WgError> {
+    let hash = hash_secret(secret.trim());
+    let mut tokens = load_tokens()?;
+    let token = tokens
+        .iter_mut()
+        .find(|t| t.secret_hash == hash)
+        .ok_or_else(|| WgError::Unauthorized("Unknown or revoked API token".to_string()))?;
+
+    let required = required_scope(endpoint);
+    if token.scope < required {
+        return Err(WgError::Unauthorized(format!(
+            "{} needs a {:?} token, '{}' is {:?}",
+            endpoint, required, token.name, token.scope
+        )));
+    }
+
+    token.last_used = Some(now_secs());
+    let mut token = token.clone();
+    save_tokens(&tokens)?;
+
+    token.secret_hash.clear();
+    Ok(token)
+}
@@ -0,0 +1,169 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::elevation::sudo_output;
+use crate::privileged::record_change;
+use crate::trace;
+use crate::wireguard::{get_real_interface_name, is_interface_up, parse_config, WgError};
+
+/// An application whose traffic should go through the tunnel
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppTarget {
+    pub path: PathBuf,
+    /// `CFBundleIdentifier` of an `.app` bundle
+    pub bundle_id: Option<String>,
+    /// The binary that opens the sockets
+    pub executable: PathBuf,
+}
+
+/// pf rules sending the traffic of some applications through a tunnel
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PfAppRules {
+    pub config_name: String,
+    /// Anchor under `com.apple/`, which the stock macOS pf.conf already evaluates
+    pub anchor: String,
+    /// pf tag the rules route on
+    pub tag: String,
+    pub apps: Vec<AppTarget>,
+    /// Rules for the anchor; `%i` stands for the tunnel interface
+    pub rules: Vec<String>,
+    pub install: Vec<String>,
+    pub uninstall: Vec<String>,
+}
+
+/// Same characters as a config name, made safe for a pf tag
+fn pf_tag(config_name: &str) -> String {
+    let name: String = config_name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' })
+        .collect();
+    format!("WIREDECK_APP_{}", name)
+}
+
+fn anchor_name(config_name: &str) -> String {
+    format!("com.apple/wiredeck-apps-{}", config_name)
+}
+
+fn plist_value(info_plist: &Path, key: &str) -> Option<String> {
+    if !cfg!(target_os = "macos") {
+        return None;
+    }
+    // plutil reads binary and XML plists alike
+    let mut command = Command::new("plutil");
+    command.args(["-extract", key, "raw", "-o", "-"]).arg(info_plist);
+    let output = trace::output(&mut command).ok()?;
+    let value = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (output.status.success() && !value.is_empty()).then_some(value)
+}
+
+fn resolve_app(path: &str) -> Result<AppTarget, WgError> {
+    let path = PathBuf::from(path.trim());
+    if !path.exists() {
+        return Err(WgError::NotFound(path.display().to_string()));
+    }
+    if !path.is_dir() {
+        return Ok(AppTarget {
+            executable: path.clone(),
+            path,
+            bundle_id: None,
+        });
+    }
+
+    let info_plist = path.join("Contents").join("Info.plist");
+    let executable = plist_value(&info_plist, "CFBundleExecutable")
+        .or_else(|| path.file_stem().map(|s| s.to_string_lossy().to_string()))
+        .map(|name| path.join("Contents").join("MacOS").join(name))
+        .filter(|executable| executable.is_file())
+        .ok_or_else(|| WgError::Parse(format!("{} is not an application bundle", path.display())))?;
+    Ok(AppTarget {
+        bundle_id: plist_value(&info_plist, "CFBundleIdentifier"),
+        executable,
+        path,
+    })
+}
+
+/// Rule scaffolding for routing only the given applications (`.app` bundles or executables)
+/// through a tunnel on macOS. pf can't match a process, so the rules route traffic carrying the
+/// config's pf tag through the tunnel interface; tagging the applications' sockets is the part
+/// still to come, and until then the anchor routes nothing.
+pub fn generate_pf_app_rules(config_name: &str, app_paths: &[String]) -> Result<PfAppRules, WgError> {
+    parse_config(config_name)?;
+    if app_paths.is_empty() {
+        return Err(WgError::Parse("Choose at least one application".to_string()));
+    }
+    let apps = app_paths.iter().map(|p| resolve_app(p)).collect::<Result<Vec<_>, _>>()?;
+
+    let tag = pf_tag(config_name);
+    let anchor = anchor_name(config_name);
+    // The rules end up inside a single-quoted printf format
+    let mut rules: Vec<String> = apps
+        .iter()
+        .map(|app| {
+            let comment = match app.bundle_id {
+                Some(ref bundle_id) => format!("# {} ({})", app.executable.display(), bundle_id),
+                None => format!("# {}", app.executable.display()),
+            };
+            comment.replace(['\'', '%', '\\'], "")
+        })
+        .collect();
+    for family in ["inet", "inet6"] {
+        rules.push(format!("pass out quick on ! %i route-to (%i) {} tagged {} keep state", family, tag));
+    }
+
+    let install = vec![format!(
+        "printf '{}\\n' | pfctl -a {} -f - && (pfctl -E 2>/dev/null || true)",
+        rules.join("\\n"),
+        anchor
+    )];
+    let uninstall = vec![format!("pfctl -a {} -F all 2>/dev/null || true", anchor)];
+    Ok(PfAppRules {
+        config_name: config_name.to_string(),
+        anchor,
+        tag,
+        apps,
+        rules,
+        install,
+        uninstall,
+    })
+}
+
+/// Load the application routing anchor for a running tunnel, replacing any loaded before
+pub fn install_pf_app_rules(config_name: &str, app_paths: &[String]) -> Result<PfAppRules, WgError> {
+    if !cfg!(target_os = "macos") {
+        return Err(WgError::Refused("Application routing uses pf and is only available on macOS".to_string()));
+    }
+    if !is_interface_up(config_name)? {
+        return Err(WgError::CommandFailed(format!("{} is not up", config_name)));
+    }
+    let rules = generate_pf_app_rules(config_name, app_paths)?;
+    let interface = get_real_interface_name(config_name)?;
+    let commands: Vec<String> = rules.install.iter().map(|c| c.replace("%i", &interface)).collect();
+    for command in &commands {
+        let output = sudo_output(["sh", "-c", command])?;
+        if !output.status.success() {
+            return Err(WgError::CommandFailed(String::from_utf8_lossy(&output.stderr).trim().to_string()));
+        }
+    }
+    record_change("app_routing", Some(config_name), commands, rules.uninstall.clone());
+    Ok(rules)
+}
+
+/// Flush a tunnel's application routing anchor
+pub fn remove_pf_app_rules(config_name: &str) -> Result<(), WgError> {
+    if !cfg!(target_os = "macos") {
+        return Err(WgError::Refused("Application routing uses pf and is only available on macOS".to_string()));
+    }
+    let anchor = anchor_name(config_name);
+    let output = sudo_output(["pfctl", "-a", &anchor, "-F", "all"])?;
+    if !output.status.success() {
+        return Err(WgError::CommandFailed(String::from_utf8_lossy(&output.stderr).trim().to_string()));
+    }
+    record_change(
+        "app_routing_removed",
+        Some(config_name),
+        vec![format!("pfctl -a {} -F all", anchor)],
+        Vec::new(),
+    );
+    Ok(())
+}
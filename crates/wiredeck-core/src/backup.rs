@@ -0,0 +1,215 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::settings;
+use crate::store::{self, now_secs};
+use crate::wireguard::WgError;
+
+/// Backups kept per config when no retention is set
+const DEFAULT_RETENTION: usize = 10;
+
+/// Where config backups go and how many are kept
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BackupPolicy {
+    /// Directory for backups, e.g. on an encrypted volume; `None` keeps a `.conf.bak` beside each config
+    pub destination: Option<PathBuf>,
+    /// Backups kept per config in the destination; 0 means the default of 10
+    pub retention: usize,
+}
+
+impl BackupPolicy {
+    fn keep(&self) -> usize {
+        if self.retention == 0 {
+            DEFAULT_RETENTION
+        } else {
+            self.retention
+        }
+    }
+}
+
+/// A timestamped copy of a config in the backup destination, named `<config>-<created_at>-<n>.conf`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfigBackup {
+    pub path: PathBuf,
+    pub created_at: u64,
+    /// Counts up for backups made within the same second
+    pub sequence: u32,
+}
+
+/// The configured backup policy
+pub fn get_backup_policy() -> BackupPolicy {
+    settings::load_settings().map(|s| s.backups).unwrap_or_default()
+}
+
+/// Send backups to `destination`, or back beside the configs with `None`.
+/// The directory is created if needed and must be writable.
+pub fn set_backup_destination(destination: Option<PathBuf>) -> Result<BackupPolicy, WgError> {
+    if let Some(ref dir) = destination {
+        if !dir.is_absolute() {
+            return Err(WgError::Parse(format!("Backup destination must be an absolute path: {}", dir.display())));
+        }
+        // A folder picked by the user keeps its permissions; the per-config folders are private
+        if !dir.is_dir() {
+            store::create_private_dir(dir)?;
+        }
+        let probe = dir.join(".wiredeck-write-test");
+        fs::write(&probe, b"")?;
+        let _ = fs::remove_file(&probe);
+    }
+
+    let mut settings = settings::load_settings()?;
+    settings.backups.destination = destination;
+    settings::save_settings(&settings)?;
+    Ok(settings.backups)
+}
+
+/// Keep this many backups per config in the destination, pruning older ones right away
+pub fn set_backup_retention(retention: usize) -> Result<BackupPolicy, WgError> {
+    let mut settings = settings::load_settings()?;
+    settings.backups.retention = retention;
+    settings::save_settings(&settings)?;
+
+    if let Some(ref dir) = settings.backups.destination {
+        if dir.is_dir() {
+            for entry in fs::read_dir(dir)?.flatten() {
+                if entry.path().is_dir() {
+                    prune(&entry.path(), settings.backups.keep())?;
+                }
+            }
+        }
+    }
+    Ok(settings.backups)
+}
+
+/// Folder of the backup destination holding one config's backups
+pub fn config_backup_dir(policy: &BackupPolicy, config_name: &str) -> Option<PathBuf> {
+    policy.destination.as_ref().map(|dir| dir.join(config_name))
+}
+
+/// Backups of a config in the destination, newest first
+pub fn list_config_backups(config_name: &str) -> Result<Vec<ConfigBackup>, WgError> {
+    let Some(dir) = config_backup_dir(&get_backup_policy(), config_name) else {
+        return Ok(Vec::new());
+    };
+    backups_in(&dir)
+}
+
+fn backups_in(dir: &Path) -> Result<Vec<ConfigBackup>, WgError> {
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut backups: Vec<ConfigBackup> = fs::read_dir(dir)?
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry.path();
+            let stem = path.file_stem()?.to_str()?.to_string();
+            let mut parts = stem.rsplitn(3, '-');
+            let sequence = parts.next()?.parse().ok()?;
+            let created_at = parts.next()?.parse().ok()?;
+            (path.extension()? == "conf").then_some(ConfigBackup {
+                path,
+                created_at,
+                sequence,
+            })
+        })
+        .collect();
+    backups.sort_by_key(|b| std::cmp::Reverse((b.created_at, b.sequence)));
+    Ok(backups)
+}
+
+fn prune(dir: &Path, keep: usize) -> Result<(), WgError> {
+    for old in backups_in(dir)?.into_iter().skip(keep) {
+        fs::remove_file(old.path)?;
+    }
+    Ok(())
+}
+
+/// Copy `content` (the config about to be replaced) into the backup destination.
+/// Returns `false` without writing anything when no destination is configured.
+pub fn store_config_backup(config_name: &str, content: &[u8]) -> Result<bool, WgError> {
+    let policy = get_backup_policy();
+    let Some(dir) = config_backup_dir(&policy, config_name) else {
+        return Ok(false);
+    };
+    // Backups hold private keys, so neither the folder nor the files are readable by others
+    store::create_private_dir(&dir)?;
+
+    let created_at = now_secs();
+    // Several saves in the same second each get their own file, numbered in order
+    let sequence = backups_in(&dir)?
+        .iter()
+        .filter(|b| b.created_at == created_at)
+        .map(|b| b.sequence + 1)
+        .max()
+        .unwrap_or(0);
+    store::write_new_private_file(&dir.join(format!("{}-{}-{}.conf", config_name, created_at, sequence)), content)?;
+    prune(&dir, policy.keep())?;
+    Ok(true)
+}
+
+/// Keep a file or folder holding secrets out of Time Machine and Spotlight. Best effort: Time
+/// Machine is told through `tmutil`, and Spotlight skips folders holding `.metadata_never_index`.
+/// Other platforms have no system-wide equivalent, so nothing happens there.
+pub fn exclude_from_system_backups(path: &Path) {
+    if !cfg!(target_os = "macos") {
+        return;
+    }
+    let _ = Command::new("tmutil").arg("addexclusion").arg(path).output();
+    let folder = if path.is_dir() { Some(path) } else { path.parent() };
+    if let Some(marker) = folder.map(|f| f.join(".metadata_never_index")) {
+        if !marker.exists() {
+            let _ = fs::write(marker, b"");
+        }
+    }
+}
+
+/// Folder for temporary copies of configs, kept out of Time Machine and Spotlight. It lives in
+/// the per-user runtime directory (`XDG_RUNTIME_DIR`) or else in app data, never in the shared
+/// temp directory: a config staged here is installed into /etc/wireguard as root, so another user
+/// must not be able to create the folder first or swap files in it. The folder is created 0700
+/// and refused if it is a symlink, belongs to someone else or is open to other users.
+pub fn private_temp_dir() -> Result<PathBuf, WgError> {
+    let dir = std::env::var_os("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute() && dir.is_dir())
+        .map(|dir| dir.join("wiredeck"))
+        .unwrap_or_else(|| store::get_app_data_dir().join("tmp"));
+    if let Some(parent) = dir.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let mut builder = fs::DirBuilder::new();
+    #[cfg(unix)]
+    std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+    match builder.create(&dir) {
+        Ok(()) => exclude_from_system_backups(&dir),
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {}
+        Err(e) => return Err(WgError::Io(e)),
+    }
+    check_private_dir(&dir)?;
+    Ok(dir)
+}
+
+#[cfg(unix)]
+fn check_private_dir(dir: &Path) -> Result<(), WgError> {
+    use std::os::unix::fs::MetadataExt;
+
+    let metadata = fs::symlink_metadata(dir)?;
+    // SAFETY: geteuid takes no arguments and cannot fail
+    let uid = unsafe { libc::geteuid() };
+    if !metadata.is_dir() || metadata.uid() != uid {
+        return Err(WgError::Refused(format!("{} is not a folder owned by this user", dir.display())));
+    }
+    if metadata.mode() & 0o077 != 0 {
+        return Err(WgError::Refused(format!("{} is accessible to other users", dir.display())));
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn check_private_dir(_dir: &Path) -> Result<(), WgError> {
+    Ok(())
+}
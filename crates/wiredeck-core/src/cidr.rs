@@ -0,0 +1,164 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use crate::wireguard::WgError;
+
+/// An address with a prefix length, e.g. `10.0.0.1/24`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Cidr {
+    pub addr: IpAddr,
+    pub prefix: u8,
+}
+
+impl Cidr {
+    /// Parse `addr/prefix`; a bare address becomes a host route (/32 or /128)
+    pub fn parse(value: &str) -> Result<Self, WgError> {
+        let value = value.trim();
+        let (addr, prefix) = match value.split_once('/') {
+            Some((addr, prefix)) => (addr, Some(prefix)),
+            None => (value, None),
+        };
+
+        let addr: IpAddr = addr
+            .parse()
+            .map_err(|_| WgError::Parse(format!("Invalid IP address in '{}'", value)))?;
+        let max = Self::max_prefix(&addr);
+
+        let prefix = match prefix {
+            Some(p) => p
+                .parse::<u8>()
+                .ok()
+                .filter(|p| *p <= max)
+                .ok_or_else(|| WgError::Parse(format!("Invalid prefix length in '{}'", value)))?,
+            None => max,
+        };
+
+        Ok(Cidr { addr, prefix })
+    }
+
+    /// Parse a comma-separated list such as an AllowedIPs value
+    pub fn parse_list(value: &str) -> Result<Vec<Self>, WgError> {
+        value
+            .split(',')
+            .map(|s| s.trim())
+            .filter(|s| !s.is_empty())
+            .map(Self::parse)
+            .collect()
+    }
+
+    /// Parse one CIDR per entry, e.g. an interface's addresses
+    pub fn parse_all(values: &[String]) -> Result<Vec<Self>, WgError> {
+        values.iter().map(|v| Self::parse(v)).collect()
+    }
+
+    fn max_prefix(addr: &IpAddr) -> u8 {
+        match addr {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        }
+    }
+
+    /// Whether this is a single-host prefix
+    pub fn is_host(&self) -> bool {
+        self.prefix == Self::max_prefix(&self.addr)
+    }
+
+    /// The network address with host bits cleared
+    pub fn network(&self) -> Cidr {
+        let addr = match self.addr {
+            IpAddr::V4(v4) => {
+                let mask = if self.prefix == 0 { 0 } else { u32::MAX << (32 - self.prefix) };
+                IpAddr::V4(Ipv4Addr::from(u32::from(v4) & mask))
+            }
+            IpAddr::V6(v6) => {
+                let mask = if self.prefix == 0 { 0 } else { u128::MAX << (128 - self.prefix) };
+                IpAddr::V6(Ipv6Addr::from(u128::from(v6) & mask))
+            }
+        };
+        Cidr { addr, prefix: self.prefix }
+    }
+
+    /// Whether `ip` falls inside this prefix
+    pub fn contains(&self, ip: &IpAddr) -> bool {
+        match (self.addr, ip) {
+            (IpAddr::V4(_), IpAddr::V4(_)) | (IpAddr::V6(_), IpAddr::V6(_)) => {
+                Cidr { addr: *ip, prefix: self.prefix }.network().addr == self.network().addr
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether the two prefixes share any address
+    pub fn overlaps(&self, other: &Cidr) -> bool {
+        let shorter = if self.prefix <= other.prefix { self } else { other };
+        let longer = if shorter == self { other } else { self };
+        shorter.contains(&longer.addr)
+    }
+}
+
+impl fmt::Display for Cidr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.addr, self.prefix)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cidr(value: &str) -> Cidr {
+        Cidr::parse(value).unwrap()
+    }
+
+    fn ip(value: &str) -> IpAddr {
+        value.parse().unwrap()
+    }
+
+    #[test]
+    fn network_clears_host_bits() {
+        assert_eq!(cidr("192.168.1.77/24").network(), cidr("192.168.1.0/24"));
+        assert_eq!(cidr("10.1.2.3/0").network(), cidr("0.0.0.0/0"));
+        assert_eq!(cidr("10.1.2.3/32").network(), cidr("10.1.2.3/32"));
+        assert_eq!(cidr("2001:db8:1::5/48").network(), cidr("2001:db8:1::/48"));
+        assert_eq!(cidr("2001:db8:1::5/0").network(), cidr("::/0"));
+        assert_eq!(cidr("2001:db8::5/128").network(), cidr("2001:db8::5/128"));
+    }
+
+    #[test]
+    fn zero_prefix_contains_its_whole_family() {
+        assert!(cidr("0.0.0.0/0").contains(&ip("203.0.113.5")));
+        assert!(cidr("0.0.0.0/0").contains(&ip("255.255.255.255")));
+        assert!(cidr("::/0").contains(&ip("2001:db8::1")));
+        assert!(!cidr("0.0.0.0/0").contains(&ip("::1")));
+        assert!(!cidr("::/0").contains(&ip("10.0.0.1")));
+    }
+
+    #[test]
+    fn host_prefix_contains_only_itself() {
+        assert!(cidr("10.0.0.1/32").contains(&ip("10.0.0.1")));
+        assert!(!cidr("10.0.0.1/32").contains(&ip("10.0.0.2")));
+        assert!(cidr("2001:db8::1/128").contains(&ip("2001:db8::1")));
+        assert!(!cidr("2001:db8::1/128").contains(&ip("2001:db8::2")));
+        assert!(cidr("10.0.0.1").is_host());
+        assert!(cidr("2001:db8::1").is_host());
+    }
+
+    #[test]
+    fn overlaps_in_both_directions() {
+        assert!(cidr("0.0.0.0/0").overlaps(&cidr("10.0.0.1/32")));
+        assert!(cidr("10.0.0.1/32").overlaps(&cidr("0.0.0.0/0")));
+        assert!(cidr("10.0.0.0/16").overlaps(&cidr("10.0.3.0/24")));
+        assert!(cidr("10.0.0.1/32").overlaps(&cidr("10.0.0.1/32")));
+        assert!(!cidr("10.0.0.1/32").overlaps(&cidr("10.0.0.2/32")));
+        assert!(!cidr("10.0.0.0/24").overlaps(&cidr("10.0.1.0/24")));
+        assert!(cidr("::/0").overlaps(&cidr("2001:db8::1/128")));
+        assert!(!cidr("2001:db8::1/128").overlaps(&cidr("2001:db8::2/128")));
+    }
+
+    #[test]
+    fn families_never_overlap() {
+        assert!(!cidr("0.0.0.0/0").overlaps(&cidr("::/0")));
+        assert!(!cidr("::/0").overlaps(&cidr("0.0.0.0/0")));
+    }
+}
@@ -0,0 +1,136 @@
+use rand::RngCore;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::wireguard::WgError;
+
+/// How long a confirmation token stays valid
+const TOKEN_TTL: Duration = Duration::from_secs(120);
+
+/// Which safety rules ask for confirmation before a mutation goes through
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConfirmationPolicy {
+    /// Deleting the only remaining peer of a config
+    pub delete_last_peer: bool,
+    /// Bringing down a tunnel that other running tunnels depend on
+    pub disconnect_dependency: bool,
+    /// Bringing down a tunnel that is busy moving data
+    pub disconnect_active_traffic: bool,
+    /// How long traffic is sampled before a disconnect
+    pub active_traffic_window_secs: u64,
+    /// Bytes within the window above which a tunnel counts as busy
+    pub active_traffic_threshold_bytes: u64,
+    /// Acting on a wiredeck:// link opened from outside the app
+    pub deep_links: bool,
+}
+
+impl Default for ConfirmationPolicy {
+    fn default() -> Self {
+        ConfirmationPolicy {
+            delete_last_peer: true,
+            disconnect_dependency: true,
+            disconnect_active_traffic: true,
+            active_traffic_window_secs: 3,
+            active_traffic_threshold_bytes: 512 * 1024,
+            deep_links: true,
+        }
+    }
+}
+
+/// Result of a guarded command: either it ran, or the caller must re-invoke with the token
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum Guarded<T> {
+    Done { result: T },
+    RequiresConfirmation { reason: String, token: String },
+}
+
+struct PendingConfirmation {
+    action: String,
+    target: String,
+    issued: Instant,
+}
+
+static PENDING: Mutex<Option<HashMap<String, PendingConfirmation>>> = Mutex::new(None);
+
+fn new_token() -> String {
+    let mut bytes = [0u8; 16];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Spend `token` if it is an unexpired confirmation for this action and target.
+/// Lets callers skip expensive rule evaluation when the user already confirmed.
+pub fn consume(action: &str, target: &str, token: &str) -> bool {
+    let Ok(mut guard) = PENDING.lock() else {
+        return false;
+    };
+    let Some(pending) = guard.as_mut() else {
+        return false;
+    };
+
+    match pending.get(token) {
+        Some(p) if p.action == action && p.target == target && p.issued.elapsed() < TOKEN_TTL => {
+            pending.remove(token);
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Decide whether a mutation may proceed.
+///
+/// `reasons` are the safety rules that fired for this `action` on `target`. With none, or with
+/// a valid `token` previously issued for the same action and target, returns `Ok(None)` and the
+/// caller goes ahead. Otherwise a fresh one-shot token is issued and returned to hand back.
+pub fn check(
+    action: &str,
+    target: &str,
+    reasons: Vec<String>,
+    token: Option<&str>,
+) -> Result<Option<(String, String)>, WgError> {
+    if reasons.is_empty() {
+        return Ok(None);
+    }
+
+    let mut guard = PENDING
+        .lock()
+        .map_err(|_| WgError::CommandFailed("Confirmation store poisoned".to_string()))?;
+    let pending = guard.get_or_insert_with(HashMap::new);
+    pending.retain(|_, p| p.issued.elapsed() < TOKEN_TTL);
+
+    if let Some(token) = token {
+        if let Some(p) = pending.remove(token) {
+            if p.action == action && p.target == target {
+                return Ok(None);
+            }
+        }
+    }
+
+    let token = new_token();
+    pending.insert(
+        token.clone(),
+        PendingConfirmation {
+            action: action.to_string(),
+            target: target.to_string(),
+            issued: Instant::now(),
+        },
+    );
+
+    Ok(Some((reasons.join(" "), token)))
+}
+
+/// Run `f` unless a safety rule requires confirmation first
+pub fn guard<T, F>(action: &str, target: &str, reasons: Vec<String>, token: Option<&str>, f: F) -> Result<Guarded<T>, WgError>
+where
+    F: FnOnce() -> Result<T, WgError>,
+{
+    match check(action, target, reasons, token)? {
+        Some((reason, token)) => Ok(Guarded::RequiresConfirmation { reason, token }),
+        None => Ok(Guarded::Done { result: f()? }),
+    }
+}
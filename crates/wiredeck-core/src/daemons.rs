@@ -0,0 +1,231 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::elevation::sudo_output;
+use crate::privileged::record_change;
+use crate::trace;
+use crate::wireguard::{bring_up, is_interface_up, WgError};
+
+/// Where system launchd jobs live; wg-quick needs root, so agents can't run it
+const LAUNCH_DAEMONS_DIR: &str = "/Library/LaunchDaemons";
+
+/// How long a detection result is reused; status is polled far more often than daemons change
+const DETECT_TTL: Duration = Duration::from_secs(30);
+
+/// What runs a tunnel outside WireDeck
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ManagerKind {
+    /// A `brew services` entry
+    BrewServices,
+    /// A hand-written launchd daemon
+    Launchd,
+}
+
+/// A service that brings a tunnel up on its own
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExternalManager {
+    pub kind: ManagerKind,
+    /// brew service name or launchd label
+    pub name: String,
+    pub plist: Option<PathBuf>,
+    /// Loaded, so it may bring the tunnel back after WireDeck takes it down
+    pub active: bool,
+}
+
+impl ExternalManager {
+    fn describe(&self) -> String {
+        match self.kind {
+            ManagerKind::BrewServices => format!("brew services ({})", self.name),
+            ManagerKind::Launchd => format!("the launchd daemon {}", self.name),
+        }
+    }
+}
+
+/// Outcome of `take_over_management`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TakeOver {
+    /// The service that was stopped and disabled
+    pub previous: ExternalManager,
+    pub interface_up: bool,
+    /// Stopping the service took the tunnel down and WireDeck brought it back
+    pub brought_up: bool,
+}
+
+/// When a config was last checked, and what was found
+type Detection = (Instant, Option<ExternalManager>);
+
+static DETECTED: Mutex<Option<HashMap<String, Detection>>> = Mutex::new(None);
+
+/// Whether a launchd plist runs wg-quick (or wireguard-go) for this config
+fn plist_runs_config(plist: &str, config_name: &str) -> bool {
+    (plist.contains("wg-quick") || plist.contains("wireguard"))
+        && (plist.contains(&format!("<string>{}</string>", config_name))
+            || plist.contains(&format!("/{}.conf</string>", config_name)))
+}
+
+fn plist_label(plist: &str) -> Option<String> {
+    let rest = &plist[plist.find("<key>Label</key>")? + "<key>Label</key>".len()..];
+    let start = rest.find("<string>")? + "<string>".len();
+    let end = rest[start..].find("</string>")?;
+    Some(rest[start..start + end].trim().to_string())
+}
+
+#[derive(Deserialize)]
+struct BrewService {
+    name: String,
+    status: Option<String>,
+    file: Option<PathBuf>,
+}
+
+fn brew_services(config_name: &str) -> Option<ExternalManager> {
+    let output = trace::output(Command::new("brew").args(["services", "list", "--json"])).ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let services: Vec<BrewService> = serde_json::from_slice(&output.stdout).ok()?;
+    services.into_iter().find_map(|service| {
+        let plist = service.file?;
+        let text = fs::read_to_string(&plist).ok()?;
+        plist_runs_config(&text, config_name).then(|| ExternalManager {
+            kind: ManagerKind::BrewServices,
+            name: service.name,
+            plist: Some(plist),
+            active: service.status.as_deref() == Some("started"),
+        })
+    })
+}
+
+fn launchd_loaded(label: &str) -> bool {
+    trace::output(Command::new("launchctl").args(["print", &format!("system/{}", label)]))
+        .map(|o| o.status.success())
+        .unwrap_or(false)
+}
+
+fn launchd_daemon(config_name: &str) -> Option<ExternalManager> {
+    let mut plists: Vec<PathBuf> = fs::read_dir(LAUNCH_DAEMONS_DIR)
+        .ok()?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "plist"))
+        .collect();
+    plists.sort();
+    plists.into_iter().find_map(|path| {
+        let text = fs::read_to_string(&path).ok()?;
+        if !plist_runs_config(&text, config_name) {
+            return None;
+        }
+        let label = plist_label(&text)
+            .or_else(|| path.file_stem().map(|s| s.to_string_lossy().to_string()))?;
+        Some(ExternalManager {
+            kind: ManagerKind::Launchd,
+            active: launchd_loaded(&label),
+            name: label,
+            plist: Some(path),
+        })
+    })
+}
+
+/// Look for a service outside WireDeck that runs this config, bypassing the cache
+fn detect(config_name: &str) -> Option<ExternalManager> {
+    if !cfg!(target_os = "macos") {
+        return None;
+    }
+    // brew services are launchd daemons too; asking brew first names them the way users know them
+    brew_services(config_name).or_else(|| launchd_daemon(config_name))
+}
+
+/// The service outside WireDeck that runs this tunnel, if any
+pub fn get_external_manager(config_name: &str) -> Option<ExternalManager> {
+    let mut guard = DETECTED.lock().unwrap_or_else(|e| e.into_inner());
+    let cache = guard.get_or_insert_with(HashMap::new);
+    if let Some((at, manager)) = cache.get(config_name) {
+        if at.elapsed() < DETECT_TTL {
+            return manager.clone();
+        }
+    }
+    let manager = detect(config_name);
+    cache.insert(config_name.to_string(), (Instant::now(), manager.clone()));
+    manager
+}
+
+fn forget(config_name: &str) {
+    if let Some(cache) = DETECTED.lock().unwrap_or_else(|e| e.into_inner()).as_mut() {
+        cache.remove(config_name);
+    }
+}
+
+/// Refuse bringing a tunnel up or down while another service manages it; the two would fight
+pub(crate) fn refuse_if_managed(config_name: &str, action: &str) -> Result<(), WgError> {
+    match get_external_manager(config_name) {
+        Some(manager) if manager.active => Err(WgError::Refused(format!(
+            "{} is managed by {}; {} there, or take over management in WireDeck first",
+            config_name,
+            manager.describe(),
+            action
+        ))),
+        _ => Ok(()),
+    }
+}
+
+fn run(args: &[&str], plist: Option<&Path>) -> Result<(), WgError> {
+    // Daemons are root's; a user's own brew services are not
+    let output = if plist.is_some_and(|p| p.starts_with(LAUNCH_DAEMONS_DIR)) {
+        sudo_output(args)?
+    } else {
+        trace::output(Command::new(args[0]).args(&args[1..]))?
+    };
+    if !output.status.success() {
+        return Err(WgError::CommandFailed(String::from_utf8_lossy(&output.stderr).trim().to_string()));
+    }
+    Ok(())
+}
+
+/// Stop and disable the service running a tunnel so WireDeck manages it from now on. The
+/// tunnel stays up: if stopping the service took it down, it is brought back with wg-quick.
+pub fn take_over_management(config_name: &str) -> Result<TakeOver, WgError> {
+    let manager = detect(config_name)
+        .ok_or_else(|| WgError::NotFound(format!("an external manager for {}", config_name)))?;
+    let was_up = is_interface_up(config_name).unwrap_or(false);
+    let plist = manager.plist.as_deref();
+
+    let (commands, revert) = match manager.kind {
+        ManagerKind::BrewServices => {
+            run(&["brew", "services", "stop", &manager.name], plist)?;
+            (
+                vec![format!("brew services stop {}", manager.name)],
+                vec![format!("brew services start {}", manager.name)],
+            )
+        }
+        ManagerKind::Launchd => {
+            let target = format!("system/{}", manager.name);
+            if manager.active {
+                run(&["launchctl", "bootout", &target], plist)?;
+            }
+            // Keeps it from loading again at boot
+            run(&["launchctl", "disable", &target], plist)?;
+            let path = plist.map(|p| p.display().to_string()).unwrap_or_default();
+            (
+                vec![format!("launchctl bootout {}", target), format!("launchctl disable {}", target)],
+                vec![format!("launchctl enable {}", target), format!("launchctl bootstrap system {}", path)],
+            )
+        }
+    };
+    record_change("take_over_management", Some(config_name), commands, revert);
+    forget(config_name);
+
+    let mut brought_up = false;
+    if was_up && !is_interface_up(config_name).unwrap_or(false) {
+        bring_up(config_name)?;
+        brought_up = true;
+    }
+    Ok(TakeOver {
+        previous: manager,
+        interface_up: is_interface_up(config_name).unwrap_or(false),
+        brought_up,
+    })
+}
@@ -0,0 +1,138 @@
+use serde::{Deserialize, Serialize};
+
+use crate::confirm::{self, Guarded};
+use crate::remote::import_remote_config;
+use crate::settings;
+use crate::tunnels::{self, TunnelActivation};
+use crate::wireguard::{validate_config_name, WgConfig, WgError};
+
+/// URL scheme registered for WireDeck
+pub const SCHEME: &str = "wiredeck";
+
+/// What a `wiredeck://` link asks the app to do
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum DeepLink {
+    /// `wiredeck://import?url=https://...&name=office`
+    Import { url: String, config_name: String },
+    /// `wiredeck://connect/<name>`
+    Connect { config_name: String },
+}
+
+/// Result of acting on a deep link
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum DeepLinkOutcome {
+    Import { config: Box<WgConfig> },
+    Connect { activation: TunnelActivation },
+}
+
+impl DeepLink {
+    /// Sentence shown to the user before the link is acted on
+    pub fn describe(&self) -> String {
+        match self {
+            DeepLink::Import { url, config_name } => {
+                format!("A link wants to import {} as {}.", url, config_name)
+            }
+            DeepLink::Connect { config_name } => format!("A link wants to bring up {}.", config_name),
+        }
+    }
+}
+
+fn percent_decode(value: &str) -> Result<String, WgError> {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' => {
+                let hex = value
+                    .get(i + 1..i + 3)
+                    .and_then(|h| u8::from_str_radix(h, 16).ok())
+                    .ok_or_else(|| WgError::Parse(format!("Bad percent-encoding in '{}'", value)))?;
+                decoded.push(hex);
+                i += 3;
+            }
+            b'+' => {
+                decoded.push(b' ');
+                i += 1;
+            }
+            b => {
+                decoded.push(b);
+                i += 1;
+            }
+        }
+    }
+
+    String::from_utf8(decoded).map_err(|_| WgError::Parse(format!("'{}' is not valid UTF-8", value)))
+}
+
+fn query_param(query: &str, name: &str) -> Result<Option<String>, WgError> {
+    for pair in query.split('&') {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        if key == name {
+            return percent_decode(value).map(Some);
+        }
+    }
+    Ok(None)
+}
+
+/// Config name from the last path segment of a URL, e.g. `.../office.conf` -> `office`
+fn name_from_url(url: &str) -> Option<String> {
+    let path = url.split(['?', '#']).next()?;
+    let segment = path.rsplit('/').next()?;
+    let name = segment.strip_suffix(".conf").unwrap_or(segment);
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+/// Parse a `wiredeck://` link, validating everything it names
+pub fn parse_deep_link(link: &str) -> Result<DeepLink, WgError> {
+    let rest = link
+        .strip_prefix(SCHEME)
+        .and_then(|r| r.strip_prefix("://"))
+        .ok_or_else(|| WgError::Parse(format!("Not a {}:// link", SCHEME)))?;
+    let (path, query) = rest.split_once('?').unwrap_or((rest, ""));
+    let path = path.trim_end_matches('/');
+
+    if path == "import" {
+        let url = query_param(query, "url")?
+            .ok_or_else(|| WgError::Parse("Import link has no url".to_string()))?;
+        if !url.starts_with("https://") {
+            return Err(WgError::Parse("Import links must point at an https:// URL".to_string()));
+        }
+        let config_name = match query_param(query, "name")? {
+            Some(name) => name,
+            None => name_from_url(&url).ok_or_else(|| WgError::Parse("Import link has no name".to_string()))?,
+        };
+        validate_config_name(&config_name)?;
+        return Ok(DeepLink::Import { url, config_name });
+    }
+
+    if let Some(name) = path.strip_prefix("connect/") {
+        let config_name = percent_decode(name)?;
+        validate_config_name(&config_name)?;
+        return Ok(DeepLink::Connect { config_name });
+    }
+
+    Err(WgError::Parse(format!("Unsupported link '{}'", link)))
+}
+
+/// Act on a deep link, asking for confirmation first unless policy turns that off
+pub fn run_deep_link(link: &str, token: Option<&str>) -> Result<Guarded<DeepLinkOutcome>, WgError> {
+    let deep_link = parse_deep_link(link)?;
+
+    let mut reasons = Vec::new();
+    if settings::load_settings()?.confirmations.deep_links {
+        reasons.push(deep_link.describe());
+    }
+
+    confirm::guard("deep_link", link, reasons, token, || match &deep_link {
+        DeepLink::Import { url, config_name } => Ok(DeepLinkOutcome::Import {
+            config: Box::new(import_remote_config(config_name, url)?),
+        }),
+        DeepLink::Connect { config_name } => Ok(DeepLinkOutcome::Connect {
+            activation: tunnels::connect(config_name)?,
+        }),
+    })
+}
@@ -0,0 +1,265 @@
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::guests::render_cloud_init;
+use crate::store::{self, now_secs};
+use crate::wireguard::{parse_config, require_private_key, WgError};
+
+const HOSTS_FILE: &str = "deployed_hosts.json";
+
+/// DigitalOcean assigns the public address a little after creation
+const ADDRESS_WAIT: Duration = Duration::from_secs(120);
+const ADDRESS_POLL: Duration = Duration::from_secs(5);
+
+const HETZNER_API: &str = "https://api.hetzner.cloud/v1";
+const DIGITALOCEAN_API: &str = "https://api.digitalocean.com/v2";
+
+/// Where a VPS is created
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CloudProvider {
+    Hetzner,
+    DigitalOcean,
+}
+
+impl CloudProvider {
+    /// Smallest plan, location and image that run an exit node comfortably
+    fn defaults(self) -> (&'static str, &'static str, &'static str) {
+        match self {
+            CloudProvider::Hetzner => ("cx22", "fsn1", "ubuntu-24.04"),
+            CloudProvider::DigitalOcean => ("s-1vcpu-512mb-10gb", "fra1", "ubuntu-24-04-x64"),
+        }
+    }
+}
+
+/// What to create; the token is used for this request only and never stored
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeployRequest {
+    pub provider: CloudProvider,
+    pub api_token: String,
+    /// Config the VPS runs, usually a server config with a ListenPort
+    pub config_name: String,
+    /// Server hostname, `wiredeck-<config>` by default
+    pub host_name: Option<String>,
+    /// Provider location or region slug, e.g. "nbg1" or "ams3"
+    pub region: Option<String>,
+    /// Provider plan, e.g. "cx22" or "s-1vcpu-1gb"
+    pub size: Option<String>,
+}
+
+/// A VPS WireDeck created, registered so the app knows where the config runs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeployedHost {
+    pub id: String,
+    pub provider: CloudProvider,
+    /// The provider's id for the server or droplet
+    pub server_id: u64,
+    pub host_name: String,
+    pub config_name: String,
+    pub region: String,
+    pub ipv4: Option<String>,
+    pub created_at: u64,
+}
+
+fn load_hosts() -> Result<Vec<DeployedHost>, WgError> {
+    store::load(HOSTS_FILE)
+}
+
+fn save_hosts(hosts: &[DeployedHost]) -> Result<(), WgError> {
+    store::save(HOSTS_FILE, &hosts)
+}
+
+fn random_id() -> String {
+    let mut bytes = [0u8; 8];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Providers accept letters, digits and dashes in server names
+fn validate_host_name(name: &str) -> Result<(), WgError> {
+    let valid = !name.is_empty()
+        && name.len() <= 63
+        && !name.starts_with('-')
+        && !name.ends_with('-')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-');
+    if !valid {
+        return Err(WgError::Parse(format!("{} isn't a valid host name", name)));
+    }
+    Ok(())
+}
+
+/// Send a provider API request, turning error responses into their message
+fn call(method: &str, url: &str, token: &str, body: Option<Value>) -> Result<Value, WgError> {
+    let request = ureq::request(method, url)
+        .timeout(Duration::from_secs(30))
+        .set("Authorization", &format!("Bearer {}", token))
+        .set("Content-Type", "application/json");
+    let response = match body {
+        Some(body) => request.send_string(&body.to_string()),
+        None => request.call(),
+    };
+
+    match response {
+        Ok(response) => {
+            let text = response.into_string()?;
+            serde_json::from_str(&text).map_err(|e| WgError::Parse(format!("Unexpected reply from {}: {}", url, e)))
+        }
+        Err(ureq::Error::Status(code, response)) => {
+            let text = response.into_string().unwrap_or_default();
+            // Hetzner: {"error": {"message"}}, DigitalOcean: {"message"}
+            let message = serde_json::from_str::<Value>(&text)
+                .ok()
+                .and_then(|v| {
+                    v.pointer("/error/message")
+                        .or_else(|| v.get("message"))
+                        .and_then(Value::as_str)
+                        .map(str::to_string)
+                })
+                .unwrap_or(text);
+            Err(WgError::CommandFailed(format!("{} returned {}: {}", url, code, message)))
+        }
+        Err(e) => Err(WgError::CommandFailed(format!("Failed to reach {}: {}", url, e))),
+    }
+}
+
+fn create_hetzner(
+    request: &DeployRequest,
+    host_name: &str,
+    region: &str,
+    size: &str,
+    image: &str,
+    user_data: &str,
+) -> Result<(u64, Option<String>), WgError> {
+    let body = json!({
+        "name": host_name,
+        "server_type": size,
+        "location": region,
+        "image": image,
+        "user_data": user_data,
+    });
+    let reply = call("POST", &format!("{}/servers", HETZNER_API), &request.api_token, Some(body))?;
+
+    let server_id = reply
+        .pointer("/server/id")
+        .and_then(Value::as_u64)
+        .ok_or_else(|| WgError::Parse("Hetzner didn't return a server id".to_string()))?;
+    let ipv4 = reply
+        .pointer("/server/public_net/ipv4/ip")
+        .and_then(Value::as_str)
+        .map(str::to_string);
+    Ok((server_id, ipv4))
+}
+
+fn droplet_public_ipv4(droplet: &Value) -> Option<String> {
+    droplet
+        .pointer("/networks/v4")
+        .and_then(Value::as_array)?
+        .iter()
+        .find(|n| n.get("type").and_then(Value::as_str) == Some("public"))
+        .and_then(|n| n.get("ip_address").and_then(Value::as_str))
+        .map(str::to_string)
+}
+
+fn create_digitalocean(
+    request: &DeployRequest,
+    host_name: &str,
+    region: &str,
+    size: &str,
+    image: &str,
+    user_data: &str,
+) -> Result<(u64, Option<String>), WgError> {
+    let body = json!({
+        "name": host_name,
+        "region": region,
+        "size": size,
+        "image": image,
+        "user_data": user_data,
+    });
+    let reply = call("POST", &format!("{}/droplets", DIGITALOCEAN_API), &request.api_token, Some(body))?;
+    let droplet_id = reply
+        .pointer("/droplet/id")
+        .and_then(Value::as_u64)
+        .ok_or_else(|| WgError::Parse("DigitalOcean didn't return a droplet id".to_string()))?;
+
+    let started = Instant::now();
+    while started.elapsed() < ADDRESS_WAIT {
+        thread::sleep(ADDRESS_POLL);
+        let url = format!("{}/droplets/{}", DIGITALOCEAN_API, droplet_id);
+        // A failed poll is retried; bailing out here would lose track of a droplet that exists
+        let Ok(reply) = call("GET", &url, &request.api_token, None) else {
+            continue;
+        };
+        if let Some(ip) = reply.get("droplet").and_then(droplet_public_ipv4) {
+            return Ok((droplet_id, Some(ip)));
+        }
+    }
+    // The droplet exists either way; register it without an address rather than lose track of it
+    Ok((droplet_id, None))
+}
+
+/// Create a VPS that installs WireGuard and brings up `config_name` on first boot,
+/// then register it as a deployed host
+pub fn deploy_exit_node(request: &DeployRequest) -> Result<DeployedHost, WgError> {
+    let config = parse_config(&request.config_name)?;
+    require_private_key(&config)?;
+    if config.interface.listen_port.is_none() {
+        return Err(WgError::Parse(format!(
+            "{} has no ListenPort; an exit node has to accept connections",
+            config.name
+        )));
+    }
+    if request.api_token.trim().is_empty() {
+        return Err(WgError::Parse("An API token is required".to_string()));
+    }
+
+    let host_name = request
+        .host_name
+        .clone()
+        .unwrap_or_else(|| format!("wiredeck-{}", config.name.replace(['_', '.', '=', '+'], "-")));
+    validate_host_name(&host_name)?;
+
+    let (default_size, default_region, image) = request.provider.defaults();
+    let region = request.region.as_deref().unwrap_or(default_region);
+    let size = request.size.as_deref().unwrap_or(default_size);
+    let user_data = render_cloud_init(&config);
+
+    let (server_id, ipv4) = match request.provider {
+        CloudProvider::Hetzner => create_hetzner(request, &host_name, region, size, image, &user_data)?,
+        CloudProvider::DigitalOcean => create_digitalocean(request, &host_name, region, size, image, &user_data)?,
+    };
+
+    let host = DeployedHost {
+        id: random_id(),
+        provider: request.provider,
+        server_id,
+        host_name,
+        config_name: config.name,
+        region: region.to_string(),
+        ipv4,
+        created_at: now_secs(),
+    };
+    let mut hosts = load_hosts()?;
+    hosts.push(host.clone());
+    save_hosts(&hosts)?;
+
+    Ok(host)
+}
+
+/// Hosts created with `deploy_exit_node`
+pub fn list_deployed_hosts() -> Result<Vec<DeployedHost>, WgError> {
+    load_hosts()
+}
+
+/// Drop a host from the registry; the VPS itself keeps running until deleted at the provider
+pub fn forget_deployed_host(id: &str) -> Result<(), WgError> {
+    let mut hosts = load_hosts()?;
+    let before = hosts.len();
+    hosts.retain(|h| h.id != id);
+    if hosts.len() == before {
+        return Err(WgError::NotFound(format!("deployed host {}", id)));
+    }
+    save_hosts(&hosts)
+}
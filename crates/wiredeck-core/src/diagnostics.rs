@@ -0,0 +1,57 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// How serious a finding is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+/// One result of a check, with an optional command or action that fixes it
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Finding {
+    pub severity: Severity,
+    pub message: String,
+    pub fix: Option<String>,
+    /// Path of the config field it is about, as in the config's JSON, e.g. `peers[0].allowed_ips`
+    #[serde(default)]
+    pub field: Option<String>,
+}
+
+impl Finding {
+    pub fn info(message: impl Into<String>) -> Self {
+        Finding {
+            severity: Severity::Info,
+            message: message.into(),
+            fix: None,
+            field: None,
+        }
+    }
+
+    pub fn warning(message: impl Into<String>, fix: Option<String>) -> Self {
+        Finding {
+            severity: Severity::Warning,
+            message: message.into(),
+            fix,
+            field: None,
+        }
+    }
+
+    pub fn error(message: impl Into<String>, fix: Option<String>) -> Self {
+        Finding {
+            severity: Severity::Error,
+            message: message.into(),
+            fix,
+            field: None,
+        }
+    }
+
+    /// Point the finding at a config field, so an editor can show it next to that field
+    pub fn with_field(mut self, field: impl Into<String>) -> Self {
+        self.field = Some(field.into());
+        self
+    }
+}
@@ -0,0 +1,132 @@
+use serde::{Deserialize, Serialize};
+use std::ffi::{OsStr, OsString};
+use std::io::{self, Write};
+use std::process::{Command, Output, Stdio};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+pub use zeroize::Zeroizing;
+
+use crate::trace;
+
+/// After the user dismisses a prompt, privileged commands fail as before for this long
+/// instead of asking again on every status poll
+const DECLINE_COOLDOWN: Duration = Duration::from_secs(60);
+
+/// Asks the user for their password, saying why; `None` when they cancel or nobody answers
+pub type PasswordPrompt = dyn Fn(&str) -> Option<Zeroizing<String>> + Send + Sync;
+
+static PROMPT: Mutex<Option<Arc<PasswordPrompt>>> = Mutex::new(None);
+
+/// Held while a prompt is open so concurrent commands wait for one answer instead of each asking
+static PROMPTING: Mutex<Option<Instant>> = Mutex::new(None);
+
+/// Whether privileged commands can run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Elevation {
+    /// Without asking: as root, through NOPASSWD or with cached credentials. Always on Windows,
+    /// where it only shows once a command fails.
+    Ready,
+    /// sudo wants the password, which the installed prompt will ask for
+    NeedsPassword,
+    /// sudo is missing, or wants a password with nobody to ask
+    Unavailable,
+}
+
+/// Find out whether privileged commands would run, without prompting
+pub fn check_elevation() -> Elevation {
+    if cfg!(windows) {
+        return Elevation::Ready;
+    }
+    match sudo_non_interactive(&[OsString::from("true")]) {
+        Ok(output) if output.status.success() => Elevation::Ready,
+        Ok(output) if needs_password(&output) && PROMPT.lock().unwrap().is_some() => Elevation::NeedsPassword,
+        _ => Elevation::Unavailable,
+    }
+}
+
+/// Install the prompt used when sudo needs a password. Without one, such commands just fail.
+pub fn set_password_prompt(prompt: Arc<PasswordPrompt>) {
+    *PROMPT.lock().unwrap() = Some(prompt);
+}
+
+fn needs_password(output: &Output) -> bool {
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    stderr.contains("a password is required") || stderr.contains("a terminal is required")
+}
+
+fn sudo_non_interactive(args: &[OsString]) -> io::Result<Output> {
+    trace::output(Command::new("sudo").arg("-n").args(args).stdin(Stdio::null()))
+}
+
+/// Run `sudo -S`, handing it the password on stdin. The copy written to the pipe is wiped after.
+fn sudo_with_password(args: &[OsString], password: &str) -> io::Result<Output> {
+    let started = Instant::now();
+    let mut command = Command::new("sudo");
+    command
+        .args(["-S", "-p", ""])
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    let mut child = command.spawn()?;
+
+    let mut line = Zeroizing::new(Vec::with_capacity(password.len() + 1));
+    line.extend_from_slice(password.as_bytes());
+    line.push(b'\n');
+    if let Some(mut stdin) = child.stdin.take() {
+        // sudo may exit before reading (e.g. cached credentials); the output says what happened
+        let _ = stdin.write_all(&line);
+    }
+
+    let output = child.wait_with_output();
+    trace::record(&command, output.as_ref().ok().map(|o| o.status), started);
+    output
+}
+
+/// Run a command as root.
+///
+/// `sudo -n` is tried first so NOPASSWD rules and cached credentials work silently. When sudo
+/// wants a password, it is asked for through the installed prompt and fed to `sudo -S`.
+/// On Windows the command runs as WireDeck's own (elevated) user.
+pub fn sudo_output<I, S>(args: I) -> io::Result<Output>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    let args: Vec<OsString> = args.into_iter().map(|a| a.as_ref().to_os_string()).collect();
+    if cfg!(windows) {
+        // There's no sudo; managing tunnel services needs WireDeck itself to run as Administrator
+        let (program, rest) = args
+            .split_first()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no command to run"))?;
+        return trace::output(Command::new(program).args(rest));
+    }
+    let output = sudo_non_interactive(&args)?;
+    if !needs_password(&output) {
+        return Ok(output);
+    }
+
+    let Some(prompt) = PROMPT.lock().unwrap().clone() else {
+        return Ok(output);
+    };
+    let mut declined_at = PROMPTING.lock().unwrap();
+    if declined_at.is_some_and(|at| at.elapsed() < DECLINE_COOLDOWN) {
+        return Ok(output);
+    }
+
+    // Another command may have been answered while this one waited for the lock
+    let retry = sudo_non_interactive(&args)?;
+    if !needs_password(&retry) {
+        return Ok(retry);
+    }
+
+    let program = args.first().map(|a| a.to_string_lossy().to_string()).unwrap_or_default();
+    let Some(password) = prompt(&format!("WireDeck needs administrator rights to run {}", program)) else {
+        *declined_at = Some(Instant::now());
+        return Ok(retry);
+    };
+    *declined_at = None;
+    sudo_with_password(&args, &password)
+}
@@ -0,0 +1,359 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::net::{IpAddr, Ipv6Addr, SocketAddr, ToSocketAddrs};
+
+use crate::elevation::sudo_output;
+use crate::locks::with_config_lock;
+use crate::metadata;
+use crate::privileged::record_change;
+use crate::settings;
+use crate::wireguard::{
+    check_config_for_save, get_peer_status, get_real_interface_name, list_configs, parse_config, save_config, WgError,
+};
+
+/// Address family a peer endpoint should be resolved to
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AddressFamily {
+    /// Use whatever the resolver returns, honoring the global IPv6 preference
+    #[default]
+    Auto,
+    Ipv4,
+    Ipv6,
+}
+
+/// A peer endpoint split into host and port
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Endpoint {
+    pub host: String,
+    pub port: u16,
+}
+
+impl Endpoint {
+    /// Parse `host:port`, `a.b.c.d:port` or `[v6]:port`
+    pub fn parse(value: &str) -> Result<Self, WgError> {
+        let value = value.trim();
+
+        let (host, port) = if let Some(rest) = value.strip_prefix('[') {
+            let (host, port) = rest
+                .split_once("]:")
+                .ok_or_else(|| WgError::Parse(format!("Invalid endpoint '{}': expected [address]:port", value)))?;
+            host.parse::<Ipv6Addr>()
+                .map_err(|_| WgError::Parse(format!("Invalid IPv6 address in endpoint '{}'", value)))?;
+            (host, port)
+        } else {
+            let (host, port) = value
+                .rsplit_once(':')
+                .ok_or_else(|| WgError::Parse(format!("Endpoint '{}' is missing a port", value)))?;
+            if host.contains(':') {
+                return Err(WgError::Parse(format!(
+                    "IPv6 endpoint '{}' must be written as [address]:port",
+                    value
+                )));
+            }
+            if !is_valid_host(host) {
+                return Err(WgError::Parse(format!("Invalid host in endpoint '{}'", value)));
+            }
+            (host, port)
+        };
+
+        let port: u16 = port
+            .parse()
+            .ok()
+            .filter(|p| *p != 0)
+            .ok_or_else(|| WgError::Parse(format!("Invalid port in endpoint '{}'", value)))?;
+
+        Ok(Endpoint {
+            host: host.to_string(),
+            port,
+        })
+    }
+
+    /// Literal IP address of the host, if it isn't a hostname
+    pub fn ip(&self) -> Option<IpAddr> {
+        self.host.parse().ok()
+    }
+}
+
+impl fmt::Display for Endpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.ip() {
+            Some(IpAddr::V6(_)) => write!(f, "[{}]:{}", self.host, self.port),
+            _ => write!(f, "{}:{}", self.host, self.port),
+        }
+    }
+}
+
+fn is_valid_host(host: &str) -> bool {
+    !host.is_empty()
+        && host.len() <= 253
+        && host.split('.').all(|label| {
+            !label.is_empty()
+                && label.len() <= 63
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        })
+}
+
+/// Check that an endpoint string is well-formed
+pub fn validate_endpoint(value: &str) -> Result<(), WgError> {
+    Endpoint::parse(value).map(|_| ())
+}
+
+/// Canonical form of an endpoint for writing back to a config; unparseable values are kept verbatim
+pub fn format_endpoint(value: &str) -> String {
+    Endpoint::parse(value)
+        .map(|e| e.to_string())
+        .unwrap_or_else(|_| value.to_string())
+}
+
+/// Result of resolving a peer endpoint according to its family preference
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResolvedEndpoint {
+    pub public_key: String,
+    pub endpoint: String,
+    pub family: AddressFamily,
+    pub address: String,
+    pub candidates: Vec<String>,
+}
+
+/// Resolve an endpoint and pick the address matching the family preference
+pub fn resolve_endpoint(endpoint: &str, family: AddressFamily, prefer_ipv6: bool) -> Result<(SocketAddr, Vec<SocketAddr>), WgError> {
+    let parsed = Endpoint::parse(endpoint)?;
+    let candidates: Vec<SocketAddr> = (parsed.host.as_str(), parsed.port)
+        .to_socket_addrs()
+        .map_err(|e| WgError::CommandFailed(format!("Failed to resolve {}: {}", parsed.host, e)))?
+        .collect();
+
+    let chosen = match family {
+        AddressFamily::Ipv4 => candidates.iter().find(|a| a.is_ipv4()),
+        AddressFamily::Ipv6 => candidates.iter().find(|a| a.is_ipv6()),
+        AddressFamily::Auto if prefer_ipv6 => candidates
+            .iter()
+            .find(|a| a.is_ipv6())
+            .or_else(|| candidates.first()),
+        AddressFamily::Auto => candidates
+            .iter()
+            .find(|a| a.is_ipv4())
+            .or_else(|| candidates.first()),
+    };
+
+    let chosen = *chosen.ok_or_else(|| {
+        WgError::NotFound(format!("No {:?} address for {}", family, parsed.host))
+    })?;
+
+    Ok((chosen, candidates))
+}
+
+/// Resolve a single peer's endpoint from a config using its stored preference
+pub fn resolve_peer_endpoint(config_name: &str, public_key: &str) -> Result<ResolvedEndpoint, WgError> {
+    let config = parse_config(config_name)?;
+    let peer = config
+        .peers
+        .iter()
+        .find(|p| p.public_key == public_key)
+        .ok_or_else(|| WgError::NotFound(format!("peer {}", public_key)))?;
+
+    let endpoint = peer
+        .endpoint
+        .as_deref()
+        .ok_or_else(|| WgError::NotFound(format!("endpoint for peer {}", public_key)))?;
+
+    let family = metadata::get_peer_metadata(config_name, public_key)?.endpoint_family;
+    let prefer_ipv6 = settings::load_settings()?.prefer_ipv6_endpoints;
+    let (address, candidates) = resolve_endpoint(endpoint, family, prefer_ipv6)?;
+
+    Ok(ResolvedEndpoint {
+        public_key: public_key.to_string(),
+        endpoint: endpoint.to_string(),
+        family,
+        address: address.to_string(),
+        candidates: candidates.iter().map(|a| a.to_string()).collect(),
+    })
+}
+
+/// Outcome of `apply_endpoint_preferences`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EndpointPreferences {
+    pub applied: Vec<ResolvedEndpoint>,
+    /// Peers whose endpoint couldn't be parsed, resolved or set, by name (or key) with the reason
+    pub skipped: Vec<(String, String)>,
+}
+
+/// Re-point hostname endpoints of a running interface at the preferred address family.
+/// wg itself always takes the first resolver answer, so this is applied after bring-up.
+/// A peer whose endpoint fails is skipped and reported, the others are still applied.
+pub fn apply_endpoint_preferences(config_name: &str) -> Result<EndpointPreferences, WgError> {
+    let config = parse_config(config_name)?;
+    let interface_name = get_real_interface_name(config_name)?;
+    let live = get_peer_status(config_name).unwrap_or_default();
+    let mut report = EndpointPreferences::default();
+
+    for peer in &config.peers {
+        let Some(ref endpoint) = peer.endpoint else {
+            continue;
+        };
+        let label = || peer.name.clone().unwrap_or_else(|| peer.public_key.clone());
+
+        // Literal addresses leave nothing to choose from
+        match Endpoint::parse(endpoint) {
+            Ok(parsed) if parsed.ip().is_some() => continue,
+            Ok(_) => {}
+            Err(e) => {
+                report.skipped.push((label(), e.to_string()));
+                continue;
+            }
+        }
+
+        let resolved = match resolve_peer_endpoint(config_name, &peer.public_key) {
+            Ok(resolved) => resolved,
+            Err(e) => {
+                report.skipped.push((label(), e.to_string()));
+                continue;
+            }
+        };
+
+        let output = sudo_output([
+            "wg",
+            "set",
+            &interface_name,
+            "peer",
+            &peer.public_key,
+            "endpoint",
+            &resolved.address,
+        ])?;
+
+        if !output.status.success() {
+            report.skipped.push((label(), String::from_utf8_lossy(&output.stderr).trim().to_string()));
+            continue;
+        }
+
+        let set_endpoint = |address: &str| {
+            format!("wg set {} peer {} endpoint {}", interface_name, peer.public_key, address)
+        };
+        let previous = live
+            .iter()
+            .find(|s| s.public_key == peer.public_key)
+            .and_then(|s| s.endpoint.as_deref());
+        record_change(
+            "endpoint_override",
+            Some(config_name),
+            vec![set_endpoint(&resolved.address)],
+            previous.map(set_endpoint).into_iter().collect(),
+        );
+
+        report.applied.push(resolved);
+    }
+
+    Ok(report)
+}
+
+/// Which configs an endpoint host replacement applies to
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReplaceScope {
+    Config(String),
+    All,
+}
+
+/// One peer endpoint affected by a host replacement
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EndpointReplacement {
+    pub config_name: String,
+    pub public_key: String,
+    pub peer_name: Option<String>,
+    pub old_endpoint: String,
+    pub new_endpoint: String,
+}
+
+/// Outcome of `replace_endpoint_host`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HostReplacement {
+    /// Endpoints changed, or that would be with `dry_run`
+    pub replacements: Vec<EndpointReplacement>,
+    /// Configs that couldn't be read or saved, with the reason; their endpoints are unchanged
+    pub failed: Vec<(String, String)>,
+}
+
+/// Replace the host part of every matching peer endpoint, keeping ports.
+/// With `dry_run` nothing is written and the returned list is a preview of the changes.
+/// A config that can't be read or saved is reported in `failed` and the others are still done;
+/// a dry run makes the same checks a save would.
+pub fn replace_endpoint_host(
+    old_host: &str,
+    new_host: &str,
+    scope: &ReplaceScope,
+    dry_run: bool,
+) -> Result<HostReplacement, WgError> {
+    // Validate the new host by building an endpoint with it
+    let sample = Endpoint {
+        host: new_host.trim().trim_matches(|c| c == '[' || c == ']').to_string(),
+        port: 51820,
+    };
+    validate_endpoint(&sample.to_string())?;
+
+    let old_host = old_host.trim().trim_matches(|c| c == '[' || c == ']');
+
+    let config_names = match scope {
+        ReplaceScope::Config(name) => vec![name.clone()],
+        ReplaceScope::All => list_configs()?,
+    };
+
+    let mut report = HostReplacement::default();
+    for config_name in config_names {
+        let replaced = with_config_lock(&config_name, || {
+            replace_in_config(&config_name, old_host, &sample.host, dry_run)
+        });
+        match replaced {
+            Ok(replacements) => report.replacements.extend(replacements),
+            Err(e) => report.failed.push((config_name, e.to_string())),
+        }
+    }
+
+    Ok(report)
+}
+
+fn replace_in_config(
+    config_name: &str,
+    old_host: &str,
+    new_host: &str,
+    dry_run: bool,
+) -> Result<Vec<EndpointReplacement>, WgError> {
+    let mut config = parse_config(config_name)?;
+    let mut replacements = Vec::new();
+
+    for peer in config.peers.iter_mut() {
+        let Some(ref current) = peer.endpoint else {
+            continue;
+        };
+        let Ok(mut parsed) = Endpoint::parse(current) else {
+            continue;
+        };
+        if !parsed.host.eq_ignore_ascii_case(old_host) {
+            continue;
+        }
+
+        parsed.host = new_host.to_string();
+        let new_endpoint = parsed.to_string();
+
+        replacements.push(EndpointReplacement {
+            config_name: config_name.to_string(),
+            public_key: peer.public_key.clone(),
+            peer_name: peer.name.clone(),
+            old_endpoint: current.clone(),
+            new_endpoint: new_endpoint.clone(),
+        });
+
+        peer.endpoint = Some(new_endpoint);
+    }
+
+    if !replacements.is_empty() {
+        if dry_run {
+            check_config_for_save(&config)?;
+        } else {
+            save_config(&config)?;
+        }
+    }
+    Ok(replacements)
+}
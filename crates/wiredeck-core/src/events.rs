@@ -0,0 +1,223 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use crate::simulate::{interface_details, peer_diffs, PeerChangeKind};
+use crate::store::{self, now_secs};
+use crate::wireguard::{parse_config_content, WgError};
+
+/// Socket external consumers connect to, in the app data directory
+const EVENT_SOCKET_FILE: &str = "events.sock";
+
+/// What happened to a config file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeOp {
+    Created,
+    Updated,
+}
+
+/// One config mutation, as delivered to the frontend and to socket consumers
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfigChanged {
+    pub op: ChangeOp,
+    pub config_name: String,
+    pub at: u64,
+    /// Public keys of the peers added, removed or changed
+    pub affected_keys: Vec<String>,
+    /// One line per change, e.g. "Added peer alice"; keys are never included
+    pub summary: Vec<String>,
+}
+
+/// Envelope of a socket line, so consumers can tell event types apart as more are added
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+enum Event<'a> {
+    ConfigChanged(&'a ConfigChanged),
+}
+
+/// Receives every config change; installed by the app shell
+pub type ChangeListener = dyn Fn(&ConfigChanged) + Send + Sync;
+
+static LISTENER: Mutex<Option<Arc<ChangeListener>>> = Mutex::new(None);
+
+/// Install the callback config changes are pushed to
+pub fn set_change_listener(listener: Arc<ChangeListener>) {
+    *LISTENER.lock().unwrap_or_else(|e| e.into_inner()) = Some(listener);
+}
+
+/// Whether anyone would receive an event; saves reading the previous file when nobody listens
+pub(crate) fn is_observed() -> bool {
+    LISTENER.lock().unwrap_or_else(|e| e.into_inner()).is_some() || socket::is_listening()
+}
+
+/// Describe a write of `current` over `previous` (`None` for a new file)
+fn describe(config_name: &str, path: &Path, previous: Option<&str>, current: &str) -> ConfigChanged {
+    let mut event = ConfigChanged {
+        op: if previous.is_some() { ChangeOp::Updated } else { ChangeOp::Created },
+        config_name: config_name.to_string(),
+        at: now_secs(),
+        affected_keys: Vec::new(),
+        summary: Vec::new(),
+    };
+
+    let Ok(new) = parse_config_content(config_name, path, current) else {
+        event.summary.push("Written with content that doesn't parse".to_string());
+        return event;
+    };
+    let old = match previous.map(|p| parse_config_content(config_name, path, p)) {
+        None => {
+            event.affected_keys = new.peers.iter().map(|p| p.public_key.clone()).collect();
+            event.summary.push(format!("Created with {} peers", new.peers.len()));
+            return event;
+        }
+        Some(Err(_)) => {
+            event.affected_keys = new.peers.iter().map(|p| p.public_key.clone()).collect();
+            event.summary.push("Replaced a file that didn't parse".to_string());
+            return event;
+        }
+        Some(Ok(old)) => old,
+    };
+
+    event.summary = interface_details(&old, &new);
+    for diff in peer_diffs(&old, &new) {
+        let label = diff.name.clone().unwrap_or_else(|| diff.public_key.chars().take(8).collect());
+        match diff.kind {
+            PeerChangeKind::Added => event.summary.push(format!("Added peer {}", label)),
+            PeerChangeKind::Removed => event.summary.push(format!("Removed peer {}", label)),
+            PeerChangeKind::Changed => {
+                event
+                    .summary
+                    .extend(diff.details.iter().map(|d| format!("Peer {}: {}", label, d)));
+            }
+        }
+        event.affected_keys.push(diff.public_key);
+    }
+    event
+}
+
+/// Publish a config write to the listener and the socket. Writes that change nothing are skipped.
+pub(crate) fn publish_change(config_name: &str, path: &Path, previous: Option<&str>, current: &str) {
+    if previous == Some(current) || !is_observed() {
+        return;
+    }
+    let event = describe(config_name, path, previous, current);
+
+    let listener = LISTENER.lock().unwrap_or_else(|e| e.into_inner()).clone();
+    if let Some(listener) = listener {
+        listener(&event);
+    }
+    if let Ok(line) = serde_json::to_string(&Event::ConfigChanged(&event)) {
+        socket::broadcast(&line);
+    }
+}
+
+/// Start or stop the event socket (one JSON object per line; connect with e.g. `nc -U`).
+/// Returns the socket path while it's listening.
+pub fn set_event_socket(enabled: bool) -> Result<Option<PathBuf>, WgError> {
+    let path = store::get_app_data_dir().join(EVENT_SOCKET_FILE);
+    if enabled {
+        socket::listen(&path)?;
+        Ok(Some(path))
+    } else {
+        socket::stop(&path);
+        Ok(None)
+    }
+}
+
+#[cfg(unix)]
+mod socket {
+    use std::fs;
+    use std::io::Write;
+    use std::os::unix::fs::PermissionsExt;
+    use std::os::unix::net::{UnixListener, UnixStream};
+    use std::path::Path;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Mutex;
+    use std::thread;
+    use std::time::Duration;
+
+    use crate::wireguard::WgError;
+
+    /// A consumer that stops reading is dropped instead of stalling saves
+    const WRITE_TIMEOUT: Duration = Duration::from_secs(1);
+
+    static LISTENING: AtomicBool = AtomicBool::new(false);
+
+    static CLIENTS: Mutex<Vec<UnixStream>> = Mutex::new(Vec::new());
+
+    pub fn is_listening() -> bool {
+        LISTENING.load(Ordering::SeqCst)
+    }
+
+    pub fn listen(path: &Path) -> Result<(), WgError> {
+        if LISTENING.swap(true, Ordering::SeqCst) {
+            return Ok(());
+        }
+        let bound = (|| {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            // A socket left behind by a previous run
+            let _ = fs::remove_file(path);
+            let listener = UnixListener::bind(path)?;
+            // Config changes name peers; only this user may listen in
+            fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+            Ok::<_, std::io::Error>(listener)
+        })();
+        let listener = match bound {
+            Ok(listener) => listener,
+            Err(e) => {
+                LISTENING.store(false, Ordering::SeqCst);
+                return Err(WgError::Io(e));
+            }
+        };
+
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                if !LISTENING.load(Ordering::SeqCst) {
+                    break;
+                }
+                if let Ok(stream) = stream {
+                    let _ = stream.set_write_timeout(Some(WRITE_TIMEOUT));
+                    CLIENTS.lock().unwrap_or_else(|e| e.into_inner()).push(stream);
+                }
+            }
+        });
+        Ok(())
+    }
+
+    pub fn stop(path: &Path) {
+        if !LISTENING.swap(false, Ordering::SeqCst) {
+            return;
+        }
+        // Wake the accept loop so it sees the flag and exits
+        let _ = UnixStream::connect(path);
+        let _ = fs::remove_file(path);
+        CLIENTS.lock().unwrap_or_else(|e| e.into_inner()).clear();
+    }
+
+    pub fn broadcast(line: &str) {
+        let mut clients = CLIENTS.lock().unwrap_or_else(|e| e.into_inner());
+        clients.retain_mut(|client| writeln!(client, "{}", line).is_ok());
+    }
+}
+
+#[cfg(not(unix))]
+mod socket {
+    use std::path::Path;
+
+    use crate::wireguard::WgError;
+
+    pub fn is_listening() -> bool {
+        false
+    }
+
+    pub fn listen(_path: &Path) -> Result<(), WgError> {
+        Err(WgError::Refused("The event socket needs Unix domain sockets".to_string()))
+    }
+
+    pub fn stop(_path: &Path) {}
+
+    pub fn broadcast(_line: &str) {}
+}
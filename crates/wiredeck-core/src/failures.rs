@@ -0,0 +1,198 @@
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::process::{Command, Stdio};
+
+use crate::firewall::{active_firewall, FirewallBackend};
+use crate::store::{self, now_secs};
+use crate::wireguard::WgError;
+
+const FAILURES_FILE: &str = "failures.json";
+
+/// Oldest failures are dropped once the history grows past this
+const MAX_FAILURES: usize = 100;
+
+/// Error text is cut to this many characters; stderr can be long
+const MESSAGE_LIMIT: usize = 500;
+
+/// Facts about the environment that explain most failures
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Environment {
+    pub os: String,
+    /// PATH as the app sees it, which for GUI apps is not the shell's
+    pub path: String,
+    pub wg_found: bool,
+    pub wg_quick_found: bool,
+    /// `sudo -n true` succeeds, i.e. no password prompt is needed
+    pub sudo_noninteractive: bool,
+    /// Host firewall that is switched on, if any
+    #[serde(default)]
+    pub firewall: Option<FirewallBackend>,
+}
+
+/// One failed command
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FailureRecord {
+    pub at: u64,
+    pub command: String,
+    pub kind: String,
+    pub message: String,
+    pub environment: Environment,
+}
+
+/// Identical failures grouped together, with a suggestion when one applies
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FailureSummary {
+    pub command: String,
+    pub kind: String,
+    pub message: String,
+    pub count: usize,
+    pub first_at: u64,
+    pub last_at: u64,
+    /// Environment at the most recent occurrence
+    pub environment: Environment,
+    pub hint: Option<String>,
+}
+
+fn on_path(binary: &str) -> bool {
+    env::var_os("PATH")
+        .map(|path| env::split_paths(&path).any(|dir| dir.join(binary).is_file()))
+        .unwrap_or(false)
+}
+
+fn environment() -> Environment {
+    Environment {
+        os: env::consts::OS.to_string(),
+        path: env::var("PATH").unwrap_or_default(),
+        wg_found: on_path("wg"),
+        wg_quick_found: on_path("wg-quick"),
+        sudo_noninteractive: Command::new("sudo")
+            .args(["-n", "true"])
+            .stdin(Stdio::null())
+            .output()
+            .map(|o| o.status.success())
+            .unwrap_or(false),
+        firewall: active_firewall(),
+    }
+}
+
+fn load_failures() -> Result<Vec<FailureRecord>, WgError> {
+    store::load_global(FAILURES_FILE)
+}
+
+/// Add a failure to the history. Best effort: the original error is what the caller reports.
+pub fn record_failure(command: &str, error: &WgError) {
+    let Ok(mut failures) = load_failures() else {
+        return;
+    };
+
+    failures.push(FailureRecord {
+        at: now_secs(),
+        command: command.to_string(),
+        kind: match error {
+            WgError::WgQuick(failure) => format!("wg_quick.{}", failure.error.kind()),
+            _ => error.kind().to_string(),
+        },
+        message: error.to_string().trim().chars().take(MESSAGE_LIMIT).collect(),
+        environment: environment(),
+    });
+    if failures.len() > MAX_FAILURES {
+        failures.drain(..failures.len() - MAX_FAILURES);
+    }
+    let _ = store::save_global(FAILURES_FILE, &failures);
+}
+
+/// A likely cause and fix for a failure, from its message and environment
+fn hint(record: &FailureRecord) -> Option<String> {
+    let message = record.message.to_lowercase();
+    let env = &record.environment;
+
+    // Recognized wg-quick failures already carry their remediation in the message
+    if record.kind.starts_with("wg_quick.") && record.kind != "wg_quick.other" {
+        return None;
+    }
+
+    let missing_tool = message.contains("no such file") || message.contains("not found") || record.kind == "io";
+    if (!env.wg_found || !env.wg_quick_found) && missing_tool {
+        return Some(
+            "wg or wg-quick isn't on the PATH WireDeck sees (apps started from the Dock or a launcher don't \
+             get your shell's PATH). Install wireguard-tools, or make sure its directory, e.g. /opt/homebrew/bin, \
+             is on the system PATH."
+                .to_string(),
+        );
+    }
+    if !env.sudo_noninteractive
+        && (message.contains("a password is required") || message.contains("a terminal is required"))
+    {
+        return Some(
+            "sudo needed a password and none was entered. Answer the password prompt, or add a NOPASSWD sudoers \
+             rule for wg and wg-quick."
+                .to_string(),
+        );
+    }
+    if message.contains("address already in use") {
+        return Some(
+            "The ListenPort is taken by another tunnel or program. Change it, or turn on ListenPort randomization."
+                .to_string(),
+        );
+    }
+    if message.contains("already exists") && record.command.contains("up") {
+        return Some("The interface is already up; bring it down first or restart it instead.".to_string());
+    }
+    if message.contains("resolvconf") {
+        return Some("wg-quick couldn't set DNS. Install openresolv, or remove DNS from the config.".to_string());
+    }
+    if message.contains("name or service not known")
+        || message.contains("temporary failure in name resolution")
+        || message.contains("nodename nor servname")
+    {
+        return Some("A peer endpoint hostname didn't resolve. Check the name and your network's DNS.".to_string());
+    }
+    if env.firewall.is_some() && (message.contains("handshake") || message.contains("timed out")) {
+        return Some(
+            "The host firewall is on and may be dropping WireGuard's UDP traffic. Check get_firewall_status for \
+             the ListenPort and the commands that open it."
+                .to_string(),
+        );
+    }
+    if message.contains("permission denied") {
+        return Some("WireDeck couldn't access the WireGuard directory or interface; check sudo access.".to_string());
+    }
+    None
+}
+
+/// Recent failures, identical ones grouped, most recent first
+pub fn get_recent_failures() -> Result<Vec<FailureSummary>, WgError> {
+    let mut summaries: Vec<FailureSummary> = Vec::new();
+
+    for record in load_failures()? {
+        let existing = summaries
+            .iter_mut()
+            .find(|s| s.command == record.command && s.kind == record.kind && s.message == record.message);
+        match existing {
+            Some(summary) => {
+                summary.count += 1;
+                summary.last_at = record.at;
+                summary.hint = hint(&record);
+                summary.environment = record.environment;
+            }
+            None => summaries.push(FailureSummary {
+                hint: hint(&record),
+                command: record.command,
+                kind: record.kind,
+                message: record.message,
+                count: 1,
+                first_at: record.at,
+                last_at: record.at,
+                environment: record.environment,
+            }),
+        }
+    }
+
+    summaries.sort_by_key(|s| std::cmp::Reverse(s.last_at));
+    Ok(summaries)
+}
+
+/// Forget all recorded failures
+pub fn clear_failures() -> Result<(), WgError> {
+    store::save_global(FAILURES_FILE, &Vec::<FailureRecord>::new())
+}
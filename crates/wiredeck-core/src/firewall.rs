@@ -0,0 +1,221 @@
+use serde::{Deserialize, Serialize};
+use std::process::Command;
+
+use crate::diagnostics::Finding;
+use crate::trace;
+use crate::wireguard::{get_real_interface_name, is_interface_up, parse_config, WgError};
+
+/// macOS Application Firewall control tool
+const SOCKETFILTERFW: &str = "/usr/libexec/ApplicationFirewall/socketfilterfw";
+
+/// The host firewall in charge on this machine
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FirewallBackend {
+    /// macOS Application Firewall
+    MacosAlf,
+    Ufw,
+    Firewalld,
+}
+
+/// Whether the host firewall is on and might get in the tunnel's way
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FirewallStatus {
+    /// `None` when no known firewall is installed
+    pub backend: Option<FirewallBackend>,
+    pub active: bool,
+    /// `None` when there is no port to check or the rules couldn't be read
+    pub listen_port_allowed: Option<bool>,
+    pub findings: Vec<Finding>,
+}
+
+/// Run a command and return its stdout if it succeeded
+fn run(program: &str, args: &[&str]) -> Option<String> {
+    trace::output(Command::new(program).args(args))
+        .ok()
+        .filter(|o| o.status.success())
+        .map(|o| String::from_utf8_lossy(&o.stdout).to_string())
+}
+
+/// Which firewall is active, without the per-port detail. Cheap enough for the environment check.
+pub fn active_firewall() -> Option<FirewallBackend> {
+    let status = detect(None, None);
+    status.backend.filter(|_| status.active)
+}
+
+/// Inspect the host firewall. With a config, also check that its ListenPort is let in and that
+/// traffic on its interface isn't filtered, and suggest the commands that would open them.
+pub fn get_firewall_status(config_name: Option<&str>) -> Result<FirewallStatus, WgError> {
+    let Some(name) = config_name else {
+        return Ok(detect(None, None));
+    };
+    let config = parse_config(name)?;
+    let interface = if is_interface_up(name).unwrap_or(false) {
+        get_real_interface_name(name).unwrap_or_else(|_| name.to_string())
+    } else {
+        name.to_string()
+    };
+    let port = config.interface.listen_port;
+    Ok(detect(port, Some(&interface)))
+}
+
+/// Firewall findings for a config, for folding into other reports
+pub fn firewall_findings(config_name: &str) -> Vec<Finding> {
+    match get_firewall_status(Some(config_name)) {
+        Ok(status) => status.findings,
+        Err(e) => vec![Finding::warning(format!("Could not check the host firewall: {}", e), None)],
+    }
+}
+
+fn detect(port: Option<u16>, interface: Option<&str>) -> FirewallStatus {
+    if cfg!(target_os = "macos") {
+        check_alf(port)
+    } else if run("firewall-cmd", &["--state"]).is_some_and(|s| s.trim() == "running") {
+        check_firewalld(port, interface)
+    } else {
+        check_ufw(port, interface)
+    }
+}
+
+fn inactive(backend: Option<FirewallBackend>) -> FirewallStatus {
+    FirewallStatus {
+        backend,
+        active: false,
+        listen_port_allowed: None,
+        findings: vec![Finding::info("No host firewall is active.")],
+    }
+}
+
+fn check_alf(port: Option<u16>) -> FirewallStatus {
+    let Some(state) = run(SOCKETFILTERFW, &["--getglobalstate"]) else {
+        return inactive(None);
+    };
+    // "Firewall is enabled. (State = 1)"; state 2 blocks everything but essential services
+    if !state.contains("enabled") {
+        return inactive(Some(FirewallBackend::MacosAlf));
+    }
+    let block_all = state.contains("State = 2")
+        || run(SOCKETFILTERFW, &["--getblockall"]).is_some_and(|s| s.contains("block all non-essential"));
+
+    let mut findings = Vec::new();
+    let allowed = if block_all {
+        findings.push(Finding::error(
+            "The macOS firewall blocks all incoming connections, so peers can't reach this host's ListenPort.",
+            Some(format!("sudo {} --setblockall off", SOCKETFILTERFW)),
+        ));
+        Some(false)
+    } else {
+        findings.push(Finding::info(
+            "The macOS firewall is on. It filters per app, and wireguard-go must be allowed to accept \
+             incoming connections for peers to reach this host.",
+        ));
+        None
+    };
+    if port.is_some() && !block_all {
+        findings.push(Finding::warning(
+            "If peers can't connect, allow wireguard-go through the macOS firewall.",
+            Some(format!(
+                "sudo {fw} --add \"$(which wireguard-go)\" && sudo {fw} --unblockapp \"$(which wireguard-go)\"",
+                fw = SOCKETFILTERFW
+            )),
+        ));
+    }
+
+    FirewallStatus {
+        backend: Some(FirewallBackend::MacosAlf),
+        active: true,
+        listen_port_allowed: port.and(allowed),
+        findings,
+    }
+}
+
+fn check_ufw(port: Option<u16>, interface: Option<&str>) -> FirewallStatus {
+    let Some(status) = run("sudo", &["-n", "ufw", "status"]) else {
+        let installed = run("which", &["ufw"]).is_some();
+        if !installed {
+            return inactive(None);
+        }
+        return FirewallStatus {
+            backend: Some(FirewallBackend::Ufw),
+            active: false,
+            listen_port_allowed: None,
+            findings: vec![Finding::warning("ufw is installed but its status needs sudo to read.", None)],
+        };
+    };
+    if !status.contains("Status: active") {
+        return inactive(Some(FirewallBackend::Ufw));
+    }
+
+    let mut findings = Vec::new();
+    // Rules look like "51820/udp    ALLOW    Anywhere"
+    let allows = |target: &str| {
+        status
+            .lines()
+            .any(|l| l.split_whitespace().next() == Some(target) && l.contains("ALLOW"))
+    };
+    let allowed = port.map(|port| allows(&format!("{}/udp", port)) || allows(&port.to_string()));
+    match (port, allowed) {
+        (Some(port), Some(false)) => findings.push(Finding::error(
+            format!("ufw is active and doesn't allow UDP port {}; peers can't reach this host.", port),
+            Some(format!("sudo ufw allow {}/udp", port)),
+        )),
+        (Some(port), _) => findings.push(Finding::info(format!("ufw allows UDP port {}.", port))),
+        (None, _) => findings.push(Finding::info("ufw is active.")),
+    }
+    if let Some(interface) = interface {
+        if !status.contains(interface) {
+            findings.push(Finding::warning(
+                format!("ufw has no rule for {}; forwarded tunnel traffic may be dropped.", interface),
+                Some(format!("sudo ufw route allow in on {} && sudo ufw allow in on {}", interface, interface)),
+            ));
+        }
+    }
+
+    FirewallStatus {
+        backend: Some(FirewallBackend::Ufw),
+        active: true,
+        listen_port_allowed: allowed,
+        findings,
+    }
+}
+
+fn check_firewalld(port: Option<u16>, interface: Option<&str>) -> FirewallStatus {
+    let mut findings = Vec::new();
+    let ports = run("firewall-cmd", &["--list-ports"]).unwrap_or_default();
+    let services = run("firewall-cmd", &["--list-services"]).unwrap_or_default();
+
+    let allowed = port.map(|port| {
+        ports.split_whitespace().any(|p| p == format!("{}/udp", port))
+            || (port == 51820 && services.split_whitespace().any(|s| s == "wireguard"))
+    });
+    match (port, allowed) {
+        (Some(port), Some(false)) => findings.push(Finding::error(
+            format!("firewalld is running and doesn't open UDP port {}; peers can't reach this host.", port),
+            Some(format!(
+                "sudo firewall-cmd --permanent --add-port={}/udp && sudo firewall-cmd --reload",
+                port
+            )),
+        )),
+        (Some(port), _) => findings.push(Finding::info(format!("firewalld opens UDP port {}.", port))),
+        (None, _) => findings.push(Finding::info("firewalld is running.")),
+    }
+    if let Some(interface) = interface {
+        let zone = run("firewall-cmd", &["--get-zone-of-interface", interface]);
+        if zone.as_deref().map(str::trim) != Some("trusted") {
+            findings.push(Finding::warning(
+                format!("{} isn't in firewalld's trusted zone; traffic from peers may be filtered.", interface),
+                Some(format!(
+                    "sudo firewall-cmd --permanent --zone=trusted --add-interface={} && sudo firewall-cmd --reload",
+                    interface
+                )),
+            ));
+        }
+    }
+
+    FirewallStatus {
+        backend: Some(FirewallBackend::Firewalld),
+        active: true,
+        listen_port_allowed: allowed,
+        findings,
+    }
+}
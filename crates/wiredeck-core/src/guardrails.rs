@@ -0,0 +1,111 @@
+use serde::{Deserialize, Serialize};
+
+use crate::diagnostics::Finding;
+use crate::settings;
+use crate::wireguard::{serialize_config, WgConfig, WgError};
+
+/// Size limits for configs. Writes past the maximums are refused; validation warns past the
+/// warning thresholds, where wg-quick bring-up and the editor start to slow down.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Guardrails {
+    pub max_peers: usize,
+    pub max_config_bytes: usize,
+    pub warn_peers: usize,
+    pub warn_config_bytes: usize,
+}
+
+impl Default for Guardrails {
+    fn default() -> Self {
+        Guardrails {
+            max_peers: 2000,
+            max_config_bytes: 512 * 1024,
+            warn_peers: 500,
+            warn_config_bytes: 128 * 1024,
+        }
+    }
+}
+
+/// The configured guardrails
+pub fn guardrails() -> Guardrails {
+    settings::load_settings().map(|s| s.guardrails).unwrap_or_default()
+}
+
+fn kib(bytes: usize) -> String {
+    format!("{} KiB", bytes.div_ceil(1024))
+}
+
+/// Refuse content that is over the configured maximums, before it is parsed or written
+pub fn check_content_size(config_name: &str, content_len: usize) -> Result<(), WgError> {
+    let limits = guardrails();
+    if content_len > limits.max_config_bytes {
+        return Err(WgError::Refused(format!(
+            "{} would be {}, over the {} limit for a config; raise it in the guardrail settings if this is intended",
+            config_name,
+            kib(content_len),
+            kib(limits.max_config_bytes)
+        )));
+    }
+    Ok(())
+}
+
+/// Refuse a config that is over the configured maximums
+pub fn check_config_limits(config: &WgConfig) -> Result<(), WgError> {
+    let limits = guardrails();
+    if config.peers.len() > limits.max_peers {
+        return Err(WgError::Refused(format!(
+            "{} would have {} peers, over the limit of {}; raise it in the guardrail settings if this is intended",
+            config.name,
+            config.peers.len(),
+            limits.max_peers
+        )));
+    }
+    check_content_size(&config.name, serialize_config(config).len())
+}
+
+/// Findings for a config near or past the guardrails
+pub fn limit_findings(config: &WgConfig) -> Vec<Finding> {
+    let limits = guardrails();
+    let peers = config.peers.len();
+    let size = serialize_config(config).len();
+    let mut findings = Vec::new();
+
+    if peers > limits.max_peers {
+        findings.push(Finding::error(
+            format!(
+                "{} has {} peers, over the limit of {}; it can't be saved as is",
+                config.name, peers, limits.max_peers
+            ),
+            Some("Split the peers across several configs, or raise the limit".to_string()),
+        ));
+    } else if peers > limits.warn_peers {
+        findings.push(Finding::warning(
+            format!("{} has {} peers; bring-up and editing get slow past {}", config.name, peers, limits.warn_peers),
+            Some("Consider splitting the peers across several configs".to_string()),
+        ));
+    }
+
+    if size > limits.max_config_bytes {
+        findings.push(Finding::error(
+            format!(
+                "{} is {}, over the {} limit; it can't be saved as is",
+                config.name,
+                kib(size),
+                kib(limits.max_config_bytes)
+            ),
+            Some("Remove unused peers or comments, or raise the limit".to_string()),
+        ));
+    } else if size > limits.warn_config_bytes {
+        findings.push(Finding::warning(
+            format!(
+                "{} is {}; configs past {} slow down loading and saving",
+                config.name,
+                kib(size),
+                kib(limits.warn_config_bytes)
+            ),
+            None,
+        ));
+    }
+
+    findings
+}
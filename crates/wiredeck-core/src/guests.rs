@@ -0,0 +1,102 @@
+use serde::{Deserialize, Serialize};
+
+use crate::wireguard::{parse_config, serialize_config, WgConfig, WgError};
+
+/// Image whose `/config/wg_confs` layout the compose export follows
+const COMPOSE_IMAGE: &str = "lscr.io/linuxserver/wireguard:latest";
+
+/// A docker-compose project running one tunnel
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ComposeExport {
+    /// Contents of `docker-compose.yml`
+    pub compose: String,
+    /// Where the config goes, relative to the compose file
+    pub config_path: String,
+    pub config: String,
+}
+
+/// Whether the tunnel accepts connections (and likely routes for its peers), not just dials out
+fn is_listening(config: &WgConfig) -> bool {
+    config.interface.listen_port.is_some()
+}
+
+/// Indent every line of `text` for a YAML block scalar
+fn block_scalar(text: &str, indent: usize) -> String {
+    let pad = " ".repeat(indent);
+    text.lines()
+        .map(|line| if line.is_empty() { String::new() } else { format!("{}{}", pad, line) })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// A linuxserver/wireguard style compose file for a config
+pub fn render_docker_compose(config: &WgConfig) -> ComposeExport {
+    let config_path = format!("config/wg_confs/{}.conf", config.name);
+    let mut compose = format!(
+        "# WireDeck export of {name}. Put the config at ./{path} next to this file.\n\
+         services:\n  \
+           wireguard:\n    \
+             image: {image}\n    \
+             container_name: wiredeck-{name}\n    \
+             cap_add:\n      - NET_ADMIN\n      - SYS_MODULE\n    \
+             environment:\n      - PUID=1000\n      - PGID=1000\n      - TZ=Etc/UTC\n    \
+             volumes:\n      - ./config:/config\n      - /lib/modules:/lib/modules\n",
+        name = config.name,
+        path = config_path,
+        image = COMPOSE_IMAGE,
+    );
+    if is_listening(config) {
+        compose.push_str(&format!(
+            "    ports:\n      - \"{port}:{port}/udp\"\n",
+            port = config.interface.listen_port.unwrap_or_default()
+        ));
+    }
+    compose.push_str("    sysctls:\n      - net.ipv4.conf.all.src_valid_mark=1\n");
+    if is_listening(config) {
+        compose.push_str("      - net.ipv4.ip_forward=1\n");
+    }
+    compose.push_str("    restart: unless-stopped\n");
+
+    ComposeExport {
+        compose,
+        config_path,
+        config: serialize_config(config),
+    }
+}
+
+/// A `#cloud-config` document that installs WireGuard and brings the tunnel up at boot
+pub fn render_cloud_init(config: &WgConfig) -> String {
+    let mut doc = format!("#cloud-config\n# WireDeck export of {}\npackage_update: true\npackages:\n  - wireguard\n", config.name);
+    if !config.interface.dns.is_empty() || !config.interface.dns_search.is_empty() {
+        doc.push_str("  # wg-quick sets DNS through resolvconf\n  - openresolv\n");
+    }
+
+    doc.push_str(&format!(
+        "write_files:\n  - path: /etc/wireguard/{}.conf\n    owner: root:root\n    permissions: '0600'\n    content: |\n{}\n",
+        config.name,
+        block_scalar(&serialize_config(config), 6)
+    ));
+    if is_listening(config) {
+        doc.push_str(
+            "  - path: /etc/sysctl.d/99-wiredeck.conf\n    content: |\n      \
+             net.ipv4.ip_forward = 1\n      net.ipv6.conf.all.forwarding = 1\n",
+        );
+    }
+
+    doc.push_str("runcmd:\n");
+    if is_listening(config) {
+        doc.push_str("  - [sysctl, --system]\n");
+    }
+    doc.push_str(&format!("  - [systemctl, enable, --now, wg-quick@{}]\n", config.name));
+    doc
+}
+
+/// Compose project for a saved config
+pub fn export_docker_compose(config_name: &str) -> Result<ComposeExport, WgError> {
+    Ok(render_docker_compose(&parse_config(config_name)?))
+}
+
+/// Cloud-init user data for a saved config
+pub fn export_cloud_init(config_name: &str) -> Result<String, WgError> {
+    Ok(render_cloud_init(&parse_config(config_name)?))
+}
@@ -0,0 +1,183 @@
+use serde::{Deserialize, Serialize};
+
+use crate::settings;
+use crate::wireguard::WgError;
+
+/// Languages generated artifacts (PDF sheets, the self-service portal) can be written in.
+/// PDFs use the base-14 fonts, so only languages Latin-1 covers are offered.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Language {
+    #[default]
+    En,
+    De,
+    Fr,
+    Es,
+}
+
+impl Language {
+    /// BCP 47 tag, for `<html lang>`
+    pub fn tag(self) -> &'static str {
+        match self {
+            Language::En => "en",
+            Language::De => "de",
+            Language::Fr => "fr",
+            Language::Es => "es",
+        }
+    }
+}
+
+/// Every user-facing sentence in generated artifacts. `{name}`-style placeholders are filled with `fill`.
+pub struct Strings {
+    pub sheet_title: &'static str,
+    pub qr_too_large: &'static str,
+    pub setup: &'static str,
+    pub install: &'static str,
+    pub scan_phone: &'static str,
+    pub scan_computer: &'static str,
+    pub type_in: &'static str,
+    pub replace_key: &'static str,
+    pub activate: &'static str,
+    pub tunnel_address: &'static str,
+    pub server: &'static str,
+    pub no_secrets: &'static str,
+    pub has_secrets: &'static str,
+    pub configuration: &'static str,
+    pub portal_intro: &'static str,
+    pub portal_submit: &'static str,
+    pub portal_scan: &'static str,
+    pub portal_download: &'static str,
+    pub portal_checking: &'static str,
+    pub portal_invalid: &'static str,
+    pub portal_unsigned: &'static str,
+    pub portal_failed: &'static str,
+}
+
+const EN: Strings = Strings {
+    sheet_title: "WireGuard tunnel: {name}",
+    qr_too_large: "This config is too large to fit in a QR code.",
+    setup: "Setup",
+    install: "1. Install WireGuard for your device from https://www.wireguard.com/install/",
+    scan_phone: "2. On a phone, tap + and choose \"Scan from QR code\", then scan the code above.",
+    scan_computer: "   On a computer, choose \"Add empty tunnel\" and type in the configuration below.",
+    type_in: "2. Choose \"Add empty tunnel\" and type in the configuration below.",
+    replace_key: "2. Create a tunnel with the settings below and replace {redacted} with your private key.",
+    activate: "3. Name the tunnel \"{name}\" and activate it.",
+    tunnel_address: "Tunnel address: {address}",
+    server: "Server: {servers}",
+    no_secrets: "This sheet contains no private key, so it is safe to store or share.",
+    has_secrets: "This sheet contains a private key. Store it like a password and shred it when done.",
+    configuration: "Configuration",
+    portal_intro: "Enter the access code your administrator gave you to download your WireGuard config.",
+    portal_submit: "Get my config",
+    portal_scan: "Scan the code with the WireGuard app, or download the file and import it.",
+    portal_download: "Download config",
+    portal_checking: "Checking…",
+    portal_invalid: "That access code is not valid (or has been revoked).",
+    portal_unsigned: "This file was not published by your administrator. Do not use it.",
+    portal_failed: "Could not open your config:",
+};
+
+const DE: Strings = Strings {
+    sheet_title: "WireGuard-Tunnel: {name}",
+    qr_too_large: "Diese Konfiguration ist zu groß für einen QR-Code.",
+    setup: "Einrichtung",
+    install: "1. Installiere WireGuard für dein Gerät von https://www.wireguard.com/install/",
+    scan_phone: "2. Auf dem Handy auf + tippen, \"QR-Code scannen\" wählen und den Code oben scannen.",
+    scan_computer: "   Am Computer \"Leeren Tunnel hinzufügen\" wählen und die Konfiguration unten eintippen.",
+    type_in: "2. \"Leeren Tunnel hinzufügen\" wählen und die Konfiguration unten eintippen.",
+    replace_key: "2. Einen Tunnel mit den Einstellungen unten anlegen und {redacted} durch deinen privaten Schlüssel ersetzen.",
+    activate: "3. Den Tunnel \"{name}\" nennen und aktivieren.",
+    tunnel_address: "Tunnel-Adresse: {address}",
+    server: "Server: {servers}",
+    no_secrets: "Dieses Blatt enthält keinen privaten Schlüssel und kann gefahrlos aufbewahrt oder weitergegeben werden.",
+    has_secrets: "Dieses Blatt enthält einen privaten Schlüssel. Bewahre es wie ein Passwort auf und vernichte es danach.",
+    configuration: "Konfiguration",
+    portal_intro: "Gib den Zugangscode ein, den du von deinem Administrator bekommen hast, um deine WireGuard-Konfiguration herunterzuladen.",
+    portal_submit: "Konfiguration abrufen",
+    portal_scan: "Scanne den Code mit der WireGuard-App oder lade die Datei herunter und importiere sie.",
+    portal_download: "Konfiguration herunterladen",
+    portal_checking: "Wird geprüft…",
+    portal_invalid: "Dieser Zugangscode ist ungültig (oder wurde widerrufen).",
+    portal_unsigned: "Diese Datei stammt nicht von deinem Administrator. Bitte nicht verwenden.",
+    portal_failed: "Deine Konfiguration konnte nicht geöffnet werden:",
+};
+
+const FR: Strings = Strings {
+    sheet_title: "Tunnel WireGuard : {name}",
+    qr_too_large: "Cette configuration est trop grande pour tenir dans un code QR.",
+    setup: "Installation",
+    install: "1. Installez WireGuard pour votre appareil depuis https://www.wireguard.com/install/",
+    scan_phone: "2. Sur un téléphone, touchez + et choisissez « Scanner un code QR », puis scannez le code ci-dessus.",
+    scan_computer: "   Sur un ordinateur, choisissez « Ajouter un tunnel vide » et saisissez la configuration ci-dessous.",
+    type_in: "2. Choisissez « Ajouter un tunnel vide » et saisissez la configuration ci-dessous.",
+    replace_key: "2. Créez un tunnel avec les paramètres ci-dessous et remplacez {redacted} par votre clé privée.",
+    activate: "3. Nommez le tunnel « {name} » et activez-le.",
+    tunnel_address: "Adresse du tunnel : {address}",
+    server: "Serveur : {servers}",
+    no_secrets: "Cette fiche ne contient aucune clé privée : vous pouvez la conserver ou la partager sans risque.",
+    has_secrets: "Cette fiche contient une clé privée. Gardez-la comme un mot de passe et détruisez-la ensuite.",
+    configuration: "Configuration",
+    portal_intro: "Saisissez le code d'accès fourni par votre administrateur pour télécharger votre configuration WireGuard.",
+    portal_submit: "Obtenir ma configuration",
+    portal_scan: "Scannez le code avec l'application WireGuard, ou téléchargez le fichier et importez-le.",
+    portal_download: "Télécharger la configuration",
+    portal_checking: "Vérification…",
+    portal_invalid: "Ce code d'accès n'est pas valide (ou a été révoqué).",
+    portal_unsigned: "Ce fichier n'a pas été publié par votre administrateur. Ne l'utilisez pas.",
+    portal_failed: "Impossible d'ouvrir votre configuration :",
+};
+
+const ES: Strings = Strings {
+    sheet_title: "Túnel WireGuard: {name}",
+    qr_too_large: "Esta configuración es demasiado grande para un código QR.",
+    setup: "Instalación",
+    install: "1. Instala WireGuard para tu dispositivo desde https://www.wireguard.com/install/",
+    scan_phone: "2. En el móvil, toca + y elige \"Escanear código QR\"; luego escanea el código de arriba.",
+    scan_computer: "   En un ordenador, elige \"Añadir túnel vacío\" y escribe la configuración de abajo.",
+    type_in: "2. Elige \"Añadir túnel vacío\" y escribe la configuración de abajo.",
+    replace_key: "2. Crea un túnel con los ajustes de abajo y sustituye {redacted} por tu clave privada.",
+    activate: "3. Llama al túnel \"{name}\" y actívalo.",
+    tunnel_address: "Dirección del túnel: {address}",
+    server: "Servidor: {servers}",
+    no_secrets: "Esta hoja no contiene ninguna clave privada, así que puedes guardarla o compartirla sin riesgo.",
+    has_secrets: "Esta hoja contiene una clave privada. Guárdala como una contraseña y destrúyela al terminar.",
+    configuration: "Configuración",
+    portal_intro: "Introduce el código de acceso que te dio tu administrador para descargar tu configuración de WireGuard.",
+    portal_submit: "Obtener mi configuración",
+    portal_scan: "Escanea el código con la app de WireGuard, o descarga el archivo e impórtalo.",
+    portal_download: "Descargar configuración",
+    portal_checking: "Comprobando…",
+    portal_invalid: "Ese código de acceso no es válido (o ha sido revocado).",
+    portal_unsigned: "Este archivo no lo ha publicado tu administrador. No lo uses.",
+    portal_failed: "No se pudo abrir tu configuración:",
+};
+
+/// The string table for a language
+pub fn strings(language: Language) -> &'static Strings {
+    match language {
+        Language::En => &EN,
+        Language::De => &DE,
+        Language::Fr => &FR,
+        Language::Es => &ES,
+    }
+}
+
+/// Replace `{key}` placeholders in a template
+pub fn fill(template: &str, values: &[(&str, &str)]) -> String {
+    values
+        .iter()
+        .fold(template.to_string(), |text, (key, value)| text.replace(&format!("{{{}}}", key), value))
+}
+
+/// Language new artifacts are generated in
+pub fn artifact_language() -> Language {
+    settings::load_settings().map(|s| s.artifact_language).unwrap_or_default()
+}
+
+/// Choose the language for PDF sheets and self-service portals
+pub fn set_artifact_language(language: Language) -> Result<(), WgError> {
+    let mut settings = settings::load_settings()?;
+    settings.artifact_language = language;
+    settings::save_settings(&settings)
+}
@@ -0,0 +1,149 @@
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+
+use crate::store::{self, now_secs};
+use crate::wireguard::WgError;
+
+const IDEMPOTENCY_FILE: &str = "idempotency.json";
+
+/// How long a stored result answers retries with the same key
+pub const IDEMPOTENCY_TTL_SECS: u64 = 24 * 60 * 60;
+
+/// Longest key accepted; UUIDs and ULIDs fit comfortably
+const MAX_KEY_LEN: usize = 128;
+
+/// Result of a call made with an idempotency key
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct StoredResult {
+    command: String,
+    /// Hash of the call's arguments, so a key reused for a different request is refused
+    request_sha256: String,
+    created_at: u64,
+    result: Value,
+}
+
+/// Keys whose call is still running, so a retry racing the original waits its turn
+static IN_FLIGHT: Mutex<Option<HashSet<String>>> = Mutex::new(None);
+
+fn validate_key(key: &str) -> Result<(), WgError> {
+    if key.is_empty() || key.len() > MAX_KEY_LEN || !key.chars().all(|c| c.is_ascii_graphic()) {
+        return Err(WgError::Parse(format!(
+            "Idempotency keys are 1 to {} printable ASCII characters without spaces",
+            MAX_KEY_LEN
+        )));
+    }
+    Ok(())
+}
+
+fn fingerprint(request: &impl Serialize) -> Result<String, WgError> {
+    let json = serde_json::to_string(request).map_err(|e| WgError::Parse(e.to_string()))?;
+    Ok(Sha256::digest(json.as_bytes()).iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+fn load_live() -> Result<HashMap<String, StoredResult>, WgError> {
+    let mut results: HashMap<String, StoredResult> = store::load(IDEMPOTENCY_FILE)?;
+    let now = now_secs();
+    results.retain(|_, r| now.saturating_sub(r.created_at) < IDEMPOTENCY_TTL_SECS);
+    Ok(results)
+}
+
+/// Run a mutating command at most once per idempotency key: a retry with the same key and
+/// arguments gets the stored result instead of running again. Failed calls aren't stored, so
+/// they can be retried. Without a key the command just runs.
+pub fn with_idempotency<T, R>(
+    key: Option<&str>,
+    command: &str,
+    request: &R,
+    f: impl FnOnce() -> Result<T, WgError>,
+) -> Result<T, WgError>
+where
+    T: Serialize + DeserializeOwned,
+    R: Serialize,
+{
+    with_idempotency_if(key, command, request, f, |_| true)
+}
+
+/// `with_idempotency`, storing the result only when `settled` says so, e.g. not for answers
+/// that ask the caller to confirm and call again
+pub fn with_idempotency_if<T, R>(
+    key: Option<&str>,
+    command: &str,
+    request: &R,
+    f: impl FnOnce() -> Result<T, WgError>,
+    settled: impl FnOnce(&T) -> bool,
+) -> Result<T, WgError>
+where
+    T: Serialize + DeserializeOwned,
+    R: Serialize,
+{
+    let Some(key) = key else {
+        return f();
+    };
+    validate_key(key)?;
+    let request_sha256 = fingerprint(request)?;
+
+    // Claim the key before looking for a stored result, so a retry racing the original can't
+    // miss the result and run again
+    {
+        let mut in_flight = IN_FLIGHT.lock().unwrap_or_else(|e| e.into_inner());
+        if !in_flight.get_or_insert_with(HashSet::new).insert(key.to_string()) {
+            return Err(WgError::Refused(format!(
+                "A call with idempotency key {} is still running; retry once it finishes",
+                key
+            )));
+        }
+    }
+    let outcome = run_once(key, command, request_sha256, f, settled);
+    if let Some(in_flight) = IN_FLIGHT.lock().unwrap_or_else(|e| e.into_inner()).as_mut() {
+        in_flight.remove(key);
+    }
+    outcome
+}
+
+fn run_once<T: Serialize + DeserializeOwned>(
+    key: &str,
+    command: &str,
+    request_sha256: String,
+    f: impl FnOnce() -> Result<T, WgError>,
+    settled: impl FnOnce(&T) -> bool,
+) -> Result<T, WgError> {
+    if let Some(stored) = load_live()?.remove(key) {
+        if stored.command != command || stored.request_sha256 != request_sha256 {
+            return Err(WgError::Refused(format!(
+                "Idempotency key {} was already used for a different {} request",
+                key, stored.command
+            )));
+        }
+        return serde_json::from_value(stored.result).map_err(|e| WgError::Parse(e.to_string()));
+    }
+
+    let result = f()?;
+    if settled(&result) {
+        // Best effort: the change already happened, and failing the call now would invite the
+        // very retry the key guards against
+        let _ = store_result(key, command, request_sha256, &result);
+    }
+    Ok(result)
+}
+
+/// Results are stored whole and replayed as they were, private keys of returned configs
+/// included, so replaying never hands back a config that would lose its key when saved. What
+/// keeps them private is `store::save`, which writes the file 0600 in a 0700 folder.
+fn store_result(key: &str, command: &str, request_sha256: String, result: &impl Serialize) -> Result<(), WgError> {
+    let result = serde_json::to_value(result).map_err(|e| WgError::Parse(e.to_string()))?;
+    let mut results = load_live()?;
+    results.insert(
+        key.to_string(),
+        StoredResult {
+            command: command.to_string(),
+            request_sha256,
+            created_at: now_secs(),
+            result,
+        },
+    );
+    store::save(IDEMPOTENCY_FILE, &results)
+}
@@ -0,0 +1,154 @@
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Mutex;
+
+use crate::metadata::METADATA_FILE;
+use crate::metrics::METRICS_FILE;
+use crate::remote::SUBSCRIPTIONS_FILE;
+use crate::schedule::SCHEDULE_FILE;
+use crate::store::{self, now_secs};
+use crate::wireguard::{config_file_exists, content_hash, get_wireguard_dir, read_config_text, WgError};
+
+const IDS_FILE: &str = "config_ids.json";
+
+/// Held while the registry is read and written back, so two threads never mint an id for one name
+static REGISTRY: Mutex<()> = Mutex::new(());
+
+/// The stable identity of a config file, which survives renaming it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfigIdentity {
+    /// UUID (v4) that history and settings are stored under
+    pub id: String,
+    /// File name the config has now, without `.conf`
+    pub name: String,
+    pub created_at: u64,
+    /// Hash of the content WireDeck last wrote or saw, to recognize the file under a new name
+    #[serde(default)]
+    pub content_sha256: Option<String>,
+}
+
+fn new_uuid() -> String {
+    let mut bytes = [0u8; 16];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    format!("{}-{}-{}-{}-{}", &hex[..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..])
+}
+
+fn config_exists(name: &str) -> bool {
+    config_file_exists(&get_wireguard_dir().join(format!("{}.conf", name)))
+}
+
+/// Every known config identity, including those of configs that were deleted since
+pub fn list_config_identities() -> Result<Vec<ConfigIdentity>, WgError> {
+    store::load(IDS_FILE)
+}
+
+/// The UUID of a config, assigned on first use. A config renamed outside WireDeck keeps its id
+/// when its content matches a config whose file is gone.
+pub fn config_id(name: &str) -> Result<String, WgError> {
+    let _registry = REGISTRY.lock().unwrap_or_else(|e| e.into_inner());
+    let mut ids: Vec<ConfigIdentity> = store::load(IDS_FILE)?;
+    if let Some(identity) = ids.iter().find(|i| i.name == name) {
+        return Ok(identity.id.clone());
+    }
+
+    let hash = read_config_text(&get_wireguard_dir().join(format!("{}.conf", name)))
+        .ok()
+        .map(|content| content_hash(&content));
+    let renamed = hash.as_ref().and_then(|hash| {
+        ids.iter_mut()
+            .find(|i| i.content_sha256.as_ref() == Some(hash) && !config_exists(&i.name))
+    });
+    let id = match renamed {
+        Some(identity) => {
+            identity.name = name.to_string();
+            identity.id.clone()
+        }
+        None => {
+            let id = new_uuid();
+            ids.push(ConfigIdentity {
+                id: id.clone(),
+                name: name.to_string(),
+                created_at: now_secs(),
+                content_sha256: hash,
+            });
+            id
+        }
+    };
+    store::save(IDS_FILE, &ids)?;
+    Ok(id)
+}
+
+/// The name a config id belongs to now, if it is known
+pub fn config_name(id: &str) -> Result<Option<String>, WgError> {
+    Ok(list_config_identities()?.into_iter().find(|i| i.id == id).map(|i| i.name))
+}
+
+/// Current names of every known id
+pub(crate) fn names_by_id() -> Result<HashMap<String, String>, WgError> {
+    Ok(list_config_identities()?.into_iter().map(|i| (i.id, i.name)).collect())
+}
+
+/// Remember what a config looks like after WireDeck wrote it, for recognizing a later rename
+pub(crate) fn record_content(name: &str, content: &str) -> Result<(), WgError> {
+    let _registry = REGISTRY.lock().unwrap_or_else(|e| e.into_inner());
+    let mut ids: Vec<ConfigIdentity> = store::load(IDS_FILE)?;
+    let hash = content_hash(content);
+    match ids.iter_mut().find(|i| i.name == name) {
+        Some(identity) if identity.content_sha256.as_ref() == Some(&hash) => return Ok(()),
+        Some(identity) => identity.content_sha256 = Some(hash),
+        None => ids.push(ConfigIdentity {
+            id: new_uuid(),
+            name: name.to_string(),
+            created_at: now_secs(),
+            content_sha256: Some(hash),
+        }),
+    }
+    store::save(IDS_FILE, &ids)
+}
+
+/// Schema v2 for one data directory: peer metadata and metrics are keyed by config id instead of
+/// name, and scheduled changes and remote subscriptions record the id of their config
+pub(crate) fn migrate_to_config_ids(dir: &Path) -> Result<(), WgError> {
+    let mut ids: Vec<ConfigIdentity> = store::load_from(dir.to_path_buf(), IDS_FILE)?;
+    let mut id_for = |name: &str| match ids.iter().find(|i| i.name == name) {
+        Some(identity) => identity.id.clone(),
+        None => {
+            let id = new_uuid();
+            ids.push(ConfigIdentity {
+                id: id.clone(),
+                name: name.to_string(),
+                created_at: now_secs(),
+                content_sha256: None,
+            });
+            id
+        }
+    };
+
+    for file in [METADATA_FILE, METRICS_FILE] {
+        if !dir.join(file).exists() {
+            continue;
+        }
+        let by_name: HashMap<String, Value> = store::load_from(dir.to_path_buf(), file)?;
+        let by_id: HashMap<String, Value> = by_name.into_iter().map(|(name, value)| (id_for(&name), value)).collect();
+        store::save_to(dir.to_path_buf(), file, &by_id)?;
+    }
+    for file in [SCHEDULE_FILE, SUBSCRIPTIONS_FILE] {
+        if !dir.join(file).exists() {
+            continue;
+        }
+        let mut entries: Vec<Map<String, Value>> = store::load_from(dir.to_path_buf(), file)?;
+        for entry in &mut entries {
+            if let Some(name) = entry.get("config_name").and_then(Value::as_str).map(str::to_string) {
+                entry.insert("config_id".to_string(), Value::String(id_for(&name)));
+            }
+        }
+        store::save_to(dir.to_path_buf(), file, &entries)?;
+    }
+    store::save_to(dir.to_path_buf(), IDS_FILE, &ids)
+}
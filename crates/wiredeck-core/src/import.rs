@@ -0,0 +1,207 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::Path;
+
+use crate::guardrails::{check_config_limits, check_content_size};
+use crate::wireguard::{
+    check_managed, config_file_exists, get_wireguard_dir, parse_config, parse_config_content, validate_config_name,
+    write_config_file, WgConfig, WgError,
+};
+
+/// Configs bigger than this are certainly not WireGuard configs (and guard against zip bombs)
+const MAX_CONFIG_BYTES: u64 = 1024 * 1024;
+
+/// What happened to one imported file (or one `.conf` inside a zip)
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum ImportOutcome {
+    Imported { config_name: String },
+    /// A config with this name exists; retry with `import_config_as`
+    Conflict { config_name: String, suggested_name: String },
+    Invalid { error: String },
+}
+
+/// Import result for one source
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ImportResult {
+    /// Path of the file that was imported
+    pub path: String,
+    /// Entry inside a zip archive, if the config came from one
+    pub entry: Option<String>,
+    pub outcome: ImportOutcome,
+}
+
+/// Turn a file name into a usable config name: invalid characters become `-`, cut to 15 chars
+pub fn sanitize_config_name(stem: &str) -> String {
+    let name: String = stem
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || "_=+.-".contains(c) { c } else { '-' })
+        .take(15)
+        .collect();
+    if name.is_empty() {
+        "imported".to_string()
+    } else {
+        name
+    }
+}
+
+fn config_exists(name: &str) -> bool {
+    config_file_exists(&get_wireguard_dir().join(format!("{}.conf", name)))
+}
+
+/// First free `name-2`, `name-3`, … that still fits in an interface name
+fn free_name(name: &str) -> String {
+    (2..)
+        .map(|n| {
+            let suffix = format!("-{}", n);
+            let base: String = name.chars().take(15 - suffix.len()).collect();
+            format!("{}{}", base, suffix)
+        })
+        .find(|candidate| !config_exists(candidate))
+        .unwrap_or_else(|| name.to_string())
+}
+
+fn stem_of(file_name: &str) -> &str {
+    let base = file_name.rsplit(['/', '\\']).next().unwrap_or(file_name);
+    base.strip_suffix(".conf").unwrap_or(base)
+}
+
+/// Validate content and write it as `config_name`
+fn write_imported(config_name: &str, content: &str, overwrite: bool) -> Result<WgConfig, WgError> {
+    validate_config_name(config_name)?;
+    if config_exists(config_name) {
+        if !overwrite {
+            return Err(WgError::Parse(format!("{} already exists", config_name)));
+        }
+        check_managed(&parse_config(config_name)?)?;
+    }
+
+    check_content_size(config_name, content.len())?;
+    let path = get_wireguard_dir().join(format!("{}.conf", config_name));
+    let config = parse_config_content(config_name, &path, content)?;
+    check_config_limits(&config)?;
+    write_config_file(&path, config_name, content)?;
+    Ok(config)
+}
+
+fn import_content(file_name: &str, content: &str) -> ImportOutcome {
+    let config_name = sanitize_config_name(stem_of(file_name));
+
+    if config_exists(&config_name) {
+        // Validate now so the user isn't asked to pick a name for a broken file
+        if let Err(e) = parse_config_content(&config_name, Path::new(file_name), content) {
+            return ImportOutcome::Invalid { error: e.to_string() };
+        }
+        return ImportOutcome::Conflict {
+            suggested_name: free_name(&config_name),
+            config_name,
+        };
+    }
+
+    match write_imported(&config_name, content, false) {
+        Ok(_) => ImportOutcome::Imported { config_name },
+        Err(e) => ImportOutcome::Invalid { error: e.to_string() },
+    }
+}
+
+fn read_limited(reader: impl Read) -> Result<String, WgError> {
+    let mut content = String::new();
+    reader.take(MAX_CONFIG_BYTES + 1).read_to_string(&mut content)?;
+    if content.len() as u64 > MAX_CONFIG_BYTES {
+        return Err(WgError::Parse("File is too large to be a WireGuard config".to_string()));
+    }
+    Ok(content)
+}
+
+fn zip_error(e: zip::result::ZipError) -> WgError {
+    WgError::Parse(format!("Invalid zip archive: {}", e))
+}
+
+/// Names of `.conf` entries in an archive, skipping directories and macOS metadata
+fn zip_config_entries(archive: &mut zip::ZipArchive<File>) -> Vec<String> {
+    (0..archive.len())
+        .filter_map(|i| archive.by_index(i).ok().map(|e| e.name().to_string()))
+        .filter(|name| name.ends_with(".conf") && !name.starts_with("__MACOSX/"))
+        .collect()
+}
+
+/// Read one config out of a `.conf` file or a `.zip` entry
+fn read_source(path: &Path, entry: Option<&str>) -> Result<String, WgError> {
+    match entry {
+        None => read_limited(File::open(path)?),
+        Some(entry) => {
+            let mut archive = zip::ZipArchive::new(File::open(path)?).map_err(zip_error)?;
+            let file = archive.by_name(entry).map_err(zip_error)?;
+            read_limited(file)
+        }
+    }
+}
+
+/// Import a dropped or picked `.conf` or `.zip` file; each config gets its own result
+pub fn import_path(path: &Path) -> Vec<ImportResult> {
+    let path_str = path.to_string_lossy().to_string();
+    let single = |entry: Option<String>, outcome| ImportResult {
+        path: path_str.clone(),
+        entry,
+        outcome,
+    };
+    let invalid = |e: WgError| ImportOutcome::Invalid { error: e.to_string() };
+
+    let extension = path.extension().and_then(|e| e.to_str()).map(|e| e.to_ascii_lowercase());
+    match extension.as_deref() {
+        Some("conf") => {
+            let file_name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+            let outcome = match read_source(path, None) {
+                Ok(content) => import_content(&file_name, &content),
+                Err(e) => invalid(e),
+            };
+            vec![single(None, outcome)]
+        }
+        Some("zip") => {
+            let archive = File::open(path)
+                .map_err(WgError::from)
+                .and_then(|f| zip::ZipArchive::new(f).map_err(zip_error));
+            let mut archive = match archive {
+                Ok(archive) => archive,
+                Err(e) => return vec![single(None, invalid(e))],
+            };
+
+            let entries = zip_config_entries(&mut archive);
+            if entries.is_empty() {
+                return vec![single(None, invalid(WgError::Parse("Archive contains no .conf files".to_string())))];
+            }
+
+            entries
+                .into_iter()
+                .map(|entry| {
+                    let outcome = match archive.by_name(&entry).map_err(zip_error).and_then(read_limited) {
+                        Ok(content) => import_content(&entry, &content),
+                        Err(e) => invalid(e),
+                    };
+                    single(Some(entry), outcome)
+                })
+                .collect()
+        }
+        _ => vec![single(None, invalid(WgError::Parse("Only .conf and .zip files can be imported".to_string())))],
+    }
+}
+
+/// Finish an import that hit a name conflict, under a new name or by overwriting
+pub fn import_config_as(path: &Path, entry: Option<&str>, config_name: &str, overwrite: bool) -> Result<WgConfig, WgError> {
+    if !path.is_file() {
+        return Err(WgError::NotFound(path.to_string_lossy().to_string()));
+    }
+    let content = read_source(path, entry)?;
+    write_imported(config_name, &content, overwrite)
+}
+
+/// Whether a path looks like something `import_path` handles
+pub fn is_importable(path: &Path) -> bool {
+    fs::metadata(path).map(|m| m.is_file()).unwrap_or(false)
+        && path
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| e.eq_ignore_ascii_case("conf") || e.eq_ignore_ascii_case("zip"))
+}
@@ -0,0 +1,155 @@
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::path::PathBuf;
+
+use crate::store;
+use crate::wireguard::WgError;
+
+/// Locked by the running instance; the OS releases the lock when it exits, crashed or not
+const LOCK_FILE: &str = "instance.lock";
+const SOCKET_FILE: &str = "instance.sock";
+
+/// What a launch was asked to do
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Invocation {
+    /// Arguments after the program name: deep links, files to import, flags
+    pub args: Vec<String>,
+    /// Where it was launched, for resolving relative paths
+    pub cwd: Option<PathBuf>,
+}
+
+impl Invocation {
+    /// This process's own invocation
+    pub fn current() -> Self {
+        Invocation {
+            args: env::args().skip(1).collect(),
+            cwd: env::current_dir().ok(),
+        }
+    }
+}
+
+/// The one running instance; later launches forward their invocations to it
+pub struct PrimaryInstance {
+    _lock: File,
+    listener: socket::Listener,
+}
+
+/// Outcome of `claim_instance`
+pub enum Claim {
+    /// No other instance runs; keep this alive for as long as the app does
+    Primary(PrimaryInstance),
+    /// Another instance runs; this launch should quit. `forwarded` is false when its invocation
+    /// couldn't be handed over.
+    Running { forwarded: bool },
+}
+
+/// Become the single running WireDeck, or hand this launch's invocation to the one already
+/// running. Two launches racing each other are settled by the lock file, so at most one ever
+/// touches app data and configs.
+pub fn claim_instance(invocation: &Invocation) -> Result<Claim, WgError> {
+    let dir = store::get_app_data_dir();
+    fs::create_dir_all(&dir)?;
+    let lock = OpenOptions::new().create(true).truncate(false).write(true).open(dir.join(LOCK_FILE))?;
+    match lock.try_lock() {
+        Ok(()) => Ok(Claim::Primary(PrimaryInstance {
+            listener: socket::bind(&dir.join(SOCKET_FILE))?,
+            _lock: lock,
+        })),
+        Err(TryLockError::WouldBlock) => Ok(Claim::Running {
+            forwarded: socket::forward(&dir.join(SOCKET_FILE), invocation).is_ok(),
+        }),
+        Err(TryLockError::Error(e)) => Err(WgError::Io(e)),
+    }
+}
+
+impl PrimaryInstance {
+    /// Hand every invocation forwarded by a later launch to `handle`, until the app quits
+    pub fn serve(self, handle: impl Fn(Invocation)) -> Result<(), WgError> {
+        socket::serve(self.listener, &handle)
+    }
+}
+
+#[cfg(unix)]
+mod socket {
+    use std::fs;
+    use std::io::{BufRead, BufReader, Write};
+    use std::os::unix::fs::PermissionsExt;
+    use std::os::unix::net::{UnixListener, UnixStream};
+    use std::path::Path;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    use super::Invocation;
+    use crate::wireguard::WgError;
+
+    /// How long a second launch keeps trying while the first is still starting up
+    const CONNECT_TIMEOUT: Duration = Duration::from_secs(3);
+
+    /// A client that connects and says nothing doesn't hold up the next one for long
+    const READ_TIMEOUT: Duration = Duration::from_secs(2);
+
+    pub type Listener = UnixListener;
+
+    /// Only called with the lock held, so any socket found is left over from a crash
+    pub fn bind(path: &Path) -> Result<Listener, WgError> {
+        let _ = fs::remove_file(path);
+        let listener = UnixListener::bind(path)?;
+        // Invocations can carry deep links; only this user may send them
+        fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+        Ok(listener)
+    }
+
+    pub fn forward(path: &Path, invocation: &Invocation) -> Result<(), WgError> {
+        let line = serde_json::to_string(invocation).map_err(|e| WgError::Parse(e.to_string()))?;
+        let started = Instant::now();
+        let mut stream = loop {
+            match UnixStream::connect(path) {
+                Ok(stream) => break stream,
+                Err(_) if started.elapsed() < CONNECT_TIMEOUT => thread::sleep(Duration::from_millis(100)),
+                Err(e) => return Err(WgError::Io(e)),
+            }
+        };
+        writeln!(stream, "{}", line)?;
+        Ok(())
+    }
+
+    pub fn serve(listener: Listener, handle: &dyn Fn(Invocation)) -> Result<(), WgError> {
+        for stream in listener.incoming() {
+            let Ok(stream) = stream else {
+                continue;
+            };
+            let _ = stream.set_read_timeout(Some(READ_TIMEOUT));
+            let mut line = String::new();
+            if BufReader::new(stream).read_line(&mut line).is_err() {
+                continue;
+            }
+            if let Ok(invocation) = serde_json::from_str(&line) {
+                handle(invocation);
+            }
+        }
+        Err(WgError::CommandFailed("The instance socket stopped accepting connections".to_string()))
+    }
+}
+
+#[cfg(not(unix))]
+mod socket {
+    use std::path::Path;
+
+    use super::Invocation;
+    use crate::wireguard::WgError;
+
+    pub type Listener = ();
+
+    pub fn bind(_path: &Path) -> Result<Listener, WgError> {
+        Ok(())
+    }
+
+    pub fn forward(_path: &Path, _invocation: &Invocation) -> Result<(), WgError> {
+        Err(WgError::Refused("Forwarding launches needs Unix domain sockets".to_string()))
+    }
+
+    pub fn serve(_listener: Listener, _handle: &dyn Fn(Invocation)) -> Result<(), WgError> {
+        Err(WgError::Refused("Forwarding launches needs Unix domain sockets".to_string()))
+    }
+}
@@ -0,0 +1,197 @@
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::diagnostics::Finding;
+use crate::revoked::check_config_revoked_keys;
+use crate::wireguard::{get_public_key, list_configs, parse_config, WgConfig, WgError};
+
+/// Length of a base64-encoded 32-byte WireGuard key
+const KEY_LEN: usize = 44;
+
+/// A key that failed validation, with the config field it came from
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InvalidKey {
+    /// `PrivateKey`, `PublicKey` or `PresharedKey`
+    pub field: String,
+    /// Name (or `#n` position) of the peer the key belongs to; `None` for the interface
+    pub peer: Option<String>,
+    pub reason: String,
+}
+
+impl fmt::Display for InvalidKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.peer {
+            Some(ref peer) => write!(f, "Invalid {} of peer {}: {}", self.field, peer, self.reason),
+            None => write!(f, "Invalid {}: {}", self.field, self.reason),
+        }
+    }
+}
+
+/// Attribute a key validation error to the field it was found in
+pub(crate) fn invalid_key(field: &str, peer: Option<String>, error: WgError) -> WgError {
+    let reason = match error {
+        WgError::Parse(reason) => reason,
+        other => other.to_string(),
+    };
+    WgError::InvalidKey(InvalidKey {
+        field: field.to_string(),
+        peer,
+        reason,
+    })
+}
+
+/// Strip whitespace and line breaks that sneak into keys from copy-paste
+pub fn normalize_key(key: &str) -> String {
+    key.chars().filter(|c| !c.is_whitespace()).collect()
+}
+
+/// Check that a key is base64 for exactly 32 bytes, returning the normalized key
+pub fn validate_key_format(key: &str) -> Result<String, WgError> {
+    let key = normalize_key(key);
+    if key.is_empty() {
+        return Err(WgError::Parse("Key is empty".to_string()));
+    }
+    if key.len() != KEY_LEN || !key.ends_with('=') {
+        return Err(WgError::Parse(format!(
+            "Key must be {} base64 characters ending in '=', got {}",
+            KEY_LEN,
+            key.len()
+        )));
+    }
+    if let Some(c) = key
+        .chars()
+        .find(|c| !(c.is_ascii_alphanumeric() || matches!(c, '+' | '/' | '=')))
+    {
+        return Err(WgError::Parse(format!("Key contains invalid character '{}'", c)));
+    }
+
+    match base64::engine::general_purpose::STANDARD.decode(&key) {
+        Ok(bytes) if bytes.len() == 32 => Ok(key),
+        Ok(bytes) => Err(WgError::Parse(format!("Key decodes to {} bytes, expected 32", bytes.len()))),
+        Err(e) => Err(WgError::Parse(format!("Key is not valid base64: {}", e))),
+    }
+}
+
+/// Validate a peer public key, returning it normalized.
+/// With a config, also refuse keys that are really private keys: the interface's own, or
+/// one whose derived public key matches a peer of the config.
+pub fn validate_public_key(key: &str, config: Option<&WgConfig>) -> Result<String, WgError> {
+    let key = validate_key_format(key)?;
+
+    let Some(config) = config else {
+        return Ok(key);
+    };
+
+    if config.interface.private_key.as_deref().map(normalize_key).as_deref() == Some(key.as_str()) {
+        return Err(WgError::Parse(format!(
+            "This is the private key of {}; paste the peer's public key instead",
+            config.name
+        )));
+    }
+
+    if let Ok(derived) = get_public_key(&key) {
+        if let Some(peer) = config.peers.iter().find(|p| p.public_key == derived) {
+            let peer_name = peer.name.as_deref().unwrap_or(&peer.public_key);
+            return Err(WgError::Parse(format!(
+                "This looks like the private key of peer {}; paste its public key instead",
+                peer_name
+            )));
+        }
+    }
+
+    Ok(key)
+}
+
+/// Private and derived public keys of every interface WireDeck manages. Reads every config,
+/// through sudo for root-only ones, so load it once when checking many keys.
+pub(crate) fn managed_interface_keys() -> Vec<(String, String, Option<String>)> {
+    list_configs()
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|name| parse_config(&name).ok())
+        .filter_map(|config| {
+            let private_key = normalize_key(config.interface.private_key.as_deref()?);
+            let public_key = get_public_key(&private_key).ok();
+            Some((config.name, private_key, public_key))
+        })
+        .collect()
+}
+
+fn managed_key_conflict(key: &str, managed: &[(String, String, Option<String>)]) -> Option<String> {
+    managed.iter().find_map(|(name, private_key, public_key)| {
+        if private_key == key {
+            Some(format!("This is the private key of interface {}; never put it in a peer", name))
+        } else if public_key.as_deref() == Some(key) {
+            Some(format!("This is the public key of your own interface {}, not of a peer", name))
+        } else {
+            None
+        }
+    })
+}
+
+/// Refuse a peer public key that is one of our own interfaces' private or public keys
+pub fn check_managed_key_conflict(key: &str) -> Result<(), WgError> {
+    check_managed_key_conflict_in(key, &managed_interface_keys())
+}
+
+/// `check_managed_key_conflict` against keys already loaded with `managed_interface_keys`
+pub(crate) fn check_managed_key_conflict_in(
+    key: &str,
+    managed: &[(String, String, Option<String>)],
+) -> Result<(), WgError> {
+    match managed_key_conflict(&normalize_key(key), managed) {
+        Some(message) => Err(WgError::Parse(message)),
+        None => Ok(()),
+    }
+}
+
+/// Refuse to write a config whose keys aren't base64 for 32 bytes. A PrivateKey that isn't
+/// 44 characters or `=`-terminated is taken for a placeholder of a reference config and kept.
+pub fn validate_config_keys(config: &WgConfig) -> Result<(), WgError> {
+    if let Some(ref private_key) = config.interface.private_key {
+        let key = normalize_key(private_key);
+        if key.len() == KEY_LEN || key.ends_with('=') {
+            validate_key_format(&key).map_err(|e| invalid_key("PrivateKey", None, e))?;
+        }
+    }
+    for (index, peer) in config.peers.iter().enumerate() {
+        let label = || Some(peer.name.clone().unwrap_or_else(|| format!("#{}", index + 1)));
+        validate_key_format(&peer.public_key).map_err(|e| invalid_key("PublicKey", label(), e))?;
+        if let Some(ref preshared_key) = peer.preshared_key {
+            validate_key_format(preshared_key).map_err(|e| invalid_key("PresharedKey", label(), e))?;
+        }
+    }
+    Ok(())
+}
+
+/// Key problems across all peers of a config
+pub fn check_config_keys(config: &WgConfig) -> Vec<Finding> {
+    let managed = managed_interface_keys();
+    let mut findings = Vec::new();
+
+    for (index, peer) in config.peers.iter().enumerate() {
+        // The key itself may be a private key, so don't echo it back
+        let label = peer.name.clone().unwrap_or_else(|| format!("#{}", index + 1));
+        let problem = validate_public_key(&peer.public_key, Some(config))
+            .err()
+            .map(|e| e.to_string())
+            .or_else(|| managed_key_conflict(&normalize_key(&peer.public_key), &managed));
+
+        if let Some(problem) = problem {
+            findings.push(Finding::error(
+                format!("Peer {}: {}", label, problem),
+                Some("Replace it with the peer's public key and rotate any exposed private key".to_string()),
+            ));
+        }
+        if let Some(Err(e)) = peer.preshared_key.as_deref().map(validate_key_format) {
+            findings.push(Finding::error(
+                format!("Peer {}: preshared key is invalid: {}", label, e),
+                Some("Generate a new preshared key and set it on both ends".to_string()),
+            ));
+        }
+    }
+
+    findings.extend(check_config_revoked_keys(config));
+    findings
+}
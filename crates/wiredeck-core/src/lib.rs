@@ -0,0 +1,73 @@
+//! WireGuard configuration handling shared by the WireDeck app and other Rust tools.
+//!
+//! Parsing, serialization and validation work on plain files and strings; functions that
+//! touch live interfaces shell out to `wg` / `wg-quick` (through `sudo` where needed).
+
+pub mod addressing;
+pub mod anonymize;
+pub mod api;
+pub mod approuting;
+pub mod backup;
+pub mod cidr;
+pub mod confirm;
+pub mod daemons;
+pub mod deeplink;
+pub mod deploy;
+pub mod diagnostics;
+pub mod elevation;
+pub mod endpoint;
+pub mod events;
+pub mod failures;
+pub mod firewall;
+pub mod guardrails;
+pub mod guests;
+pub mod i18n;
+pub mod idempotency;
+pub mod identity;
+pub mod import;
+pub mod instance;
+pub mod keys;
+pub mod locks;
+pub mod metadata;
+pub mod metrics;
+pub mod migrations;
+pub mod netinfo;
+pub mod operations;
+pub mod pdf;
+pub mod peers;
+pub mod platform;
+pub mod power;
+pub mod preflight;
+pub mod privileged;
+pub mod qos;
+pub mod quarantine;
+pub mod reload;
+pub mod remote;
+pub mod revoked;
+pub mod sandbox;
+pub mod saveconfig;
+pub mod schedule;
+pub mod schema;
+pub mod selfservice;
+pub mod server;
+pub mod services;
+pub mod simulate;
+pub mod settings;
+pub mod snippets;
+pub mod splitdns;
+pub mod status;
+pub mod store;
+pub mod tempaccess;
+pub mod topology;
+pub mod trace;
+pub mod traffic;
+pub mod tune;
+pub mod tunnels;
+pub mod validation;
+pub mod verify;
+pub mod wgquick;
+pub mod wireguard;
+pub mod workspace;
+pub mod x25519;
+
+pub use wireguard::{Interface, Peer, PeerStatus, WgConfig, WgError};
@@ -0,0 +1,21 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// One mutex per config name, created on first use
+static CONFIG_LOCKS: Mutex<Option<HashMap<String, Arc<Mutex<()>>>>> = Mutex::new(None);
+
+/// Run `f` while holding the lock for `config_name`, so read-modify-write cycles coming from
+/// different windows or background tasks don't interleave and lose each other's changes
+pub fn with_config_lock<T>(config_name: &str, f: impl FnOnce() -> T) -> T {
+    let lock = CONFIG_LOCKS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get_or_insert_with(HashMap::new)
+        .entry(config_name.to_string())
+        .or_default()
+        .clone();
+
+    // A panic in another holder doesn't leave the file any less consistent than it was
+    let _guard = lock.lock().unwrap_or_else(|e| e.into_inner());
+    f()
+}
@@ -0,0 +1,214 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::endpoint::AddressFamily;
+use crate::identity::{config_id, names_by_id};
+use crate::platform::Platform;
+use crate::store;
+use crate::wireguard::WgError;
+
+pub(crate) const METADATA_FILE: &str = "metadata.json";
+
+/// Longest attachment value or label accepted
+const MAX_ATTACHMENT_LEN: usize = 2048;
+
+/// What an attachment points at
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AttachmentKind {
+    /// http(s) link, e.g. a ticket or an asset page
+    Url,
+    /// Identifier in an inventory or asset management system
+    InventoryId,
+}
+
+/// A link from a peer to something outside WireDeck
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Attachment {
+    pub kind: AttachmentKind,
+    pub value: String,
+    pub label: Option<String>,
+    pub added_at: u64,
+}
+
+/// An attachment found by `search_peer_attachments`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AttachmentMatch {
+    pub config_name: String,
+    pub public_key: String,
+    pub attachment: Attachment,
+}
+
+/// WireDeck-only information about a peer that has no place in the .conf file
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PeerMetadata {
+    pub endpoint_family: AddressFamily,
+    /// Kind of device, as recorded at enrollment or set by hand
+    pub platform: Option<Platform>,
+    /// Operating system, free-form ("iOS 17", "Debian 12")
+    pub os: Option<String>,
+    /// Tickets, asset pages and inventory IDs for the device behind the peer
+    pub attachments: Vec<Attachment>,
+}
+
+/// Peer metadata keyed by config id, then public key
+type MetadataStore = HashMap<String, HashMap<String, PeerMetadata>>;
+
+/// Get metadata for a peer (defaults if nothing was recorded)
+pub fn get_peer_metadata(config_name: &str, public_key: &str) -> Result<PeerMetadata, WgError> {
+    let store: MetadataStore = store::load(METADATA_FILE)?;
+    Ok(store
+        .get(&config_id(config_name)?)
+        .and_then(|peers| peers.get(public_key))
+        .cloned()
+        .unwrap_or_default())
+}
+
+/// Get metadata for every peer of a config that has any
+pub fn list_peer_metadata(config_name: &str) -> Result<HashMap<String, PeerMetadata>, WgError> {
+    let store: MetadataStore = store::load(METADATA_FILE)?;
+    Ok(store.get(&config_id(config_name)?).cloned().unwrap_or_default())
+}
+
+/// Modify a peer's metadata in place and persist it
+pub fn update_peer_metadata<F>(config_name: &str, public_key: &str, update: F) -> Result<PeerMetadata, WgError>
+where
+    F: FnOnce(&mut PeerMetadata),
+{
+    let mut store: MetadataStore = store::load(METADATA_FILE)?;
+    let entry = store
+        .entry(config_id(config_name)?)
+        .or_default()
+        .entry(public_key.to_string())
+        .or_default();
+    update(entry);
+    let updated = entry.clone();
+    store::save(METADATA_FILE, &store)?;
+    Ok(updated)
+}
+
+/// Forget a peer's metadata, e.g. after the peer was deleted
+pub fn remove_peer_metadata(config_name: &str, public_key: &str) -> Result<(), WgError> {
+    let mut store: MetadataStore = store::load(METADATA_FILE)?;
+    let id = config_id(config_name)?;
+
+    if let Some(peers) = store.get_mut(&id) {
+        if peers.remove(public_key).is_none() {
+            return Ok(());
+        }
+        if peers.is_empty() {
+            store.remove(&id);
+        }
+        store::save(METADATA_FILE, &store)?;
+    }
+
+    Ok(())
+}
+
+/// Check an http(s) URL: a host, no whitespace, nothing after the scheme that isn't printable
+fn validate_url(value: &str) -> Result<(), WgError> {
+    let rest = value
+        .strip_prefix("https://")
+        .or_else(|| value.strip_prefix("http://"))
+        .ok_or_else(|| WgError::Parse(format!("{} is not an http(s) URL", value)))?;
+    let host = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    let host = host.rsplit('@').next().unwrap_or_default();
+    if host.is_empty() || host.starts_with(':') {
+        return Err(WgError::Parse(format!("{} has no host", value)));
+    }
+    Ok(())
+}
+
+/// Trim and check an attachment before it's stored
+fn validate_attachment(
+    kind: AttachmentKind,
+    value: &str,
+    label: Option<&str>,
+) -> Result<(String, Option<String>), WgError> {
+    let value = value.trim();
+    let label = label.map(str::trim).filter(|l| !l.is_empty());
+    if value.is_empty() {
+        return Err(WgError::Parse("Attachment is empty".to_string()));
+    }
+    if value.len() > MAX_ATTACHMENT_LEN || label.is_some_and(|l| l.len() > MAX_ATTACHMENT_LEN) {
+        return Err(WgError::Parse(format!("Attachments are limited to {} characters", MAX_ATTACHMENT_LEN)));
+    }
+    if value.chars().chain(label.unwrap_or_default().chars()).any(char::is_control) {
+        return Err(WgError::Parse("Attachment contains control characters".to_string()));
+    }
+    match kind {
+        AttachmentKind::Url => {
+            if value.chars().any(char::is_whitespace) {
+                return Err(WgError::Parse(format!("{} contains whitespace", value)));
+            }
+            validate_url(value)?;
+        }
+        AttachmentKind::InventoryId => {
+            if value.chars().any(char::is_whitespace) {
+                return Err(WgError::Parse(format!("Inventory ID {} contains whitespace", value)));
+            }
+        }
+    }
+    Ok((value.to_string(), label.map(str::to_string)))
+}
+
+/// Attach a URL or inventory ID to a peer; attaching the same value again only updates its label
+pub fn add_peer_attachment(
+    config_name: &str,
+    public_key: &str,
+    kind: AttachmentKind,
+    value: &str,
+    label: Option<&str>,
+) -> Result<PeerMetadata, WgError> {
+    let (value, label) = validate_attachment(kind, value, label)?;
+    update_peer_metadata(config_name, public_key, |m| {
+        match m.attachments.iter_mut().find(|a| a.kind == kind && a.value == value) {
+            Some(existing) => existing.label = label,
+            None => m.attachments.push(Attachment {
+                kind,
+                value,
+                label,
+                added_at: store::now_secs(),
+            }),
+        }
+    })
+}
+
+/// Remove an attachment from a peer by value
+pub fn remove_peer_attachment(config_name: &str, public_key: &str, value: &str) -> Result<PeerMetadata, WgError> {
+    let value = value.trim();
+    update_peer_metadata(config_name, public_key, |m| m.attachments.retain(|a| a.value != value))
+}
+
+/// Attachments whose value or label contains `query` (case-insensitive), across every config,
+/// e.g. to find the peer of an asset tag or a ticket number
+pub fn search_peer_attachments(query: &str) -> Result<Vec<AttachmentMatch>, WgError> {
+    let query = query.trim().to_lowercase();
+    if query.is_empty() {
+        return Ok(Vec::new());
+    }
+    let store: MetadataStore = store::load(METADATA_FILE)?;
+    let names = names_by_id()?;
+
+    let mut matches: Vec<AttachmentMatch> = store
+        .into_iter()
+        .flat_map(|(id, peers)| {
+            let config_name = names.get(&id).cloned().unwrap_or(id);
+            peers.into_iter().flat_map(move |(public_key, metadata)| {
+                let config_name = config_name.clone();
+                metadata.attachments.into_iter().map(move |attachment| AttachmentMatch {
+                    config_name: config_name.clone(),
+                    public_key: public_key.clone(),
+                    attachment,
+                })
+            })
+        })
+        .filter(|m| {
+            m.attachment.value.to_lowercase().contains(&query)
+                || m.attachment.label.as_deref().is_some_and(|l| l.to_lowercase().contains(&query))
+        })
+        .collect();
+    matches.sort_by(|a, b| (&a.config_name, &a.public_key).cmp(&(&b.config_name, &b.public_key)));
+    Ok(matches)
+}
@@ -0,0 +1,381 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::identity::config_id;
+use crate::power::{PowerSource, PowerState};
+use crate::store::{self, now_secs};
+use crate::wireguard::{get_peer_status, is_interface_up, PeerStatus, WgError};
+
+pub(crate) const METRICS_FILE: &str = "peer_metrics.json";
+
+/// A peer counts as online while its last handshake is younger than this.
+/// WireGuard rejects sessions older than 180s, so a live peer always re-handshakes within it.
+pub const ONLINE_HANDSHAKE_SECS: u64 = 180;
+
+/// Oldest events are dropped once a peer's timeline grows past this
+const MAX_TIMELINE_EVENTS: usize = 500;
+
+/// Oldest endpoint spans are dropped once a peer's history grows past this
+const MAX_ENDPOINT_SPANS: usize = 200;
+
+/// How often the status monitor samples each running tunnel
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PollingPolicy {
+    /// While a window is showing the tunnel's live status
+    pub active_interval_secs: u64,
+    /// While nobody is watching the tunnel
+    pub idle_interval_secs: u64,
+    /// While nobody is watching and the machine runs on battery, or always while throttled
+    pub battery_interval_secs: u64,
+}
+
+impl Default for PollingPolicy {
+    fn default() -> Self {
+        PollingPolicy {
+            active_interval_secs: 2,
+            idle_interval_secs: 15,
+            battery_interval_secs: 60,
+        }
+    }
+}
+
+impl PollingPolicy {
+    /// Sampling interval for a tunnel, given whether it's being watched and the power state
+    pub fn interval(&self, watched: bool, power: &PowerState) -> Duration {
+        let secs = if watched && !power.should_throttle() {
+            self.active_interval_secs
+        } else if power.source == PowerSource::Battery || power.should_throttle() {
+            self.battery_interval_secs
+        } else {
+            self.idle_interval_secs
+        };
+        Duration::from_secs(secs.max(1))
+    }
+}
+
+/// What the status monitor saw for a peer on its last pass
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PeerObservation {
+    pub observed_at: u64,
+    pub endpoint: Option<String>,
+    pub latest_handshake: Option<u64>,
+    pub online: bool,
+    /// Transfer counters, which start over whenever the interface is recreated
+    #[serde(default)]
+    pub transfer_rx: Option<u64>,
+    #[serde(default)]
+    pub transfer_tx: Option<u64>,
+}
+
+/// A discrete change in a peer's state
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum PeerEventKind {
+    FirstSeen { endpoint: Option<String> },
+    EndpointChanged { from: String, to: String },
+    WentOffline { last_handshake: Option<u64> },
+    KeepaliveRestored { offline_secs: Option<u64> },
+}
+
+/// A timeline entry for one peer
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PeerEvent {
+    pub at: u64,
+    #[serde(flatten)]
+    pub kind: PeerEventKind,
+}
+
+/// An event together with the peer it belongs to, as reported by `observe_peers`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PeerEventNotice {
+    pub config_name: String,
+    pub public_key: String,
+    pub event: PeerEvent,
+}
+
+/// A stretch of time during which a peer kept the same endpoint
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EndpointSpan {
+    pub endpoint: String,
+    pub first_seen: u64,
+    pub last_seen: u64,
+}
+
+/// Where a peer's usage is counted from, set by `mark_stats_baseline`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StatsBaseline {
+    pub marked_at: u64,
+    /// Counters that count as zero: their values at the mark, or 0 once the interface restarted
+    pub rx_counter: u64,
+    pub tx_counter: u64,
+    /// Usage counted before the interface last restarted and reset its counters
+    pub carried_rx: u64,
+    pub carried_tx: u64,
+}
+
+/// Bytes a peer moved since its baseline
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StatsUsage {
+    pub since: u64,
+    pub rx_bytes: u64,
+    pub tx_bytes: u64,
+}
+
+/// Everything the metrics store keeps for one peer
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PeerMetrics {
+    pub first_seen: Option<u64>,
+    pub last: Option<PeerObservation>,
+    pub timeline: Vec<PeerEvent>,
+    /// Endpoints in the order they were seen; one going back and forth shows up as alternating spans
+    pub endpoints: Vec<EndpointSpan>,
+    pub baseline: Option<StatsBaseline>,
+}
+
+/// Extend the current endpoint span, or start a new one when the endpoint moved
+fn record_endpoint(metrics: &mut PeerMetrics, observation: &PeerObservation) {
+    let Some(ref endpoint) = observation.endpoint else {
+        return;
+    };
+    match metrics.endpoints.last_mut() {
+        Some(span) if &span.endpoint == endpoint => span.last_seen = observation.observed_at,
+        _ => metrics.endpoints.push(EndpointSpan {
+            endpoint: endpoint.clone(),
+            first_seen: observation.observed_at,
+            last_seen: observation.observed_at,
+        }),
+    }
+    let overflow = metrics.endpoints.len().saturating_sub(MAX_ENDPOINT_SPANS);
+    metrics.endpoints.drain(..overflow);
+}
+
+/// Move the usage so far into the baseline when the counters went down since `last`, which means
+/// the interface was recreated in between
+fn carry_over_reset(baseline: &mut StatsBaseline, last: Option<&PeerObservation>, rx: u64, tx: u64) {
+    let Some((last_rx, last_tx)) = last
+        .filter(|l| l.observed_at >= baseline.marked_at)
+        .and_then(|l| Some((l.transfer_rx?, l.transfer_tx?)))
+    else {
+        return;
+    };
+    if rx < last_rx || tx < last_tx {
+        baseline.carried_rx += last_rx.saturating_sub(baseline.rx_counter);
+        baseline.carried_tx += last_tx.saturating_sub(baseline.tx_counter);
+        baseline.rx_counter = 0;
+        baseline.tx_counter = 0;
+    }
+}
+
+fn counter(value: &Option<String>) -> Option<u64> {
+    value.as_deref().and_then(|v| v.parse().ok())
+}
+
+/// Peer metrics keyed by config id, then public key
+type MetricsStore = HashMap<String, HashMap<String, PeerMetrics>>;
+
+fn observe(status: &PeerStatus, now: u64) -> PeerObservation {
+    let latest_handshake = status.latest_handshake.as_deref().and_then(|h| h.parse().ok());
+    PeerObservation {
+        observed_at: now,
+        endpoint: status.endpoint.clone(),
+        latest_handshake,
+        online: latest_handshake
+            .map(|h: u64| now.saturating_sub(h) < ONLINE_HANDSHAKE_SECS)
+            .unwrap_or(false),
+        transfer_rx: counter(&status.transfer_rx),
+        transfer_tx: counter(&status.transfer_tx),
+    }
+}
+
+/// Compare a new observation against what was stored and derive events
+fn derive_events(metrics: &PeerMetrics, current: &PeerObservation) -> Vec<PeerEventKind> {
+    let mut events = Vec::new();
+
+    if metrics.first_seen.is_none() {
+        if current.latest_handshake.is_some() {
+            events.push(PeerEventKind::FirstSeen {
+                endpoint: current.endpoint.clone(),
+            });
+        }
+        return events;
+    }
+
+    let Some(last) = &metrics.last else {
+        return events;
+    };
+
+    if let (Some(from), Some(to)) = (&last.endpoint, &current.endpoint) {
+        if from != to {
+            events.push(PeerEventKind::EndpointChanged {
+                from: from.clone(),
+                to: to.clone(),
+            });
+        }
+    }
+
+    if last.online && !current.online {
+        events.push(PeerEventKind::WentOffline {
+            last_handshake: current.latest_handshake,
+        });
+    } else if !last.online && current.online {
+        let offline_since = metrics
+            .timeline
+            .iter()
+            .rev()
+            .find(|e| matches!(e.kind, PeerEventKind::WentOffline { .. }))
+            .map(|e| e.at);
+        events.push(PeerEventKind::KeepaliveRestored {
+            offline_secs: offline_since.map(|since| current.observed_at.saturating_sub(since)),
+        });
+    }
+
+    events
+}
+
+/// Sample a running tunnel's peers, record any state changes and return the new events
+pub fn observe_peers(config_name: &str) -> Result<Vec<PeerEventNotice>, WgError> {
+    let statuses = get_peer_status(config_name)?;
+    let now = now_secs();
+
+    let mut store: MetricsStore = store::load(METRICS_FILE)?;
+    let peers = store.entry(config_id(config_name)?).or_default();
+    let mut notices = Vec::new();
+
+    for status in &statuses {
+        let current = observe(status, now);
+        let metrics = peers.entry(status.public_key.clone()).or_default();
+
+        for kind in derive_events(metrics, &current) {
+            if matches!(kind, PeerEventKind::FirstSeen { .. }) {
+                metrics.first_seen = Some(now);
+            }
+            let event = PeerEvent { at: now, kind };
+            metrics.timeline.push(event.clone());
+            notices.push(PeerEventNotice {
+                config_name: config_name.to_string(),
+                public_key: status.public_key.clone(),
+                event,
+            });
+        }
+
+        let overflow = metrics.timeline.len().saturating_sub(MAX_TIMELINE_EVENTS);
+        metrics.timeline.drain(..overflow);
+        record_endpoint(metrics, &current);
+        if let (Some(baseline), Some(rx), Some(tx)) =
+            (metrics.baseline.as_mut(), current.transfer_rx, current.transfer_tx)
+        {
+            carry_over_reset(baseline, metrics.last.as_ref(), rx, tx);
+        }
+        metrics.last = Some(current);
+    }
+
+    store::save(METRICS_FILE, &store)?;
+    Ok(notices)
+}
+
+/// Events recorded for a peer, oldest first
+pub fn get_peer_timeline(config_name: &str, public_key: &str) -> Result<Vec<PeerEvent>, WgError> {
+    let store: MetricsStore = store::load(METRICS_FILE)?;
+    Ok(store
+        .get(&config_id(config_name)?)
+        .and_then(|peers| peers.get(public_key))
+        .map(|m| m.timeline.clone())
+        .unwrap_or_default())
+}
+
+/// Everything recorded for the peers of a config, by public key
+pub fn list_peer_metrics(config_name: &str) -> Result<HashMap<String, PeerMetrics>, WgError> {
+    let store: MetricsStore = store::load(METRICS_FILE)?;
+    Ok(store.get(&config_id(config_name)?).cloned().unwrap_or_default())
+}
+
+/// Endpoints a peer was seen at, oldest first
+pub fn get_endpoint_history(config_name: &str, public_key: &str) -> Result<Vec<EndpointSpan>, WgError> {
+    let store: MetricsStore = store::load(METRICS_FILE)?;
+    Ok(store
+        .get(&config_id(config_name)?)
+        .and_then(|peers| peers.get(public_key))
+        .map(|m| m.endpoints.clone())
+        .unwrap_or_default())
+}
+
+/// Drop everything recorded for a peer, e.g. when it is deleted
+pub fn remove_peer_metrics(config_name: &str, public_key: &str) -> Result<(), WgError> {
+    let mut store: MetricsStore = store::load(METRICS_FILE)?;
+    let id = config_id(config_name)?;
+    if let Some(peers) = store.get_mut(&id) {
+        if peers.remove(public_key).is_some() {
+            if peers.is_empty() {
+                store.remove(&id);
+            }
+            store::save(METRICS_FILE, &store)?;
+        }
+    }
+    Ok(())
+}
+
+/// A peer's transfer counters right now, or `None` while its tunnel is down
+fn live_counters(config_name: &str, public_key: &str) -> Result<Option<(u64, u64)>, WgError> {
+    if !is_interface_up(config_name)? {
+        return Ok(None);
+    }
+    Ok(get_peer_status(config_name)?
+        .iter()
+        .find(|s| s.public_key == public_key)
+        .map(|s| (counter(&s.transfer_rx).unwrap_or(0), counter(&s.transfer_tx).unwrap_or(0))))
+}
+
+/// Count a peer's usage from now on, e.g. at the start of a billing month. Only the metrics store
+/// changes; the interface's own counters keep running.
+pub fn mark_stats_baseline(config_name: &str, public_key: &str) -> Result<StatsBaseline, WgError> {
+    // A peer that isn't connected yet starts from zero whenever it does
+    let (rx, tx) = live_counters(config_name, public_key)?.unwrap_or((0, 0));
+    let baseline = StatsBaseline {
+        marked_at: now_secs(),
+        rx_counter: rx,
+        tx_counter: tx,
+        carried_rx: 0,
+        carried_tx: 0,
+    };
+
+    let mut store: MetricsStore = store::load(METRICS_FILE)?;
+    store
+        .entry(config_id(config_name)?)
+        .or_default()
+        .entry(public_key.to_string())
+        .or_default()
+        .baseline = Some(baseline.clone());
+    store::save(METRICS_FILE, &store)?;
+    Ok(baseline)
+}
+
+/// What a peer moved since its baseline, or `None` if none was marked. With the tunnel down this
+/// is as of the status monitor's last sample.
+pub fn get_stats_since_baseline(config_name: &str, public_key: &str) -> Result<Option<StatsUsage>, WgError> {
+    let store: MetricsStore = store::load(METRICS_FILE)?;
+    let Some(metrics) = store.get(&config_id(config_name)?).and_then(|peers| peers.get(public_key)) else {
+        return Ok(None);
+    };
+    let Some(mut baseline) = metrics.baseline.clone() else {
+        return Ok(None);
+    };
+
+    let last = metrics.last.as_ref().filter(|l| l.observed_at >= baseline.marked_at);
+    let (rx, tx) = match live_counters(config_name, public_key)? {
+        Some((rx, tx)) => {
+            carry_over_reset(&mut baseline, metrics.last.as_ref(), rx, tx);
+            (rx, tx)
+        }
+        None => last
+            .and_then(|l| Some((l.transfer_rx?, l.transfer_tx?)))
+            .unwrap_or((baseline.rx_counter, baseline.tx_counter)),
+    };
+    Ok(Some(StatsUsage {
+        since: baseline.marked_at,
+        rx_bytes: baseline.carried_rx + rx.saturating_sub(baseline.rx_counter),
+        tx_bytes: baseline.carried_tx + tx.saturating_sub(baseline.tx_counter),
+    }))
+}
@@ -0,0 +1,149 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::identity::migrate_to_config_ids;
+use crate::remote::migrate_to_sha256_hashes;
+use crate::store::{self, get_app_data_dir, now_secs};
+use crate::wireguard::WgError;
+
+const VERSION_FILE: &str = "schema_version.json";
+
+/// Schema version written by this build of WireDeck
+pub const CURRENT_SCHEMA_VERSION: u32 = 3;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct SchemaVersion {
+    version: u32,
+}
+
+/// What `run_migrations` did on startup
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MigrationReport {
+    pub from_version: u32,
+    pub to_version: u32,
+    pub backup_dir: Option<PathBuf>,
+}
+
+/// A step that upgrades one data directory (the root or a workspace) from `version - 1` to `version`
+struct Migration {
+    version: u32,
+    description: &'static str,
+    apply: fn(&Path) -> Result<(), WgError>,
+}
+
+/// Every migration, oldest first. Append new ones and bump `CURRENT_SCHEMA_VERSION`.
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "Baseline: files written before versioning already use the v1 layout",
+        apply: |_| Ok(()),
+    },
+    Migration {
+        version: 2,
+        description: "Key peer metadata, metrics, schedules and remote subscriptions by config id",
+        apply: migrate_to_config_ids,
+    },
+    Migration {
+        version: 3,
+        description: "Compare remote config downloads by SHA-256",
+        apply: migrate_to_sha256_hashes,
+    },
+];
+
+fn json_files(dir: &Path) -> Result<Vec<PathBuf>, WgError> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_file() && path.extension().is_some_and(|e| e == "json") {
+            files.push(path);
+        }
+    }
+    Ok(files)
+}
+
+/// The app data root followed by every workspace directory
+fn data_dirs(root: &Path) -> Result<Vec<PathBuf>, WgError> {
+    let mut dirs = vec![root.to_path_buf()];
+    let workspaces = root.join("workspaces");
+    if workspaces.is_dir() {
+        for entry in fs::read_dir(workspaces)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                dirs.push(path);
+            }
+        }
+    }
+    Ok(dirs)
+}
+
+/// Copy every JSON file of the app data dir, workspaces included, into `backups/schema-v<from>-<timestamp>`
+fn backup_app_data(root: &Path, from_version: u32) -> Result<PathBuf, WgError> {
+    let backup_dir = root
+        .join("backups")
+        .join(format!("schema-v{}-{}", from_version, now_secs()));
+
+    for dir in data_dirs(root)? {
+        let target = backup_dir.join(dir.strip_prefix(root).unwrap_or(&dir));
+        fs::create_dir_all(&target)?;
+        for file in json_files(&dir)? {
+            if let Some(name) = file.file_name() {
+                fs::copy(&file, target.join(name))?;
+            }
+        }
+    }
+
+    Ok(backup_dir)
+}
+
+/// The schema version of the app data on disk; data from before versioning counts as 0
+pub fn schema_version() -> Result<u32, WgError> {
+    let dir = get_app_data_dir();
+    if dir.join(VERSION_FILE).exists() {
+        return Ok(store::load_global::<SchemaVersion>(VERSION_FILE)?.version);
+    }
+
+    let has_data = dir.exists() && !json_files(&dir)?.is_empty();
+    Ok(if has_data { 0 } else { CURRENT_SCHEMA_VERSION })
+}
+
+/// Bring the app data directory up to the current schema, backing it up first.
+/// Data from a newer WireDeck is left untouched and reported as an error.
+pub fn run_migrations() -> Result<MigrationReport, WgError> {
+    let dir = get_app_data_dir();
+    let from_version = schema_version()?;
+
+    if from_version > CURRENT_SCHEMA_VERSION {
+        return Err(WgError::Parse(format!(
+            "App data uses schema v{} but this WireDeck only understands up to v{}; refusing to touch it",
+            from_version, CURRENT_SCHEMA_VERSION
+        )));
+    }
+
+    let mut report = MigrationReport {
+        from_version,
+        to_version: from_version,
+        backup_dir: None,
+    };
+
+    let pending: Vec<&Migration> = MIGRATIONS.iter().filter(|m| m.version > from_version).collect();
+    if !pending.is_empty() {
+        fs::create_dir_all(&dir)?;
+        report.backup_dir = Some(backup_app_data(&dir, from_version)?);
+
+        for migration in pending {
+            for data_dir in data_dirs(&dir)? {
+                (migration.apply)(&data_dir).map_err(|e| {
+                    WgError::Parse(format!("Migration to v{} ({}) failed: {}", migration.version, migration.description, e))
+                })?;
+            }
+            // Record progress per step so a failure resumes from where it stopped
+            store::save_global(VERSION_FILE, &SchemaVersion { version: migration.version })?;
+            report.to_version = migration.version;
+        }
+    } else if !dir.join(VERSION_FILE).exists() {
+        store::save_global(VERSION_FILE, &SchemaVersion { version: CURRENT_SCHEMA_VERSION })?;
+    }
+
+    Ok(report)
+}
@@ -0,0 +1,159 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::process::Command;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::settings;
+use crate::trace;
+use crate::wireguard::{PeerStatus, WgError};
+
+/// Lookups are repeated at most this often per address
+const CACHE_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// Which endpoint lookups may run; each sends the peer's IP to a resolver or Team Cymru, so both are opt-in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EndpointLookup {
+    pub reverse_dns: bool,
+    /// AS number and holder via Team Cymru's DNS whois
+    pub asn: bool,
+}
+
+/// Where a peer's current endpoint address lives on the internet
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct EndpointInfo {
+    pub ip: String,
+    pub reverse_dns: Option<String>,
+    pub asn: Option<u32>,
+    /// AS holder, e.g. "CLOUDFLARENET - Cloudflare, Inc., US"
+    pub network_name: Option<String>,
+    pub country: Option<String>,
+}
+
+type InfoCache = HashMap<(IpAddr, EndpointLookup), (Instant, EndpointInfo)>;
+
+static CACHE: Mutex<Option<InfoCache>> = Mutex::new(None);
+
+/// First answer of a `dig +short` query, unquoted and without the trailing dot
+fn dig_short(args: &[&str]) -> Option<String> {
+    let output = trace::output(Command::new("dig").args(["+short", "+time=2", "+tries=1"]).args(args)).ok()?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let answer = stdout.lines().map(str::trim).find(|l| !l.is_empty())?;
+    Some(answer.trim_matches('"').trim_end_matches('.').to_string())
+}
+
+fn reverse_dns(ip: IpAddr) -> Option<String> {
+    if Command::new("dig").arg("-v").output().is_ok() {
+        return dig_short(&["-x", &ip.to_string()]);
+    }
+
+    // Not every Linux install ships dig; `host` prints "... domain name pointer name."
+    let output = trace::output(Command::new("host").arg(ip.to_string())).ok()?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    stdout
+        .lines()
+        .find_map(|l| l.split_once("domain name pointer "))
+        .map(|(_, name)| name.trim().trim_end_matches('.').to_string())
+}
+
+/// Addresses no public AS announces
+fn is_private(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => {
+            let carrier_nat = v4.octets()[0] == 100 && (v4.octets()[1] & 0xc0) == 64;
+            v4.is_private() || v4.is_loopback() || v4.is_link_local() || carrier_nat
+        }
+        IpAddr::V6(v6) => {
+            let first = v6.segments()[0];
+            v6.is_loopback() || (first & 0xfe00) == 0xfc00 || (first & 0xffc0) == 0xfe80
+        }
+    }
+}
+
+/// `4.3.2.1.origin.asn.cymru.com` style name for an address
+fn cymru_origin_name(ip: IpAddr) -> String {
+    match ip {
+        IpAddr::V4(v4) => {
+            let o = v4.octets();
+            format!("{}.{}.{}.{}.origin.asn.cymru.com", o[3], o[2], o[1], o[0])
+        }
+        IpAddr::V6(v6) => {
+            let nibbles: Vec<String> = v6
+                .octets()
+                .iter()
+                .rev()
+                .flat_map(|b| [b & 0x0f, b >> 4])
+                .map(|n| format!("{:x}", n))
+                .collect();
+            format!("{}.origin6.asn.cymru.com", nibbles.join("."))
+        }
+    }
+}
+
+/// ASN, country and holder name, from "13335 | 1.1.1.0/24 | AU | apnic | 2011-08-11"
+/// and then "13335 | US | arin | 2010-07-14 | CLOUDFLARENET - Cloudflare, Inc., US"
+fn asn_lookup(ip: IpAddr) -> (Option<u32>, Option<String>, Option<String>) {
+    let Some(origin) = dig_short(&["TXT", &cymru_origin_name(ip)]) else {
+        return (None, None, None);
+    };
+    let fields: Vec<&str> = origin.split('|').map(str::trim).collect();
+    // Prefixes announced by several ASes list them all; the first is as good as any
+    let asn: Option<u32> = fields.first().and_then(|f| f.split_whitespace().next()).and_then(|a| a.parse().ok());
+    let country = fields.get(2).filter(|c| !c.is_empty()).map(|c| c.to_string());
+
+    let network_name = asn
+        .and_then(|asn| dig_short(&["TXT", &format!("AS{}.asn.cymru.com", asn)]))
+        .and_then(|holder| holder.split('|').nth(4).map(|n| n.trim().to_string()))
+        .filter(|n| !n.is_empty());
+
+    (asn, country, network_name)
+}
+
+/// Look up an endpoint address, reusing recent answers
+pub fn lookup_endpoint_info(ip: IpAddr, lookup: EndpointLookup) -> EndpointInfo {
+    let key = (ip, lookup);
+    if let Some((at, info)) = CACHE.lock().unwrap().as_ref().and_then(|c| c.get(&key)) {
+        if at.elapsed() < CACHE_TTL {
+            return info.clone();
+        }
+    }
+
+    let (asn, country, network_name) = if lookup.asn && !is_private(ip) {
+        asn_lookup(ip)
+    } else {
+        (None, None, None)
+    };
+    let info = EndpointInfo {
+        ip: ip.to_string(),
+        reverse_dns: if lookup.reverse_dns { reverse_dns(ip) } else { None },
+        asn,
+        network_name,
+        country,
+    };
+
+    CACHE
+        .lock()
+        .unwrap()
+        .get_or_insert_with(HashMap::new)
+        .insert(key, (Instant::now(), info.clone()));
+    info
+}
+
+/// Fill in `endpoint_info` for peers with a live endpoint, as far as the lookup settings allow
+pub fn enrich_peer_statuses(statuses: &mut [PeerStatus]) -> Result<(), WgError> {
+    let lookup = settings::load_settings()?.endpoint_lookup;
+    if !lookup.reverse_dns && !lookup.asn {
+        return Ok(());
+    }
+
+    for status in statuses {
+        let address = status.endpoint.as_deref().and_then(|e| e.parse::<SocketAddr>().ok());
+        if let Some(address) = address {
+            status.endpoint_info = Some(lookup_endpoint_info(address.ip(), lookup));
+        }
+    }
+    Ok(())
+}
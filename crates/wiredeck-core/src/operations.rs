@@ -0,0 +1,132 @@
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use crate::confirm::{self, Guarded};
+use crate::locks::with_config_lock;
+use crate::store::{self, now_secs};
+use crate::wireguard::{
+    content_hash, get_wireguard_dir, parse_config, read_config_text, write_config_file, WgConfig, WgError,
+};
+
+const OPERATIONS_FILE: &str = "operations.json";
+
+/// Snapshots kept per workspace; the oldest go first
+const MAX_SNAPSHOTS: usize = 50;
+
+/// A risky operation and the config as it was just before it ran
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OperationSnapshot {
+    pub id: String,
+    /// Command that made the change, e.g. "import_roster"
+    pub operation: String,
+    pub config_name: String,
+    pub taken_at: u64,
+    pub rolled_back_at: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct StoredSnapshot {
+    #[serde(flatten)]
+    info: OperationSnapshot,
+    /// File contents before the operation
+    before: String,
+    /// Hash of the file right after it, to tell whether anything changed since
+    after_sha256: String,
+}
+
+fn load_snapshots() -> Result<Vec<StoredSnapshot>, WgError> {
+    store::load(OPERATIONS_FILE)
+}
+
+fn save_snapshots(snapshots: &[StoredSnapshot]) -> Result<(), WgError> {
+    store::save(OPERATIONS_FILE, &snapshots)
+}
+
+fn random_id() -> String {
+    let mut bytes = [0u8; 8];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn config_path(config_name: &str) -> PathBuf {
+    get_wireguard_dir().join(format!("{}.conf", config_name))
+}
+
+/// Run a risky operation on a config, keeping a copy of the file from just before it so
+/// `rollback_operation` can undo exactly that change. Nothing is kept if the operation fails or
+/// leaves the file as it was. Callers hold the config lock.
+pub(crate) fn with_snapshot<T>(
+    operation: &str,
+    config_name: &str,
+    f: impl FnOnce() -> Result<T, WgError>,
+) -> Result<T, WgError> {
+    let path = config_path(config_name);
+    let before = read_config_text(&path)?;
+    let result = f()?;
+
+    let after = read_config_text(&path)?;
+    if after != before {
+        let mut snapshots = load_snapshots()?;
+        snapshots.push(StoredSnapshot {
+            info: OperationSnapshot {
+                id: random_id(),
+                operation: operation.to_string(),
+                config_name: config_name.to_string(),
+                taken_at: now_secs(),
+                rolled_back_at: None,
+            },
+            before,
+            after_sha256: content_hash(&after),
+        });
+        let excess = snapshots.len().saturating_sub(MAX_SNAPSHOTS);
+        snapshots.drain(..excess);
+        save_snapshots(&snapshots)?;
+    }
+    Ok(result)
+}
+
+/// Snapshots taken before risky operations, newest first, optionally only for one config
+pub fn list_operation_snapshots(config_name: Option<&str>) -> Result<Vec<OperationSnapshot>, WgError> {
+    Ok(load_snapshots()?
+        .into_iter()
+        .rev()
+        .map(|s| s.info)
+        .filter(|s| config_name.is_none_or(|name| s.config_name == name))
+        .collect())
+}
+
+/// Put a config back the way it was before one operation. Needs confirmation when the file was
+/// changed again afterwards, since those later edits are lost too. Only the file is restored;
+/// a running interface keeps its state until it's restarted.
+pub fn rollback_operation(op_id: &str, token: Option<&str>) -> Result<Guarded<WgConfig>, WgError> {
+    let snapshot = load_snapshots()?
+        .into_iter()
+        .find(|s| s.info.id == op_id)
+        .ok_or_else(|| WgError::NotFound(format!("operation {}", op_id)))?;
+    let name = snapshot.info.config_name.clone();
+
+    with_config_lock(&name, || {
+        let path = config_path(&name);
+        let current = read_config_text(&path).ok();
+
+        let mut reasons = Vec::new();
+        if current.as_deref().map(content_hash).as_deref() != Some(snapshot.after_sha256.as_str()) {
+            reasons.push(format!(
+                "{} changed after {}; rolling back also discards those later changes.",
+                name, snapshot.info.operation
+            ));
+        }
+
+        confirm::guard("rollback_operation", op_id, reasons, token, || {
+            write_config_file(&path, &name, &snapshot.before)?;
+
+            let mut snapshots = load_snapshots()?;
+            if let Some(stored) = snapshots.iter_mut().find(|s| s.info.id == op_id) {
+                stored.info.rolled_back_at = Some(now_secs());
+                save_snapshots(&snapshots)?;
+            }
+            parse_config(&name)
+        })
+    })
+}
@@ -0,0 +1,270 @@
+use pdf_writer::{Content, Name, Pdf, Rect, Ref, Str};
+use qrcode::{Color, QrCode};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+use crate::i18n::{artifact_language, fill, strings, Language, Strings};
+use crate::wireguard::{parse_config, serialize_config, WgConfig, WgError};
+
+/// A4 in PDF points
+const PAGE_WIDTH: f32 = 595.0;
+const PAGE_HEIGHT: f32 = 842.0;
+const MARGIN: f32 = 50.0;
+
+const BODY_SIZE: f32 = 10.0;
+const LINE_HEIGHT: f32 = 13.0;
+const HEADING_SIZE: f32 = 13.0;
+const TITLE_SIZE: f32 = 18.0;
+const QR_SIZE: f32 = 220.0;
+
+/// Courier glyphs are 0.6 em wide, so this many fit between the margins
+const MONO_COLUMNS: usize = ((PAGE_WIDTH - 2.0 * MARGIN) / (BODY_SIZE * 0.6)) as usize;
+/// Rough fit for Helvetica body text
+const SANS_COLUMNS: usize = 95;
+
+const FONT_SANS: Name = Name(b"F1");
+const FONT_BOLD: Name = Name(b"F2");
+const FONT_MONO: Name = Name(b"F3");
+
+const REDACTED: &str = "<redacted>";
+
+/// What goes on a printed config sheet
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PdfExportOptions {
+    /// Print the private key; without it the sheet documents the tunnel but can't recreate it
+    pub include_secrets: bool,
+    /// Print a QR code of the config (only possible with secrets, since it has to be importable)
+    pub include_qr: bool,
+    pub include_instructions: bool,
+    /// Sheet title, the config name by default
+    pub title: Option<String>,
+    /// Language of the sheet's text, the artifact language setting by default
+    pub language: Option<Language>,
+}
+
+impl Default for PdfExportOptions {
+    fn default() -> Self {
+        Self {
+            include_secrets: false,
+            include_qr: true,
+            include_instructions: true,
+            title: None,
+            language: None,
+        }
+    }
+}
+
+/// Base-14 fonts with WinAnsiEncoding cover Latin-1; anything else prints as `?`
+fn pdf_text(text: &str) -> Vec<u8> {
+    text.chars()
+        .map(|c| if (c as u32) < 0x100 && !c.is_control() { c as u8 } else { b'?' })
+        .collect()
+}
+
+fn wrap(line: &str, columns: usize) -> Vec<String> {
+    if line.is_empty() {
+        return vec![String::new()];
+    }
+    let chars: Vec<char> = line.chars().collect();
+    chars.chunks(columns).map(|chunk| chunk.iter().collect()).collect()
+}
+
+/// Top-to-bottom layout that starts a new page when the current one is full
+struct Layout {
+    pages: Vec<Content>,
+    y: f32,
+}
+
+impl Layout {
+    fn new() -> Self {
+        Self {
+            pages: vec![Content::new()],
+            y: PAGE_HEIGHT - MARGIN,
+        }
+    }
+
+    fn page(&mut self) -> &mut Content {
+        self.pages.last_mut().unwrap()
+    }
+
+    fn reserve(&mut self, height: f32) {
+        if self.y - height < MARGIN {
+            self.pages.push(Content::new());
+            self.y = PAGE_HEIGHT - MARGIN;
+        }
+    }
+
+    fn line(&mut self, font: Name, size: f32, text: &str) {
+        let height = size.max(BODY_SIZE) + LINE_HEIGHT - BODY_SIZE;
+        self.reserve(height);
+        self.y -= height;
+        let y = self.y;
+        let bytes = pdf_text(text);
+        self.page()
+            .begin_text()
+            .set_font(font, size)
+            .next_line(MARGIN, y)
+            .show(Str(&bytes))
+            .end_text();
+    }
+
+    fn gap(&mut self) {
+        self.y -= LINE_HEIGHT;
+    }
+
+    fn heading(&mut self, text: &str) {
+        self.gap();
+        self.line(FONT_BOLD, HEADING_SIZE, text);
+        self.gap();
+    }
+
+    fn paragraph(&mut self, text: &str) {
+        for line in wrap(text, SANS_COLUMNS) {
+            self.line(FONT_SANS, BODY_SIZE, &line);
+        }
+    }
+
+    fn code(&mut self, text: &str) {
+        for line in text.lines().flat_map(|l| wrap(l, MONO_COLUMNS)) {
+            self.line(FONT_MONO, BODY_SIZE, &line);
+        }
+    }
+
+    fn qr(&mut self, code: &QrCode) {
+        // Four modules of quiet zone on each side
+        let modules = code.width() + 8;
+        let module = QR_SIZE / modules as f32;
+        self.reserve(QR_SIZE);
+        self.y -= QR_SIZE;
+        let (left, bottom) = (MARGIN, self.y);
+
+        let page = self.page();
+        page.save_state().set_fill_gray(0.0);
+        for (i, color) in code.to_colors().into_iter().enumerate() {
+            if color == Color::Dark {
+                let (col, row) = (i % code.width(), i / code.width());
+                let x = left + (col + 4) as f32 * module;
+                let y = bottom + QR_SIZE - (row + 5) as f32 * module;
+                page.rect(x, y, module, module);
+            }
+        }
+        page.fill_nonzero().restore_state();
+    }
+}
+
+/// The config as printed: the private key is blanked unless secrets were asked for
+fn printable_config(config: &WgConfig, include_secrets: bool) -> WgConfig {
+    let mut printable = config.clone();
+    if !include_secrets && printable.interface.private_key.is_some() {
+        printable.interface.private_key = Some(REDACTED.to_string());
+    }
+    printable
+}
+
+fn write_instructions(layout: &mut Layout, config: &WgConfig, options: &PdfExportOptions, text: &Strings) {
+    layout.heading(text.setup);
+    let mut steps = vec![text.install.to_string()];
+    if options.include_secrets && options.include_qr {
+        steps.push(text.scan_phone.to_string());
+        steps.push(text.scan_computer.to_string());
+    } else if options.include_secrets {
+        steps.push(text.type_in.to_string());
+    } else {
+        steps.push(fill(text.replace_key, &[("redacted", REDACTED)]));
+    }
+    steps.push(fill(text.activate, &[("name", &config.name)]));
+    for step in steps {
+        layout.paragraph(&step);
+    }
+
+    let endpoints: Vec<&str> = config.peers.iter().filter_map(|p| p.endpoint.as_deref()).collect();
+    layout.gap();
+    layout.paragraph(&fill(text.tunnel_address, &[("address", &config.interface.address.join(", "))]));
+    if !endpoints.is_empty() {
+        layout.paragraph(&fill(text.server, &[("servers", &endpoints.join(", "))]));
+    }
+    if !options.include_secrets {
+        layout.paragraph(text.no_secrets);
+    } else {
+        layout.paragraph(text.has_secrets);
+    }
+}
+
+/// Render a printable sheet for a config as PDF bytes
+pub fn render_config_pdf(config: &WgConfig, options: &PdfExportOptions) -> Result<Vec<u8>, WgError> {
+    let printable = printable_config(config, options.include_secrets);
+    let text = serialize_config(&printable);
+    let strings = strings(options.language.unwrap_or_else(artifact_language));
+    let mut layout = Layout::new();
+
+    let title = options
+        .title
+        .clone()
+        .unwrap_or_else(|| fill(strings.sheet_title, &[("name", &config.name)]));
+    layout.line(FONT_BOLD, TITLE_SIZE, &title);
+
+    if options.include_qr && options.include_secrets {
+        layout.gap();
+        match QrCode::new(text.as_bytes()) {
+            Ok(code) => layout.qr(&code),
+            Err(_) => layout.paragraph(strings.qr_too_large),
+        }
+    }
+
+    if options.include_instructions {
+        write_instructions(&mut layout, config, options, strings);
+    }
+
+    layout.heading(strings.configuration);
+    layout.code(&text);
+
+    let catalog_id = Ref::new(1);
+    let tree_id = Ref::new(2);
+    let font_ids = [
+        (FONT_SANS, Ref::new(3), "Helvetica"),
+        (FONT_BOLD, Ref::new(4), "Helvetica-Bold"),
+        (FONT_MONO, Ref::new(5), "Courier"),
+    ];
+    let page_ids: Vec<(Ref, Ref)> = (0..layout.pages.len() as i32)
+        .map(|i| (Ref::new(6 + 2 * i), Ref::new(7 + 2 * i)))
+        .collect();
+
+    let mut pdf = Pdf::new();
+    pdf.catalog(catalog_id).pages(tree_id);
+    pdf.pages(tree_id)
+        .kids(page_ids.iter().map(|(page, _)| *page))
+        .count(page_ids.len() as i32);
+    for (_, id, base) in font_ids {
+        pdf.type1_font(id)
+            .base_font(Name(base.as_bytes()))
+            .encoding_predefined(Name(b"WinAnsiEncoding"));
+    }
+
+    for ((page_id, content_id), content) in page_ids.into_iter().zip(layout.pages) {
+        let mut page = pdf.page(page_id);
+        page.parent(tree_id)
+            .media_box(Rect::new(0.0, 0.0, PAGE_WIDTH, PAGE_HEIGHT))
+            .contents(content_id);
+        let mut resources = page.resources();
+        let mut fonts = resources.fonts();
+        for (name, id, _) in font_ids {
+            fonts.pair(name, id);
+        }
+        drop(fonts);
+        drop(resources);
+        drop(page);
+        pdf.stream(content_id, &content.finish());
+    }
+
+    Ok(pdf.finish())
+}
+
+/// Write a printable PDF sheet for a saved config to `output_path`
+pub fn export_config_pdf(config_name: &str, options: &PdfExportOptions, output_path: &Path) -> Result<(), WgError> {
+    let config = parse_config(config_name)?;
+    let bytes = render_config_pdf(&config, options)?;
+    fs::write(output_path, bytes)?;
+    Ok(())
+}
@@ -0,0 +1,642 @@
+use rand::RngCore;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::ffi::OsString;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::addressing::allocate_peer_address;
+use crate::backup::private_temp_dir;
+use crate::cidr::Cidr;
+use crate::confirm::{self, Guarded};
+use crate::elevation::sudo_output;
+use crate::endpoint::validate_endpoint;
+use crate::guardrails::guardrails;
+use crate::keys::{
+    check_managed_key_conflict_in, invalid_key, managed_interface_keys, normalize_key, validate_key_format,
+    validate_public_key,
+};
+use crate::locks::with_config_lock;
+use crate::metadata;
+use crate::metrics;
+use crate::operations::with_snapshot;
+use crate::privileged::record_change;
+use crate::revoked::check_not_revoked;
+use crate::settings;
+use crate::store;
+use crate::wireguard::{
+    get_real_interface_name, is_interface_up, parse_config, save_config, Comments, Peer, WgConfig, WgError,
+};
+
+const DISABLED_PEERS_FILE: &str = "disabled_peers.json";
+
+/// Validate a peer against the config it goes into, normalizing its public key.
+/// `managed` comes from `managed_interface_keys`, loaded once per batch of peers.
+fn validate_peer(
+    peer: &mut Peer,
+    config: &WgConfig,
+    managed: &[(String, String, Option<String>)],
+) -> Result<(), WgError> {
+    peer.public_key =
+        validate_public_key(&peer.public_key, Some(config)).map_err(|e| invalid_key("PublicKey", peer.name.clone(), e))?;
+    check_managed_key_conflict_in(&peer.public_key, managed)?;
+    check_not_revoked(&peer.public_key)?;
+    // An emptied field in the editor means no preshared key
+    peer.preshared_key = match peer.preshared_key.as_deref().map(normalize_key) {
+        Some(key) if !key.is_empty() => Some(
+            validate_key_format(&key).map_err(|e| invalid_key("PresharedKey", peer.name.clone(), e))?,
+        ),
+        _ => None,
+    };
+    if let Some(ref endpoint) = peer.endpoint {
+        validate_endpoint(endpoint)?;
+    }
+    Ok(())
+}
+
+/// Append a peer to a config
+pub fn add_peer(config_name: &str, mut peer: Peer) -> Result<WgConfig, WgError> {
+    with_config_lock(config_name, || {
+        let mut config = parse_config(config_name)?;
+        validate_peer(&mut peer, &config, &managed_interface_keys())?;
+
+        config.peers.push(peer);
+        save_config(&config)?;
+        Ok(config)
+    })
+}
+
+/// Replace the peer identified by `public_key`
+pub fn update_peer(config_name: &str, public_key: &str, mut updated_peer: Peer) -> Result<WgConfig, WgError> {
+    with_config_lock(config_name, || {
+        let mut config = parse_config(config_name)?;
+        validate_peer(&mut updated_peer, &config, &managed_interface_keys())?;
+
+        let peer = config
+            .peers
+            .iter_mut()
+            .find(|p| p.public_key == public_key)
+            .ok_or_else(|| WgError::NotFound(format!("peer {}", public_key)))?;
+
+        // Editors that don't know about comments or unknown keys send none; keep the ones in the file
+        if updated_peer.comments.is_empty() {
+            updated_peer.comments = std::mem::take(&mut peer.comments);
+        }
+        if updated_peer.extra.is_empty() {
+            updated_peer.extra = std::mem::take(&mut peer.extra);
+        }
+        *peer = updated_peer;
+        save_config(&config)?;
+        Ok(config)
+    })
+}
+
+/// Safety rules that make deleting this peer need confirmation
+pub(crate) fn delete_reasons(config: &WgConfig, public_key: &str) -> Result<Vec<String>, WgError> {
+    let policy = settings::load_settings()?.confirmations;
+
+    let mut reasons = Vec::new();
+    if policy.delete_last_peer && config.peers.len() == 1 && config.peers[0].public_key == public_key {
+        reasons.push(format!("This is the last peer of {}; the tunnel will have nobody to talk to.", config.name));
+    }
+    Ok(reasons)
+}
+
+fn remove_peer(mut config: WgConfig, public_key: &str) -> Result<WgConfig, WgError> {
+    config.peers.retain(|p| p.public_key != public_key);
+    save_config(&config)?;
+    metadata::remove_peer_metadata(&config.name, public_key)?;
+    metrics::remove_peer_metrics(&config.name, public_key)?;
+    Ok(config)
+}
+
+/// Remove a peer, asking for confirmation first when policy requires it
+pub fn delete_peer(config_name: &str, public_key: &str, token: Option<&str>) -> Result<Guarded<WgConfig>, WgError> {
+    with_config_lock(config_name, || {
+        let config = parse_config(config_name)?;
+        let reasons = delete_reasons(&config, public_key)?;
+
+        confirm::guard("delete_peer", &format!("{}/{}", config_name, public_key), reasons, token, || {
+            with_snapshot("delete_peer", config_name, || remove_peer(config, public_key))
+        })
+    })
+}
+
+/// Remove a peer without asking; for changes that were confirmed when they were scheduled
+pub(crate) fn delete_peer_confirmed(config_name: &str, public_key: &str) -> Result<WgConfig, WgError> {
+    with_config_lock(config_name, || {
+        let config = parse_config(config_name)?;
+        if !config.peers.iter().any(|p| p.public_key == public_key) {
+            return Err(WgError::NotFound(format!("peer {}", public_key)));
+        }
+        with_snapshot("delete_peer", config_name, || remove_peer(config, public_key))
+    })
+}
+
+/// A peer taken out of its config, kept so it can be put back as it was
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DisabledPeer {
+    pub config_name: String,
+    pub peer: Peer,
+    pub disabled_at: u64,
+}
+
+fn load_disabled() -> Result<Vec<DisabledPeer>, WgError> {
+    store::load(DISABLED_PEERS_FILE)
+}
+
+fn save_disabled(disabled: &[DisabledPeer]) -> Result<(), WgError> {
+    store::save(DISABLED_PEERS_FILE, &disabled)
+}
+
+/// Peers disabled in a config
+pub fn list_disabled_peers(config_name: &str) -> Result<Vec<DisabledPeer>, WgError> {
+    Ok(load_disabled()?.into_iter().filter(|d| d.config_name == config_name).collect())
+}
+
+/// Cut a peer off by taking it out of the config; its metadata and history are kept for `enable_peer`
+pub fn disable_peer(config_name: &str, public_key: &str) -> Result<WgConfig, WgError> {
+    with_config_lock(config_name, || {
+        let mut config = parse_config(config_name)?;
+        let index = config
+            .peers
+            .iter()
+            .position(|p| p.public_key == public_key)
+            .ok_or_else(|| WgError::NotFound(format!("peer {}", public_key)))?;
+
+        let peer = config.peers.remove(index);
+        let mut disabled = load_disabled()?;
+        disabled.retain(|d| !(d.config_name == config_name && d.peer.public_key == public_key));
+        disabled.push(DisabledPeer {
+            config_name: config_name.to_string(),
+            peer,
+            disabled_at: store::now_secs(),
+        });
+        save_disabled(&disabled)?;
+        save_config(&config)?;
+        Ok(config)
+    })
+}
+
+/// Put a disabled peer back into its config
+pub fn enable_peer(config_name: &str, public_key: &str) -> Result<WgConfig, WgError> {
+    with_config_lock(config_name, || {
+        let mut disabled = load_disabled()?;
+        let index = disabled
+            .iter()
+            .position(|d| d.config_name == config_name && d.peer.public_key == public_key)
+            .ok_or_else(|| WgError::NotFound(format!("disabled peer {}", public_key)))?;
+
+        let mut config = parse_config(config_name)?;
+        if config.peers.iter().any(|p| p.public_key == public_key) {
+            return Err(WgError::Refused(format!("{} already has a peer with this key", config_name)));
+        }
+        let mut peer = disabled[index].peer.clone();
+        validate_peer(&mut peer, &config, &managed_interface_keys())?;
+        config.peers.push(peer);
+        save_config(&config)?;
+
+        disabled.remove(index);
+        save_disabled(&disabled)?;
+        Ok(config)
+    })
+}
+
+/// Add a peer to the running interface too, so it can connect without a restart. Returns false,
+/// doing nothing, when the tunnel is down and will pick the peer up from the file anyway.
+pub(crate) fn add_live_peer(config_name: &str, peer: &Peer) -> Result<bool, WgError> {
+    if !is_interface_up(config_name)? {
+        return Ok(false);
+    }
+    let interface_name = get_real_interface_name(config_name)?;
+    let mut args = vec![
+        "wg".to_string(),
+        "set".to_string(),
+        interface_name.clone(),
+        "peer".to_string(),
+        peer.public_key.clone(),
+        "allowed-ips".to_string(),
+        peer.allowed_ips.replace(' ', ""),
+    ];
+    if let Some(keepalive) = peer.persistent_keepalive {
+        args.extend(["persistent-keepalive".to_string(), keepalive.to_string()]);
+    }
+    let commands = vec![args.join(" ")];
+    let key_file = peer.preshared_key.as_deref().map(write_key_file).transpose()?;
+    if let Some(ref key_file) = key_file {
+        args.extend(["preshared-key".to_string(), key_file.display().to_string()]);
+    }
+    let output = sudo_output(&args);
+    if let Some(key_file) = key_file {
+        let _ = fs::remove_file(key_file);
+    }
+    let output = output?;
+    if !output.status.success() {
+        return Err(WgError::CommandFailed(String::from_utf8_lossy(&output.stderr).to_string()));
+    }
+    record_change(
+        "live_peer_add",
+        Some(config_name),
+        commands,
+        vec![format!("wg set {} peer {} remove", interface_name, peer.public_key)],
+    );
+    Ok(true)
+}
+
+/// Take a peer off the running interface; false when the tunnel is down
+pub(crate) fn remove_live_peer(config_name: &str, public_key: &str) -> Result<bool, WgError> {
+    if !is_interface_up(config_name)? {
+        return Ok(false);
+    }
+    let interface_name = get_real_interface_name(config_name)?;
+    let output = sudo_output(["wg", "set", &interface_name, "peer", public_key, "remove"])?;
+    if !output.status.success() {
+        return Err(WgError::CommandFailed(String::from_utf8_lossy(&output.stderr).to_string()));
+    }
+    record_change(
+        "live_peer_remove",
+        Some(config_name),
+        vec![format!("wg set {} peer {} remove", interface_name, public_key)],
+        Vec::new(),
+    );
+    Ok(true)
+}
+
+/// Outcome of `kick_peer`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KickedPeer {
+    pub public_key: String,
+    /// Where the fresh handshake goes; `None` when the interface waits for the peer to reconnect
+    pub endpoint: Option<String>,
+}
+
+/// Write a preshared key to a file only this user can read, for `wg set ... preshared-key`
+fn write_key_file(key: &str) -> Result<PathBuf, WgError> {
+    let mut suffix = [0u8; 8];
+    rand::thread_rng().fill_bytes(&mut suffix);
+    let suffix: String = suffix.iter().map(|b| format!("{:02x}", b)).collect();
+    let path = private_temp_dir()?.join(format!("wiredeck_psk_{}", suffix));
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options.open(&path)?.write_all(key.as_bytes())?;
+    Ok(path)
+}
+
+/// Force a fresh handshake with a peer that looks stuck on a dead session: it is removed from the
+/// running interface and added straight back, dropping its session and any roamed endpoint. The
+/// config file is untouched; AllowedIPs, keepalive and preshared key are carried over from the
+/// live peer, and the endpoint comes from the config so a hostname is resolved again.
+pub fn kick_peer(config_name: &str, public_key: &str) -> Result<KickedPeer, WgError> {
+    let config = parse_config(config_name)?;
+    let interface_name = get_real_interface_name(config_name)?;
+    let output = sudo_output(["wg", "show", &interface_name, "dump"])?;
+    if !output.status.success() {
+        return Err(WgError::CommandFailed(String::from_utf8_lossy(&output.stderr).to_string()));
+    }
+    let live = parse_dump(&String::from_utf8_lossy(&output.stdout))?
+        .into_iter()
+        .find(|p| p.public_key == public_key)
+        .ok_or_else(|| WgError::NotFound(format!("peer {} on {}", public_key, interface_name)))?;
+    let endpoint = config
+        .peers
+        .iter()
+        .find(|p| p.public_key == public_key)
+        .and_then(|p| p.endpoint.clone());
+
+    let allowed_ips = live.allowed_ips.replace(' ', "");
+    let mut add: Vec<OsString> = ["wg", "set", &interface_name, "peer", public_key, "allowed-ips", &allowed_ips]
+        .into_iter()
+        .map(OsString::from)
+        .collect();
+    if let Some(ref endpoint) = endpoint {
+        add.extend(["endpoint".into(), endpoint.into()]);
+    }
+    if let Some(keepalive) = live.persistent_keepalive {
+        add.extend(["persistent-keepalive".into(), keepalive.to_string().into()]);
+    }
+    // Written before the peer is removed, so failing here leaves it untouched
+    let key_file = live.preshared_key.as_deref().map(write_key_file).transpose()?;
+    if let Some(ref key_file) = key_file {
+        add.extend(["preshared-key".into(), key_file.into()]);
+    }
+
+    let remove = ["wg", "set", &interface_name, "peer", public_key, "remove"];
+    let readd = || {
+        let output = sudo_output(remove)?;
+        if !output.status.success() {
+            return Err(WgError::CommandFailed(String::from_utf8_lossy(&output.stderr).to_string()));
+        }
+        let reason = match sudo_output(&add) {
+            Ok(output) if output.status.success() => return Ok(()),
+            Ok(output) => String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            Err(e) => e.to_string(),
+        };
+        Err(WgError::CommandFailed(format!(
+            "{} was removed from {} but couldn't be added back ({}); restarting the tunnel restores it",
+            public_key, interface_name, reason
+        )))
+    };
+    let readded = readd();
+    if let Some(key_file) = key_file {
+        let _ = fs::remove_file(key_file);
+    }
+    readded?;
+
+    // The session is gone for good, so there is nothing to revert
+    record_change(
+        "kick_peer",
+        Some(config_name),
+        vec![remove.join(" "), format!("wg set {} peer {} allowed-ips {}", interface_name, public_key, allowed_ips)],
+        Vec::new(),
+    );
+    Ok(KickedPeer {
+        public_key: public_key.to_string(),
+        endpoint,
+    })
+}
+
+/// A peer line from `wg show <iface> dump` (or `wg show all dump`)
+struct DumpPeer {
+    public_key: String,
+    allowed_ips: String,
+    persistent_keepalive: Option<u16>,
+    preshared_key: Option<String>,
+}
+
+/// Outcome of mirroring a pasted dump into a config
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct DumpImport {
+    pub config: Option<WgConfig>,
+    /// Public keys of peers that were new
+    pub added: Vec<String>,
+    /// Public keys of existing peers whose AllowedIPs or keepalive changed
+    pub updated: Vec<String>,
+    pub unchanged: Vec<String>,
+    /// Peers that couldn't be imported, with the reason
+    pub skipped: Vec<(String, String)>,
+}
+
+fn dump_field(value: &str) -> Option<&str> {
+    (value != "(none)" && value != "off" && !value.is_empty()).then_some(value)
+}
+
+/// Parse pasted dump output; the interface line (which carries the remote private key) is dropped
+fn parse_dump(dump_text: &str) -> Result<Vec<DumpPeer>, WgError> {
+    let mut interfaces = Vec::new();
+    let mut peers = Vec::new();
+
+    for line in dump_text.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        let fields: Vec<&str> = line.split('\t').collect();
+        // `all dump` prefixes every line with the interface name
+        let fields = match fields.len() {
+            4 | 8 => fields,
+            5 | 9 => {
+                if !interfaces.contains(&fields[0]) {
+                    interfaces.push(fields[0]);
+                }
+                fields[1..].to_vec()
+            }
+            _ => {
+                return Err(WgError::Parse(format!(
+                    "Not `wg show dump` output (tab-separated fields expected): '{}'",
+                    line
+                )))
+            }
+        };
+        if fields.len() == 4 {
+            continue;
+        }
+
+        peers.push(DumpPeer {
+            public_key: fields[0].to_string(),
+            allowed_ips: dump_field(fields[3]).unwrap_or_default().replace(',', ", "),
+            persistent_keepalive: dump_field(fields[7]).and_then(|k| k.parse().ok()),
+            preshared_key: dump_field(fields[1]).map(str::to_string),
+        });
+    }
+
+    if interfaces.len() > 1 {
+        return Err(WgError::Parse(format!(
+            "Dump covers several interfaces ({}); paste the output for one",
+            interfaces.join(", ")
+        )));
+    }
+    if peers.is_empty() {
+        return Err(WgError::Parse("Dump contains no peers".to_string()));
+    }
+    Ok(peers)
+}
+
+/// Create or update peers from pasted `wg show ... dump` output, e.g. copied from a server over SSH.
+/// Endpoints in a server's dump are wherever its clients last roamed from, so they aren't copied.
+pub fn import_peers_from_dump(config_name: &str, dump_text: &str) -> Result<DumpImport, WgError> {
+    let dump_peers = parse_dump(dump_text)?;
+
+    with_config_lock(config_name, || {
+        let mut config = parse_config(config_name)?;
+        let mut report = DumpImport::default();
+
+        // Refuse oversized pastes up front instead of validating thousands of keys first
+        let max_peers = guardrails().max_peers;
+        let new_peers = dump_peers.iter().filter(|d| !config.peers.iter().any(|p| p.public_key == d.public_key));
+        let total = config.peers.len() + new_peers.count();
+        if total > max_peers {
+            return Err(WgError::Refused(format!(
+                "Importing this dump would give {} {} peers, over the limit of {}",
+                config_name, total, max_peers
+            )));
+        }
+
+        let managed = managed_interface_keys();
+        for dump_peer in dump_peers {
+            let mut peer = Peer {
+                public_key: dump_peer.public_key,
+                preshared_key: dump_peer.preshared_key,
+                allowed_ips: dump_peer.allowed_ips,
+                persistent_keepalive: dump_peer.persistent_keepalive,
+                endpoint: None,
+                name: None,
+                extra: Vec::new(),
+                comments: Comments::default(),
+            };
+            if let Err(e) = validate_peer(&mut peer, &config, &managed) {
+                report.skipped.push((peer.public_key, e.to_string()));
+                continue;
+            }
+
+            match config.peers.iter_mut().find(|p| p.public_key == peer.public_key) {
+                Some(existing) => {
+                    if existing.allowed_ips == peer.allowed_ips && existing.persistent_keepalive == peer.persistent_keepalive {
+                        report.unchanged.push(peer.public_key);
+                    } else {
+                        existing.allowed_ips = peer.allowed_ips;
+                        existing.persistent_keepalive = peer.persistent_keepalive;
+                        report.updated.push(peer.public_key);
+                    }
+                }
+                None => {
+                    report.added.push(peer.public_key.clone());
+                    config.peers.push(peer);
+                }
+            }
+        }
+
+        if !report.added.is_empty() || !report.updated.is_empty() {
+            with_snapshot("import_peers_from_dump", config_name, || save_config(&config))?;
+        }
+        report.config = Some(config);
+        Ok(report)
+    })
+}
+
+/// A `name,public_key[,ip]` line of a roster file
+struct RosterEntry {
+    line: usize,
+    name: String,
+    public_key: String,
+    address: Option<Cidr>,
+}
+
+/// Outcome of loading a roster into a config
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct RosterImport {
+    pub config: Option<WgConfig>,
+    /// Names of peers that were new
+    pub added: Vec<String>,
+    /// Names of existing peers whose name or address changed
+    pub updated: Vec<String>,
+    pub unchanged: Vec<String>,
+    /// Rows that couldn't be imported, as `line N (name)` with the reason
+    pub skipped: Vec<(String, String)>,
+    /// Addresses handed out to rows that didn't name one, by peer name
+    pub allocated: Vec<(String, String)>,
+}
+
+/// Parse a roster: one `name,public_key[,ip]` per line, `#` comments and blank lines ignored.
+/// A bare IP becomes a host route. Malformed rows are added to `invalid`.
+fn parse_roster(content: &str, invalid: &mut Vec<(String, String)>) -> Result<Vec<RosterEntry>, WgError> {
+    let mut entries = Vec::new();
+
+    for (index, raw) in content.lines().enumerate() {
+        let line = raw.split('#').next().unwrap_or_default().trim();
+        if line.is_empty() {
+            continue;
+        }
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+        let label = format!("line {}", index + 1);
+        let (name, public_key, address) = match fields.as_slice() {
+            [name, key] => (*name, *key, None),
+            [name, key, ip] if !ip.is_empty() => (*name, *key, Some(*ip)),
+            [name, key, _] => (*name, *key, None),
+            _ => {
+                invalid.push((label, "expected name,public_key[,ip]".to_string()));
+                continue;
+            }
+        };
+        if name.is_empty() {
+            invalid.push((label, "name is empty".to_string()));
+            continue;
+        }
+        let address = match address.map(Cidr::parse).transpose() {
+            Ok(address) => address,
+            Err(e) => {
+                invalid.push((format!("{} ({})", label, name), e.to_string()));
+                continue;
+            }
+        };
+        entries.push(RosterEntry {
+            line: index + 1,
+            name: name.to_string(),
+            public_key: public_key.to_string(),
+            address,
+        });
+    }
+
+    if entries.is_empty() && invalid.is_empty() {
+        return Err(WgError::Parse("Roster contains no peers".to_string()));
+    }
+    Ok(entries)
+}
+
+/// Bulk-load peers from a roster file (`name,public_key[,ip]` per line), e.g. one kept in git.
+/// Rows without an IP get the next free host address of the config's tunnel subnet.
+/// Peers already in the config are matched by key; their name, and address when given, are updated.
+pub fn import_roster(config_name: &str, roster_path: &Path) -> Result<RosterImport, WgError> {
+    let mut invalid = Vec::new();
+    let entries = parse_roster(&fs::read_to_string(roster_path)?, &mut invalid)?;
+
+    with_config_lock(config_name, || {
+        let mut config = parse_config(config_name)?;
+        let mut report = RosterImport {
+            skipped: invalid,
+            ..Default::default()
+        };
+
+        let max_peers = guardrails().max_peers;
+        let new_peers = entries.iter().filter(|e| !config.peers.iter().any(|p| p.public_key == e.public_key));
+        let total = config.peers.len() + new_peers.count();
+        if total > max_peers {
+            return Err(WgError::Refused(format!(
+                "Importing this roster would give {} {} peers, over the limit of {}",
+                config_name, total, max_peers
+            )));
+        }
+
+        let managed = managed_interface_keys();
+        for entry in entries {
+            let label = format!("line {} ({})", entry.line, entry.name);
+            let mut peer = Peer {
+                public_key: entry.public_key,
+                preshared_key: None,
+                allowed_ips: entry.address.map(|a| a.to_string()).unwrap_or_default(),
+                persistent_keepalive: None,
+                endpoint: None,
+                name: Some(entry.name.clone()),
+                extra: Vec::new(),
+                comments: Comments::default(),
+            };
+            if let Err(e) = validate_peer(&mut peer, &config, &managed) {
+                report.skipped.push((label, e.to_string()));
+                continue;
+            }
+
+            if let Some(existing) = config.peers.iter_mut().find(|p| p.public_key == peer.public_key) {
+                let address_changed = entry.address.is_some() && existing.allowed_ips != peer.allowed_ips;
+                if existing.name != peer.name || address_changed {
+                    existing.name = peer.name;
+                    if address_changed {
+                        existing.allowed_ips = peer.allowed_ips;
+                    }
+                    report.updated.push(entry.name);
+                } else {
+                    report.unchanged.push(entry.name);
+                }
+                continue;
+            }
+
+            if entry.address.is_none() {
+                match allocate_peer_address(&config) {
+                    Ok(address) => {
+                        peer.allowed_ips = address.to_string();
+                        report.allocated.push((entry.name.clone(), peer.allowed_ips.clone()));
+                    }
+                    Err(e) => {
+                        report.skipped.push((label, e.to_string()));
+                        continue;
+                    }
+                }
+            }
+            report.added.push(entry.name);
+            config.peers.push(peer);
+        }
+
+        if !report.added.is_empty() || !report.updated.is_empty() {
+            with_snapshot("import_roster", config_name, || save_config(&config))?;
+        }
+        report.config = Some(config);
+        Ok(report)
+    })
+}
@@ -0,0 +1,147 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+use crate::cidr::Cidr;
+use crate::endpoint::Endpoint;
+use crate::metadata::{list_peer_metadata, update_peer_metadata, PeerMetadata};
+use crate::metrics::{list_peer_metrics, PeerEventKind, PeerMetrics};
+use crate::wireguard::{parse_config, Peer, WgError};
+
+/// The standard WireGuard port; servers tend to keep it, mobile apps pick a random one
+const DEFAULT_WG_PORT: u16 = 51820;
+
+/// Distinct endpoint addresses after which a peer counts as roaming between networks
+const ROAMING_ENDPOINTS: usize = 3;
+
+/// Offline spells in the timeline after which a roaming peer looks like a phone going to sleep
+const PHONE_OFFLINE_SPELLS: usize = 4;
+
+/// Kind of device behind a peer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Platform {
+    Phone,
+    Computer,
+    Server,
+    Router,
+}
+
+/// Where a peer's platform came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlatformSource {
+    /// Recorded at enrollment or set by hand
+    Recorded,
+    /// Guessed from the config and what the status monitor saw
+    Detected,
+}
+
+/// Platform of one peer for the fleet view
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PeerPlatform {
+    pub public_key: String,
+    pub platform: Option<Platform>,
+    pub os: Option<String>,
+    /// `None` when nothing is known and nothing could be guessed
+    pub source: Option<PlatformSource>,
+    /// Why the guess was made; empty for recorded platforms
+    pub reasons: Vec<String>,
+}
+
+/// Guess a peer's platform from its config entry and monitor history. Only a hint: a laptop that
+/// never moves looks like a server.
+pub fn detect_platform(peer: &Peer, metrics: Option<&PeerMetrics>) -> Option<(Platform, Vec<String>)> {
+    let routes = Cidr::parse_list(&peer.allowed_ips).unwrap_or_default();
+    if let Some(subnet) = routes.iter().find(|c| c.prefix > 0 && !c.is_host()) {
+        return Some((Platform::Router, vec![format!("Routes the subnet {}", subnet)]));
+    }
+
+    if let Some(ref endpoint) = peer.endpoint {
+        let mut reasons = vec![format!("Reached at a fixed endpoint {}", endpoint)];
+        if peer.persistent_keepalive.is_some() {
+            reasons.push("Kept alive from this side, as for a server behind a stable address".to_string());
+        }
+        return Some((Platform::Server, reasons));
+    }
+
+    let metrics = metrics?;
+    let addresses: BTreeSet<String> = metrics
+        .endpoints
+        .iter()
+        .filter_map(|span| Endpoint::parse(&span.endpoint).ok()?.ip().map(|ip| ip.to_string()))
+        .collect();
+    let offline_spells = metrics
+        .timeline
+        .iter()
+        .filter(|e| matches!(e.kind, PeerEventKind::WentOffline { .. }))
+        .count();
+    let live_port = metrics
+        .last
+        .as_ref()
+        .and_then(|o| o.endpoint.as_deref())
+        .and_then(|e| Endpoint::parse(e).ok())
+        .map(|e| e.port);
+
+    if addresses.len() >= ROAMING_ENDPOINTS {
+        let roaming = format!("Seen from {} different addresses", addresses.len());
+        if offline_spells >= PHONE_OFFLINE_SPELLS {
+            let sleeps = format!("Went offline {} times", offline_spells);
+            return Some((Platform::Phone, vec![roaming, sleeps]));
+        }
+        return Some((Platform::Computer, vec![roaming]));
+    }
+    if addresses.len() == 1 && offline_spells == 0 && live_port == Some(DEFAULT_WG_PORT) {
+        return Some((
+            Platform::Server,
+            vec![format!("Always online from one address on port {}", DEFAULT_WG_PORT)],
+        ));
+    }
+    None
+}
+
+/// Platforms of every peer of a config: recorded ones as they are, a guess for the rest
+pub fn list_peer_platforms(config_name: &str) -> Result<Vec<PeerPlatform>, WgError> {
+    let config = parse_config(config_name)?;
+    let metadata = list_peer_metadata(config_name)?;
+    let metrics = list_peer_metrics(config_name)?;
+
+    Ok(config
+        .peers
+        .iter()
+        .map(|peer| {
+            let recorded = metadata.get(&peer.public_key);
+            let os = recorded.and_then(|m| m.os.clone());
+            if let Some(platform) = recorded.and_then(|m| m.platform) {
+                return PeerPlatform {
+                    public_key: peer.public_key.clone(),
+                    platform: Some(platform),
+                    os,
+                    source: Some(PlatformSource::Recorded),
+                    reasons: Vec::new(),
+                };
+            }
+            let detected = detect_platform(peer, metrics.get(&peer.public_key));
+            PeerPlatform {
+                public_key: peer.public_key.clone(),
+                platform: detected.as_ref().map(|(p, _)| *p),
+                os,
+                source: detected.as_ref().map(|_| PlatformSource::Detected),
+                reasons: detected.map(|(_, r)| r).unwrap_or_default(),
+            }
+        })
+        .collect())
+}
+
+/// Record a peer's platform and OS; `None` clears them so detection takes over again
+pub fn set_peer_platform(
+    config_name: &str,
+    public_key: &str,
+    platform: Option<Platform>,
+    os: Option<String>,
+) -> Result<PeerMetadata, WgError> {
+    let os = os.map(|o| o.trim().to_string()).filter(|o| !o.is_empty());
+    update_peer_metadata(config_name, public_key, |m| {
+        m.platform = platform;
+        m.os = os;
+    })
+}
@@ -0,0 +1,121 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+/// On battery at or below this charge, heavy work pauses even without low-power mode
+const LOW_BATTERY_PERCENT: u8 = 20;
+
+/// Where the machine is currently drawing power from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PowerSource {
+    Ac,
+    Battery,
+    /// Couldn't tell; callers treat this like AC
+    Unknown,
+}
+
+/// Power conditions background work throttles itself on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PowerState {
+    pub source: PowerSource,
+    /// macOS Low Power Mode, or the power-saver profile on Linux
+    pub low_power_mode: bool,
+    pub battery_percent: Option<u8>,
+}
+
+impl PowerState {
+    /// Whether heavy background work (speed tests, fast sampling) should pause
+    pub fn should_throttle(&self) -> bool {
+        self.low_power_mode
+            || (self.source == PowerSource::Battery && self.battery_percent.is_some_and(|p| p <= LOW_BATTERY_PERCENT))
+    }
+}
+
+fn command_stdout(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// `pmset` reads the same IOKit power sources the menu bar battery does
+fn macos_power_state() -> PowerState {
+    let batt = command_stdout("pmset", &["-g", "batt"]).unwrap_or_default();
+    let source = if batt.contains("'Battery Power'") {
+        PowerSource::Battery
+    } else if batt.contains("'AC Power'") {
+        PowerSource::Ac
+    } else {
+        PowerSource::Unknown
+    };
+    // "-InternalBattery-0 (id=...)	85%; discharging; ..."
+    let battery_percent = batt
+        .split_whitespace()
+        .find_map(|word| word.strip_suffix("%;").and_then(|p| p.parse().ok()));
+
+    let low_power_mode = command_stdout("pmset", &["-g"])
+        .unwrap_or_default()
+        .lines()
+        .any(|line| line.split_whitespace().collect::<Vec<_>>() == ["lowpowermode", "1"]);
+
+    PowerState {
+        source,
+        low_power_mode,
+        battery_percent,
+    }
+}
+
+fn linux_power_state() -> PowerState {
+    let low_power_mode = command_stdout("powerprofilesctl", &["get"]).is_some_and(|p| p.trim() == "power-saver");
+    let Ok(supplies) = fs::read_dir("/sys/class/power_supply") else {
+        return PowerState {
+            source: PowerSource::Unknown,
+            low_power_mode,
+            battery_percent: None,
+        };
+    };
+
+    let read = |dir: &Path, file: &str| {
+        fs::read_to_string(dir.join(file))
+            .map(|s| s.trim().to_string())
+            .unwrap_or_default()
+    };
+    let mut on_mains = false;
+    let mut has_battery = false;
+    let mut battery_percent = None;
+    for supply in supplies.flatten() {
+        let dir = supply.path();
+        match read(&dir, "type").as_str() {
+            "Mains" | "USB" if read(&dir, "online") == "1" => on_mains = true,
+            "Battery" => {
+                has_battery = true;
+                battery_percent = battery_percent.or(read(&dir, "capacity").parse().ok());
+            }
+            _ => {}
+        }
+    }
+
+    // Desktops and VMs have no battery at all
+    let source = if on_mains || !has_battery {
+        PowerSource::Ac
+    } else {
+        PowerSource::Battery
+    };
+    PowerState {
+        source,
+        low_power_mode,
+        battery_percent,
+    }
+}
+
+/// Current power source, low-power mode and battery level
+pub fn get_power_state() -> PowerState {
+    if cfg!(target_os = "macos") {
+        macos_power_state()
+    } else {
+        linux_power_state()
+    }
+}
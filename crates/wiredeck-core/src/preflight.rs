@@ -0,0 +1,340 @@
+use serde::{Deserialize, Serialize};
+use std::io::ErrorKind;
+use std::net::{Ipv4Addr, Ipv6Addr, UdpSocket};
+use std::process::Command;
+
+use crate::cidr::Cidr;
+use crate::diagnostics::Severity;
+use crate::elevation::{check_elevation, Elevation};
+use crate::endpoint::{resolve_endpoint, AddressFamily};
+use crate::keys::{check_config_keys, validate_config_keys};
+use crate::server::listen_port_findings;
+use crate::trace;
+use crate::wireguard::{is_interface_up, list_configs, parse_config, WgConfig, WgError};
+
+/// How one preflight check came out
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckStatus {
+    Pass,
+    /// Bring-up will likely work, but something deserves a look
+    Warn,
+    /// Bring-up would fail or misbehave
+    Fail,
+    /// Doesn't apply to this config
+    Skipped,
+}
+
+/// One line of the checklist
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PreflightCheck {
+    /// Stable identifier: `not_running`, `keys`, `listen_port`, `subnets`, `endpoints` or `privileges`
+    pub id: String,
+    pub label: String,
+    pub status: CheckStatus,
+    pub detail: Option<String>,
+    pub fix: Option<String>,
+}
+
+/// Everything checked before bringing a tunnel up
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Preflight {
+    pub config_name: String,
+    /// No check failed
+    pub ready: bool,
+    pub checks: Vec<PreflightCheck>,
+}
+
+fn check(id: &str, label: &str, status: CheckStatus, detail: Option<String>, fix: Option<&str>) -> PreflightCheck {
+    PreflightCheck {
+        id: id.to_string(),
+        label: label.to_string(),
+        status,
+        detail,
+        fix: fix.map(str::to_string),
+    }
+}
+
+fn check_not_running(config_name: &str) -> PreflightCheck {
+    match is_interface_up(config_name) {
+        Ok(false) => check("not_running", "Not running yet", CheckStatus::Pass, None, None),
+        Ok(true) => check(
+            "not_running",
+            "Not running yet",
+            CheckStatus::Fail,
+            Some(format!("{} is already up", config_name)),
+            Some("Stop it first, or restart it instead"),
+        ),
+        Err(e) => check("not_running", "Not running yet", CheckStatus::Warn, Some(e.to_string()), None),
+    }
+}
+
+fn check_keys(config: &WgConfig) -> PreflightCheck {
+    const LABEL: &str = "Keys are valid";
+    if let Err(e) = validate_config_keys(config) {
+        return check("keys", LABEL, CheckStatus::Fail, Some(e.to_string()), None);
+    }
+    if !config.interface.has_private_key {
+        return check(
+            "keys",
+            LABEL,
+            CheckStatus::Fail,
+            Some("The config has no usable PrivateKey".to_string()),
+            Some("Add a PrivateKey, e.g. from generate_keypair"),
+        );
+    }
+    let findings = check_config_keys(config);
+    let worst = findings.iter().map(|f| f.severity).max();
+    let detail = (!findings.is_empty())
+        .then(|| findings.iter().map(|f| f.message.as_str()).collect::<Vec<_>>().join(" "));
+    let status = match worst {
+        Some(Severity::Error) => CheckStatus::Fail,
+        Some(Severity::Warning) => CheckStatus::Warn,
+        _ => CheckStatus::Pass,
+    };
+    check("keys", LABEL, status, detail, None)
+}
+
+/// The port is bound by the kernel (or wireguard-go) when the tunnel comes up; binding it here
+/// for a moment tells whether something else holds it
+fn check_listen_port(config: &WgConfig) -> PreflightCheck {
+    const LABEL: &str = "Listen port is free";
+    if let Some(finding) = listen_port_findings(config).into_iter().find(|f| f.severity == Severity::Error) {
+        return PreflightCheck {
+            id: "listen_port".to_string(),
+            label: LABEL.to_string(),
+            status: CheckStatus::Fail,
+            detail: Some(finding.message),
+            fix: finding.fix,
+        };
+    }
+    let Some(port) = config.interface.listen_port else {
+        return check(
+            "listen_port",
+            LABEL,
+            CheckStatus::Skipped,
+            Some("No ListenPort; a free one is picked on bring-up".to_string()),
+            None,
+        );
+    };
+
+    let v4 = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, port)).map(drop);
+    let v6 = UdpSocket::bind((Ipv6Addr::UNSPECIFIED, port)).map(drop);
+    match (v4, v6) {
+        (Err(e), _) | (_, Err(e)) if e.kind() == ErrorKind::AddrInUse => check(
+            "listen_port",
+            LABEL,
+            CheckStatus::Fail,
+            Some(format!("UDP port {} is already in use, possibly by another tunnel", port)),
+            Some("Pick another ListenPort, or stop whatever holds the port"),
+        ),
+        // Ports below 1024 are fine for the kernel, which binds them as root
+        (Err(e), _) if e.kind() == ErrorKind::PermissionDenied => check(
+            "listen_port",
+            LABEL,
+            CheckStatus::Pass,
+            Some(format!("UDP port {} is privileged and can't be probed without root", port)),
+            None,
+        ),
+        _ => check("listen_port", LABEL, CheckStatus::Pass, Some(format!("UDP port {}", port)), None),
+    }
+}
+
+/// Prefixes a config claims: its own addresses and its peers' AllowedIPs, except default routes
+fn claimed_prefixes(config: &WgConfig) -> Vec<Cidr> {
+    let mut prefixes: Vec<Cidr> = Cidr::parse_all(&config.interface.address)
+        .unwrap_or_default()
+        .iter()
+        .map(Cidr::network)
+        .collect();
+    for peer in &config.peers {
+        let routes = Cidr::parse_list(&peer.allowed_ips).unwrap_or_default();
+        prefixes.extend(routes.into_iter().filter(|c| c.prefix > 0));
+    }
+    prefixes
+}
+
+/// Networks of this machine's interfaces, by interface name; loopback and link-local left out
+fn local_networks() -> Vec<(String, Cidr)> {
+    let mut networks = Vec::new();
+    if cfg!(target_os = "linux") {
+        // `2: eth0    inet 192.168.1.5/24 brd ...`
+        let Ok(output) = trace::output(Command::new("ip").args(["-o", "addr", "show"])) else {
+            return networks;
+        };
+        for line in String::from_utf8_lossy(&output.stdout).lines() {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if let [_, interface, "inet" | "inet6", address, ..] = fields[..] {
+                if let Ok(cidr) = Cidr::parse(address) {
+                    networks.push((interface.trim_end_matches(':').to_string(), cidr.network()));
+                }
+            }
+        }
+    } else if cfg!(target_os = "macos") {
+        // `en0: flags=...` then `\tinet 192.168.1.5 netmask 0xffffff00 ...` / `\tinet6 fd00::5 prefixlen 64`
+        let Ok(output) = trace::output(&mut Command::new("ifconfig")) else {
+            return networks;
+        };
+        let mut interface = String::new();
+        for line in String::from_utf8_lossy(&output.stdout).lines() {
+            if !line.starts_with(char::is_whitespace) {
+                interface = line.split(':').next().unwrap_or_default().to_string();
+                continue;
+            }
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let cidr = match fields[..] {
+                ["inet", address, "netmask", mask, ..] => u32::from_str_radix(mask.trim_start_matches("0x"), 16)
+                    .ok()
+                    .and_then(|mask| Cidr::parse(&format!("{}/{}", address, mask.count_ones())).ok()),
+                ["inet6", address, "prefixlen", prefix, ..] => {
+                    Cidr::parse(&format!("{}/{}", address.split('%').next().unwrap_or(address), prefix)).ok()
+                }
+                _ => None,
+            };
+            if let Some(cidr) = cidr {
+                networks.push((interface.clone(), cidr.network()));
+            }
+        }
+    }
+    networks.retain(|(_, cidr)| !cidr.addr.is_loopback() && !is_link_local(cidr));
+    networks
+}
+
+fn is_link_local(cidr: &Cidr) -> bool {
+    match cidr.addr {
+        std::net::IpAddr::V4(v4) => v4.is_link_local(),
+        std::net::IpAddr::V6(v6) => (v6.segments()[0] & 0xffc0) == 0xfe80,
+    }
+}
+
+fn check_subnets(config: &WgConfig) -> PreflightCheck {
+    const LABEL: &str = "No subnet conflicts";
+    let ours = claimed_prefixes(config);
+    let overlapping = |theirs: &Cidr| ours.iter().find(|cidr| cidr.overlaps(theirs));
+
+    // Two running tunnels claiming the same prefix fight over its routes
+    let mut conflicts = Vec::new();
+    for name in list_configs().unwrap_or_default() {
+        if name == config.name || !is_interface_up(&name).unwrap_or(false) {
+            continue;
+        }
+        let Ok(other) = parse_config(&name) else {
+            continue;
+        };
+        let hit = claimed_prefixes(&other)
+            .into_iter()
+            .find_map(|theirs| overlapping(&theirs).map(|ours| (*ours, theirs)));
+        if let Some((ours, theirs)) = hit {
+            conflicts.push(format!("{} overlaps {} of the running tunnel {}", ours, theirs, name));
+        }
+    }
+    if !conflicts.is_empty() {
+        return check(
+            "subnets",
+            LABEL,
+            CheckStatus::Fail,
+            Some(conflicts.join("; ")),
+            Some("Stop the other tunnel, or give the configs separate subnets"),
+        );
+    }
+
+    // Overlapping the local network is sometimes the point, e.g. reaching a LAN through the tunnel
+    let local: Vec<String> = local_networks()
+        .iter()
+        .filter_map(|(interface, theirs)| {
+            overlapping(theirs).map(|ours| format!("{} overlaps {} on {}", ours, theirs, interface))
+        })
+        .collect();
+    if !local.is_empty() {
+        return check(
+            "subnets",
+            LABEL,
+            CheckStatus::Warn,
+            Some(local.join("; ")),
+            Some("Hosts on the local network may become unreachable while the tunnel is up"),
+        );
+    }
+    check("subnets", LABEL, CheckStatus::Pass, None, None)
+}
+
+fn check_endpoints(config: &WgConfig) -> PreflightCheck {
+    const LABEL: &str = "Peer endpoints resolve";
+    let endpoints: Vec<(String, &str)> = config
+        .peers
+        .iter()
+        .enumerate()
+        .filter_map(|(index, peer)| {
+            let label = peer.name.clone().unwrap_or_else(|| format!("#{}", index + 1));
+            peer.endpoint.as_deref().map(|endpoint| (label, endpoint))
+        })
+        .collect();
+    if endpoints.is_empty() {
+        return check(
+            "endpoints",
+            LABEL,
+            CheckStatus::Skipped,
+            Some("No peer has an Endpoint; they connect to this host".to_string()),
+            None,
+        );
+    }
+
+    let failed: Vec<String> = endpoints
+        .iter()
+        .filter_map(|(label, endpoint)| {
+            resolve_endpoint(endpoint, AddressFamily::Auto, false)
+                .err()
+                .map(|e| format!("Peer {} ({}): {}", label, endpoint, e))
+        })
+        .collect();
+    if failed.is_empty() {
+        return check("endpoints", LABEL, CheckStatus::Pass, None, None);
+    }
+    check(
+        "endpoints",
+        LABEL,
+        CheckStatus::Fail,
+        Some(failed.join("; ")),
+        Some("Check the hostname and this machine's DNS and network connection"),
+    )
+}
+
+fn check_privileges() -> PreflightCheck {
+    const LABEL: &str = "Administrator rights";
+    match check_elevation() {
+        Elevation::Ready => check("privileges", LABEL, CheckStatus::Pass, None, None),
+        Elevation::NeedsPassword => check(
+            "privileges",
+            LABEL,
+            CheckStatus::Pass,
+            Some("Your password will be asked for".to_string()),
+            None,
+        ),
+        Elevation::Unavailable => check(
+            "privileges",
+            LABEL,
+            CheckStatus::Fail,
+            Some("sudo is missing, or needs a password and there is no way to ask for it".to_string()),
+            Some("Allow wg-quick in sudoers with NOPASSWD, or run WireDeck from a session that can prompt"),
+        ),
+    }
+}
+
+/// The checklist to go through before bringing a tunnel up: not already up, valid keys, a free
+/// listen port, no subnet clashing with running tunnels or local networks, resolvable endpoints
+/// and a way to become root. `ready` is false when any check would make `wg-quick up` fail.
+pub fn preflight(config_name: &str) -> Result<Preflight, WgError> {
+    let config = parse_config(config_name)?;
+    let checks = vec![
+        check_not_running(config_name),
+        check_keys(&config),
+        check_listen_port(&config),
+        check_subnets(&config),
+        check_endpoints(&config),
+        check_privileges(),
+    ];
+    Ok(Preflight {
+        config_name: config_name.to_string(),
+        ready: checks.iter().all(|c| c.status != CheckStatus::Fail),
+        checks,
+    })
+}
@@ -0,0 +1,109 @@
+use serde::{Deserialize, Serialize};
+
+use crate::elevation::sudo_output;
+use crate::store::{self, now_secs};
+use crate::wireguard::WgError;
+
+const LOG_FILE: &str = "privileged_log.json";
+
+/// Oldest entries are dropped once the log grows past this
+const MAX_ENTRIES: usize = 200;
+
+/// One change WireDeck made to the system as root, with the commands that undo it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PrivilegedChange {
+    pub id: u64,
+    pub at: u64,
+    /// e.g. `interface_up`, `nat_live`, `endpoint_override`
+    pub action: String,
+    pub config_name: Option<String>,
+    pub commands: Vec<String>,
+    /// Shell commands that undo `commands`, in the order they should run
+    pub revert: Vec<String>,
+    pub reverted_at: Option<u64>,
+}
+
+fn load_log() -> Result<Vec<PrivilegedChange>, WgError> {
+    store::load_global(LOG_FILE)
+}
+
+fn save_log(log: &[PrivilegedChange]) -> Result<(), WgError> {
+    store::save_global(LOG_FILE, &log)
+}
+
+/// Record a privileged change. Best effort: failing to write the log never fails the change itself.
+pub fn record_change(action: &str, config_name: Option<&str>, commands: Vec<String>, revert: Vec<String>) {
+    let Ok(mut log) = load_log() else {
+        return;
+    };
+
+    log.push(PrivilegedChange {
+        id: log.last().map(|c| c.id + 1).unwrap_or(1),
+        at: now_secs(),
+        action: action.to_string(),
+        config_name: config_name.map(str::to_string),
+        commands,
+        revert,
+        reverted_at: None,
+    });
+    if log.len() > MAX_ENTRIES {
+        log.drain(..log.len() - MAX_ENTRIES);
+    }
+    let _ = save_log(&log);
+}
+
+/// Logged changes, newest last
+pub fn list_privileged_changes() -> Result<Vec<PrivilegedChange>, WgError> {
+    load_log()
+}
+
+/// A shell script undoing a change, for running by hand (e.g. over SSH when locked out)
+pub fn revert_script(change: &PrivilegedChange) -> String {
+    let mut script = format!(
+        "#!/bin/sh\n# Reverts WireDeck change #{} ({}{})\n",
+        change.id,
+        change.action,
+        change.config_name.as_deref().map(|n| format!(" on {}", n)).unwrap_or_default()
+    );
+    for command in &change.revert {
+        script.push_str(&format!("sudo sh -c '{}'\n", command.replace('\'', r"'\''")));
+    }
+    script
+}
+
+/// Revert script for a logged change by id
+pub fn get_revert_script(id: u64) -> Result<String, WgError> {
+    load_log()?
+        .iter()
+        .find(|c| c.id == id)
+        .map(revert_script)
+        .ok_or_else(|| WgError::NotFound(format!("privileged change #{}", id)))
+}
+
+/// Undo the most recent change that hasn't been reverted yet
+pub fn revert_last_privileged_change() -> Result<PrivilegedChange, WgError> {
+    let mut log = load_log()?;
+    let change = log
+        .iter_mut()
+        .rev()
+        .find(|c| c.reverted_at.is_none() && !c.revert.is_empty())
+        .ok_or_else(|| WgError::NotFound("privileged change to revert".to_string()))?;
+
+    // Keep going after a failure so as much as possible is undone, then report everything that failed
+    let mut failures = Vec::new();
+    for command in &change.revert {
+        let output = sudo_output(["sh", "-c", command])?;
+        if !output.status.success() {
+            failures.push(format!("{}: {}", command, String::from_utf8_lossy(&output.stderr).trim()));
+        }
+    }
+
+    change.reverted_at = Some(now_secs());
+    let change = change.clone();
+    save_log(&log)?;
+
+    if !failures.is_empty() {
+        return Err(WgError::CommandFailed(failures.join("\n")));
+    }
+    Ok(change)
+}
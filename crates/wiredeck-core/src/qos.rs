@@ -0,0 +1,285 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+use crate::locks::with_config_lock;
+use crate::store;
+use crate::wireguard::{add_hook_commands, parse_config, remove_hook_commands, save_config, Peer, WgConfig, WgError};
+
+const RATE_LIMITS_FILE: &str = "rate_limits.json";
+
+/// Bandwidth cap for a single peer, in kbit/s
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RateLimit {
+    /// Traffic sent from this host to the peer
+    pub download_kbit: Option<u32>,
+    /// Traffic received from the peer
+    pub upload_kbit: Option<u32>,
+}
+
+/// Shell commands implementing a rate limit, suitable for PostUp/PostDown
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TcRules {
+    pub post_up: Vec<String>,
+    pub post_down: Vec<String>,
+}
+
+/// A tracked rate limit for one peer of a config
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RateLimitEntry {
+    pub public_key: String,
+    pub rate_limit: RateLimit,
+    pub rules: TcRules,
+    pub installed: bool,
+    /// tc class id and dummynet pipe number the rules use; `None` for entries made before ids
+    /// were stored, whose rules use `shaping_id` of the key
+    #[serde(default)]
+    pub shaping_id: Option<u16>,
+}
+
+impl RateLimitEntry {
+    fn id(&self) -> u16 {
+        self.shaping_id.unwrap_or_else(|| shaping_id(&self.public_key))
+    }
+}
+
+/// Rate limits keyed by config name
+type RateLimitStore = HashMap<String, Vec<RateLimitEntry>>;
+
+/// Lowest and highest shaping ids handed out
+const MIN_SHAPING_ID: u16 = 10;
+const MAX_SHAPING_ID: u16 = 0xfff8;
+
+/// Preferred per-peer identifier used for tc class ids and dummynet pipe numbers.
+/// Always even, so `id + 1` can be used for the upload direction.
+fn shaping_id(public_key: &str) -> u16 {
+    // FNV-1a, folded into 10..0xfffe
+    let mut hash: u32 = 0x811c9dc5;
+    for byte in public_key.bytes() {
+        hash ^= byte as u32;
+        hash = hash.wrapping_mul(0x01000193);
+    }
+    let id = (hash % 0xfff0) as u16 + 10;
+    id & !1
+}
+
+/// The id a peer's rules use: the one it already has, else its preferred id or, when another
+/// peer holds that one, the next free one after it. Ids are unique across all configs, since
+/// dummynet pipes and pf anchors are global on macOS. A peer whose id clashes with another's,
+/// as two hashed ids of older entries can, moves to a free one.
+fn assign_shaping_id(store: &RateLimitStore, config_name: &str, public_key: &str) -> Result<u16, WgError> {
+    let mut own = None;
+    let mut taken = HashSet::new();
+    for (name, entries) in store {
+        for entry in entries {
+            if name == config_name && entry.public_key == public_key {
+                own = Some(entry.id());
+            } else {
+                taken.insert(entry.id());
+            }
+        }
+    }
+    if let Some(id) = own.filter(|id| !taken.contains(id)) {
+        return Ok(id);
+    }
+
+    let mut id = shaping_id(public_key);
+    for _ in MIN_SHAPING_ID..=MAX_SHAPING_ID {
+        if !taken.contains(&id) {
+            return Ok(id);
+        }
+        id = if id >= MAX_SHAPING_ID { MIN_SHAPING_ID } else { id + 2 };
+    }
+    Err(WgError::Refused("Every tc class id is in use by another rate limit".to_string()))
+}
+
+fn peer_networks(peer: &Peer) -> Vec<&str> {
+    peer.allowed_ips
+        .split(',')
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+/// Generate tc (Linux) or dummynet (macOS) commands that cap a peer's bandwidth.
+/// `%i` is left for wg-quick to substitute with the interface name.
+pub fn generate_tc_rules(config: &WgConfig, peer: &Peer, rate_limit: &RateLimit) -> Result<TcRules, WgError> {
+    let store: RateLimitStore = store::load(RATE_LIMITS_FILE)?;
+    let id = assign_shaping_id(&store, &config.name, &peer.public_key)?;
+    rules_with_id(config, peer, rate_limit, id)
+}
+
+fn rules_with_id(config: &WgConfig, peer: &Peer, rate_limit: &RateLimit, id: u16) -> Result<TcRules, WgError> {
+    if !config.peers.iter().any(|p| p.public_key == peer.public_key) {
+        return Err(WgError::NotFound(format!("peer {} in {}", peer.public_key, config.name)));
+    }
+
+    let networks = peer_networks(peer);
+    if networks.is_empty() {
+        return Err(WgError::Parse("Peer has no AllowedIPs to match traffic on".to_string()));
+    }
+
+    if rate_limit.download_kbit.is_none() && rate_limit.upload_kbit.is_none() {
+        return Ok(TcRules::default());
+    }
+
+    if cfg!(target_os = "macos") {
+        Ok(dummynet_rules(id, &networks, rate_limit))
+    } else {
+        Ok(linux_tc_rules(id, &networks, rate_limit))
+    }
+}
+
+fn linux_tc_rules(id: u16, networks: &[&str], rate_limit: &RateLimit) -> TcRules {
+    let mut rules = TcRules::default();
+
+    if let Some(kbit) = rate_limit.download_kbit {
+        rules.post_up.push("tc qdisc add dev %i root handle 1: htb 2>/dev/null || true".to_string());
+        rules.post_up.push(format!("tc class add dev %i parent 1: classid 1:{:x} htb rate {}kbit", id, kbit));
+        for net in networks {
+            let (proto, matcher) = if net.contains(':') { ("ipv6", "ip6") } else { ("ip", "ip") };
+            rules.post_up.push(format!(
+                "tc filter add dev %i parent 1: protocol {} prio 1 u32 match {} dst {} flowid 1:{:x}",
+                proto, matcher, net, id
+            ));
+        }
+    }
+
+    if let Some(kbit) = rate_limit.upload_kbit {
+        // Burst of roughly 10ms worth of traffic, at least 16k
+        let burst = (kbit / 800).max(16);
+        rules.post_up.push("tc qdisc add dev %i handle ffff: ingress 2>/dev/null || true".to_string());
+        for net in networks {
+            let (proto, matcher) = if net.contains(':') { ("ipv6", "ip6") } else { ("ip", "ip") };
+            rules.post_up.push(format!(
+                "tc filter add dev %i parent ffff: protocol {} prio 1 u32 match {} src {} police rate {}kbit burst {}k drop flowid :1",
+                proto, matcher, net, kbit, burst
+            ));
+        }
+    }
+
+    // Qdiscs and filters disappear together with the interface, nothing to tear down
+    rules
+}
+
+fn dummynet_rules(id: u16, networks: &[&str], rate_limit: &RateLimit) -> TcRules {
+    let mut rules = TcRules::default();
+    let mut pf_rules = Vec::new();
+
+    if let Some(kbit) = rate_limit.download_kbit {
+        rules.post_up.push(format!("dnctl pipe {} config bw {}Kbit/s", id, kbit));
+        for net in networks {
+            pf_rules.push(format!("dummynet out quick to {} pipe {}", net, id));
+        }
+        rules.post_down.push(format!("dnctl pipe delete {} 2>/dev/null || true", id));
+    }
+
+    if let Some(kbit) = rate_limit.upload_kbit {
+        rules.post_up.push(format!("dnctl pipe {} config bw {}Kbit/s", id + 1, kbit));
+        for net in networks {
+            pf_rules.push(format!("dummynet in quick from {} pipe {}", net, id + 1));
+        }
+        rules.post_down.push(format!("dnctl pipe delete {} 2>/dev/null || true", id + 1));
+    }
+
+    // The default macOS pf.conf already evaluates dummynet anchors under com.apple/*
+    let anchor = format!("com.apple/wiredeck-qos-{}", id);
+    rules.post_up.push(format!(
+        "printf '{}\\n' | pfctl -a {} -f - && (pfctl -E 2>/dev/null || true)",
+        pf_rules.join("\\n"),
+        anchor
+    ));
+    rules.post_down.insert(0, format!("pfctl -a {} -F all 2>/dev/null || true", anchor));
+
+    rules
+}
+
+fn uninstall_rules(config: &mut WgConfig, rules: &TcRules) {
+    remove_hook_commands(&mut config.interface.post_up, &rules.post_up);
+    remove_hook_commands(&mut config.interface.post_down, &rules.post_down);
+}
+
+/// List the peers of a config that have a rate limit
+pub fn list_rate_limits(config_name: &str) -> Result<Vec<RateLimitEntry>, WgError> {
+    let store: RateLimitStore = store::load(RATE_LIMITS_FILE)?;
+    Ok(store.get(config_name).cloned().unwrap_or_default())
+}
+
+/// Set (or replace) a peer's rate limit, optionally installing the rules into PostUp/PostDown
+pub fn set_rate_limit(
+    config_name: &str,
+    public_key: &str,
+    rate_limit: RateLimit,
+    install: bool,
+) -> Result<RateLimitEntry, WgError> {
+    with_config_lock(config_name, || {
+        let mut config = parse_config(config_name)?;
+        let peer = config
+            .peers
+            .iter()
+            .find(|p| p.public_key == public_key)
+            .cloned()
+            .ok_or_else(|| WgError::NotFound(format!("peer {}", public_key)))?;
+
+        let mut store: RateLimitStore = store::load(RATE_LIMITS_FILE)?;
+        let id = assign_shaping_id(&store, config_name, public_key)?;
+        let rules = rules_with_id(&config, &peer, &rate_limit, id)?;
+
+        let entries = store.entry(config_name.to_string()).or_default();
+
+        let mut config_changed = false;
+        if let Some(previous) = entries.iter().find(|e| e.public_key == public_key) {
+            if previous.installed {
+                uninstall_rules(&mut config, &previous.rules);
+                config_changed = true;
+            }
+        }
+        entries.retain(|e| e.public_key != public_key);
+
+        if install {
+            add_hook_commands(&mut config.interface.post_up, &rules.post_up);
+            add_hook_commands(&mut config.interface.post_down, &rules.post_down);
+            config_changed = true;
+        }
+
+        if config_changed {
+            save_config(&config)?;
+        }
+
+        let entry = RateLimitEntry {
+            public_key: public_key.to_string(),
+            rate_limit,
+            rules,
+            installed: install,
+            shaping_id: Some(id),
+        };
+        entries.push(entry.clone());
+        store::save(RATE_LIMITS_FILE, &store)?;
+
+        Ok(entry)
+    })
+}
+
+/// Remove a peer's rate limit, including any rules installed into PostUp/PostDown
+pub fn clear_rate_limit(config_name: &str, public_key: &str) -> Result<(), WgError> {
+    with_config_lock(config_name, || {
+        let mut store: RateLimitStore = store::load(RATE_LIMITS_FILE)?;
+
+        let Some(entries) = store.get_mut(config_name) else {
+            return Ok(());
+        };
+
+        if let Some(previous) = entries.iter().find(|e| e.public_key == public_key) {
+            if previous.installed {
+                let mut config = parse_config(config_name)?;
+                uninstall_rules(&mut config, &previous.rules);
+                save_config(&config)?;
+            }
+        }
+
+        entries.retain(|e| e.public_key != public_key);
+        if entries.is_empty() {
+            store.remove(config_name);
+        }
+        store::save(RATE_LIMITS_FILE, &store)
+    })
+}
@@ -0,0 +1,202 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::process::Command;
+
+use crate::backup::list_config_backups;
+use crate::store::{self, now_secs};
+use crate::validation::ConfigReport;
+use crate::operations::with_snapshot;
+use crate::wireguard::{
+    config_file_exists, get_wireguard_dir, parse_config, parse_config_content, read_config_text, write_config_file,
+    WgConfig, WgError,
+};
+
+const QUARANTINE_FILE: &str = "quarantine.json";
+
+/// What the tunnel list can offer for a quarantined config
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QuarantineAction {
+    OpenInRawEditor,
+    RestoreFromBackup,
+}
+
+/// Stored mark on a config that failed to parse
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct QuarantineMark {
+    error: String,
+    detected_at: u64,
+}
+
+/// A config that doesn't parse: still listed, with why and what can be done about it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuarantinedConfig {
+    pub name: String,
+    pub path: PathBuf,
+    /// The parse error
+    pub error: String,
+    pub detected_at: u64,
+    /// Backups that parse, newest first; the first is what `restore_from_backup` uses by default
+    pub backups: Vec<PathBuf>,
+    pub actions: Vec<QuarantineAction>,
+}
+
+fn config_path(name: &str) -> PathBuf {
+    get_wireguard_dir().join(format!("{}.conf", name))
+}
+
+/// Backups of a config that parse, newest first: the backup destination, then the `.conf.bak`
+fn restorable_backups(name: &str) -> Vec<PathBuf> {
+    let path = config_path(name);
+    let mut candidates: Vec<PathBuf> = list_config_backups(name)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|b| b.path)
+        .collect();
+    candidates.push(path.with_extension("conf.bak"));
+
+    candidates
+        .into_iter()
+        .filter(|c| config_file_exists(c))
+        .filter(|c| read_config_text(c).is_ok_and(|content| parse_config_content(name, &path, &content).is_ok()))
+        .collect()
+}
+
+fn describe(name: &str, mark: QuarantineMark) -> QuarantinedConfig {
+    let backups = restorable_backups(name);
+    let mut actions = vec![QuarantineAction::OpenInRawEditor];
+    if !backups.is_empty() {
+        actions.push(QuarantineAction::RestoreFromBackup);
+    }
+    QuarantinedConfig {
+        name: name.to_string(),
+        path: config_path(name),
+        error: mark.error,
+        detected_at: mark.detected_at,
+        backups,
+        actions,
+    }
+}
+
+fn mark(name: &str, error: &str) -> Result<QuarantinedConfig, WgError> {
+    let mut marks: HashMap<String, QuarantineMark> = store::load(QUARANTINE_FILE)?;
+    let entry = marks.entry(name.to_string()).or_insert_with(|| QuarantineMark {
+        error: String::new(),
+        detected_at: now_secs(),
+    });
+    entry.error = error.to_string();
+    let entry = entry.clone();
+    store::save(QUARANTINE_FILE, &marks)?;
+    Ok(describe(name, entry))
+}
+
+fn release(name: &str) -> Result<(), WgError> {
+    let mut marks: HashMap<String, QuarantineMark> = store::load(QUARANTINE_FILE)?;
+    if marks.remove(name).is_some() {
+        store::save(QUARANTINE_FILE, &marks)?;
+    }
+    Ok(())
+}
+
+/// Parse a config, quarantining it when it doesn't parse and releasing it once it does again
+pub fn load_config(name: &str) -> Result<WgConfig, WgError> {
+    match parse_config(name) {
+        Ok(config) => {
+            release(name)?;
+            Ok(config)
+        }
+        Err(WgError::Parse(error)) => {
+            let hint = format!("{} (quarantined; open it in an editor or restore a backup)", error);
+            // Stored the way validation reports word it, so both paths agree
+            mark(name, &WgError::Parse(error).to_string())?;
+            Err(WgError::Parse(hint))
+        }
+        // Kept structured, so the editor can jump to the line
+        Err(e @ WgError::Syntax(_)) => {
+            mark(name, &e.to_string())?;
+            Err(e)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Bring the quarantine in line with a validation pass: files that failed to parse are marked,
+/// files that parse again are released, and marks for deleted files are dropped
+pub fn sync_quarantine(reports: &[ConfigReport]) -> Result<Vec<QuarantinedConfig>, WgError> {
+    let mut marks: HashMap<String, QuarantineMark> = store::load(QUARANTINE_FILE)?;
+    let before = marks.len();
+    marks.retain(|name, _| reports.iter().any(|r| &r.name == name && r.error.is_some()));
+    let mut changed = marks.len() != before;
+
+    for report in reports {
+        let Some(ref error) = report.error else {
+            continue;
+        };
+        let entry = marks.entry(report.name.clone()).or_insert_with(|| QuarantineMark {
+            error: String::new(),
+            detected_at: now_secs(),
+        });
+        if &entry.error != error {
+            entry.error = error.clone();
+            changed = true;
+        }
+    }
+
+    if changed {
+        store::save(QUARANTINE_FILE, &marks)?;
+    }
+    Ok(collect(marks))
+}
+
+fn collect(marks: HashMap<String, QuarantineMark>) -> Vec<QuarantinedConfig> {
+    let mut list: Vec<QuarantinedConfig> = marks.into_iter().map(|(name, mark)| describe(&name, mark)).collect();
+    list.sort_by(|a, b| a.name.cmp(&b.name));
+    list
+}
+
+/// Configs currently quarantined, by name
+pub fn list_quarantined_configs() -> Result<Vec<QuarantinedConfig>, WgError> {
+    Ok(collect(store::load(QUARANTINE_FILE)?))
+}
+
+/// Open a config file in the system text editor, for fixing what the form editor can't load.
+/// Root-owned files open read-only there; the editor will offer to save elsewhere.
+pub fn open_in_raw_editor(name: &str) -> Result<(), WgError> {
+    let path = config_path(name);
+    if !config_file_exists(&path) {
+        return Err(WgError::NotFound(name.to_string()));
+    }
+    let mut command = if cfg!(target_os = "macos") {
+        let mut open = Command::new("open");
+        open.arg("-t");
+        open
+    } else {
+        Command::new("xdg-open")
+    };
+    let output = command.arg(&path).output()?;
+    if !output.status.success() {
+        return Err(WgError::CommandFailed(String::from_utf8_lossy(&output.stderr).to_string()));
+    }
+    Ok(())
+}
+
+/// Replace a config with a backup, by default the newest one that parses. The broken file is
+/// backed up in turn, so nothing is lost.
+pub fn restore_from_backup(name: &str, backup: Option<PathBuf>) -> Result<WgConfig, WgError> {
+    let backups = restorable_backups(name);
+    let source = match backup {
+        Some(path) if backups.contains(&path) => path,
+        Some(path) => {
+            return Err(WgError::NotFound(format!("{} is not a backup of {} that parses", path.display(), name)))
+        }
+        None => backups
+            .into_iter()
+            .next()
+            .ok_or_else(|| WgError::NotFound(format!("No backup of {} parses", name)))?,
+    };
+
+    let content = read_config_text(&source)?;
+    with_snapshot("restore_from_backup", name, || write_config_file(&config_path(name), name, &content))?;
+    load_config(name)
+}
@@ -0,0 +1,106 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::time::SystemTime;
+
+use crate::quarantine::load_config;
+use crate::simulate::{interface_details, peer_diffs, PeerDiff};
+use crate::wireguard::{get_wireguard_dir, list_configs, WgConfig, WgError};
+
+/// Changed on disk while the user has unsaved edits; the UI offers merge or discard
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReloadConflict {
+    /// The file as it is now
+    pub disk: WgConfig,
+    /// How the unsaved edits differ from the file, as saving them would apply it
+    pub interface_changes: Vec<String>,
+    pub peers: Vec<PeerDiff>,
+}
+
+/// Outcome of `reload_config_if_unchanged`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum ReloadOutcome {
+    /// The file is still the version the UI loaded
+    Unchanged,
+    /// The file changed and nothing unsaved is in the way
+    Reloaded { config: WgConfig },
+    Conflict { conflict: ReloadConflict },
+}
+
+/// Reload a config the UI has open after it changed on disk. `known_hash` is the `content_hash`
+/// of the version the UI loaded; `unsaved` the user's edits, if any. Edits are never dropped
+/// silently: with unsaved edits and a changed file, the answer is a conflict to resolve.
+pub fn reload_config_if_unchanged(
+    name: &str,
+    known_hash: &str,
+    unsaved: Option<&WgConfig>,
+) -> Result<ReloadOutcome, WgError> {
+    let disk = load_config(name)?;
+    if disk.content_hash.as_deref() == Some(known_hash) {
+        return Ok(ReloadOutcome::Unchanged);
+    }
+    let Some(unsaved) = unsaved else {
+        return Ok(ReloadOutcome::Reloaded { config: disk });
+    };
+
+    let interface_changes = interface_details(&disk, unsaved);
+    let peers = peer_diffs(&disk, unsaved);
+    if interface_changes.is_empty() && peers.is_empty() {
+        // The outside edit made the same change the user did
+        return Ok(ReloadOutcome::Reloaded { config: disk });
+    }
+    Ok(ReloadOutcome::Conflict {
+        conflict: ReloadConflict {
+            disk,
+            interface_changes,
+            peers,
+        },
+    })
+}
+
+/// Modification time and size of a config file; enough to notice edits without reading it
+type FileStamp = (Option<SystemTime>, u64);
+
+fn scan() -> HashMap<String, FileStamp> {
+    let dir = get_wireguard_dir();
+    list_configs()
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|name| {
+            let meta = fs::metadata(dir.join(format!("{}.conf", name))).ok()?;
+            Some((name, (meta.modified().ok(), meta.len())))
+        })
+        .collect()
+}
+
+/// Polls the config directory for files added, edited or removed behind WireDeck's back
+pub struct ConfigWatcher {
+    stamps: HashMap<String, FileStamp>,
+}
+
+impl ConfigWatcher {
+    pub fn new() -> Self {
+        ConfigWatcher { stamps: scan() }
+    }
+
+    /// Names of configs that changed since the last poll, sorted
+    pub fn poll(&mut self) -> Vec<String> {
+        let current = scan();
+        let mut changed: Vec<String> = current
+            .iter()
+            .filter(|(name, stamp)| self.stamps.get(*name) != Some(stamp))
+            .map(|(name, _)| name.clone())
+            .chain(self.stamps.keys().filter(|name| !current.contains_key(*name)).cloned())
+            .collect();
+        changed.sort();
+        self.stamps = current;
+        changed
+    }
+}
+
+impl Default for ConfigWatcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,299 @@
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::path::Path;
+use std::time::Duration;
+
+use crate::guardrails::check_config_limits;
+use crate::identity::{config_id, names_by_id};
+use crate::keys::validate_config_keys;
+use crate::store::{self, now_secs};
+use crate::wireguard::{
+    check_managed, config_file_exists, content_hash, get_wireguard_dir, parse_config_content, validate_config_name,
+    write_config_file, WgConfig, WgError,
+};
+
+pub(crate) const SUBSCRIPTIONS_FILE: &str = "remote_configs.json";
+
+/// Shortest refresh interval accepted, to avoid hammering the portal
+const MIN_INTERVAL_SECS: u64 = 60;
+
+/// Credentials sent when downloading a remote config. They are kept in app data, which only
+/// this user can read, and never handed back: `list_subscriptions` blanks the secret parts.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RemoteAuth {
+    #[default]
+    None,
+    Basic { username: String, password: String },
+    Bearer { token: String },
+}
+
+impl RemoteAuth {
+    /// The same kind of credentials with the password or token left empty
+    fn redacted(&self) -> RemoteAuth {
+        match self {
+            RemoteAuth::None => RemoteAuth::None,
+            RemoteAuth::Basic { username, .. } => RemoteAuth::Basic {
+                username: username.clone(),
+                password: String::new(),
+            },
+            RemoteAuth::Bearer { .. } => RemoteAuth::Bearer { token: String::new() },
+        }
+    }
+}
+
+/// A config kept in sync with a file published over HTTPS
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RemoteSubscription {
+    /// Stable id of the config, so the subscription follows it through a rename
+    #[serde(default)]
+    pub config_id: String,
+    pub config_name: String,
+    pub url: String,
+    #[serde(default)]
+    pub auth: RemoteAuth,
+    pub interval_secs: u64,
+    pub etag: Option<String>,
+    /// SHA-256 of the last downloaded body, for servers that don't send ETags
+    pub content_hash: Option<String>,
+    /// Unix timestamp of the last check, successful or not
+    pub last_checked: Option<u64>,
+    pub last_error: Option<String>,
+}
+
+/// Outcome of checking one subscription
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum RefreshOutcome {
+    Unchanged { config_name: String },
+    Updated { config_name: String, config: Box<WgConfig> },
+    Failed { config_name: String, error: String },
+}
+
+/// Subscriptions, under the names their configs have now
+fn load_subscriptions() -> Result<Vec<RemoteSubscription>, WgError> {
+    let mut subscriptions: Vec<RemoteSubscription> = store::load(SUBSCRIPTIONS_FILE)?;
+    let names = names_by_id()?;
+    for subscription in &mut subscriptions {
+        if let Some(name) = names.get(&subscription.config_id) {
+            subscription.config_name = name.clone();
+        }
+    }
+    Ok(subscriptions)
+}
+
+fn save_subscriptions(subscriptions: &[RemoteSubscription]) -> Result<(), WgError> {
+    store::save(SUBSCRIPTIONS_FILE, &subscriptions)
+}
+
+/// List all remote config subscriptions, without their passwords and tokens
+pub fn list_subscriptions() -> Result<Vec<RemoteSubscription>, WgError> {
+    let mut subscriptions = load_subscriptions()?;
+    for subscription in &mut subscriptions {
+        subscription.auth = subscription.auth.redacted();
+    }
+    Ok(subscriptions)
+}
+
+/// Subscribe a config to a remote URL and download it right away
+pub fn subscribe_remote_config(
+    config_name: &str,
+    url: &str,
+    auth: RemoteAuth,
+    interval_secs: u64,
+) -> Result<WgConfig, WgError> {
+    validate_config_name(config_name)?;
+
+    if !url.starts_with("https://") {
+        return Err(WgError::Parse("Remote configs must be fetched over https://".to_string()));
+    }
+
+    let mut subscriptions = load_subscriptions()?;
+    if subscriptions.iter().any(|s| s.config_name == config_name) {
+        return Err(WgError::Parse(format!("{} is already subscribed to a remote config", config_name)));
+    }
+
+    let mut subscription = RemoteSubscription {
+        config_id: config_id(config_name)?,
+        config_name: config_name.to_string(),
+        url: url.to_string(),
+        auth,
+        interval_secs: interval_secs.max(MIN_INTERVAL_SECS),
+        etag: None,
+        content_hash: None,
+        last_checked: None,
+        last_error: None,
+    };
+
+    // An initial failure means the subscription is not saved at all
+    let config = match fetch(&mut subscription)? {
+        Some(config) => config,
+        None => return Err(WgError::CommandFailed("Server returned no config".to_string())),
+    };
+
+    subscriptions.push(subscription);
+    save_subscriptions(&subscriptions)?;
+
+    Ok(config)
+}
+
+/// Download a config from an https URL once, without subscribing to it
+pub fn import_remote_config(config_name: &str, url: &str) -> Result<WgConfig, WgError> {
+    validate_config_name(config_name)?;
+
+    if !url.starts_with("https://") {
+        return Err(WgError::Parse("Remote configs must be fetched over https://".to_string()));
+    }
+    if config_file_exists(&get_wireguard_dir().join(format!("{}.conf", config_name))) {
+        return Err(WgError::Parse(format!("{} already exists", config_name)));
+    }
+
+    let mut subscription = RemoteSubscription {
+        config_id: String::new(),
+        config_name: config_name.to_string(),
+        url: url.to_string(),
+        auth: RemoteAuth::None,
+        interval_secs: MIN_INTERVAL_SECS,
+        etag: None,
+        content_hash: None,
+        last_checked: None,
+        last_error: None,
+    };
+
+    fetch(&mut subscription)?.ok_or_else(|| WgError::CommandFailed("Server returned no config".to_string()))
+}
+
+/// Stop refreshing a config from its remote URL (the local file is kept)
+pub fn unsubscribe_remote_config(config_name: &str) -> Result<(), WgError> {
+    let mut subscriptions = load_subscriptions()?;
+    let before = subscriptions.len();
+    subscriptions.retain(|s| s.config_name != config_name);
+
+    if subscriptions.len() == before {
+        return Err(WgError::NotFound(format!("remote subscription for {}", config_name)));
+    }
+
+    save_subscriptions(&subscriptions)
+}
+
+/// Check every subscription whose interval has elapsed (or all of them with `force`)
+pub fn refresh_subscriptions(force: bool) -> Result<Vec<RefreshOutcome>, WgError> {
+    let mut subscriptions = load_subscriptions()?;
+    let now = now_secs();
+    let mut outcomes = Vec::new();
+
+    for subscription in subscriptions.iter_mut() {
+        let due = subscription
+            .last_checked
+            .map(|t| now >= t + subscription.interval_secs)
+            .unwrap_or(true);
+
+        if !force && !due {
+            continue;
+        }
+
+        let config_name = subscription.config_name.clone();
+        let outcome = match fetch(subscription) {
+            Ok(Some(config)) => RefreshOutcome::Updated {
+                config_name,
+                config: Box::new(config),
+            },
+            Ok(None) => RefreshOutcome::Unchanged { config_name },
+            Err(e) => RefreshOutcome::Failed {
+                config_name,
+                error: e.to_string(),
+            },
+        };
+        outcomes.push(outcome);
+    }
+
+    save_subscriptions(&subscriptions)?;
+    Ok(outcomes)
+}
+
+/// Download a subscription's URL and store it; returns `None` when nothing changed
+fn fetch(subscription: &mut RemoteSubscription) -> Result<Option<WgConfig>, WgError> {
+    subscription.last_checked = Some(now_secs());
+
+    let result = download(subscription);
+    subscription.last_error = result.as_ref().err().map(|e| e.to_string());
+
+    let (etag, content) = match result? {
+        Some(download) => download,
+        None => return Ok(None),
+    };
+
+    let content_hash = content_hash(&content);
+    if subscription.content_hash.as_deref() == Some(content_hash.as_str()) {
+        subscription.etag = etag;
+        return Ok(None);
+    }
+
+    let path = get_wireguard_dir().join(format!("{}.conf", subscription.config_name));
+    let config = parse_config_content(&subscription.config_name, &path, &content)
+        .and_then(|config| check_download(&config).map(|_| config))
+        .inspect_err(|e| subscription.last_error = Some(e.to_string()))?;
+
+    write_config_file(&path, &subscription.config_name, &content)?;
+    subscription.etag = etag;
+    subscription.content_hash = Some(content_hash);
+
+    Ok(Some(config))
+}
+
+/// The checks `save_config` makes before writing. A marker in the downloaded file doesn't
+/// block its own updates; a local config managed by another tool does.
+fn check_download(config: &WgConfig) -> Result<(), WgError> {
+    check_managed(&WgConfig {
+        managed_by: None,
+        ..config.clone()
+    })?;
+    validate_config_keys(config)?;
+    check_config_limits(config)
+}
+
+/// Hashes of downloads were `DefaultHasher` values, which change between Rust releases. They
+/// can't be converted, so they are dropped and the next refresh compares by SHA-256.
+pub(crate) fn migrate_to_sha256_hashes(dir: &Path) -> Result<(), WgError> {
+    if !dir.join(SUBSCRIPTIONS_FILE).exists() {
+        return Ok(());
+    }
+    let mut entries: Vec<Map<String, Value>> = store::load_from(dir.to_path_buf(), SUBSCRIPTIONS_FILE)?;
+    for entry in &mut entries {
+        entry.insert("content_hash".to_string(), Value::Null);
+    }
+    store::save_to(dir.to_path_buf(), SUBSCRIPTIONS_FILE, &entries)
+}
+
+fn download(subscription: &RemoteSubscription) -> Result<Option<(Option<String>, String)>, WgError> {
+    let mut request = ureq::get(&subscription.url).timeout(Duration::from_secs(30));
+
+    match subscription.auth {
+        RemoteAuth::None => {}
+        RemoteAuth::Basic { ref username, ref password } => {
+            let credentials = base64::engine::general_purpose::STANDARD.encode(format!("{}:{}", username, password));
+            request = request.set("Authorization", &format!("Basic {}", credentials));
+        }
+        RemoteAuth::Bearer { ref token } => {
+            request = request.set("Authorization", &format!("Bearer {}", token));
+        }
+    }
+
+    if let Some(ref etag) = subscription.etag {
+        request = request.set("If-None-Match", etag);
+    }
+
+    let response = request
+        .call()
+        .map_err(|e| WgError::CommandFailed(format!("Failed to fetch {}: {}", subscription.url, e)))?;
+
+    if response.status() == 304 {
+        return Ok(None);
+    }
+
+    let etag = response.header("ETag").map(|s| s.to_string());
+    let content = response.into_string()?;
+
+    Ok(Some((etag, content)))
+}
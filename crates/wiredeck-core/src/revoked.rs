@@ -0,0 +1,146 @@
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+
+use crate::diagnostics::Finding;
+use crate::keys::{normalize_key, validate_key_format};
+use crate::settings;
+use crate::store;
+use crate::wireguard::{get_public_key, WgConfig, WgError};
+
+/// Where `add_revoked_key` starts a list when none is configured yet
+const DEFAULT_LIST_FILE: &str = "revoked_keys.txt";
+
+/// A public key that must not be used anymore, e.g. of a lost device
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RevokedKey {
+    pub public_key: String,
+    pub reason: Option<String>,
+}
+
+/// Parse a revocation list: one public key per line, optionally followed by `# reason`.
+/// Blank lines and lines starting with `#` are skipped.
+pub fn parse_revocation_list(content: &str) -> Vec<RevokedKey> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let (key, reason) = match line.split_once('#') {
+                Some((key, reason)) => (key, Some(reason.trim()).filter(|r| !r.is_empty())),
+                None => (line, None),
+            };
+            let key = normalize_key(key);
+            (!key.is_empty()).then(|| RevokedKey {
+                public_key: key,
+                reason: reason.map(str::to_string),
+            })
+        })
+        .collect()
+}
+
+/// The configured revocation list file, if the check is enabled
+pub fn revocation_list_path() -> Option<PathBuf> {
+    settings::load_settings().ok().and_then(|s| s.revocation_list)
+}
+
+/// Point the check at a revocation list file, or turn it off with `None`
+pub fn set_revocation_list(path: Option<PathBuf>) -> Result<(), WgError> {
+    let mut settings = settings::load_settings()?;
+    settings.revocation_list = path;
+    settings::save_settings(&settings)
+}
+
+/// Keys in the configured revocation list; empty when the check is off or the file is missing
+pub fn list_revoked_keys() -> Result<Vec<RevokedKey>, WgError> {
+    let Some(path) = revocation_list_path() else {
+        return Ok(Vec::new());
+    };
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    Ok(parse_revocation_list(&fs::read_to_string(&path)?))
+}
+
+/// Append a key to the revocation list, starting one in the app data directory if none is configured
+pub fn add_revoked_key(public_key: &str, reason: Option<&str>) -> Result<RevokedKey, WgError> {
+    let public_key = validate_key_format(public_key)?;
+    let reason = reason.map(|r| r.replace(['\r', '\n'], " ").trim().to_string()).filter(|r| !r.is_empty());
+
+    if let Some(existing) = list_revoked_keys()?.into_iter().find(|k| k.public_key == public_key) {
+        return Ok(existing);
+    }
+
+    let path = match revocation_list_path() {
+        Some(path) => path,
+        None => {
+            let path = store::get_app_data_dir().join(DEFAULT_LIST_FILE);
+            set_revocation_list(Some(path.clone()))?;
+            path
+        }
+    };
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let mut line = public_key.clone();
+    if let Some(ref reason) = reason {
+        line.push_str(&format!("  # {}", reason));
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(&path)?;
+    // Don't glue the new key onto a last line that lacks its newline
+    let needs_newline = fs::read(&path)?.last().is_some_and(|b| *b != b'\n');
+    if needs_newline {
+        writeln!(file)?;
+    }
+    writeln!(file, "{}", line)?;
+
+    Ok(RevokedKey { public_key, reason })
+}
+
+fn describe(revoked: &RevokedKey) -> String {
+    match revoked.reason {
+        Some(ref reason) => format!("revoked ({})", reason),
+        None => "revoked".to_string(),
+    }
+}
+
+/// Refuse a public key that is on the revocation list
+pub fn check_not_revoked(public_key: &str) -> Result<(), WgError> {
+    let key = normalize_key(public_key);
+    match list_revoked_keys()?.iter().find(|k| k.public_key == key) {
+        Some(revoked) => Err(WgError::Refused(format!("This key is {}; generate a new one", describe(revoked)))),
+        None => Ok(()),
+    }
+}
+
+/// Revoked keys still present in a config, as its interface key or as peers
+pub fn check_config_revoked_keys(config: &WgConfig) -> Vec<Finding> {
+    let revoked = list_revoked_keys().unwrap_or_default();
+    if revoked.is_empty() {
+        return Vec::new();
+    }
+    let find = |key: &str| revoked.iter().find(|k| k.public_key == key);
+    let mut findings = Vec::new();
+
+    let private_key = config.interface.private_key.as_deref().map(normalize_key);
+    if let Some(Ok(interface_key)) = private_key.map(|k| get_public_key(&k)) {
+        if let Some(entry) = find(&interface_key) {
+            findings.push(Finding::error(
+                format!("The interface key of {} is {}", config.name, describe(entry)),
+                Some("Generate a new key pair and hand the new public key to every peer".to_string()),
+            ));
+        }
+    }
+
+    for peer in &config.peers {
+        if let Some(entry) = find(&normalize_key(&peer.public_key)) {
+            let label = peer.name.as_deref().unwrap_or(&peer.public_key);
+            findings.push(Finding::error(
+                format!("Peer {} uses a key that is {}", label, describe(entry)),
+                Some("Remove the peer, or replace its key once the device has a new one".to_string()),
+            ));
+        }
+    }
+
+    findings
+}
@@ -0,0 +1,228 @@
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::ffi::OsStr;
+use std::fs;
+use std::net::{IpAddr, Ipv4Addr, UdpSocket};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::addressing::free_ipv4_subnet;
+use crate::backup::private_temp_dir;
+use crate::elevation::sudo_output;
+use crate::wgquick::parse_failure;
+use crate::wireguard::{
+    generate_keypair, get_peer_status, serialize_config, Comments, Interface, Peer, WgConfig, WgError,
+};
+
+/// How long to wait for the two sandbox interfaces to complete a handshake
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Interval between packets sent to trigger the handshake
+const POKE_INTERVAL: Duration = Duration::from_millis(500);
+
+/// One stage of the sandbox self-test
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SandboxStep {
+    pub name: String,
+    pub ok: bool,
+    pub detail: Option<String>,
+}
+
+/// Outcome of `create_sandbox_tunnel`: every stage that ran, in order
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SandboxReport {
+    pub passed: bool,
+    pub steps: Vec<SandboxStep>,
+    pub duration_ms: u64,
+}
+
+#[derive(Default)]
+struct Steps(Vec<SandboxStep>);
+
+impl Steps {
+    /// Record a stage; returns its value when it succeeded
+    fn run<T>(&mut self, name: &str, result: Result<(T, Option<String>), WgError>) -> Option<T> {
+        let (ok, detail, value) = match result {
+            Ok((value, detail)) => (true, detail, Some(value)),
+            Err(e) => (false, Some(e.to_string()), None),
+        };
+        self.0.push(SandboxStep {
+            name: name.to_string(),
+            ok,
+            detail,
+        });
+        value
+    }
+}
+
+/// The two configs on disk and the address that only routes from A to B
+struct SandboxPair {
+    paths: [PathBuf; 2],
+    probe: Ipv4Addr,
+}
+
+/// Two distinct localhost UDP ports nothing is listening on right now
+fn free_udp_ports() -> Result<(u16, u16), WgError> {
+    // Hold both sockets at once so the OS can't hand out the same port twice
+    let a = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0))?;
+    let b = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0))?;
+    Ok((a.local_addr()?.port(), b.local_addr()?.port()))
+}
+
+fn sandbox_config(
+    dir: &Path,
+    name: &str,
+    private_key: String,
+    address: Ipv4Addr,
+    port: u16,
+    peer: Peer,
+) -> WgConfig {
+    WgConfig {
+        name: name.to_string(),
+        path: dir.join(format!("{}.conf", name)),
+        interface: Interface {
+            private_key: Some(private_key),
+            has_private_key: true,
+            address: vec![format!("{}/32", address)],
+            listen_port: Some(port),
+            dns: Vec::new(),
+            dns_search: Vec::new(),
+            mtu: None,
+            table: None,
+            fw_mark: None,
+            pre_up: Vec::new(),
+            post_up: Vec::new(),
+            pre_down: Vec::new(),
+            post_down: Vec::new(),
+            save_config: false,
+            extra: Vec::new(),
+            comments: Comments::default(),
+        },
+        peers: vec![peer],
+        managed_by: None,
+        content_hash: None,
+    }
+}
+
+fn sandbox_peer(public_key: String, port: u16, allowed: Ipv4Addr) -> Peer {
+    Peer {
+        public_key,
+        preshared_key: None,
+        allowed_ips: format!("{}/32", allowed),
+        persistent_keepalive: None,
+        endpoint: Some(format!("127.0.0.1:{}", port)),
+        name: None,
+        extra: Vec::new(),
+        comments: Comments::default(),
+    }
+}
+
+fn wg_quick(action: &str, path: &Path) -> Result<((), Option<String>), WgError> {
+    let output = sudo_output([OsStr::new("wg-quick"), OsStr::new(action), path.as_os_str()])?;
+    if !output.status.success() {
+        return Err(WgError::WgQuick(parse_failure(action, &String::from_utf8_lossy(&output.stderr))));
+    }
+    Ok(((), None))
+}
+
+/// Both interfaces complete a handshake over localhost. Traffic to the peer's *address* would be
+/// delivered locally without touching WireGuard, so packets go to an address only A routes to B.
+fn await_handshake(name: &str, probe: Ipv4Addr) -> Result<((), Option<String>), WgError> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))?;
+    let started = Instant::now();
+    while started.elapsed() < HANDSHAKE_TIMEOUT {
+        let _ = socket.send_to(b"wiredeck sandbox", (IpAddr::V4(probe), 9));
+        thread::sleep(POKE_INTERVAL);
+
+        let shook = get_peer_status(name)?
+            .iter()
+            .any(|p| p.latest_handshake.as_deref().is_some_and(|h| h != "0"));
+        if shook {
+            return Ok(((), Some(format!("after {} ms", started.elapsed().as_millis()))));
+        }
+    }
+    Err(WgError::CommandFailed(format!(
+        "No handshake within {} s; check that no firewall drops UDP on localhost",
+        HANDSHAKE_TIMEOUT.as_secs()
+    )))
+}
+
+/// Self-test that keys, tooling and privileges work: bring up two throwaway tunnels that point at
+/// each other on localhost, wait for them to shake hands, then tear both down again. The configs
+/// live in a private temp folder, never in the WireGuard directory, and are deleted afterwards.
+pub fn create_sandbox_tunnel() -> SandboxReport {
+    let started = Instant::now();
+    let mut steps = Steps::default();
+    let mut suffix = [0u8; 2];
+    rand::thread_rng().fill_bytes(&mut suffix);
+    let tag = format!("{:02x}{:02x}", suffix[0], suffix[1]);
+    let names = [format!("wdsbx{}a", tag), format!("wdsbx{}b", tag)];
+
+    let keys = steps.run(
+        "Generate keys",
+        generate_keypair().and_then(|a| Ok(([a, generate_keypair()?], None))),
+    );
+    let setup = keys.and_then(|keys| {
+        let written = write_configs(&names, keys).map(|(pair, detail)| (pair, Some(detail)));
+        steps.run("Write configs", written)
+    });
+
+    let mut up: Vec<PathBuf> = Vec::new();
+    if let Some(ref pair) = setup {
+        for (name, path) in names.iter().zip(&pair.paths) {
+            if steps.run(&format!("Bring up {}", name), wg_quick("up", path)).is_none() {
+                break;
+            }
+            up.push(path.clone());
+        }
+        if up.len() == pair.paths.len() {
+            steps.run("Handshake", await_handshake(&names[0], pair.probe));
+        }
+    }
+
+    for path in up.iter().rev() {
+        let name = path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+        steps.run(&format!("Tear down {}", name), wg_quick("down", path));
+    }
+    if let Some(pair) = setup {
+        for path in pair.paths {
+            let _ = fs::remove_file(path);
+        }
+    }
+
+    SandboxReport {
+        passed: steps.0.iter().all(|s| s.ok) && steps.0.iter().any(|s| s.name == "Handshake"),
+        steps: steps.0,
+        duration_ms: started.elapsed().as_millis() as u64,
+    }
+}
+
+/// Write both configs, with a description of the pair for the report
+fn write_configs(names: &[String; 2], keys: [(String, String); 2]) -> Result<(SandboxPair, String), WgError> {
+    let [(private_a, public_a), (private_b, public_b)] = keys;
+    let IpAddr::V4(net) = free_ipv4_subnet()?.addr else {
+        return Err(WgError::Parse("Expected an IPv4 subnet".to_string()));
+    };
+    let net = net.octets();
+    let host = |n: u8| Ipv4Addr::new(net[0], net[1], net[2], n);
+    let (address_a, address_b, probe) = (host(1), host(2), host(3));
+    let (port_a, port_b) = free_udp_ports()?;
+
+    let dir = private_temp_dir()?.join("sandbox");
+    fs::create_dir_all(&dir)?;
+    let peer_b = sandbox_peer(public_b, port_b, probe);
+    let a = sandbox_config(&dir, &names[0], private_a, address_a, port_a, peer_b);
+    let peer_a = sandbox_peer(public_a, port_a, address_a);
+    let b = sandbox_config(&dir, &names[1], private_b, address_b, port_b, peer_a);
+
+    for config in [&a, &b] {
+        fs::write(&config.path, serialize_config(config))?;
+    }
+    let detail = format!("{} on :{} and {} on :{}", names[0], port_a, names[1], port_b);
+    let pair = SandboxPair {
+        paths: [a.path, b.path],
+        probe,
+    };
+    Ok((pair, detail))
+}
@@ -0,0 +1,71 @@
+use crate::diagnostics::Finding;
+use crate::elevation::sudo_output;
+use crate::events;
+use crate::locks::with_config_lock;
+use crate::operations::with_snapshot;
+use crate::wgquick::parse_failure;
+use crate::wireguard::{
+    get_wireguard_dir, is_interface_up, parse_config, read_config_text, save_config, WgConfig, WgError,
+};
+
+/// What saving a config now would run into because of `SaveConfig = true`, if anything
+pub fn check_save_config(config_name: &str) -> Result<Option<Finding>, WgError> {
+    let config = parse_config(config_name)?;
+    if !config.interface.save_config {
+        return Ok(None);
+    }
+
+    if is_interface_up(config_name)? {
+        return Ok(Some(Finding::warning(
+            format!(
+                "{} has SaveConfig = true and is up: wg-quick will overwrite the file with the live state when it goes down, discarding edits made now",
+                config_name
+            ),
+            Some("Sync the live state into the file first, or turn SaveConfig off".to_string()),
+        )));
+    }
+
+    Ok(Some(Finding::info(format!(
+        "{} has SaveConfig = true: changes made with wg while it's up are written back to the file on down",
+        config_name
+    ))))
+}
+
+/// Write the running interface's state into the file (`wg-quick save`), so edits start from what
+/// wg-quick would write on down anyway. wg-quick rewrites the whole file, so comments are lost.
+pub fn sync_live_state(config_name: &str) -> Result<WgConfig, WgError> {
+    with_config_lock(config_name, || {
+        if !is_interface_up(config_name)? {
+            return Err(WgError::CommandFailed(format!("{} is not up", config_name)));
+        }
+
+        // wg-quick writes the file itself, so the change event is built from before and after reads
+        let path = get_wireguard_dir().join(format!("{}.conf", config_name));
+        let observed = events::is_observed();
+        let previous = observed.then(|| read_config_text(&path).ok()).flatten();
+        with_snapshot("sync_live_state", config_name, || {
+            let output = sudo_output(["wg-quick", "save", config_name])?;
+            if !output.status.success() {
+                return Err(WgError::WgQuick(parse_failure("save", &String::from_utf8_lossy(&output.stderr))));
+            }
+            Ok(())
+        })?;
+        if observed {
+            if let Ok(current) = read_config_text(&path) {
+                events::publish_change(config_name, &path, previous.as_deref(), &current);
+            }
+        }
+
+        parse_config(config_name)
+    })
+}
+
+/// Turn `SaveConfig` on or off in a config
+pub fn set_save_config(config_name: &str, enabled: bool) -> Result<WgConfig, WgError> {
+    with_config_lock(config_name, || {
+        let mut config = parse_config(config_name)?;
+        config.interface.save_config = enabled;
+        save_config(&config)?;
+        Ok(config)
+    })
+}
@@ -0,0 +1,173 @@
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+
+use crate::confirm::{self, Guarded};
+use crate::identity::{config_id, names_by_id};
+use crate::peers;
+use crate::store;
+use crate::wireguard::{parse_config, WgError};
+
+pub(crate) const SCHEDULE_FILE: &str = "scheduled_changes.json";
+
+/// What happens to a peer once its grace period is over
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PeerChangeKind {
+    Delete,
+    Disable,
+}
+
+/// A peer change that takes effect later, so its user can be warned first
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduledPeerChange {
+    pub id: String,
+    /// Stable id of the config, so a rename before the change comes due doesn't lose it
+    #[serde(default)]
+    pub config_id: String,
+    pub config_name: String,
+    pub public_key: String,
+    pub peer_name: Option<String>,
+    pub kind: PeerChangeKind,
+    pub scheduled_at: u64,
+    pub effective_at: u64,
+    /// Also take the peer off the running interface when the change applies, not only the file
+    #[serde(default)]
+    pub live: bool,
+}
+
+/// A scheduled change that came due, with the error if it couldn't be applied
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppliedPeerChange {
+    pub change: ScheduledPeerChange,
+    pub error: Option<String>,
+}
+
+/// Scheduled changes, under the names their configs have now
+fn load_schedule() -> Result<Vec<ScheduledPeerChange>, WgError> {
+    let mut changes: Vec<ScheduledPeerChange> = store::load(SCHEDULE_FILE)?;
+    let names = names_by_id()?;
+    for change in &mut changes {
+        if let Some(name) = names.get(&change.config_id) {
+            change.config_name = name.clone();
+        }
+    }
+    Ok(changes)
+}
+
+fn save_schedule(changes: &[ScheduledPeerChange]) -> Result<(), WgError> {
+    store::save(SCHEDULE_FILE, &changes)
+}
+
+fn random_id() -> String {
+    let mut bytes = [0u8; 8];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Delete or disable a peer after `grace_secs`. Deletions ask for the same confirmation as
+/// `delete_peer`, up front, since nobody is around to confirm when the change comes due.
+/// Scheduling a peer again replaces its earlier schedule.
+pub fn schedule_peer_change(
+    config_name: &str,
+    public_key: &str,
+    kind: PeerChangeKind,
+    grace_secs: u64,
+    token: Option<&str>,
+) -> Result<Guarded<ScheduledPeerChange>, WgError> {
+    let config = parse_config(config_name)?;
+    let peer = config
+        .peers
+        .iter()
+        .find(|p| p.public_key == public_key)
+        .ok_or_else(|| WgError::NotFound(format!("peer {}", public_key)))?;
+
+    let reasons = match kind {
+        PeerChangeKind::Delete => peers::delete_reasons(&config, public_key)?,
+        PeerChangeKind::Disable => Vec::new(),
+    };
+    let target = format!("{}/{}", config_name, public_key);
+    confirm::guard("schedule_peer_change", &target, reasons, token, || {
+        schedule_confirmed(config_name, public_key, peer.name.clone(), kind, grace_secs, false)
+    })
+}
+
+/// Schedule a change without asking; for callers that create the peer themselves
+pub(crate) fn schedule_confirmed(
+    config_name: &str,
+    public_key: &str,
+    peer_name: Option<String>,
+    kind: PeerChangeKind,
+    grace_secs: u64,
+    live: bool,
+) -> Result<ScheduledPeerChange, WgError> {
+    let now = store::now_secs();
+    let change = ScheduledPeerChange {
+        id: random_id(),
+        config_id: config_id(config_name)?,
+        config_name: config_name.to_string(),
+        public_key: public_key.to_string(),
+        peer_name,
+        kind,
+        scheduled_at: now,
+        effective_at: now + grace_secs,
+        live,
+    };
+
+    let mut changes = load_schedule()?;
+    changes.retain(|c| !(c.config_name == config_name && c.public_key == public_key));
+    changes.push(change.clone());
+    save_schedule(&changes)?;
+    Ok(change)
+}
+
+/// Pending changes, soonest first, optionally for one config
+pub fn list_scheduled_changes(config_name: Option<&str>) -> Result<Vec<ScheduledPeerChange>, WgError> {
+    let mut changes: Vec<_> = load_schedule()?
+        .into_iter()
+        .filter(|c| config_name.is_none_or(|name| c.config_name == name))
+        .collect();
+    changes.sort_by_key(|c| c.effective_at);
+    Ok(changes)
+}
+
+/// Call off a pending change; returns it, or `None` if it already ran or never existed
+pub fn cancel_scheduled_change(id: &str) -> Result<Option<ScheduledPeerChange>, WgError> {
+    let mut changes = load_schedule()?;
+    let Some(index) = changes.iter().position(|c| c.id == id) else {
+        return Ok(None);
+    };
+    let change = changes.remove(index);
+    save_schedule(&changes)?;
+    Ok(Some(change))
+}
+
+/// Apply every change whose grace period is over. Each runs once: a change that fails
+/// (e.g. the peer was removed by hand meanwhile) is reported and dropped, not retried.
+pub fn run_due_changes() -> Result<Vec<AppliedPeerChange>, WgError> {
+    let now = store::now_secs();
+    let (due, pending): (Vec<_>, Vec<_>) = load_schedule()?.into_iter().partition(|c| c.effective_at <= now);
+    if due.is_empty() {
+        return Ok(Vec::new());
+    }
+    save_schedule(&pending)?;
+
+    Ok(due
+        .into_iter()
+        .map(|change| {
+            let result = match change.kind {
+                PeerChangeKind::Delete => peers::delete_peer_confirmed(&change.config_name, &change.public_key),
+                PeerChangeKind::Disable => peers::disable_peer(&change.config_name, &change.public_key),
+            }
+            .and_then(|_| {
+                if change.live {
+                    peers::remove_live_peer(&change.config_name, &change.public_key)?;
+                }
+                Ok(())
+            });
+            AppliedPeerChange {
+                error: result.err().map(|e| e.to_string()),
+                change,
+            }
+        })
+        .collect())
+}
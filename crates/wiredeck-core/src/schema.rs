@@ -0,0 +1,37 @@
+use schemars::gen::SchemaSettings;
+use serde_json::{json, Value};
+
+use crate::confirm::Guarded;
+use crate::diagnostics::Finding;
+use crate::import::ImportResult;
+use crate::peers::{DumpImport, RosterImport};
+use crate::trace::Traced;
+use crate::wgquick::UpReport;
+use crate::wireguard::{Peer, PeerStatus, WgConfig};
+
+/// Version of the published schema; bumped whenever a change to it would break existing clients
+pub const SCHEMA_VERSION: u32 = 2;
+
+/// JSON Schema (draft-07) of the types crossing the API: configs, peers, live status and the
+/// payloads commands return. Every type is under `definitions`, keyed by its Rust name.
+pub fn api_schema() -> Value {
+    let mut gen = SchemaSettings::draft07().into_generator();
+    gen.subschema_for::<WgConfig>();
+    gen.subschema_for::<Peer>();
+    gen.subschema_for::<PeerStatus>();
+    gen.subschema_for::<Finding>();
+    gen.subschema_for::<UpReport>();
+    gen.subschema_for::<ImportResult>();
+    gen.subschema_for::<DumpImport>();
+    gen.subschema_for::<RosterImport>();
+    gen.subschema_for::<Guarded<WgConfig>>();
+    gen.subschema_for::<Traced<WgConfig>>();
+    gen.subschema_for::<Traced<UpReport>>();
+
+    json!({
+        "$schema": "http://json-schema.org/draft-07/schema#",
+        "title": "WireDeck API",
+        "version": SCHEMA_VERSION,
+        "definitions": gen.take_definitions(),
+    })
+}
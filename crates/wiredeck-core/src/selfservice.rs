@@ -0,0 +1,407 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use qrcode::{Color, QrCode};
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM};
+use ring::rand::{SecureRandom, SystemRandom};
+use ring::signature::{Ed25519KeyPair, KeyPair};
+use ring::{digest, pbkdf2};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::fs;
+use std::num::NonZeroU32;
+use std::path::PathBuf;
+
+use crate::i18n::{artifact_language, strings};
+use crate::metadata::update_peer_metadata;
+use crate::platform::Platform;
+use crate::store::{self, now_secs};
+use crate::wireguard::{
+    get_public_key, parse_config, require_private_key, serialize_config, split_list, Comments, Interface, Peer, WgConfig,
+    WgError,
+};
+
+const PORTALS_FILE: &str = "selfservice.json";
+
+const INDEX_HTML: &str = include_str!("../assets/selfservice/index.html");
+const PORTAL_JS: &str = include_str!("../assets/selfservice/portal.js");
+
+/// Slows down guessing access codes against a downloaded data file
+const PBKDF2_ITERATIONS: u32 = 210_000;
+
+/// Hashed with the access code to name a user's data file, so the bundle has no user list to browse
+const FILE_ID_PREFIX: &str = "wiredeck-selfservice:";
+
+/// Crockford base32, without look-alike letters
+const CODE_ALPHABET: &[u8] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+const CODE_LENGTH: usize = 16;
+
+/// A peer whose owner may download their own client config from the portal
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SelfServiceUser {
+    pub id: String,
+    pub name: String,
+    /// The peer in the server config this user's client config belongs to
+    pub public_key: String,
+    pub approved_at: u64,
+    /// Only set inside the portal store; cleared (and omitted) in anything handed out
+    #[serde(skip_serializing_if = "String::is_empty", default)]
+    client_private_key: String,
+    #[serde(skip_serializing_if = "String::is_empty", default)]
+    access_code: String,
+}
+
+/// A freshly approved user; the access code is shown once, to pass on to them
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApprovedUser {
+    pub user: SelfServiceUser,
+    pub access_code: String,
+}
+
+/// A static download portal for the peers of one server config
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SelfServicePortal {
+    pub config_name: String,
+    /// Directory the bundle is written to, e.g. a web server's document root
+    pub output_dir: PathBuf,
+    /// `host:port` clients connect to
+    pub endpoint: String,
+    /// AllowedIPs in the generated client configs
+    pub allowed_ips: String,
+    pub title: String,
+    pub users: Vec<SelfServiceUser>,
+    pub last_generated: Option<u64>,
+    /// PKCS#8 Ed25519 key the data files are signed with; never handed out
+    #[serde(skip_serializing_if = "String::is_empty", default)]
+    signing_key: String,
+}
+
+fn load_portals() -> Result<Vec<SelfServicePortal>, WgError> {
+    store::load(PORTALS_FILE)
+}
+
+fn save_portals(portals: &[SelfServicePortal]) -> Result<(), WgError> {
+    store::save(PORTALS_FILE, &portals)
+}
+
+fn crypto_error(_: ring::error::Unspecified) -> WgError {
+    WgError::CommandFailed("Cryptographic operation failed".to_string())
+}
+
+fn random_bytes<const N: usize>() -> Result<[u8; N], WgError> {
+    let mut bytes = [0u8; N];
+    SystemRandom::new().fill(&mut bytes).map_err(crypto_error)?;
+    Ok(bytes)
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Access codes as shown to users: `ABCD-EFGH-JKMN-PQRS`
+fn new_access_code() -> Result<String, WgError> {
+    let bytes = random_bytes::<CODE_LENGTH>()?;
+    let chars: Vec<char> = bytes.iter().map(|b| CODE_ALPHABET[*b as usize % CODE_ALPHABET.len()] as char).collect();
+    Ok(chars.chunks(4).map(|c| c.iter().collect::<String>()).collect::<Vec<_>>().join("-"))
+}
+
+/// The code as typed into the portal: case, dashes and spaces don't matter
+fn normalize_code(code: &str) -> String {
+    code.chars().filter(|c| !c.is_whitespace() && *c != '-').collect::<String>().to_uppercase()
+}
+
+fn file_id(code: &str) -> String {
+    let digest = digest::digest(&digest::SHA256, format!("{}{}", FILE_ID_PREFIX, code).as_bytes());
+    hex(&digest.as_ref()[..16])
+}
+
+fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+pub(crate) fn qr_svg(text: &str) -> Option<String> {
+    let code = QrCode::new(text.as_bytes()).ok()?;
+    let width = code.width();
+    // Four modules of quiet zone on each side
+    let mut path = String::new();
+    for (i, color) in code.to_colors().into_iter().enumerate() {
+        if color == Color::Dark {
+            path.push_str(&format!("M{} {}h1v1h-1z", i % width + 4, i / width + 4));
+        }
+    }
+    Some(format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 {size} {size}\" shape-rendering=\"crispEdges\">\
+         <rect width=\"{size}\" height=\"{size}\" fill=\"#fff\"/><path d=\"{path}\" fill=\"#000\"/></svg>",
+        size = width + 8,
+        path = path
+    ))
+}
+
+/// The client side of a server peer: its own key and addresses, the server as its only peer
+fn client_config(
+    server: &WgConfig,
+    server_public_key: &str,
+    portal: &SelfServicePortal,
+    user: &SelfServiceUser,
+    peer: &Peer,
+) -> WgConfig {
+    WgConfig {
+        name: server.name.clone(),
+        path: PathBuf::from(format!("{}.conf", server.name)),
+        interface: Interface {
+            private_key: Some(user.client_private_key.clone()),
+            has_private_key: true,
+            address: split_list(&peer.allowed_ips),
+            listen_port: None,
+            dns: server.interface.dns.clone(),
+            dns_search: server.interface.dns_search.clone(),
+            mtu: server.interface.mtu,
+            table: None,
+            fw_mark: None,
+            pre_up: Vec::new(),
+            post_up: Vec::new(),
+            pre_down: Vec::new(),
+            post_down: Vec::new(),
+            save_config: false,
+            extra: Vec::new(),
+            comments: Comments::default(),
+        },
+        peers: vec![Peer {
+            public_key: server_public_key.to_string(),
+            preshared_key: None,
+            allowed_ips: portal.allowed_ips.clone(),
+            persistent_keepalive: Some(25),
+            endpoint: Some(portal.endpoint.clone()),
+            name: Some(portal.title.clone()),
+            extra: Vec::new(),
+            comments: Comments::default(),
+        }],
+        managed_by: None,
+        content_hash: None,
+    }
+}
+
+/// Encrypt a user's payload under their access code and sign the result
+fn seal(payload: &[u8], code: &str, signer: &Ed25519KeyPair) -> Result<String, WgError> {
+    let salt = random_bytes::<16>()?;
+    let iv = random_bytes::<12>()?;
+    let mut key = [0u8; 32];
+    let iterations = NonZeroU32::new(PBKDF2_ITERATIONS).unwrap();
+    pbkdf2::derive(pbkdf2::PBKDF2_HMAC_SHA256, iterations, &salt, code.as_bytes(), &mut key);
+
+    let key = LessSafeKey::new(UnboundKey::new(&AES_256_GCM, &key).map_err(crypto_error)?);
+    let mut sealed = payload.to_vec();
+    key.seal_in_place_append_tag(Nonce::assume_unique_for_key(iv), Aad::empty(), &mut sealed)
+        .map_err(crypto_error)?;
+
+    let (salt, iv, ciphertext) = (STANDARD.encode(salt), STANDARD.encode(iv), STANDARD.encode(sealed));
+    let signature = signer.sign(format!("{}.{}.{}", salt, iv, ciphertext).as_bytes());
+    Ok(json!({
+        "version": 1,
+        "salt": salt,
+        "iv": iv,
+        "ciphertext": ciphertext,
+        "signature": STANDARD.encode(signature.as_ref()),
+    })
+    .to_string())
+}
+
+/// Write the bundle for a portal, replacing whatever data files the last run left
+fn write_bundle(portal: &SelfServicePortal, server: &WgConfig) -> Result<(), WgError> {
+    let corrupt = || WgError::Parse("Portal signing key is corrupt".to_string());
+    let pkcs8 = STANDARD.decode(&portal.signing_key).map_err(|_| corrupt())?;
+    let signer = Ed25519KeyPair::from_pkcs8(&pkcs8).map_err(|_| corrupt())?;
+    let server_public_key = get_public_key(require_private_key(server)?)?;
+
+    let data_dir = portal.output_dir.join("data");
+    fs::create_dir_all(&data_dir)?;
+    for entry in fs::read_dir(&data_dir)?.flatten() {
+        if entry.path().extension().is_some_and(|e| e == "json") {
+            fs::remove_file(entry.path())?;
+        }
+    }
+
+    // Users whose peer was removed from the server config simply get no file
+    for user in &portal.users {
+        let Some(peer) = server.peers.iter().find(|p| p.public_key == user.public_key) else {
+            continue;
+        };
+        let text = serialize_config(&client_config(server, &server_public_key, portal, user, peer));
+        let payload = json!({
+            "name": user.name,
+            "file_name": format!("{}.conf", server.name),
+            "qr_svg": qr_svg(&text),
+            "config": text,
+        });
+        let code = normalize_code(&user.access_code);
+        let sealed = seal(payload.to_string().as_bytes(), &code, &signer)?;
+        fs::write(data_dir.join(format!("{}.json", file_id(&code))), sealed)?;
+    }
+
+    let language = artifact_language();
+    let text = strings(language);
+    let index = INDEX_HTML
+        .replace("{{LANG}}", language.tag())
+        .replace("{{TITLE}}", &html_escape(&portal.title))
+        .replace("{{SIGNING_KEY}}", &STANDARD.encode(signer.public_key().as_ref()))
+        .replace("{{T_INTRO}}", &html_escape(text.portal_intro))
+        .replace("{{T_SUBMIT}}", &html_escape(text.portal_submit))
+        .replace("{{T_SCAN}}", &html_escape(text.portal_scan))
+        .replace("{{T_DOWNLOAD}}", &html_escape(text.portal_download));
+    let script_text = json!({
+        "checking": text.portal_checking,
+        "invalid": text.portal_invalid,
+        "unsigned": text.portal_unsigned,
+        "failed": text.portal_failed,
+    });
+    let script = PORTAL_JS
+        .replace("{{PBKDF2_ITERATIONS}}", &PBKDF2_ITERATIONS.to_string())
+        .replace("{{FILE_ID_PREFIX}}", FILE_ID_PREFIX)
+        .replace("{{TEXT}}", &script_text.to_string());
+    fs::write(portal.output_dir.join("index.html"), index)?;
+    fs::write(portal.output_dir.join("portal.js"), script)?;
+    Ok(())
+}
+
+/// A portal as handed out, without keys or access codes
+fn public_view(portal: &SelfServicePortal) -> SelfServicePortal {
+    let mut portal = portal.clone();
+    portal.signing_key.clear();
+    for user in &mut portal.users {
+        user.client_private_key.clear();
+        user.access_code.clear();
+    }
+    portal
+}
+
+/// Modify the portal of a config in place and persist it
+fn update_portal<T, F>(config_name: &str, update: F) -> Result<T, WgError>
+where
+    F: FnOnce(&mut SelfServicePortal) -> Result<T, WgError>,
+{
+    let mut portals = load_portals()?;
+    let portal = portals
+        .iter_mut()
+        .find(|p| p.config_name == config_name)
+        .ok_or_else(|| WgError::NotFound(format!("self-service portal for {}", config_name)))?;
+    let result = update(portal)?;
+    save_portals(&portals)?;
+    Ok(result)
+}
+
+/// The portal set up for a config, if any
+pub fn get_selfservice_portal(config_name: &str) -> Result<Option<SelfServicePortal>, WgError> {
+    Ok(load_portals()?.iter().find(|p| p.config_name == config_name).map(public_view))
+}
+
+/// Create or change the portal for a server config; users and the signing key are kept
+pub fn configure_selfservice_portal(
+    config_name: &str,
+    output_dir: PathBuf,
+    endpoint: &str,
+    allowed_ips: Option<&str>,
+    title: Option<&str>,
+) -> Result<SelfServicePortal, WgError> {
+    let config = parse_config(config_name)?;
+    if endpoint.trim().is_empty() {
+        return Err(WgError::Parse("The endpoint clients connect to is required".to_string()));
+    }
+
+    let mut portals = load_portals()?;
+    if !portals.iter().any(|p| p.config_name == config_name) {
+        let rng = SystemRandom::new();
+        let signing_key = Ed25519KeyPair::generate_pkcs8(&rng).map_err(crypto_error)?;
+        portals.push(SelfServicePortal {
+            config_name: config_name.to_string(),
+            output_dir: output_dir.clone(),
+            endpoint: String::new(),
+            allowed_ips: String::new(),
+            title: String::new(),
+            users: Vec::new(),
+            last_generated: None,
+            signing_key: STANDARD.encode(signing_key.as_ref()),
+        });
+    }
+
+    let portal = portals.iter_mut().find(|p| p.config_name == config_name).unwrap();
+    portal.output_dir = output_dir;
+    portal.endpoint = endpoint.trim().to_string();
+    portal.allowed_ips = allowed_ips.unwrap_or("0.0.0.0/0, ::/0").to_string();
+    portal.title = title.map(str::to_string).unwrap_or_else(|| format!("{} VPN", config.name));
+    let updated = public_view(portal);
+    save_portals(&portals)?;
+    Ok(updated)
+}
+
+/// Let the owner of a peer download their config. WireDeck only knows the peer's public key,
+/// so the client's private key has to be supplied; it must match. The device's platform, when
+/// given, is recorded in the peer's metadata.
+pub fn approve_selfservice_user(
+    config_name: &str,
+    public_key: &str,
+    client_private_key: &str,
+    name: &str,
+    platform: Option<Platform>,
+) -> Result<ApprovedUser, WgError> {
+    let config = parse_config(config_name)?;
+    if !config.peers.iter().any(|p| p.public_key == public_key) {
+        return Err(WgError::NotFound(format!("peer {} in {}", public_key, config_name)));
+    }
+    if get_public_key(client_private_key)? != public_key {
+        return Err(WgError::Parse("The private key doesn't belong to this peer".to_string()));
+    }
+
+    let access_code = new_access_code()?;
+    let user = update_portal(config_name, |portal| {
+        portal.users.retain(|u| u.public_key != public_key);
+        let user = SelfServiceUser {
+            id: hex(&random_bytes::<8>()?),
+            name: name.trim().to_string(),
+            public_key: public_key.to_string(),
+            approved_at: now_secs(),
+            client_private_key: client_private_key.trim().to_string(),
+            access_code: access_code.clone(),
+        };
+        portal.users.push(user.clone());
+        Ok(user)
+    })?;
+
+    if platform.is_some() {
+        update_peer_metadata(config_name, public_key, |m| m.platform = platform)?;
+    }
+
+    let mut user = user;
+    user.client_private_key.clear();
+    user.access_code.clear();
+    Ok(ApprovedUser { user, access_code })
+}
+
+/// Withdraw a user's access; their file disappears on the next generation
+pub fn revoke_selfservice_user(config_name: &str, user_id: &str) -> Result<(), WgError> {
+    update_portal(config_name, |portal| {
+        let before = portal.users.len();
+        portal.users.retain(|u| u.id != user_id);
+        if portal.users.len() == before {
+            return Err(WgError::NotFound(format!("self-service user {}", user_id)));
+        }
+        Ok(())
+    })
+}
+
+/// Write the static portal (index.html, portal.js and one signed, encrypted file per user)
+pub fn generate_selfservice_bundle(config_name: &str) -> Result<SelfServicePortal, WgError> {
+    let server = parse_config(config_name)?;
+    update_portal(config_name, |portal| {
+        write_bundle(portal, &server)?;
+        portal.last_generated = Some(now_secs());
+        Ok(public_view(portal))
+    })
+}
+
+/// Regenerate the portal of a config that was just saved, if it has one. Best effort:
+/// a failing bundle never fails the save.
+pub fn sync_selfservice_bundle(config: &WgConfig) {
+    let has_portal = load_portals().is_ok_and(|portals| portals.iter().any(|p| p.config_name == config.name));
+    if has_portal {
+        let _ = generate_selfservice_bundle(&config.name);
+    }
+}
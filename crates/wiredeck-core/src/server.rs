@@ -0,0 +1,322 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::process::Command;
+
+use crate::cidr::Cidr;
+use crate::diagnostics::Finding;
+use crate::elevation::sudo_output;
+use crate::firewall::firewall_findings;
+use crate::locks::with_config_lock;
+use crate::privileged::record_change;
+use crate::snippets::{render_snippet, RenderedSnippet};
+use crate::trace;
+use crate::wireguard::{
+    add_hook_commands, get_real_interface_name, hook_commands, is_interface_up, parse_config, save_config, WgConfig,
+    WgError,
+};
+
+/// Forwarding/NAT state relevant to a config acting as a server
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ForwardingReport {
+    pub config_name: String,
+    pub subnet: Option<String>,
+    pub findings: Vec<Finding>,
+}
+
+/// Where `setup_server_nat` puts the generated rules
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NatApplyMode {
+    /// Append to PostUp/PostDown so wg-quick installs them on every up
+    Config,
+    /// Install them now on the running interface, leaving the file alone
+    Live,
+}
+
+/// Whether a config waits for peers to connect or only dials out
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfigRole {
+    /// Some peer has no Endpoint, so it has to reach this host on its ListenPort
+    Server,
+    /// Every peer has an Endpoint; any local port will do
+    Client,
+}
+
+/// Run a command and return its stdout if it succeeded
+fn run(program: &str, args: &[&str]) -> Option<String> {
+    trace::output(Command::new(program).args(args))
+        .ok()
+        .filter(|o| o.status.success())
+        .map(|o| String::from_utf8_lossy(&o.stdout).to_string())
+}
+
+/// The IPv4 network of the interface Address, if any
+pub fn tunnel_subnet(config: &WgConfig) -> Option<Cidr> {
+    config
+        .interface
+        .address
+        .iter()
+        .find_map(|a| Cidr::parse(a).ok().filter(|c| c.addr.is_ipv4()))
+        .map(|c| c.network())
+}
+
+/// Server when any peer lacks an Endpoint, client otherwise
+pub fn config_role(config: &WgConfig) -> ConfigRole {
+    if config.peers.iter().any(|p| p.endpoint.is_none()) {
+        ConfigRole::Server
+    } else {
+        ConfigRole::Client
+    }
+}
+
+/// Check the ListenPort against the config's role: servers need one, clients are better off without
+pub fn listen_port_findings(config: &WgConfig) -> Vec<Finding> {
+    let waiting = config.peers.iter().filter(|p| p.endpoint.is_none()).count();
+    match (config_role(config), config.interface.listen_port) {
+        (ConfigRole::Server, None) => vec![Finding::error(
+            format!(
+                "{} peer(s) have no Endpoint and must connect to this host, but it has no ListenPort.",
+                waiting
+            ),
+            Some("Set a ListenPort, e.g. 51820, and open it in the firewall".to_string()),
+        )],
+        (ConfigRole::Client, Some(port)) => vec![Finding::info(format!(
+            "Client config with a fixed ListenPort {}; without one the kernel picks a free port.",
+            port
+        ))],
+        _ => Vec::new(),
+    }
+}
+
+/// Whether the config routes everything through a peer, i.e. is a client of a full tunnel
+fn is_full_tunnel_client(config: &WgConfig) -> bool {
+    config
+        .peers
+        .iter()
+        .any(|p| p.allowed_ips.split(',').any(|a| a.trim() == "0.0.0.0/0"))
+}
+
+fn post_up_mentions(config: &WgConfig, needle: &str) -> bool {
+    config.interface.post_up.iter().any(|p| p.contains(needle))
+}
+
+/// Inspect IP forwarding and NAT for a config's tunnel subnet
+pub fn check_forwarding(config_name: &str) -> Result<ForwardingReport, WgError> {
+    let config = parse_config(config_name)?;
+    let subnet = tunnel_subnet(&config);
+    let mut findings = Vec::new();
+
+    if is_full_tunnel_client(&config) {
+        findings.push(Finding::info(
+            "This config sends all traffic to a peer; forwarding and NAT only matter on the server side.",
+        ));
+    }
+
+    findings.extend(listen_port_findings(&config));
+    if cfg!(target_os = "macos") {
+        check_macos(&config, subnet.as_ref(), &mut findings);
+    } else {
+        check_linux(&config, subnet.as_ref(), &mut findings);
+    }
+    if !is_full_tunnel_client(&config) {
+        findings.extend(firewall_findings(config_name));
+    }
+
+    Ok(ForwardingReport {
+        config_name: config_name.to_string(),
+        subnet: subnet.map(|s| s.to_string()),
+        findings,
+    })
+}
+
+fn check_linux(config: &WgConfig, subnet: Option<&Cidr>, findings: &mut Vec<Finding>) {
+    let forwarding = std::fs::read_to_string("/proc/sys/net/ipv4/ip_forward")
+        .ok()
+        .or_else(|| run("sysctl", &["-n", "net.ipv4.ip_forward"]));
+
+    match forwarding.as_deref().map(str::trim) {
+        Some("1") => findings.push(Finding::info("IPv4 forwarding is enabled.")),
+        Some(_) if post_up_mentions(config, "ip_forward=1") => {
+            findings.push(Finding::info("IPv4 forwarding is off now but PostUp enables it."))
+        }
+        Some(_) => findings.push(Finding::error(
+            "IPv4 forwarding is disabled, so peers can't reach anything beyond this host.",
+            Some("sysctl -w net.ipv4.ip_forward=1 (persist it in /etc/sysctl.conf)".to_string()),
+        )),
+        None => findings.push(Finding::warning("Could not read net.ipv4.ip_forward.", None)),
+    }
+
+    let Some(subnet) = subnet else {
+        findings.push(Finding::warning("Interface has no IPv4 Address; skipping NAT checks.", None));
+        return;
+    };
+    let subnet_str = subnet.to_string();
+
+    let nat_rules = run("sudo", &["-n", "iptables", "-t", "nat", "-S", "POSTROUTING"]);
+    let has_masquerade = match nat_rules {
+        Some(ref rules) => rules.lines().any(|l| {
+            l.contains("MASQUERADE") && (l.contains(&subnet_str) || !l.contains(" -s "))
+        }),
+        // Fall back to nftables when iptables isn't available
+        None => run("sudo", &["-n", "nft", "list", "ruleset"])
+            .map(|r| r.contains("masquerade"))
+            .unwrap_or(false),
+    };
+
+    if has_masquerade {
+        findings.push(Finding::info(format!("A masquerade rule covers {}.", subnet_str)));
+    } else if post_up_mentions(config, "MASQUERADE") {
+        findings.push(Finding::info("PostUp installs a masquerade rule when the tunnel comes up."));
+    } else {
+        findings.push(Finding::error(
+            format!("No masquerade rule for {}; peers will have no internet access through this host.", subnet_str),
+            Some("Apply the 'nat_masquerade' snippet or run setup_server_nat".to_string()),
+        ));
+    }
+
+    if let Some(forward) = run("sudo", &["-n", "iptables", "-S", "FORWARD"]) {
+        let drops_by_default = forward.lines().any(|l| l.trim() == "-P FORWARD DROP");
+        let accepts_tunnel = forward.lines().any(|l| l.contains("-j ACCEPT") && (l.contains("-i ") || l.contains("-o ")))
+            || post_up_mentions(config, "FORWARD");
+        if drops_by_default && !accepts_tunnel {
+            findings.push(Finding::error(
+                "The FORWARD chain drops by default and nothing accepts tunnel traffic.",
+                Some("Apply the 'forward_tunnel' snippet".to_string()),
+            ));
+        }
+    }
+}
+
+fn check_macos(config: &WgConfig, subnet: Option<&Cidr>, findings: &mut Vec<Finding>) {
+    match run("sysctl", &["-n", "net.inet.ip.forwarding"]).as_deref().map(str::trim) {
+        Some("1") => findings.push(Finding::info("IPv4 forwarding is enabled.")),
+        Some(_) if post_up_mentions(config, "net.inet.ip.forwarding=1") => {
+            findings.push(Finding::info("IPv4 forwarding is off now but PostUp enables it."))
+        }
+        Some(_) => findings.push(Finding::error(
+            "IPv4 forwarding is disabled, so peers can't reach anything beyond this host.",
+            Some("sudo sysctl -w net.inet.ip.forwarding=1".to_string()),
+        )),
+        None => findings.push(Finding::warning("Could not read net.inet.ip.forwarding.", None)),
+    }
+
+    let pf_enabled = run("sudo", &["-n", "pfctl", "-s", "info"])
+        .map(|info| info.contains("Status: Enabled"));
+    match pf_enabled {
+        Some(true) => {}
+        Some(false) => findings.push(Finding::error(
+            "pf is disabled, so no NAT rules are active.",
+            Some("sudo pfctl -E".to_string()),
+        )),
+        None => findings.push(Finding::warning("Could not query pf (needs sudo).", None)),
+    }
+
+    let Some(subnet) = subnet else {
+        findings.push(Finding::warning("Interface has no IPv4 Address; skipping NAT checks.", None));
+        return;
+    };
+    let subnet_str = subnet.to_string();
+
+    let nat = [
+        run("sudo", &["-n", "pfctl", "-s", "nat"]),
+        run("sudo", &["-n", "pfctl", "-a", "com.apple/wiredeck-nat", "-s", "nat"]),
+    ];
+    let has_nat = nat
+        .iter()
+        .flatten()
+        .any(|rules| rules.lines().any(|l| l.starts_with("nat") && l.contains(&subnet_str)));
+
+    if has_nat {
+        findings.push(Finding::info(format!("A pf NAT rule covers {}.", subnet_str)));
+    } else if post_up_mentions(config, "pfctl") {
+        findings.push(Finding::info("PostUp loads pf rules when the tunnel comes up."));
+    } else {
+        findings.push(Finding::error(
+            format!("No pf NAT rule for {}; peers will have no internet access through this host.", subnet_str),
+            Some("Apply the 'pf_nat' snippet or run setup_server_nat".to_string()),
+        ));
+    }
+}
+
+/// Snippets that together give peers forwarded, NATed access on this platform
+fn nat_snippet_ids() -> &'static [&'static str] {
+    if cfg!(target_os = "macos") {
+        &["pf_nat"]
+    } else {
+        &["ip_forwarding", "forward_tunnel", "nat_masquerade"]
+    }
+}
+
+/// Generate forwarding and masquerade rules for a config's tunnel subnet
+pub fn generate_nat_rules(config: &WgConfig, wan_interface: &str) -> Result<RenderedSnippet, WgError> {
+    if tunnel_subnet(config).is_none() {
+        return Err(WgError::Parse("Interface has no IPv4 Address to NAT".to_string()));
+    }
+
+    let params = HashMap::from([("wan_interface".to_string(), wan_interface.to_string())]);
+    let mut rules = RenderedSnippet {
+        post_up: Vec::new(),
+        post_down: Vec::new(),
+    };
+    for id in nat_snippet_ids() {
+        let rendered = render_snippet(config, id, params.clone())?;
+        rules.post_up.extend(rendered.post_up);
+        rules.post_down.extend(rendered.post_down);
+    }
+
+    Ok(rules)
+}
+
+/// Generate NAT rules for a config and write them into PostUp/PostDown or apply them live
+pub fn setup_server_nat(config_name: &str, wan_interface: &str, mode: NatApplyMode) -> Result<RenderedSnippet, WgError> {
+    with_config_lock(config_name, || {
+        let mut config = parse_config(config_name)?;
+        let rules = generate_nat_rules(&config, wan_interface)?;
+
+        match mode {
+            NatApplyMode::Config => {
+                let missing = |hook: &[String], commands: &[String]| -> Vec<String> {
+                    let present: Vec<&str> = hook_commands(hook).collect();
+                    commands.iter().filter(|c| !present.contains(&c.as_str())).cloned().collect()
+                };
+                let post_up = missing(&config.interface.post_up, &rules.post_up);
+                let post_down = missing(&config.interface.post_down, &rules.post_down);
+                if post_up.is_empty() && post_down.is_empty() {
+                    return Ok(rules);
+                }
+                add_hook_commands(&mut config.interface.post_up, &post_up);
+                add_hook_commands(&mut config.interface.post_down, &post_down);
+                save_config(&config)?;
+            }
+            NatApplyMode::Live => {
+                if !is_interface_up(config_name)? {
+                    return Err(WgError::CommandFailed(format!("{} is not up", config_name)));
+                }
+                let interface = get_real_interface_name(config_name)?;
+                let mut applied = Vec::new();
+                let mut failure = None;
+                for command in &rules.post_up {
+                    let command = command.replace("%i", &interface);
+                    let output = sudo_output(["sh", "-c", &command])?;
+                    if !output.status.success() {
+                        failure = Some(format!("{}: {}", command, String::from_utf8_lossy(&output.stderr).trim()));
+                        break;
+                    }
+                    applied.push(command);
+                }
+
+                // Log whatever did run, so a partial setup can still be undone
+                if !applied.is_empty() {
+                    let revert = rules.post_down.iter().map(|c| c.replace("%i", &interface)).collect();
+                    record_change("nat_live", Some(config_name), applied, revert);
+                }
+                if let Some(failure) = failure {
+                    return Err(WgError::CommandFailed(failure));
+                }
+            }
+        }
+
+        Ok(rules)
+    })
+}
@@ -0,0 +1,86 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Mutex;
+use std::time::Instant;
+
+use crate::store::now_secs;
+
+/// Path every embedded HTTP server answers for liveness checks
+pub const HEALTHZ_PATH: &str = "/healthz";
+
+struct Running {
+    address: SocketAddr,
+    started_at: u64,
+    started: Instant,
+}
+
+static SERVICES: Mutex<Option<HashMap<String, Running>>> = Mutex::new(None);
+
+/// An embedded server (REST API, metrics exporter, ...) and where it listens
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmbeddedService {
+    pub name: String,
+    pub address: String,
+    pub port: u16,
+    pub started_at: u64,
+    pub uptime_secs: u64,
+}
+
+/// Keeps a service listed as running; dropping it (e.g. when the server thread exits) unlists it
+pub struct ServiceRegistration {
+    name: String,
+}
+
+impl Drop for ServiceRegistration {
+    fn drop(&mut self) {
+        if let Some(services) = SERVICES.lock().unwrap().as_mut() {
+            services.remove(&self.name);
+        }
+    }
+}
+
+/// Record that an embedded server is listening on `address`. Call once the socket is bound.
+pub fn register_service(name: &str, address: SocketAddr) -> ServiceRegistration {
+    let mut guard = SERVICES.lock().unwrap();
+    guard.get_or_insert_with(HashMap::new).insert(
+        name.to_string(),
+        Running {
+            address,
+            started_at: now_secs(),
+            started: Instant::now(),
+        },
+    );
+    ServiceRegistration { name: name.to_string() }
+}
+
+/// Embedded servers currently listening, by name
+pub fn embedded_services_status() -> Vec<EmbeddedService> {
+    let guard = SERVICES.lock().unwrap();
+    let mut services: Vec<EmbeddedService> = guard
+        .iter()
+        .flatten()
+        .map(|(name, running)| EmbeddedService {
+            name: name.clone(),
+            address: running.address.ip().to_string(),
+            port: running.address.port(),
+            started_at: running.started_at,
+            uptime_secs: running.started.elapsed().as_secs(),
+        })
+        .collect();
+    services.sort_by(|a, b| a.name.cmp(&b.name));
+    services
+}
+
+/// Status code and JSON body for a `/healthz` request to the named service, `None` for other paths.
+/// Unauthenticated on purpose: it reveals only that the service is up and for how long.
+pub fn healthz_response(service: &str, path: &str) -> Option<(u16, String)> {
+    if path.split('?').next() != Some(HEALTHZ_PATH) {
+        return None;
+    }
+    let (code, body) = match embedded_services_status().into_iter().find(|s| s.name == service) {
+        Some(status) => (200, serde_json::json!({ "status": "ok", "uptime_secs": status.uptime_secs })),
+        None => (503, serde_json::json!({ "status": "stopping" })),
+    };
+    Some((code, body.to_string()))
+}
@@ -0,0 +1,49 @@
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use crate::backup::BackupPolicy;
+use crate::confirm::ConfirmationPolicy;
+use crate::guardrails::Guardrails;
+use crate::i18n::Language;
+use crate::metrics::PollingPolicy;
+use crate::netinfo::EndpointLookup;
+use crate::store;
+use crate::wireguard::WgError;
+
+const SETTINGS_FILE: &str = "settings.json";
+
+/// Application-wide settings persisted in the app data directory
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AppSettings {
+    /// Prefer IPv6 when a peer endpoint resolves to both address families
+    pub prefer_ipv6_endpoints: bool,
+    /// Which mutations need an explicit confirmation round-trip
+    pub confirmations: ConfirmationPolicy,
+    /// How often the status monitor samples running tunnels
+    pub status_polling: PollingPolicy,
+    /// Opt-in reverse DNS / ASN lookups for peer endpoints
+    pub endpoint_lookup: EndpointLookup,
+    /// Return the external commands each operation ran alongside its result
+    pub command_trace: bool,
+    /// Language of PDF sheets and self-service portals
+    pub artifact_language: Language,
+    /// File of revoked public keys (one per line, `#` comments); keys in it are refused when set
+    pub revocation_list: Option<PathBuf>,
+    /// Where config backups are written and how many are kept
+    pub backups: BackupPolicy,
+    /// Peer count and file size limits for configs
+    pub guardrails: Guardrails,
+    /// Also publish config change events on a Unix socket in the app data directory
+    pub event_socket: bool,
+}
+
+/// Load settings, falling back to defaults for anything missing
+pub fn load_settings() -> Result<AppSettings, WgError> {
+    store::load_global(SETTINGS_FILE)
+}
+
+/// Persist settings
+pub fn save_settings(settings: &AppSettings) -> Result<(), WgError> {
+    store::save_global(SETTINGS_FILE, settings)
+}
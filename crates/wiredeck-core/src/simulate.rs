@@ -0,0 +1,234 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+
+use crate::cidr::Cidr;
+use crate::metrics::ONLINE_HANDSHAKE_SECS;
+use crate::store::now_secs;
+use crate::wireguard::{get_peer_status, is_interface_up, parse_config, Peer, PeerStatus, WgConfig, WgError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PeerChangeKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+/// How one peer would differ on the interface
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PeerDiff {
+    pub public_key: String,
+    pub name: Option<String>,
+    pub kind: PeerChangeKind,
+    /// What changed, e.g. "AllowedIPs 10.0.0.2/32 -> 10.0.0.3/32"
+    pub details: Vec<String>,
+}
+
+/// A peer whose traffic would be cut by the change
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DroppedConnection {
+    pub public_key: String,
+    pub name: Option<String>,
+    pub reason: String,
+}
+
+/// What applying a proposed config would do to the running tunnel, without doing it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Simulation {
+    pub config_name: String,
+    pub interface_up: bool,
+    /// Interface-level changes only take effect after a restart, which interrupts every peer
+    pub requires_restart: bool,
+    pub interface_changes: Vec<String>,
+    pub peers: Vec<PeerDiff>,
+    /// Routes wg-quick would add or remove for AllowedIPs
+    pub routes_added: Vec<String>,
+    pub routes_removed: Vec<String>,
+    pub dropped: Vec<DroppedConnection>,
+}
+
+/// Routes wg-quick installs for a config: every peer's AllowedIPs, normalized
+fn routes(config: &WgConfig) -> BTreeSet<String> {
+    config
+        .peers
+        .iter()
+        .flat_map(|p| allowed(&p.allowed_ips))
+        .collect()
+}
+
+fn allowed(allowed_ips: &str) -> Vec<String> {
+    match Cidr::parse_list(allowed_ips) {
+        Ok(list) => list.iter().map(|c| c.network().to_string()).collect(),
+        // Unparseable entries still show up in the diff as written
+        Err(_) => allowed_ips.split(',').map(|a| a.trim().to_string()).filter(|a| !a.is_empty()).collect(),
+    }
+}
+
+fn changed<T: PartialEq + std::fmt::Debug>(details: &mut Vec<String>, label: &str, old: &T, new: &T) {
+    if old != new {
+        details.push(format!("{} {:?} -> {:?}", label, old, new));
+    }
+}
+
+/// Unknown keys that were added, removed or changed; values aren't shown since some hold secrets
+fn extra_changed(details: &mut Vec<String>, old: &[(String, String)], new: &[(String, String)]) {
+    let keys: BTreeSet<&str> = old.iter().chain(new).map(|(key, _)| key.as_str()).collect();
+    for key in keys {
+        let values = |extra: &[(String, String)]| -> Vec<String> {
+            extra.iter().filter(|(k, _)| k == key).map(|(_, v)| v.clone()).collect()
+        };
+        if values(old) != values(new) {
+            details.push(format!("{} changes", key));
+        }
+    }
+}
+
+fn peer_details(old: &Peer, new: &Peer) -> Vec<String> {
+    let mut details = Vec::new();
+    if allowed(&old.allowed_ips) != allowed(&new.allowed_ips) {
+        details.push(format!("AllowedIPs {} -> {}", old.allowed_ips, new.allowed_ips));
+    }
+    changed(&mut details, "Endpoint", &old.endpoint, &new.endpoint);
+    changed(&mut details, "PersistentKeepalive", &old.persistent_keepalive, &new.persistent_keepalive);
+    changed(&mut details, "Name", &old.name, &new.name);
+    extra_changed(&mut details, &old.extra, &new.extra);
+    details
+}
+
+pub(crate) fn interface_details(old: &WgConfig, new: &WgConfig) -> Vec<String> {
+    let (old, new) = (&old.interface, &new.interface);
+    let mut details = Vec::new();
+    if old.private_key.as_deref().map(str::trim) != new.private_key.as_deref().map(str::trim) {
+        // Never echo keys
+        details.push("PrivateKey changes: every peer must be given the new public key".to_string());
+    }
+    changed(&mut details, "Address", &old.address, &new.address);
+    changed(&mut details, "ListenPort", &old.listen_port, &new.listen_port);
+    changed(&mut details, "DNS", &old.dns, &new.dns);
+    changed(&mut details, "DNS search domains", &old.dns_search, &new.dns_search);
+    changed(&mut details, "MTU", &old.mtu, &new.mtu);
+    changed(&mut details, "Table", &old.table, &new.table);
+    changed(&mut details, "FwMark", &old.fw_mark, &new.fw_mark);
+    changed(&mut details, "PreUp", &old.pre_up, &new.pre_up);
+    changed(&mut details, "PostUp", &old.post_up, &new.post_up);
+    changed(&mut details, "PreDown", &old.pre_down, &new.pre_down);
+    changed(&mut details, "PostDown", &old.post_down, &new.post_down);
+    changed(&mut details, "SaveConfig", &old.save_config, &new.save_config);
+    extra_changed(&mut details, &old.extra, &new.extra);
+    details
+}
+
+/// How the peers of two versions of a config differ: removed and changed ones in the old
+/// order, then added ones
+pub(crate) fn peer_diffs(old: &WgConfig, new: &WgConfig) -> Vec<PeerDiff> {
+    let mut peers = Vec::new();
+    for before in &old.peers {
+        match new.peers.iter().find(|p| p.public_key == before.public_key) {
+            None => peers.push(PeerDiff {
+                public_key: before.public_key.clone(),
+                name: before.name.clone(),
+                kind: PeerChangeKind::Removed,
+                details: Vec::new(),
+            }),
+            Some(after) => {
+                let details = peer_details(before, after);
+                if !details.is_empty() {
+                    peers.push(PeerDiff {
+                        public_key: before.public_key.clone(),
+                        name: after.name.clone(),
+                        kind: PeerChangeKind::Changed,
+                        details,
+                    });
+                }
+            }
+        }
+    }
+    for after in &new.peers {
+        if !old.peers.iter().any(|p| p.public_key == after.public_key) {
+            peers.push(PeerDiff {
+                public_key: after.public_key.clone(),
+                name: after.name.clone(),
+                kind: PeerChangeKind::Added,
+                details: Vec::new(),
+            });
+        }
+    }
+    peers
+}
+
+fn is_active(status: Option<&PeerStatus>, now: u64) -> bool {
+    status
+        .and_then(|s| s.latest_handshake.as_deref())
+        .and_then(|h| h.parse::<u64>().ok())
+        .is_some_and(|h| h > 0 && now.saturating_sub(h) < ONLINE_HANDSHAKE_SECS)
+}
+
+/// Report what saving `proposed` over `config_name` would change on the live interface: peers
+/// added, removed or changed, routes that come and go, and which active peers would lose traffic.
+pub fn simulate_change(config_name: &str, proposed: &WgConfig) -> Result<Simulation, WgError> {
+    let current = parse_config(config_name)?;
+    let interface_up = is_interface_up(config_name).unwrap_or(false);
+    let live: HashMap<String, PeerStatus> = if interface_up {
+        get_peer_status(config_name)?.into_iter().map(|s| (s.public_key.clone(), s)).collect()
+    } else {
+        HashMap::new()
+    };
+    let now = now_secs();
+    let active = |key: &str| interface_up && is_active(live.get(key), now);
+
+    let interface_changes = interface_details(&current, proposed);
+    let requires_restart = interface_up && !interface_changes.is_empty();
+
+    let peers = peer_diffs(&current, proposed);
+    let mut dropped = Vec::new();
+    for diff in &peers {
+        if !active(&diff.public_key) {
+            continue;
+        }
+        let old = current.peers.iter().find(|p| p.public_key == diff.public_key);
+        let new = proposed.peers.iter().find(|p| p.public_key == diff.public_key);
+        let reason = match (diff.kind, old, new) {
+            (PeerChangeKind::Removed, _, _) => "Removed while connected".to_string(),
+            (PeerChangeKind::Changed, Some(old), Some(new)) => {
+                let lost: Vec<String> = allowed(&old.allowed_ips)
+                    .into_iter()
+                    .filter(|a| !allowed(&new.allowed_ips).contains(a))
+                    .collect();
+                if lost.is_empty() {
+                    continue;
+                }
+                format!("Traffic for {} would no longer reach it", lost.join(", "))
+            }
+            _ => continue,
+        };
+        dropped.push(DroppedConnection {
+            public_key: diff.public_key.clone(),
+            name: diff.name.clone(),
+            reason,
+        });
+    }
+
+    if requires_restart {
+        for peer in proposed.peers.iter().filter(|p| active(&p.public_key)) {
+            if !dropped.iter().any(|d| d.public_key == peer.public_key) {
+                dropped.push(DroppedConnection {
+                    public_key: peer.public_key.clone(),
+                    name: peer.name.clone(),
+                    reason: "Interrupted while the tunnel restarts".to_string(),
+                });
+            }
+        }
+    }
+
+    let (before, after) = (routes(&current), routes(proposed));
+    Ok(Simulation {
+        config_name: config_name.to_string(),
+        interface_up,
+        requires_restart,
+        interface_changes,
+        peers,
+        routes_added: after.difference(&before).cloned().collect(),
+        routes_removed: before.difference(&after).cloned().collect(),
+        dropped,
+    })
+}
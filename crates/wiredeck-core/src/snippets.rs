@@ -0,0 +1,238 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::locks::with_config_lock;
+use crate::server::tunnel_subnet;
+use crate::store;
+use crate::wireguard::{add_hook_commands, hook_commands, parse_config, save_config, WgConfig, WgError};
+
+const SNIPPETS_FILE: &str = "snippets.json";
+
+/// A variable a snippet needs, written as `{name}` in its commands
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnippetParam {
+    pub name: String,
+    pub description: String,
+}
+
+/// A named PostUp/PostDown template
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snippet {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub params: Vec<SnippetParam>,
+    pub post_up: Vec<String>,
+    pub post_down: Vec<String>,
+    #[serde(default)]
+    pub builtin: bool,
+}
+
+/// Commands produced by rendering a snippet
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RenderedSnippet {
+    pub post_up: Vec<String>,
+    pub post_down: Vec<String>,
+}
+
+fn param(name: &str, description: &str) -> SnippetParam {
+    SnippetParam {
+        name: name.to_string(),
+        description: description.to_string(),
+    }
+}
+
+fn commands(lines: &[&str]) -> Vec<String> {
+    lines.iter().map(|s| s.to_string()).collect()
+}
+
+/// Templates shipped with WireDeck
+pub fn builtin_snippets() -> Vec<Snippet> {
+    vec![
+        Snippet {
+            id: "nat_masquerade".to_string(),
+            name: "NAT masquerade (iptables)".to_string(),
+            description: "Let peers reach the internet through this host's WAN interface".to_string(),
+            params: vec![
+                param("wan_interface", "Interface facing the internet, e.g. eth0"),
+                param("subnet", "Tunnel subnet; defaults to the interface Address network"),
+            ],
+            post_up: commands(&["iptables -t nat -A POSTROUTING -s {subnet} -o {wan_interface} -j MASQUERADE"]),
+            post_down: commands(&["iptables -t nat -D POSTROUTING -s {subnet} -o {wan_interface} -j MASQUERADE"]),
+            builtin: true,
+        },
+        Snippet {
+            id: "forward_tunnel".to_string(),
+            name: "Forward tunnel traffic (iptables)".to_string(),
+            description: "Accept forwarded packets to and from the tunnel interface".to_string(),
+            params: vec![],
+            post_up: commands(&[
+                "iptables -A FORWARD -i %i -j ACCEPT",
+                "iptables -A FORWARD -o %i -j ACCEPT",
+            ]),
+            post_down: commands(&[
+                "iptables -D FORWARD -i %i -j ACCEPT",
+                "iptables -D FORWARD -o %i -j ACCEPT",
+            ]),
+            builtin: true,
+        },
+        Snippet {
+            id: "ip_forwarding".to_string(),
+            name: "Enable IP forwarding (Linux)".to_string(),
+            description: "Turn on IPv4 forwarding; left enabled on down since other services may rely on it".to_string(),
+            params: vec![],
+            post_up: commands(&["sysctl -w net.ipv4.ip_forward=1"]),
+            post_down: vec![],
+            builtin: true,
+        },
+        Snippet {
+            id: "firewall_open_port".to_string(),
+            name: "Open firewall port (iptables)".to_string(),
+            description: "Accept incoming traffic on a port, usually the ListenPort".to_string(),
+            params: vec![
+                param("port", "Port to open; defaults to ListenPort"),
+                param("protocol", "udp or tcp; defaults to udp"),
+            ],
+            post_up: commands(&["iptables -A INPUT -p {protocol} --dport {port} -j ACCEPT"]),
+            post_down: commands(&["iptables -D INPUT -p {protocol} --dport {port} -j ACCEPT"]),
+            builtin: true,
+        },
+        Snippet {
+            id: "pf_nat".to_string(),
+            name: "NAT with pf (macOS)".to_string(),
+            description: "Enable forwarding and NAT the tunnel subnet out of the WAN interface".to_string(),
+            params: vec![
+                param("wan_interface", "Interface facing the internet, e.g. en0"),
+                param("subnet", "Tunnel subnet; defaults to the interface Address network"),
+            ],
+            post_up: commands(&[
+                "sysctl -w net.inet.ip.forwarding=1",
+                "echo 'nat on {wan_interface} from {subnet} to any -> ({wan_interface})' | pfctl -a com.apple/wiredeck-nat -f - && (pfctl -E 2>/dev/null || true)",
+            ]),
+            post_down: commands(&["pfctl -a com.apple/wiredeck-nat -F all 2>/dev/null || true"]),
+            builtin: true,
+        },
+    ]
+}
+
+fn load_user_snippets() -> Result<Vec<Snippet>, WgError> {
+    store::load(SNIPPETS_FILE)
+}
+
+/// All snippets, built-in ones first
+pub fn list_snippets() -> Result<Vec<Snippet>, WgError> {
+    let mut snippets = builtin_snippets();
+    snippets.extend(load_user_snippets()?);
+    Ok(snippets)
+}
+
+/// Create or replace a user snippet
+pub fn save_snippet(mut snippet: Snippet) -> Result<(), WgError> {
+    if builtin_snippets().iter().any(|s| s.id == snippet.id) {
+        return Err(WgError::Parse(format!("'{}' is a built-in snippet", snippet.id)));
+    }
+    snippet.builtin = false;
+
+    let mut snippets = load_user_snippets()?;
+    snippets.retain(|s| s.id != snippet.id);
+    snippets.push(snippet);
+    store::save(SNIPPETS_FILE, &snippets)
+}
+
+/// Delete a user snippet
+pub fn delete_snippet(id: &str) -> Result<(), WgError> {
+    let mut snippets = load_user_snippets()?;
+    snippets.retain(|s| s.id != id);
+    store::save(SNIPPETS_FILE, &snippets)
+}
+
+/// Values filled in when the caller doesn't provide them
+fn default_params(config: &WgConfig) -> HashMap<String, String> {
+    let mut defaults = HashMap::new();
+
+    if let Some(subnet) = tunnel_subnet(config) {
+        defaults.insert("subnet".to_string(), subnet.to_string());
+    }
+    if let Some(port) = config.interface.listen_port {
+        defaults.insert("port".to_string(), port.to_string());
+    }
+    defaults.insert("protocol".to_string(), "udp".to_string());
+
+    defaults
+}
+
+fn render_line(line: &str, values: &HashMap<String, String>) -> Result<String, WgError> {
+    let mut output = String::new();
+    let mut rest = line;
+
+    while let Some(start) = rest.find('{') {
+        output.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let end = after
+            .find('}')
+            .ok_or_else(|| WgError::Parse(format!("Unclosed placeholder in '{}'", line)))?;
+        let name = &after[..end];
+        let value = values
+            .get(name)
+            .ok_or_else(|| WgError::Parse(format!("Missing snippet parameter '{}'", name)))?;
+        output.push_str(value);
+        rest = &after[end + 1..];
+    }
+    output.push_str(rest);
+
+    Ok(output)
+}
+
+/// Render a snippet for a config without changing anything
+pub fn render_snippet(config: &WgConfig, snippet_id: &str, params: HashMap<String, String>) -> Result<RenderedSnippet, WgError> {
+    let snippet = list_snippets()?
+        .into_iter()
+        .find(|s| s.id == snippet_id)
+        .ok_or_else(|| WgError::NotFound(format!("snippet {}", snippet_id)))?;
+
+    let mut values = default_params(config);
+    for (name, value) in params {
+        // Values end up in a shell command line
+        let safe = !value.is_empty()
+            && value
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "./:_-".contains(c));
+        if !safe {
+            return Err(WgError::Parse(format!("Invalid value for snippet parameter '{}'", name)));
+        }
+        values.insert(name, value);
+    }
+
+    Ok(RenderedSnippet {
+        post_up: snippet
+            .post_up
+            .iter()
+            .map(|l| render_line(l, &values))
+            .collect::<Result<_, _>>()?,
+        post_down: snippet
+            .post_down
+            .iter()
+            .map(|l| render_line(l, &values))
+            .collect::<Result<_, _>>()?,
+    })
+}
+
+/// Render a snippet and append it to the config's PostUp/PostDown
+pub fn apply_snippet(config_name: &str, snippet_id: &str, params: HashMap<String, String>) -> Result<WgConfig, WgError> {
+    with_config_lock(config_name, || {
+        let mut config = parse_config(config_name)?;
+        let rendered = render_snippet(&config, snippet_id, params)?;
+
+        // Applying the same snippet twice would duplicate the rules
+        let already: Vec<&str> = hook_commands(&config.interface.post_up).collect();
+        if !rendered.post_up.is_empty() && rendered.post_up.iter().all(|c| already.contains(&c.as_str())) {
+            return Ok(config);
+        }
+
+        add_hook_commands(&mut config.interface.post_up, &rendered.post_up);
+        add_hook_commands(&mut config.interface.post_down, &rendered.post_down);
+        save_config(&config)?;
+
+        Ok(config)
+    })
+}
@@ -0,0 +1,207 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::path::Path;
+
+use crate::store;
+use crate::wireguard::{add_hook_commands, parse_config, remove_hook_commands, save_config, WgConfig, WgError};
+
+const SPLIT_DNS_FILE: &str = "split_dns.json";
+
+/// Present when systemd-resolved is the system resolver
+const RESOLVED_RUN_DIR: &str = "/run/systemd/resolve";
+const DNSMASQ_DIR: &str = "/etc/dnsmasq.d";
+
+/// Names under one domain that are resolved by the tunnel's own servers
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SplitDnsRule {
+    /// e.g. `corp.example`, which also covers every name below it
+    pub domain: String,
+    pub servers: Vec<String>,
+}
+
+/// How the rules are handed to the system resolver
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SplitDnsBackend {
+    /// Supplemental resolver entries in the macOS dynamic store
+    Scutil,
+    /// Routing domains on the tunnel's link
+    SystemdResolved,
+    /// `server=/domain/ip` lines in a dnsmasq drop-in
+    Dnsmasq,
+}
+
+/// Shell commands installing the rules, suitable for PostUp/PostDown
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SplitDnsHooks {
+    pub post_up: Vec<String>,
+    pub post_down: Vec<String>,
+}
+
+/// The split DNS rules of one config and the hooks that apply them
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SplitDns {
+    pub rules: Vec<SplitDnsRule>,
+    pub backend: SplitDnsBackend,
+    pub hooks: SplitDnsHooks,
+}
+
+/// Split DNS keyed by config name
+type SplitDnsStore = HashMap<String, SplitDns>;
+
+fn normalize_rule(rule: SplitDnsRule) -> Result<SplitDnsRule, WgError> {
+    let domain = rule
+        .domain
+        .trim()
+        .trim_start_matches("*.")
+        .trim_matches('.')
+        .to_ascii_lowercase();
+    let valid = !domain.is_empty()
+        && domain.split('.').all(|label| {
+            !label.is_empty()
+                && label.len() <= 63
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        });
+    if !valid {
+        return Err(WgError::Parse(format!("'{}' is not a domain name", rule.domain)));
+    }
+
+    let mut servers = Vec::new();
+    for server in &rule.servers {
+        let ip: IpAddr = server
+            .trim()
+            .parse()
+            .map_err(|_| WgError::Parse(format!("DNS server '{}' for {} is not an IP address", server, domain)))?;
+        servers.push(ip.to_string());
+    }
+    if servers.is_empty() {
+        return Err(WgError::Parse(format!("{} needs at least one DNS server", domain)));
+    }
+    Ok(SplitDnsRule { domain, servers })
+}
+
+fn detect_backend() -> Result<SplitDnsBackend, WgError> {
+    if cfg!(target_os = "macos") {
+        return Ok(SplitDnsBackend::Scutil);
+    }
+    if cfg!(target_os = "linux") {
+        if Path::new(RESOLVED_RUN_DIR).is_dir() {
+            return Ok(SplitDnsBackend::SystemdResolved);
+        }
+        if Path::new(DNSMASQ_DIR).is_dir() {
+            return Ok(SplitDnsBackend::Dnsmasq);
+        }
+        return Err(WgError::Refused(
+            "Split DNS needs systemd-resolved or dnsmasq (with /etc/dnsmasq.d)".to_string(),
+        ));
+    }
+    Err(WgError::Refused("Split DNS is only supported on macOS and Linux".to_string()))
+}
+
+fn scutil_hooks(config_name: &str, rules: &[SplitDnsRule]) -> SplitDnsHooks {
+    let mut hooks = SplitDnsHooks::default();
+    for (index, rule) in rules.iter().enumerate() {
+        let key = format!("State:/Network/Service/wiredeck-{}-{}/DNS", config_name, index);
+        hooks.post_up.push(format!(
+            "printf 'd.init\\nd.add ServerAddresses * {}\\nd.add SupplementalMatchDomains * {}\\nset {}\\n' | scutil",
+            rule.servers.join(" "),
+            rule.domain,
+            key
+        ));
+        hooks.post_down.push(format!("printf 'remove {}\\n' | scutil", key));
+    }
+    hooks
+}
+
+/// A link has one set of servers, so every rule's servers answer all of the tunnel's domains
+fn resolved_hooks(rules: &[SplitDnsRule]) -> SplitDnsHooks {
+    let mut servers: Vec<&str> = Vec::new();
+    for server in rules.iter().flat_map(|r| &r.servers) {
+        if !servers.contains(&server.as_str()) {
+            servers.push(server);
+        }
+    }
+    let domains: Vec<String> = rules.iter().map(|r| format!("~{}", r.domain)).collect();
+
+    // Link settings disappear together with the interface, nothing to tear down
+    SplitDnsHooks {
+        post_up: vec![
+            format!("resolvectl dns %i {}", servers.join(" ")),
+            format!("resolvectl domain %i {}", domains.join(" ")),
+        ],
+        post_down: Vec::new(),
+    }
+}
+
+fn dnsmasq_hooks(config_name: &str, rules: &[SplitDnsRule]) -> SplitDnsHooks {
+    let path = Path::new(DNSMASQ_DIR).join(format!("wiredeck-{}.conf", config_name));
+    let lines: Vec<String> = rules
+        .iter()
+        .flat_map(|rule| rule.servers.iter().map(move |server| format!("server=/{}/{}", rule.domain, server)))
+        .collect();
+    SplitDnsHooks {
+        post_up: vec![format!(
+            "printf '{}\\n' > {} && systemctl restart dnsmasq",
+            lines.join("\\n"),
+            path.display()
+        )],
+        post_down: vec![format!("rm -f {} && systemctl restart dnsmasq", path.display())],
+    }
+}
+
+fn generate_hooks(config_name: &str, backend: SplitDnsBackend, rules: &[SplitDnsRule]) -> SplitDnsHooks {
+    match backend {
+        SplitDnsBackend::Scutil => scutil_hooks(config_name, rules),
+        SplitDnsBackend::SystemdResolved => resolved_hooks(rules),
+        SplitDnsBackend::Dnsmasq => dnsmasq_hooks(config_name, rules),
+    }
+}
+
+fn uninstall_hooks(config: &mut WgConfig, hooks: &SplitDnsHooks) {
+    remove_hook_commands(&mut config.interface.post_up, &hooks.post_up);
+    remove_hook_commands(&mut config.interface.post_down, &hooks.post_down);
+}
+
+/// The split DNS rules of a config, if it has any
+pub fn get_split_dns(config_name: &str) -> Result<Option<SplitDns>, WgError> {
+    let store: SplitDnsStore = store::load(SPLIT_DNS_FILE)?;
+    Ok(store.get(config_name).cloned())
+}
+
+/// Send queries for the given domains to the tunnel's DNS servers and leave every other lookup
+/// to the system resolver. The rules are installed into PostUp/PostDown, so they are applied on
+/// the next bring-up and removed on bring-down; no rules clears them.
+pub fn set_split_dns(config_name: &str, rules: Vec<SplitDnsRule>) -> Result<Option<SplitDns>, WgError> {
+    let rules = rules.into_iter().map(normalize_rule).collect::<Result<Vec<_>, _>>()?;
+    // Clearing has to work wherever the rules were set, so only new rules need a backend
+    let split_dns = if rules.is_empty() {
+        None
+    } else {
+        let backend = detect_backend()?;
+        let hooks = generate_hooks(config_name, backend, &rules);
+        Some(SplitDns { rules, backend, hooks })
+    };
+
+    let mut config = parse_config(config_name)?;
+    let mut store: SplitDnsStore = store::load(SPLIT_DNS_FILE)?;
+    let previous = store.remove(config_name);
+    if previous.is_some() || split_dns.is_some() {
+        if let Some(ref previous) = previous {
+            uninstall_hooks(&mut config, &previous.hooks);
+        }
+        if let Some(ref split_dns) = split_dns {
+            add_hook_commands(&mut config.interface.post_up, &split_dns.hooks.post_up);
+            add_hook_commands(&mut config.interface.post_down, &split_dns.hooks.post_down);
+        }
+        save_config(&config)?;
+    }
+
+    if let Some(ref split_dns) = split_dns {
+        store.insert(config_name.to_string(), split_dns.clone());
+    }
+    store::save(SPLIT_DNS_FILE, &store)?;
+    Ok(split_dns)
+}
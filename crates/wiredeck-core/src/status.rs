@@ -0,0 +1,304 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::time::UNIX_EPOCH;
+
+use crate::daemons::{get_external_manager, ExternalManager};
+use crate::metrics::ONLINE_HANDSHAKE_SECS;
+use crate::privileged::list_privileged_changes;
+use crate::store::now_secs;
+use crate::traffic::total_transfer;
+use crate::wireguard::{
+    get_peer_status, get_real_interface_name, is_interface_up, list_configs, parse_config, PeerStatus, WgConfig, WgError,
+};
+
+/// The one state a tunnel is in, with whatever live data could be gathered
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum TunnelState {
+    Down {
+        /// Set when a service outside WireDeck (e.g. brew services) runs the tunnel
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        external: Option<ExternalManager>,
+    },
+    UpNoPeers {
+        interface_name: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        external: Option<ExternalManager>,
+    },
+    UpHealthy {
+        interface_name: String,
+        peers: Vec<PeerStatus>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        external: Option<ExternalManager>,
+    },
+    UpDegraded {
+        interface_name: String,
+        peers: Vec<PeerStatus>,
+        reasons: Vec<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        external: Option<ExternalManager>,
+    },
+}
+
+fn peer_label(public_key: &str, names: &[(String, Option<String>)]) -> String {
+    names
+        .iter()
+        .find(|(key, _)| key == public_key)
+        .and_then(|(_, name)| name.clone())
+        .unwrap_or_else(|| public_key.chars().take(8).collect())
+}
+
+/// Combined up/peers/health state of a tunnel; never fails just because it is down
+pub fn get_tunnel_state(config_name: &str) -> Result<TunnelState, WgError> {
+    let config = parse_config(config_name)?;
+    let external = get_external_manager(config_name);
+
+    if !is_interface_up(config_name)? {
+        return Ok(TunnelState::Down { external });
+    }
+
+    let interface_name = get_real_interface_name(config_name)?;
+    let peers = match get_peer_status(config_name) {
+        Ok(peers) => peers,
+        Err(e) => {
+            return Ok(TunnelState::UpDegraded {
+                interface_name,
+                peers: Vec::new(),
+                reasons: vec![format!("Couldn't read peer status: {}", e)],
+                external,
+            })
+        }
+    };
+
+    if peers.is_empty() {
+        return Ok(TunnelState::UpNoPeers { interface_name, external });
+    }
+
+    let names: Vec<(String, Option<String>)> = config
+        .peers
+        .iter()
+        .map(|p| (p.public_key.clone(), p.name.clone()))
+        .collect();
+    let now = now_secs();
+    let mut reasons = Vec::new();
+
+    for peer in &peers {
+        let label = peer_label(&peer.public_key, &names);
+        match peer.latest_handshake.as_deref().and_then(|h| h.parse::<u64>().ok()) {
+            None => reasons.push(format!("{} has never completed a handshake", label)),
+            Some(at) if now.saturating_sub(at) >= ONLINE_HANDSHAKE_SECS => reasons.push(format!(
+                "{} last handshook {}s ago",
+                label,
+                now.saturating_sub(at)
+            )),
+            Some(_) => {}
+        }
+    }
+
+    Ok(if reasons.is_empty() {
+        TunnelState::UpHealthy {
+            interface_name,
+            peers,
+            external,
+        }
+    } else {
+        TunnelState::UpDegraded {
+            interface_name,
+            peers,
+            reasons,
+            external,
+        }
+    })
+}
+
+/// How a tunnel is colored at a glance
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Glance {
+    Healthy,
+    /// Up, but without peers or with peers that aren't handshaking
+    Degraded,
+    Down,
+}
+
+impl TunnelState {
+    pub fn glance(&self) -> Glance {
+        match self {
+            TunnelState::Down { .. } => Glance::Down,
+            TunnelState::UpHealthy { .. } => Glance::Healthy,
+            TunnelState::UpNoPeers { .. } | TunnelState::UpDegraded { .. } => Glance::Degraded,
+        }
+    }
+
+    pub fn external(&self) -> Option<&ExternalManager> {
+        match self {
+            TunnelState::Down { external }
+            | TunnelState::UpNoPeers { external, .. }
+            | TunnelState::UpHealthy { external, .. }
+            | TunnelState::UpDegraded { external, .. } => external.as_ref(),
+        }
+    }
+
+    /// Live peer data; empty while down
+    pub fn peers(&self) -> &[PeerStatus] {
+        match self {
+            TunnelState::UpHealthy { peers, .. } | TunnelState::UpDegraded { peers, .. } => peers,
+            TunnelState::Down { .. } | TunnelState::UpNoPeers { .. } => &[],
+        }
+    }
+}
+
+/// All tunnels taken together, as a menu bar or tray icon shows them
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AggregateState {
+    /// Every tunnel is up and healthy
+    AllUp,
+    /// Some tunnels are up, or one that is up is degraded
+    Partial,
+    Down,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TunnelGlance {
+    pub config_name: String,
+    pub glance: Glance,
+    /// `status_summary_text` of the tunnel
+    pub summary: String,
+}
+
+/// Glanceable state of every tunnel, with traffic totals for a rate readout
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StatusOverview {
+    pub state: AggregateState,
+    pub tunnels: Vec<TunnelGlance>,
+    /// Bytes received and sent by all running tunnels since they came up
+    pub rx_bytes: u64,
+    pub tx_bytes: u64,
+}
+
+/// Aggregate state of all tunnels; configs that can't be read count as down
+pub fn get_status_overview() -> StatusOverview {
+    let mut tunnels = Vec::new();
+    let (mut rx_bytes, mut tx_bytes) = (0, 0);
+    for config_name in list_configs().unwrap_or_default() {
+        let (glance, summary) = match parse_config(&config_name).and_then(|c| Ok((get_tunnel_state(&config_name)?, c))) {
+            Ok((state, config)) => {
+                let (rx, tx) = total_transfer(state.peers());
+                rx_bytes += rx;
+                tx_bytes += tx;
+                (state.glance(), status_summary_text(&config, &state))
+            }
+            Err(e) => (Glance::Down, format!("Unavailable: {}", e)),
+        };
+        tunnels.push(TunnelGlance {
+            config_name,
+            glance,
+            summary,
+        });
+    }
+
+    let state = if tunnels.iter().all(|t| t.glance == Glance::Down) {
+        AggregateState::Down
+    } else if tunnels.iter().all(|t| t.glance == Glance::Healthy) {
+        AggregateState::AllUp
+    } else {
+        AggregateState::Partial
+    };
+    StatusOverview {
+        state,
+        tunnels,
+        rx_bytes,
+        tx_bytes,
+    }
+}
+
+/// When the running tunnel came up: wg-quick's name file on macOS, else WireDeck's own log of
+/// bringing it up. `None` for tunnels started elsewhere before WireDeck saw them.
+fn up_since(config_name: &str) -> Option<u64> {
+    let name_file = format!("/var/run/wireguard/{}.name", config_name);
+    if let Ok(modified) = fs::metadata(name_file).and_then(|m| m.modified()) {
+        return modified.duration_since(UNIX_EPOCH).ok().map(|d| d.as_secs());
+    }
+    list_privileged_changes()
+        .ok()?
+        .into_iter()
+        .rev()
+        .filter(|c| c.config_name.as_deref() == Some(config_name))
+        .find(|c| c.action == "interface_up" || c.action == "interface_down")
+        .filter(|c| c.action == "interface_up")
+        .map(|c| c.at)
+}
+
+/// "40s", "15m", "2h", "3d"
+fn format_duration(secs: u64) -> String {
+    match secs {
+        0..=59 => format!("{}s", secs),
+        60..=3599 => format!("{}m", secs / 60),
+        3600..=86_399 => format!("{}h", secs / 3600),
+        _ => format!("{}d", secs / 86_400),
+    }
+}
+
+/// "512 B", "3.4 MB", "1.2 GB"
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+fn plural(count: usize, word: &str) -> String {
+    format!("{} {}{}", count, word, if count == 1 { "" } else { "s" })
+}
+
+/// One-sentence summary of a tunnel for screen readers, tooltips and the tray, e.g.
+/// "Connected 2h, 3 of 5 peers active, 1.2 GB transferred"
+pub fn status_summary_text(config: &WgConfig, state: &TunnelState) -> String {
+    let configured = config.peers.len();
+    let mut parts = Vec::new();
+    match state {
+        TunnelState::Down { .. } => {
+            parts.push("Disconnected".to_string());
+            parts.push(format!("{} configured", plural(configured, "peer")));
+        }
+        _ => {
+            parts.push(match up_since(&config.name) {
+                Some(at) => format!("Connected {}", format_duration(now_secs().saturating_sub(at))),
+                None => "Connected".to_string(),
+            });
+            let now = now_secs();
+            let active = state
+                .peers()
+                .iter()
+                .filter_map(|p| p.latest_handshake.as_deref()?.parse::<u64>().ok())
+                .filter(|&at| at > 0 && now.saturating_sub(at) < ONLINE_HANDSHAKE_SECS)
+                .count();
+            parts.push(if configured == 0 {
+                "no peers".to_string()
+            } else {
+                format!("{} of {} active", active, plural(configured, "peer"))
+            });
+            let (rx, tx) = total_transfer(state.peers());
+            parts.push(format!("{} transferred", format_bytes(rx + tx)));
+        }
+    }
+    if let Some(external) = state.external() {
+        parts.push(format!("managed outside WireDeck by {}", external.name));
+    }
+    parts.join(", ")
+}
+
+/// `status_summary_text` for a config's current state
+pub fn get_status_summary_text(config_name: &str) -> Result<String, WgError> {
+    let config = parse_config(config_name)?;
+    let state = get_tunnel_state(config_name)?;
+    Ok(status_summary_text(&config, &state))
+}
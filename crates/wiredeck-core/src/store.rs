@@ -0,0 +1,111 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::wireguard::WgError;
+use crate::workspace;
+
+/// Get the directory where WireDeck keeps its own data (separate from WireGuard configs)
+pub fn get_app_data_dir() -> PathBuf {
+    let home = std::env::var_os("HOME")
+        .map(PathBuf::from)
+        .unwrap_or_else(std::env::temp_dir);
+
+    if cfg!(target_os = "macos") {
+        return home.join("Library/Application Support/WireDeck");
+    }
+
+    std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| home.join(".local/share"))
+        .join("wiredeck")
+}
+
+/// Directory holding the active workspace's data
+pub fn get_data_dir() -> PathBuf {
+    workspace::workspace_data_dir(&workspace::active_workspace_name())
+}
+
+/// Create a folder and any missing parents only this user can open (0700 on Unix), and tighten
+/// the folder itself if it already existed
+pub(crate) fn create_private_dir(dir: &Path) -> std::io::Result<()> {
+    let mut builder = fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+        builder.mode(0o700).create(dir)?;
+        fs::set_permissions(dir, fs::Permissions::from_mode(0o700))
+    }
+    #[cfg(not(unix))]
+    builder.create(dir)
+}
+
+/// Create a new file only this user can read (0600 on Unix), failing if it already exists
+pub(crate) fn write_new_private_file(path: &Path, content: &[u8]) -> std::io::Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options.open(path)?.write_all(content)
+}
+
+pub(crate) fn load_from<T: DeserializeOwned + Default>(dir: PathBuf, file_name: &str) -> Result<T, WgError> {
+    let path = dir.join(file_name);
+
+    if !path.exists() {
+        return Ok(T::default());
+    }
+
+    let content = fs::read_to_string(&path)?;
+    serde_json::from_str(&content).map_err(|e| WgError::Parse(format!("{}: {}", file_name, e)))
+}
+
+/// App data holds config snapshots, cached results and credentials, so the folder is private
+/// and each document is written 0600 to a new file that then replaces the old one
+pub(crate) fn save_to<T: Serialize>(dir: PathBuf, file_name: &str, value: &T) -> Result<(), WgError> {
+    create_private_dir(&dir)?;
+
+    let content = serde_json::to_string_pretty(value).map_err(|e| WgError::Parse(e.to_string()))?;
+    let path = dir.join(file_name);
+    let staging = dir.join(format!(".{}.tmp", file_name));
+    let _ = fs::remove_file(&staging);
+    let result = write_new_private_file(&staging, content.as_bytes()).and_then(|_| fs::rename(&staging, &path));
+    if result.is_err() {
+        let _ = fs::remove_file(&staging);
+    }
+    result?;
+
+    Ok(())
+}
+
+/// Load a JSON document from the active workspace, or its default if it doesn't exist yet
+pub fn load<T: DeserializeOwned + Default>(file_name: &str) -> Result<T, WgError> {
+    load_from(get_data_dir(), file_name)
+}
+
+/// Save a JSON document into the active workspace
+pub fn save<T: Serialize>(file_name: &str, value: &T) -> Result<(), WgError> {
+    save_to(get_data_dir(), file_name, value)
+}
+
+/// Load a JSON document shared by all workspaces
+pub fn load_global<T: DeserializeOwned + Default>(file_name: &str) -> Result<T, WgError> {
+    load_from(get_app_data_dir(), file_name)
+}
+
+/// Save a JSON document shared by all workspaces
+pub fn save_global<T: Serialize>(file_name: &str, value: &T) -> Result<(), WgError> {
+    save_to(get_app_data_dir(), file_name, value)
+}
+
+/// Current time as seconds since the Unix epoch, the timestamp format used across app data
+pub fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
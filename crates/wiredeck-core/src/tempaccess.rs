@@ -0,0 +1,133 @@
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use crate::addressing::allocate_peer_address;
+use crate::cidr::Cidr;
+use crate::endpoint::validate_endpoint;
+use crate::peers::{add_live_peer, add_peer, delete_peer_confirmed};
+use crate::schedule::{cancel_scheduled_change, schedule_confirmed, PeerChangeKind};
+use crate::selfservice::qr_svg;
+use crate::wireguard::{
+    generate_keypair, get_public_key, parse_config, require_private_key, serialize_config, Comments, Interface, Peer,
+    WgConfig, WgError,
+};
+
+/// Keepalive in handed-out client configs, so guests behind NAT stay reachable
+const GUEST_KEEPALIVE: u16 = 25;
+
+/// Guest access that removes itself: the new peer, the guest's client config, and when it ends
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TempPeer {
+    pub config_name: String,
+    pub public_key: String,
+    pub label: String,
+    /// Tunnel address given to the guest
+    pub address: String,
+    /// The guest's client config, private key included; shown once and not kept anywhere
+    pub client_config: String,
+    pub qr_svg: Option<String>,
+    /// Also added to the running interface, so the guest can connect right away
+    pub live: bool,
+    pub expires_at: u64,
+    /// The scheduled removal; cancel it to keep the peer
+    pub schedule_id: String,
+}
+
+/// The guest's side: its own key and address, the server as its only peer, routing the tunnel subnets
+fn guest_config(
+    server: &WgConfig,
+    server_public_key: &str,
+    private_key: String,
+    address: Cidr,
+    endpoint: &str,
+) -> WgConfig {
+    let subnets: Vec<String> = Cidr::parse_all(&server.interface.address)
+        .unwrap_or_default()
+        .iter()
+        .map(|c| c.network().to_string())
+        .collect();
+    WgConfig {
+        name: server.name.clone(),
+        path: PathBuf::from(format!("{}.conf", server.name)),
+        interface: Interface {
+            private_key: Some(private_key),
+            has_private_key: true,
+            address: vec![address.to_string()],
+            listen_port: None,
+            dns: server.interface.dns.clone(),
+            dns_search: server.interface.dns_search.clone(),
+            mtu: server.interface.mtu,
+            table: None,
+            fw_mark: None,
+            pre_up: Vec::new(),
+            post_up: Vec::new(),
+            pre_down: Vec::new(),
+            post_down: Vec::new(),
+            save_config: false,
+            extra: Vec::new(),
+            comments: Comments::default(),
+        },
+        peers: vec![Peer {
+            public_key: server_public_key.to_string(),
+            preshared_key: None,
+            allowed_ips: subnets.join(", "),
+            persistent_keepalive: Some(GUEST_KEEPALIVE),
+            endpoint: Some(endpoint.to_string()),
+            name: Some(server.name.clone()),
+            extra: Vec::new(),
+            comments: Comments::default(),
+        }],
+        managed_by: None,
+        content_hash: None,
+    }
+}
+
+/// Give someone access to a server config for `ttl_secs` in one step: a new keypair and address,
+/// the peer written to the config and the running interface, a client config with QR code for
+/// them, and its removal from both scheduled for when the time is up. `endpoint` is the
+/// `host:port` the guest connects to. Nothing is left behind if a step fails.
+pub fn create_temp_peer(config_name: &str, ttl_secs: u64, label: &str, endpoint: &str) -> Result<TempPeer, WgError> {
+    if ttl_secs == 0 {
+        return Err(WgError::Refused("Temporary access needs a time limit".to_string()));
+    }
+    validate_endpoint(endpoint)?;
+    let server = parse_config(config_name)?;
+    let server_public_key = get_public_key(require_private_key(&server)?)?;
+    let address = allocate_peer_address(&server)?;
+    let (private_key, public_key) = generate_keypair()?;
+
+    let peer = Peer {
+        public_key: public_key.clone(),
+        preshared_key: None,
+        allowed_ips: address.to_string(),
+        persistent_keepalive: None,
+        endpoint: None,
+        name: Some(label.to_string()),
+        extra: Vec::new(),
+        comments: Comments::default(),
+    };
+    add_peer(config_name, peer.clone())?;
+
+    let undo = || {
+        let _ = delete_peer_confirmed(config_name, &public_key);
+    };
+    let change = schedule_confirmed(config_name, &public_key, peer.name.clone(), PeerChangeKind::Delete, ttl_secs, true)
+        .inspect_err(|_| undo())?;
+    let live = add_live_peer(config_name, &peer).inspect_err(|_| {
+        let _ = cancel_scheduled_change(&change.id);
+        undo();
+    })?;
+
+    let client_config = serialize_config(&guest_config(&server, &server_public_key, private_key, address, endpoint));
+    Ok(TempPeer {
+        config_name: config_name.to_string(),
+        public_key,
+        label: label.to_string(),
+        address: address.to_string(),
+        qr_svg: qr_svg(&client_config),
+        client_config,
+        live,
+        expires_at: change.effective_at,
+        schedule_id: change.id,
+    })
+}
@@ -0,0 +1,151 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::process::Command;
+
+use crate::backup::private_temp_dir;
+use crate::keys::normalize_key;
+use crate::platform::{list_peer_platforms, Platform};
+use crate::trace;
+use crate::wireguard::{get_public_key, list_configs, parse_config, WgConfig, WgError};
+
+/// Output of `generate_topology_graph`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GraphFormat {
+    /// Graphviz source, for keeping next to the docs
+    Dot,
+    /// Rendered with Graphviz's `dot`, which must be installed
+    Svg,
+}
+
+/// Quote a string for a DOT label or id
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n"))
+}
+
+fn interface_label(config: &WgConfig) -> String {
+    let mut label = config.name.clone();
+    if !config.interface.address.is_empty() {
+        label.push_str(&format!("\n{}", config.interface.address.join(", ")));
+    }
+    if let Some(port) = config.interface.listen_port {
+        label.push_str(&format!("\n:{}", port));
+    }
+    label
+}
+
+fn peer_shape(platform: Option<Platform>) -> &'static str {
+    match platform {
+        Some(Platform::Phone) => "note",
+        Some(Platform::Computer) => "box",
+        Some(Platform::Server) => "box3d",
+        Some(Platform::Router) => "diamond",
+        None => "ellipse",
+    }
+}
+
+/// Describe every config as a Graphviz graph: this host, one node per interface, one per peer
+/// (merged when the same key is a peer of several tunnels), and edges labelled with AllowedIPs.
+/// A peer that is another local interface is drawn as a link between the two interfaces.
+fn topology_dot() -> Result<String, WgError> {
+    let configs: Vec<WgConfig> = list_configs()?.iter().filter_map(|name| parse_config(name).ok()).collect();
+    let local_keys: HashMap<String, String> = configs
+        .iter()
+        .filter_map(|c| {
+            let public_key = get_public_key(c.interface.private_key.as_deref()?).ok()?;
+            Some((normalize_key(&public_key), c.name.clone()))
+        })
+        .collect();
+
+    let mut lines = vec![
+        "graph wiredeck {".to_string(),
+        "  rankdir=LR;".to_string(),
+        "  node [fontname=\"Helvetica\", fontsize=10];".to_string(),
+        "  edge [fontname=\"Helvetica\", fontsize=9];".to_string(),
+        format!("  host [shape=house, label={}];", quote("This host")),
+    ];
+
+    // Peer nodes are collected first so a key shared by several tunnels is drawn once
+    let mut peers: BTreeMap<String, (String, Option<Platform>)> = BTreeMap::new();
+    let mut local_links: BTreeMap<(String, String), String> = BTreeMap::new();
+    let mut edges = Vec::new();
+    for config in &configs {
+        let id = quote(&format!("if:{}", config.name));
+        lines.push(format!("  {} [shape=component, label={}];", id, quote(&interface_label(config))));
+        lines.push(format!("  host -- {};", id));
+
+        let platforms: HashMap<String, Option<Platform>> = list_peer_platforms(&config.name)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|p| (p.public_key, p.platform))
+            .collect();
+        for peer in &config.peers {
+            let key = normalize_key(&peer.public_key);
+            let edge_label = quote(&peer.allowed_ips.replace(", ", "\n").replace(',', "\n"));
+            if let Some(local) = local_keys.get(&key) {
+                // Both ends usually list each other; one dashed line is enough
+                let pair = if local < &config.name {
+                    (local.clone(), config.name.clone())
+                } else {
+                    (config.name.clone(), local.clone())
+                };
+                local_links.entry(pair).or_insert(edge_label);
+                continue;
+            }
+
+            let name = peer.name.clone().unwrap_or_else(|| key.chars().take(8).collect());
+            let mut label = name;
+            if let Some(ref endpoint) = peer.endpoint {
+                label.push_str(&format!("\n{}", endpoint));
+            }
+            let platform = platforms.get(&peer.public_key).copied().flatten();
+            peers.entry(key.clone()).or_insert((label, platform));
+            edges.push(format!("  {} -- {} [label={}];", id, quote(&format!("peer:{}", key)), edge_label));
+        }
+    }
+
+    for (key, (label, platform)) in peers {
+        lines.push(format!(
+            "  {} [shape={}, label={}];",
+            quote(&format!("peer:{}", key)),
+            peer_shape(platform),
+            quote(&label)
+        ));
+    }
+    lines.extend(edges);
+    for ((a, b), label) in local_links {
+        lines.push(format!(
+            "  {} -- {} [style=dashed, label={}];",
+            quote(&format!("if:{}", a)),
+            quote(&format!("if:{}", b)),
+            label
+        ));
+    }
+    lines.push("}".to_string());
+    Ok(lines.join("\n") + "\n")
+}
+
+fn render_svg(dot: &str) -> Result<String, WgError> {
+    let source = private_temp_dir()?.join("wiredeck_topology.dot");
+    fs::write(&source, dot)?;
+    let output = trace::output(Command::new("dot").arg("-Tsvg").arg(&source));
+    let _ = fs::remove_file(&source);
+
+    let output = output
+        .map_err(|e| WgError::CommandFailed(format!("SVG output needs Graphviz's dot ({}); export DOT instead", e)))?;
+    if !output.status.success() {
+        return Err(WgError::CommandFailed(String::from_utf8_lossy(&output.stderr).to_string()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Diagram of the hosts, tunnels and peers in every config, generated so VPN docs follow the
+/// configs instead of a hand-drawn picture. Peer shapes follow their platform.
+pub fn generate_topology_graph(format: GraphFormat) -> Result<String, WgError> {
+    let dot = topology_dot()?;
+    match format {
+        GraphFormat::Dot => Ok(dot),
+        GraphFormat::Svg => render_svg(&dot),
+    }
+}
@@ -0,0 +1,84 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::io;
+use std::process::{Command, ExitStatus, Output};
+use std::time::Instant;
+
+use crate::settings;
+use crate::wireguard::WgError;
+
+/// One external command an operation ran
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CommandTrace {
+    /// Program and arguments exactly as executed, e.g. `["sudo", "-n", "wg-quick", "up", "home"]`
+    pub argv: Vec<String>,
+    /// `None` when the command couldn't be started or was killed by a signal
+    pub exit_code: Option<i32>,
+    pub duration_ms: u64,
+}
+
+/// An operation's result, with the commands it ran when command tracing is on
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Traced<T> {
+    pub result: T,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trace: Option<Vec<CommandTrace>>,
+}
+
+thread_local! {
+    // Operations run on one thread, so commands are collected per thread while a capture is open
+    static CAPTURE: RefCell<Option<Vec<CommandTrace>>> = const { RefCell::new(None) };
+}
+
+/// Record a finished command if a capture is open on this thread
+pub fn record(command: &Command, status: Option<ExitStatus>, started: Instant) {
+    CAPTURE.with(|capture| {
+        if let Some(traces) = capture.borrow_mut().as_mut() {
+            let mut argv = vec![command.get_program().to_string_lossy().to_string()];
+            argv.extend(command.get_args().map(|a| a.to_string_lossy().to_string()));
+            traces.push(CommandTrace {
+                argv,
+                exit_code: status.and_then(|s| s.code()),
+                duration_ms: started.elapsed().as_millis() as u64,
+            });
+        }
+    });
+}
+
+/// `Command::output`, recorded in the current capture
+pub fn output(command: &mut Command) -> io::Result<Output> {
+    let started = Instant::now();
+    let output = command.output();
+    record(command, output.as_ref().ok().map(|o| o.status), started);
+    output
+}
+
+/// Run `f`, collecting every command it runs through `output` or `record`
+pub fn capture<T>(f: impl FnOnce() -> T) -> (T, Vec<CommandTrace>) {
+    // Nested captures keep the outer one's commands
+    let outer = CAPTURE.with(|capture| capture.borrow_mut().replace(Vec::new()));
+    let result = f();
+    let traces = CAPTURE.with(|capture| {
+        let traces = capture.borrow_mut().take().unwrap_or_default();
+        if let Some(mut outer) = outer {
+            outer.extend(traces.iter().cloned());
+            *capture.borrow_mut() = Some(outer);
+        }
+        traces
+    });
+    (result, traces)
+}
+
+/// Run an operation, attaching its commands when `command_trace` is enabled in settings
+pub fn traced<T>(f: impl FnOnce() -> Result<T, WgError>) -> Result<Traced<T>, WgError> {
+    if !settings::load_settings()?.command_trace {
+        return Ok(Traced { result: f()?, trace: None });
+    }
+
+    let (result, trace) = capture(f);
+    Ok(Traced {
+        result: result?,
+        trace: Some(trace),
+    })
+}
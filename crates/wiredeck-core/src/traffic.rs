@@ -0,0 +1,45 @@
+use serde::{Deserialize, Serialize};
+use std::thread;
+use std::time::Duration;
+
+use crate::wireguard::{get_peer_status, PeerStatus, WgError};
+
+/// Bytes moved through a tunnel during a sampling window
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrafficSample {
+    pub window_secs: u64,
+    pub rx_bytes: u64,
+    pub tx_bytes: u64,
+}
+
+impl TrafficSample {
+    /// Received plus sent bytes
+    pub fn total_bytes(&self) -> u64 {
+        self.rx_bytes + self.tx_bytes
+    }
+}
+
+fn counter(value: &Option<String>) -> u64 {
+    value.as_deref().and_then(|v| v.parse().ok()).unwrap_or(0)
+}
+
+/// Sum of received and sent bytes over all peers
+pub fn total_transfer(statuses: &[PeerStatus]) -> (u64, u64) {
+    statuses.iter().fold((0, 0), |(rx, tx), s| {
+        (rx + counter(&s.transfer_rx), tx + counter(&s.transfer_tx))
+    })
+}
+
+/// Measure how much traffic a running tunnel moves over `window`
+pub fn measure_activity(config_name: &str, window: Duration) -> Result<TrafficSample, WgError> {
+    let (rx_before, tx_before) = total_transfer(&get_peer_status(config_name)?);
+    thread::sleep(window);
+    let (rx_after, tx_after) = total_transfer(&get_peer_status(config_name)?);
+
+    Ok(TrafficSample {
+        window_secs: window.as_secs(),
+        // Counters reset if the interface was restarted in between
+        rx_bytes: rx_after.saturating_sub(rx_before),
+        tx_bytes: tx_after.saturating_sub(tx_before),
+    })
+}
@@ -0,0 +1,246 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::process::Command;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::power::get_power_state;
+use crate::store::{self, now_secs};
+use crate::trace;
+use crate::verify::gateway_address;
+use crate::wireguard::{
+    bring_down, bring_up, get_peer_status, get_real_interface_name, is_interface_up, parse_config, save_config,
+    WgConfig, WgError,
+};
+
+const TUNE_REPORTS_FILE: &str = "tune_reports.json";
+
+/// Downloaded through the tunnel to estimate throughput
+const THROUGHPUT_URL: &str = "https://speed.cloudflare.com/__down?bytes=5000000";
+
+/// How long to wait for a handshake after restarting with new values
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Pings sent to the gateway per candidate
+const PROBE_PINGS: u32 = 5;
+
+/// Values to try; every MTU is combined with every keepalive
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TuneMatrix {
+    pub mtus: Vec<u16>,
+    pub keepalives: Vec<Option<u16>>,
+}
+
+impl Default for TuneMatrix {
+    fn default() -> Self {
+        TuneMatrix {
+            mtus: vec![1280, 1360, 1420],
+            keepalives: vec![None, Some(15), Some(25)],
+        }
+    }
+}
+
+/// One MTU/keepalive combination
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TuneCandidate {
+    pub mtu: u16,
+    pub keepalive: Option<u16>,
+}
+
+/// Measurements for one candidate
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TuneResult {
+    pub candidate: TuneCandidate,
+    pub handshake_secs: Option<f64>,
+    /// Share of full-size, don't-fragment pings to the gateway that were lost
+    pub loss_percent: Option<f64>,
+    pub throughput_kbps: Option<u64>,
+    pub error: Option<String>,
+}
+
+/// Outcome of an `auto_tune` run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TuneReport {
+    pub config_name: String,
+    pub started_at: u64,
+    pub original: TuneCandidate,
+    pub results: Vec<TuneResult>,
+    pub recommended: Option<TuneCandidate>,
+    pub applied: bool,
+}
+
+/// Latest report keyed by config name
+type TuneReportStore = HashMap<String, TuneReport>;
+
+fn current_values(config: &WgConfig) -> TuneCandidate {
+    TuneCandidate {
+        // 1420 is wg-quick's default when MTU isn't set
+        mtu: config.interface.mtu.unwrap_or(1420),
+        keepalive: config.peers.iter().find_map(|p| p.persistent_keepalive),
+    }
+}
+
+fn apply_values(config: &mut WgConfig, candidate: TuneCandidate) {
+    config.interface.mtu = Some(candidate.mtu);
+    for peer in config.peers.iter_mut().filter(|p| p.endpoint.is_some()) {
+        peer.persistent_keepalive = candidate.keepalive;
+    }
+}
+
+fn restart_with(config: &WgConfig) -> Result<(), WgError> {
+    save_config(config)?;
+    let _ = bring_down(&config.name);
+    bring_up(&config.name)?;
+    Ok(())
+}
+
+fn wait_for_handshake(config_name: &str, after: u64) -> Option<f64> {
+    let start = Instant::now();
+    while start.elapsed() < HANDSHAKE_TIMEOUT {
+        let fresh = get_peer_status(config_name)
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|s| s.latest_handshake.as_deref()?.parse::<u64>().ok())
+            .any(|h| h >= after);
+        if fresh {
+            return Some(start.elapsed().as_secs_f64());
+        }
+        thread::sleep(Duration::from_millis(500));
+    }
+    None
+}
+
+/// Ping with packets filling the MTU and fragmentation disallowed, returning the loss
+fn probe_loss(gateway: &IpAddr, mtu: u16) -> Option<f64> {
+    // 20 bytes IPv4 header + 8 bytes ICMP header
+    let payload = mtu.saturating_sub(28).to_string();
+    let count = PROBE_PINGS.to_string();
+    let df_args: &[&str] = if cfg!(target_os = "macos") { &["-D"] } else { &["-M", "do"] };
+
+    let output = trace::output(
+        Command::new("ping")
+            .args(df_args)
+            .args(["-c", &count, "-s", &payload, &gateway.to_string()]),
+    )
+    .ok()?;
+
+    // "5 packets transmitted, 5 received, 0% packet loss" (Linux) / "0.0% packet loss" (macOS)
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    stdout
+        .split(',')
+        .find(|part| part.contains("packet loss"))
+        .and_then(|part| part.trim().split('%').next())
+        .and_then(|value| value.trim().parse().ok())
+}
+
+fn measure_throughput(config_name: &str) -> Option<u64> {
+    let interface = get_real_interface_name(config_name).ok()?;
+    let output = trace::output(
+        Command::new("curl")
+            .args(["-s", "-o", "/dev/null", "--max-time", "15", "-w", "%{speed_download}"])
+            .args(["--interface", &interface, THROUGHPUT_URL]),
+    )
+    .ok()?;
+
+    if !output.status.success() {
+        return None;
+    }
+    let bytes_per_sec: f64 = String::from_utf8_lossy(&output.stdout).trim().parse().ok()?;
+    Some((bytes_per_sec * 8.0 / 1000.0) as u64)
+}
+
+fn run_candidate(base: &WgConfig, candidate: TuneCandidate) -> TuneResult {
+    let mut result = TuneResult {
+        candidate,
+        handshake_secs: None,
+        loss_percent: None,
+        throughput_kbps: None,
+        error: None,
+    };
+
+    let mut config = base.clone();
+    apply_values(&mut config, candidate);
+    let started = now_secs();
+    if let Err(e) = restart_with(&config) {
+        result.error = Some(e.to_string());
+        return result;
+    }
+
+    result.handshake_secs = wait_for_handshake(&config.name, started);
+    if result.handshake_secs.is_none() {
+        result.error = Some("No handshake after restart".to_string());
+        return result;
+    }
+
+    result.loss_percent = gateway_address(&config).and_then(|gw| probe_loss(&gw, candidate.mtu));
+    result.throughput_kbps = measure_throughput(&config.name);
+    result
+}
+
+/// Pick the candidate that handshakes, loses nothing and moves the most data; larger MTU breaks ties
+fn recommend(results: &[TuneResult]) -> Option<TuneCandidate> {
+    results
+        .iter()
+        .filter(|r| r.handshake_secs.is_some() && r.loss_percent.unwrap_or(0.0) == 0.0)
+        .max_by_key(|r| (r.throughput_kbps.unwrap_or(0), r.candidate.mtu))
+        .map(|r| r.candidate)
+}
+
+/// Restart a running tunnel with each MTU/keepalive combination, measure it and recommend the best.
+/// The original values are restored afterwards unless `apply` is set and a recommendation was found.
+pub fn auto_tune(config_name: &str, matrix: Option<TuneMatrix>, apply: bool) -> Result<TuneReport, WgError> {
+    if !is_interface_up(config_name)? {
+        return Err(WgError::CommandFailed(format!("{} must be up to tune it", config_name)));
+    }
+    // Throughput tests are the heaviest thing WireDeck does; don't run them on a struggling battery
+    if get_power_state().should_throttle() {
+        return Err(WgError::CommandFailed(
+            "Auto-tune is paused in low-power mode or on low battery".to_string(),
+        ));
+    }
+
+    let matrix = matrix.unwrap_or_default();
+    let base = parse_config(config_name)?;
+    let original = current_values(&base);
+    let started_at = now_secs();
+
+    let mut results = Vec::new();
+    for &mtu in &matrix.mtus {
+        for &keepalive in &matrix.keepalives {
+            results.push(run_candidate(&base, TuneCandidate { mtu, keepalive }));
+        }
+    }
+
+    let recommended = recommend(&results);
+    let applied = apply && recommended.is_some();
+
+    let mut final_config = base.clone();
+    match recommended.filter(|_| apply) {
+        Some(best) => apply_values(&mut final_config, best),
+        // Put back exactly what was there, including an unset MTU
+        None => final_config = base,
+    }
+    restart_with(&final_config)?;
+
+    let report = TuneReport {
+        config_name: config_name.to_string(),
+        started_at,
+        original,
+        results,
+        recommended,
+        applied,
+    };
+
+    let mut reports: TuneReportStore = store::load(TUNE_REPORTS_FILE)?;
+    reports.insert(config_name.to_string(), report.clone());
+    store::save(TUNE_REPORTS_FILE, &reports)?;
+
+    Ok(report)
+}
+
+/// The last saved tuning report for a config
+pub fn get_tune_report(config_name: &str) -> Result<Option<TuneReport>, WgError> {
+    let reports: TuneReportStore = store::load(TUNE_REPORTS_FILE)?;
+    Ok(reports.get(config_name).cloned())
+}
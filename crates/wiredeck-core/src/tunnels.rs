@@ -0,0 +1,392 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+
+use crate::confirm::{self, Guarded};
+use crate::elevation::sudo_output;
+use crate::settings;
+use crate::store;
+use crate::traffic::measure_activity;
+use crate::wgquick::UpReport;
+use crate::wireguard::{
+    bring_down, bring_up, get_real_interface_name, is_interface_up, list_configs, parse_config, WgError,
+};
+
+const TUNNELS_FILE: &str = "tunnels.json";
+const PROFILES_FILE: &str = "profiles.json";
+
+/// Per-tunnel behaviour that isn't part of the WireGuard config itself
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TunnelSettings {
+    /// Tunnels that must be up before this one
+    pub depends_on: Vec<String>,
+    /// Bring this tunnel up when WireDeck starts
+    pub auto_connect: bool,
+    /// Only one tunnel of a group may be up at a time
+    pub exclusive_group: Option<String>,
+    /// Use a random high ListenPort for every bring-up instead of the configured one
+    pub randomize_listen_port: bool,
+    /// Let WireDeck edit this tunnel even though its config names a configuration management tool
+    pub allow_managed_edits: bool,
+}
+
+/// A named set of tunnels activated together
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Profile {
+    pub name: String,
+    pub tunnels: Vec<String>,
+}
+
+/// What happened to one tunnel while activating a set of tunnels
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TunnelActivation {
+    pub config_name: String,
+    pub already_up: bool,
+    pub report: Option<UpReport>,
+    /// Tunnels of the same exclusive group that were brought down first
+    pub displaced: Vec<String>,
+    /// Port the tunnel was brought up with when its ListenPort is randomized
+    pub randomized_listen_port: Option<u16>,
+}
+
+/// Tunnel settings keyed by config name
+type TunnelStore = HashMap<String, TunnelSettings>;
+
+/// Get settings for every tunnel that has any
+pub fn list_tunnel_settings() -> Result<HashMap<String, TunnelSettings>, WgError> {
+    store::load(TUNNELS_FILE)
+}
+
+/// Get settings for one tunnel (defaults if nothing was recorded)
+pub fn get_tunnel_settings(config_name: &str) -> Result<TunnelSettings, WgError> {
+    let tunnels: TunnelStore = store::load(TUNNELS_FILE)?;
+    Ok(tunnels.get(config_name).cloned().unwrap_or_default())
+}
+
+fn update_tunnel_settings<F>(config_name: &str, update: F) -> Result<TunnelSettings, WgError>
+where
+    F: FnOnce(&mut TunnelSettings),
+{
+    let mut tunnels: TunnelStore = store::load(TUNNELS_FILE)?;
+    let entry = tunnels.entry(config_name.to_string()).or_default();
+    update(entry);
+    let updated = entry.clone();
+    store::save(TUNNELS_FILE, &tunnels)?;
+    Ok(updated)
+}
+
+/// Declare which tunnels must be up before `config_name`, rejecting cycles
+pub fn set_tunnel_dependencies(config_name: &str, depends_on: Vec<String>) -> Result<TunnelSettings, WgError> {
+    let known = list_configs()?;
+    for dep in &depends_on {
+        if dep == config_name {
+            return Err(WgError::Parse(format!("{} cannot depend on itself", config_name)));
+        }
+        if !known.contains(dep) {
+            return Err(WgError::NotFound(dep.clone()));
+        }
+    }
+
+    let mut tunnels: TunnelStore = store::load(TUNNELS_FILE)?;
+    let entry = tunnels.entry(config_name.to_string()).or_default();
+    entry.depends_on = depends_on;
+    let updated = entry.clone();
+
+    startup_order_in(&tunnels, &[config_name.to_string()])?;
+    store::save(TUNNELS_FILE, &tunnels)?;
+
+    Ok(updated)
+}
+
+/// Mark a tunnel to be brought up at launch
+pub fn set_auto_connect(config_name: &str, auto_connect: bool) -> Result<TunnelSettings, WgError> {
+    update_tunnel_settings(config_name, |t| t.auto_connect = auto_connect)
+}
+
+/// Put a tunnel into an exclusive group, or take it out with `None`
+pub fn set_exclusive_group(config_name: &str, group: Option<String>) -> Result<TunnelSettings, WgError> {
+    let group = group.map(|g| g.trim().to_string()).filter(|g| !g.is_empty());
+    update_tunnel_settings(config_name, |t| t.exclusive_group = group)
+}
+
+/// Turn ListenPort randomization on bring-up on or off
+pub fn set_randomize_listen_port(config_name: &str, randomize: bool) -> Result<TunnelSettings, WgError> {
+    update_tunnel_settings(config_name, |t| t.randomize_listen_port = randomize)
+}
+
+/// Allow or refuse edits to a config marked as managed by another tool
+pub fn set_allow_managed_edits(config_name: &str, allow: bool) -> Result<TunnelSettings, WgError> {
+    update_tunnel_settings(config_name, |t| t.allow_managed_edits = allow)
+}
+
+/// Order tunnels so every dependency comes before its dependents.
+/// Dependencies not in `names` are pulled in as well.
+pub fn startup_order(names: &[String]) -> Result<Vec<String>, WgError> {
+    let tunnels: TunnelStore = store::load(TUNNELS_FILE)?;
+    startup_order_in(&tunnels, names)
+}
+
+fn startup_order_in(tunnels: &TunnelStore, names: &[String]) -> Result<Vec<String>, WgError> {
+    fn visit(
+        name: &str,
+        tunnels: &TunnelStore,
+        visiting: &mut Vec<String>,
+        done: &mut HashSet<String>,
+        order: &mut Vec<String>,
+    ) -> Result<(), WgError> {
+        if done.contains(name) {
+            return Ok(());
+        }
+
+        if let Some(start) = visiting.iter().position(|n| n == name) {
+            let mut cycle = visiting[start..].to_vec();
+            cycle.push(name.to_string());
+            return Err(WgError::Parse(format!("Tunnel dependency cycle: {}", cycle.join(" -> "))));
+        }
+
+        visiting.push(name.to_string());
+        if let Some(settings) = tunnels.get(name) {
+            for dep in &settings.depends_on {
+                visit(dep, tunnels, visiting, done, order)?;
+            }
+        }
+        visiting.pop();
+
+        done.insert(name.to_string());
+        order.push(name.to_string());
+        Ok(())
+    }
+
+    let mut order = Vec::new();
+    let mut done = HashSet::new();
+    for name in names {
+        visit(name, tunnels, &mut Vec::new(), &mut done, &mut order)?;
+    }
+
+    Ok(order)
+}
+
+fn check_exclusive_groups(tunnels: &TunnelStore, names: &[String]) -> Result<(), WgError> {
+    let mut seen: HashMap<&str, &str> = HashMap::new();
+
+    for name in names {
+        let Some(group) = tunnels.get(name).and_then(|t| t.exclusive_group.as_deref()) else {
+            continue;
+        };
+        if let Some(other) = seen.insert(group, name) {
+            return Err(WgError::Parse(format!(
+                "{} and {} are both in exclusive group '{}'",
+                other, name, group
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+fn connect_in(tunnels: &TunnelStore, config_name: &str) -> Result<TunnelActivation, WgError> {
+    if is_interface_up(config_name)? {
+        return Ok(TunnelActivation {
+            config_name: config_name.to_string(),
+            already_up: true,
+            report: None,
+            displaced: Vec::new(),
+            randomized_listen_port: None,
+        });
+    }
+
+    let mut displaced = Vec::new();
+    if let Some(group) = tunnels.get(config_name).and_then(|t| t.exclusive_group.as_ref()) {
+        for (other, settings) in tunnels {
+            if other == config_name || settings.exclusive_group.as_ref() != Some(group) {
+                continue;
+            }
+            if is_interface_up(other)? {
+                bring_down(other)?;
+                displaced.push(other.clone());
+            }
+        }
+    }
+
+    let randomize = tunnels.get(config_name).is_some_and(|t| t.randomize_listen_port);
+    // Passed on as is, so a wg-quick failure keeps its exit code and output for the caller
+    let (report, randomized_listen_port) = if randomize {
+        let (report, port) = bring_up_with_random_port(config_name)?;
+        (report, Some(port))
+    } else {
+        (bring_up(config_name)?, None)
+    };
+
+    Ok(TunnelActivation {
+        config_name: config_name.to_string(),
+        already_up: false,
+        report: Some(report),
+        displaced,
+        randomized_listen_port,
+    })
+}
+
+/// Random ports tried before giving up, in case one is already taken
+const RANDOM_PORT_ATTEMPTS: usize = 3;
+
+/// Bring the tunnel up, then move it to a random high port with `wg set`, so the config file
+/// is never touched. A tunnel that can't be moved is taken down again rather than left on its
+/// usual, recognizable port. Refused with `SaveConfig = true`, since wg-quick would write the
+/// random port into the file when the tunnel goes down.
+fn bring_up_with_random_port(config_name: &str) -> Result<(UpReport, u16), WgError> {
+    if parse_config(config_name)?.interface.save_config {
+        return Err(WgError::Refused(format!(
+            "{} has SaveConfig = true, so a random ListenPort would be saved into its config on down; \
+             turn SaveConfig off or stop randomizing its port",
+            config_name
+        )));
+    }
+
+    let report = bring_up(config_name)?;
+    let moved = get_real_interface_name(config_name).and_then(|interface_name| {
+        let mut last_error = String::new();
+        for _ in 0..RANDOM_PORT_ATTEMPTS {
+            let port: u16 = rand::thread_rng().gen_range(49152..=65535);
+            let output = sudo_output(["wg", "set", &interface_name, "listen-port", &port.to_string()])?;
+            if output.status.success() {
+                return Ok(port);
+            }
+            last_error = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        }
+        Err(WgError::CommandFailed(last_error))
+    });
+
+    match moved {
+        Ok(port) => Ok((report, port)),
+        Err(e) => {
+            let _ = bring_down(config_name);
+            Err(WgError::CommandFailed(format!(
+                "{} came up but couldn't be moved to a random port, so it was taken down again: {}",
+                config_name, e
+            )))
+        }
+    }
+}
+
+/// Bring a tunnel up, first bringing down any other tunnel in its exclusive group
+pub fn connect(config_name: &str) -> Result<TunnelActivation, WgError> {
+    let tunnels: TunnelStore = store::load(TUNNELS_FILE)?;
+    connect_in(&tunnels, config_name)
+}
+
+/// Bring a tunnel down, asking for confirmation first when policy requires it
+pub fn disconnect(config_name: &str, token: Option<&str>) -> Result<Guarded<String>, WgError> {
+    // Re-checking the rules would sample traffic again for nothing
+    if token.is_some_and(|t| confirm::consume("disconnect", config_name, t)) {
+        return Ok(Guarded::Done {
+            result: bring_down(config_name)?,
+        });
+    }
+
+    let reasons = disconnect_reasons(config_name)?;
+    confirm::guard("disconnect", config_name, reasons, None, || bring_down(config_name))
+}
+
+fn disconnect_reasons(config_name: &str) -> Result<Vec<String>, WgError> {
+    let policy = settings::load_settings()?.confirmations;
+    let mut reasons = Vec::new();
+
+    if policy.disconnect_active_traffic && is_interface_up(config_name)? {
+        let window = Duration::from_secs(policy.active_traffic_window_secs.max(1));
+        let sample = measure_activity(config_name, window)?;
+        if sample.total_bytes() > policy.active_traffic_threshold_bytes {
+            reasons.push(format!(
+                "{} moved {} KiB in the last {}s; something may still be using it.",
+                config_name,
+                sample.total_bytes() / 1024,
+                sample.window_secs
+            ));
+        }
+    }
+
+    if policy.disconnect_dependency {
+        let tunnels: TunnelStore = store::load(TUNNELS_FILE)?;
+        let mut dependents = Vec::new();
+        for (name, settings) in &tunnels {
+            if settings.depends_on.iter().any(|d| d == config_name) && is_interface_up(name)? {
+                dependents.push(name.clone());
+            }
+        }
+        if !dependents.is_empty() {
+            dependents.sort();
+            reasons.push(format!(
+                "{} is required by running tunnel(s): {}.",
+                config_name,
+                dependents.join(", ")
+            ));
+        }
+    }
+
+    Ok(reasons)
+}
+
+/// Bring a tunnel down (ignoring errors if it already is) and connect it again
+pub fn restart(config_name: &str) -> Result<TunnelActivation, WgError> {
+    let _ = bring_down(config_name);
+    connect(config_name)
+}
+
+/// Bring up tunnels in dependency order, skipping the ones already up
+pub fn activate_tunnels(names: &[String]) -> Result<Vec<TunnelActivation>, WgError> {
+    let tunnels: TunnelStore = store::load(TUNNELS_FILE)?;
+    let order = startup_order_in(&tunnels, names)?;
+    check_exclusive_groups(&tunnels, &order)?;
+
+    order.iter().map(|name| connect_in(&tunnels, name)).collect()
+}
+
+/// Bring up every tunnel marked for auto-connect
+pub fn auto_connect() -> Result<Vec<TunnelActivation>, WgError> {
+    let tunnels: TunnelStore = store::load(TUNNELS_FILE)?;
+    let mut names: Vec<String> = tunnels
+        .iter()
+        .filter(|(_, t)| t.auto_connect)
+        .map(|(name, _)| name.clone())
+        .collect();
+    names.sort();
+
+    activate_tunnels(&names)
+}
+
+/// List saved profiles
+pub fn list_profiles() -> Result<Vec<Profile>, WgError> {
+    store::load(PROFILES_FILE)
+}
+
+/// Create or replace a profile
+pub fn save_profile(profile: Profile) -> Result<(), WgError> {
+    // Refuse profiles that could never be activated
+    let tunnels: TunnelStore = store::load(TUNNELS_FILE)?;
+    let order = startup_order_in(&tunnels, &profile.tunnels)?;
+    check_exclusive_groups(&tunnels, &order)?;
+
+    let mut profiles = list_profiles()?;
+    profiles.retain(|p| p.name != profile.name);
+    profiles.push(profile);
+    profiles.sort_by(|a, b| a.name.cmp(&b.name));
+    store::save(PROFILES_FILE, &profiles)
+}
+
+/// Delete a profile
+pub fn delete_profile(name: &str) -> Result<(), WgError> {
+    let mut profiles = list_profiles()?;
+    profiles.retain(|p| p.name != name);
+    store::save(PROFILES_FILE, &profiles)
+}
+
+/// Bring up all tunnels of a profile, dependencies first
+pub fn activate_profile(name: &str) -> Result<Vec<TunnelActivation>, WgError> {
+    let profile = list_profiles()?
+        .into_iter()
+        .find(|p| p.name == name)
+        .ok_or_else(|| WgError::NotFound(format!("profile {}", name)))?;
+
+    activate_tunnels(&profile.tunnels)
+}
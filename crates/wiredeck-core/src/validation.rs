@@ -0,0 +1,167 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::num::NonZeroUsize;
+use std::sync::Mutex;
+use std::thread;
+
+use crate::cidr::Cidr;
+use crate::diagnostics::{Finding, Severity};
+use crate::guardrails::limit_findings;
+use crate::keys::validate_key_format;
+use crate::revoked::check_config_revoked_keys;
+use crate::server::listen_port_findings;
+use crate::wireguard::{list_configs, parse_config_with_encoding, ConfigSyntaxError, WgConfig, WgError};
+
+/// Upper bound on parser threads, however many cores there are
+const MAX_WORKERS: usize = 8;
+
+/// Validation result for one config file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfigReport {
+    pub name: String,
+    /// Parsed and no error findings
+    pub valid: bool,
+    /// Why the file couldn't be read or parsed at all
+    pub error: Option<String>,
+    /// Where parsing failed, when that is the reason
+    pub syntax_error: Option<ConfigSyntaxError>,
+    pub findings: Vec<Finding>,
+}
+
+/// Checks that only need the file itself: no `wg` calls and no other configs, so they're cheap
+/// enough to run over the whole directory, or on every edit before saving
+pub fn validate_config(config: &WgConfig) -> Vec<Finding> {
+    let mut findings = Vec::new();
+
+    if !config.interface.has_private_key {
+        findings.push(
+            Finding::warning(
+                "No usable private key; this can be kept as a reference config but not brought up.",
+                Some("Add a PrivateKey, e.g. from generate_keypair".to_string()),
+            )
+            .with_field("interface.has_private_key"),
+        );
+    }
+    if config.interface.address.is_empty() {
+        findings.push(
+            Finding::warning(
+                "No Address; the tunnel comes up but this side has no IP on it.",
+                Some("Add the address assigned to this host, e.g. 10.0.0.2/32".to_string()),
+            )
+            .with_field("interface.address"),
+        );
+    } else if let Err(e) = Cidr::parse_all(&config.interface.address) {
+        findings.push(Finding::error(format!("Address: {}", e), None).with_field("interface.address"));
+    }
+
+    // Index of the first peer with each public key
+    let mut seen: HashMap<&str, usize> = HashMap::new();
+    for (index, peer) in config.peers.iter().enumerate() {
+        let label = peer.name.clone().unwrap_or_else(|| format!("#{}", index + 1));
+        let public_key = peer.public_key.trim();
+        if public_key.is_empty() {
+            findings.push(
+                Finding::error(format!("Peer {} has no PublicKey.", label), None)
+                    .with_field(format!("peers[{}].public_key", index)),
+            );
+        } else if let Err(e) = validate_key_format(public_key) {
+            findings.push(
+                Finding::error(format!("Peer {}: PublicKey: {}", label, e), None)
+                    .with_field(format!("peers[{}].public_key", index)),
+            );
+        } else if let Some(first) = seen.get(public_key) {
+            findings.push(
+                Finding::error(
+                    format!("Peer {} has the same PublicKey as peer #{}.", label, first + 1),
+                    Some("Remove one of them; WireGuard merges peers with the same key".to_string()),
+                )
+                .with_field(format!("peers[{}].public_key", index)),
+            );
+        } else {
+            seen.insert(public_key, index);
+        }
+
+        match Cidr::parse_list(&peer.allowed_ips) {
+            Ok(allowed) if allowed.is_empty() => findings.push(
+                Finding::warning(
+                    format!("Peer {} has no AllowedIPs, so no traffic is sent to or accepted from it.", label),
+                    Some("Add the peer's tunnel address, e.g. 10.0.0.2/32".to_string()),
+                )
+                .with_field(format!("peers[{}].allowed_ips", index)),
+            ),
+            Ok(_) => {}
+            Err(e) => findings.push(
+                Finding::error(format!("Peer {}: AllowedIPs: {}", label, e), None)
+                    .with_field(format!("peers[{}].allowed_ips", index)),
+            ),
+        }
+    }
+
+    findings.extend(listen_port_findings(config));
+    findings.extend(limit_findings(config));
+    findings.extend(check_config_revoked_keys(config));
+    findings
+}
+
+fn report(name: &str) -> ConfigReport {
+    match parse_config_with_encoding(name) {
+        Ok((config, encoding)) => {
+            let mut findings = validate_config(&config);
+            if let Some(note) = encoding {
+                findings.insert(
+                    0,
+                    Finding::warning(
+                        format!("The file {}.", note),
+                        Some("Save it as plain UTF-8 text without a byte order mark".to_string()),
+                    ),
+                );
+            }
+            ConfigReport {
+                name: name.to_string(),
+                valid: findings.iter().all(|f| f.severity != Severity::Error),
+                error: None,
+                syntax_error: None,
+                findings,
+            }
+        }
+        Err(e) => ConfigReport {
+            name: name.to_string(),
+            valid: false,
+            error: Some(e.to_string()),
+            syntax_error: match e {
+                WgError::Syntax(syntax) => Some(syntax),
+                _ => None,
+            },
+            findings: Vec::new(),
+        },
+    }
+}
+
+/// Parse and validate every config in the directory on a few threads, one report per file in
+/// name order. A broken file shows up here instead of only when someone opens it.
+pub fn validate_all_configs() -> Result<Vec<ConfigReport>, WgError> {
+    let names = list_configs()?;
+    let workers = thread::available_parallelism()
+        .map(NonZeroUsize::get)
+        .unwrap_or(1)
+        .min(MAX_WORKERS)
+        .min(names.len().max(1));
+
+    let next = Mutex::new(names.iter().enumerate());
+    let reports = Mutex::new(Vec::with_capacity(names.len()));
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| loop {
+                let Some((index, name)) = next.lock().unwrap().next() else {
+                    break;
+                };
+                let report = report(name);
+                reports.lock().unwrap().push((index, report));
+            });
+        }
+    });
+
+    let mut reports = reports.into_inner().unwrap();
+    reports.sort_by_key(|(index, _)| *index);
+    Ok(reports.into_iter().map(|(_, report)| report).collect())
+}
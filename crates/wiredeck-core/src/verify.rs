@@ -0,0 +1,584 @@
+use serde::{Deserialize, Serialize};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket};
+use std::process::Command;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::cidr::Cidr;
+use crate::elevation::sudo_output;
+use crate::endpoint::{resolve_endpoint, AddressFamily};
+use crate::metrics::ONLINE_HANDSHAKE_SECS;
+use crate::server::tunnel_subnet;
+use crate::settings;
+use crate::store::now_secs;
+use crate::trace;
+use crate::wireguard::{
+    get_peer_status, get_real_interface_name, is_interface_up, parse_config, Peer, WgConfig, WgError,
+};
+
+/// Service that echoes the caller's public IP as plain text
+const EXTERNAL_IP_URL: &str = "https://api.ipify.org";
+
+/// Name looked up to check DNS through the tunnel
+const DNS_PROBE_NAME: &str = "example.com";
+
+/// How long `trigger_handshake` waits when no timeout is given
+pub const DEFAULT_HANDSHAKE_WAIT_SECS: u64 = 5;
+
+/// How long `probe_endpoint_quality` probes when no duration is given
+pub const DEFAULT_PROBE_SECS: u64 = 10;
+
+/// Longest probe run accepted
+const MAX_PROBE_SECS: u64 = 120;
+
+/// Gap between probes. Linux rate-limits port-unreachable replies to about one a second per
+/// sender, so probing faster would read as loss.
+const PROBE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// First traceroute port; nothing listens there, so the host answers with port unreachable
+const PROBE_PORT: u16 = 33434;
+
+/// Loss above which the path counts as lossy
+const LOSSY_PERCENT: f64 = 5.0;
+
+/// Overall answer to "am I connected?"
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Light {
+    Green,
+    Yellow,
+    Red,
+}
+
+/// Outcome of a single verification step
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Pass,
+    Warn,
+    Fail,
+    Skipped,
+}
+
+/// One step of `verify_tunnel`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VerifyCheck {
+    pub name: String,
+    pub status: CheckStatus,
+    pub detail: String,
+}
+
+/// Combined connectivity verdict for a tunnel
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TunnelVerdict {
+    pub config_name: String,
+    pub light: Light,
+    pub checks: Vec<VerifyCheck>,
+}
+
+/// Outcome of `trigger_handshake`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HandshakeAttempt {
+    pub public_key: String,
+    /// What was done to provoke the handshake, e.g. "ping 10.0.0.2"
+    pub method: String,
+    /// Whether a handshake newer than the one before the attempt completed in time
+    pub handshake: bool,
+    pub previous_handshake: Option<u64>,
+    pub latest_handshake: Option<u64>,
+    pub waited_ms: u64,
+}
+
+/// Loss and timing of the probes sent to one address
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProbeStats {
+    pub target: String,
+    pub sent: u32,
+    pub answered: u32,
+    pub loss_percent: f64,
+    pub rtt_min_ms: Option<f64>,
+    pub rtt_avg_ms: Option<f64>,
+    pub rtt_max_ms: Option<f64>,
+    /// Mean difference between consecutive round trips
+    pub jitter_ms: Option<f64>,
+}
+
+/// What the probes say about the connection to a peer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PathVerdict {
+    /// Recent handshake and a clean path
+    Healthy,
+    /// The tunnel handshakes, but the path to the endpoint drops packets
+    LossyPath,
+    /// The endpoint answers but there's no handshake: keys, port or AllowedIPs are wrong
+    ConfigurationProblem,
+    /// Neither the endpoint nor the tunnel answers
+    Unreachable,
+    /// The endpoint filters the probes, so its path can't be judged
+    Inconclusive,
+}
+
+/// Outcome of `probe_endpoint_quality`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EndpointQuality {
+    pub public_key: String,
+    pub endpoint: String,
+    /// Whether the last handshake is recent enough to count the tunnel as up
+    pub handshake_recent: bool,
+    /// Probes to the endpoint host, outside the tunnel
+    pub path: ProbeStats,
+    /// The same probes to the peer's tunnel address, when it has one
+    pub tunnel: Option<ProbeStats>,
+    pub verdict: PathVerdict,
+    pub detail: String,
+}
+
+fn check(name: &str, status: CheckStatus, detail: impl Into<String>) -> VerifyCheck {
+    VerifyCheck {
+        name: name.to_string(),
+        status,
+        detail: detail.into(),
+    }
+}
+
+/// The peer route that sends all IPv4 traffic through the tunnel, if any
+fn full_tunnel_peer(config: &WgConfig) -> Option<&str> {
+    config
+        .peers
+        .iter()
+        .find(|p| p.allowed_ips.split(',').any(|a| a.trim() == "0.0.0.0/0"))
+        .map(|p| p.public_key.as_str())
+}
+
+/// Best guess at the tunnel address of the peer we route through
+pub fn gateway_address(config: &WgConfig) -> Option<IpAddr> {
+    for peer in &config.peers {
+        if let Ok(cidrs) = Cidr::parse_list(&peer.allowed_ips) {
+            if let Some(host) = cidrs.iter().find(|c| c.is_host() && c.addr.is_ipv4()) {
+                return Some(host.addr);
+            }
+        }
+    }
+
+    // Otherwise assume the server sits on the first address of the tunnel subnet
+    let subnet = tunnel_subnet(config)?;
+    let IpAddr::V4(network) = subnet.addr else {
+        return None;
+    };
+    let first = IpAddr::V4(Ipv4Addr::from(u32::from(network) + 1));
+    let own = config
+        .interface
+        .address
+        .iter()
+        .filter_map(|a| Cidr::parse(a).ok())
+        .any(|c| c.addr == first);
+
+    if own {
+        None
+    } else {
+        Some(first)
+    }
+}
+
+fn ping(address: &IpAddr) -> bool {
+    let timeout_flag = if cfg!(target_os = "macos") { "-t" } else { "-W" };
+    trace::output(Command::new("ping").args(["-c", "1", timeout_flag, "2", &address.to_string()]))
+        .map(|o| o.status.success())
+        .unwrap_or(false)
+}
+
+/// Ask a specific DNS server to resolve the probe name
+fn dns_answers(server: &str) -> bool {
+    let dig = trace::output(Command::new("dig").args([
+        "+short",
+        "+time=2",
+        "+tries=1",
+        &format!("@{}", server),
+        DNS_PROBE_NAME,
+    ]));
+
+    match dig {
+        Ok(output) => output.status.success() && !output.stdout.iter().all(u8::is_ascii_whitespace),
+        // Not every Linux install ships dig
+        Err(_) => trace::output(Command::new("nslookup").args(["-timeout=2", DNS_PROBE_NAME, server]))
+            .map(|o| o.status.success())
+            .unwrap_or(false),
+    }
+}
+
+/// Fetch this host's public IP through the default route, or bound to a specific interface.
+/// A config name is accepted too and mapped to its real interface (utunN on macOS).
+pub fn get_external_ip_via(interface: Option<&str>) -> Result<String, WgError> {
+    let Some(interface) = interface else {
+        let response = ureq::get(EXTERNAL_IP_URL)
+            .timeout(Duration::from_secs(5))
+            .call()
+            .map_err(|e| WgError::CommandFailed(e.to_string()))?;
+        return Ok(response.into_string()?.trim().to_string());
+    };
+
+    // ureq can't bind to a device, curl can (SO_BINDTODEVICE / IP_BOUND_IF)
+    let interface = get_real_interface_name(interface)?;
+    let output = trace::output(Command::new("curl").args([
+        "-s",
+        "--max-time",
+        "5",
+        "--interface",
+        &interface,
+        EXTERNAL_IP_URL,
+    ]))?;
+
+    if !output.status.success() {
+        return Err(WgError::CommandFailed(format!(
+            "Could not reach {} through {}",
+            EXTERNAL_IP_URL, interface
+        )));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+fn handshake_check(config_name: &str) -> VerifyCheck {
+    let statuses = match get_peer_status(config_name) {
+        Ok(statuses) => statuses,
+        Err(e) => return check("handshake", CheckStatus::Fail, e.to_string()),
+    };
+
+    let newest = statuses
+        .iter()
+        .filter_map(|s| s.latest_handshake.as_deref()?.parse::<u64>().ok())
+        .max();
+
+    match newest {
+        Some(at) => {
+            let age = now_secs().saturating_sub(at);
+            if age < ONLINE_HANDSHAKE_SECS {
+                check("handshake", CheckStatus::Pass, format!("Last handshake {}s ago", age))
+            } else {
+                check("handshake", CheckStatus::Fail, format!("Last handshake {}s ago; the peer isn't answering", age))
+            }
+        }
+        None => check("handshake", CheckStatus::Fail, "No handshake with any peer yet"),
+    }
+}
+
+fn gateway_check(config: &WgConfig) -> VerifyCheck {
+    match gateway_address(config) {
+        Some(gateway) if ping(&gateway) => check("gateway", CheckStatus::Pass, format!("{} answers ping", gateway)),
+        Some(gateway) => check(
+            "gateway",
+            CheckStatus::Warn,
+            format!("{} doesn't answer ping; it may just block ICMP", gateway),
+        ),
+        None => check("gateway", CheckStatus::Skipped, "No peer tunnel address to ping"),
+    }
+}
+
+fn external_ip_check(config: &WgConfig) -> VerifyCheck {
+    if full_tunnel_peer(config).is_none() {
+        return check("external_ip", CheckStatus::Skipped, "Not a full tunnel");
+    }
+
+    let via_tunnel = match get_external_ip_via(Some(&config.name)) {
+        Ok(ip) => ip,
+        Err(e) => {
+            return check(
+                "external_ip",
+                CheckStatus::Fail,
+                format!("Can't reach the internet through the tunnel: {}", e),
+            )
+        }
+    };
+
+    match get_external_ip_via(None) {
+        Ok(default_route) if default_route == via_tunnel => check(
+            "external_ip",
+            CheckStatus::Pass,
+            format!("Traffic exits through the tunnel as {}", via_tunnel),
+        ),
+        Ok(default_route) => check(
+            "external_ip",
+            CheckStatus::Fail,
+            format!(
+                "Traffic bypasses the tunnel: default route exits as {}, the tunnel as {}",
+                default_route, via_tunnel
+            ),
+        ),
+        Err(e) => check("external_ip", CheckStatus::Warn, format!("Default route has no internet access: {}", e)),
+    }
+}
+
+fn dns_check(config: &WgConfig) -> VerifyCheck {
+    let server = config.interface.dns.first().cloned();
+
+    match server {
+        Some(server) if dns_answers(&server) => {
+            check("dns", CheckStatus::Pass, format!("{} resolves {}", server, DNS_PROBE_NAME))
+        }
+        Some(server) => check("dns", CheckStatus::Fail, format!("{} doesn't answer DNS queries", server)),
+        None => check("dns", CheckStatus::Skipped, "No DNS server set in the config"),
+    }
+}
+
+/// Run the connectivity checks for a tunnel and fold them into a traffic light
+pub fn verify_tunnel(config_name: &str) -> Result<TunnelVerdict, WgError> {
+    let config = parse_config(config_name)?;
+
+    if !is_interface_up(config_name)? {
+        return Ok(TunnelVerdict {
+            config_name: config_name.to_string(),
+            light: Light::Red,
+            checks: vec![check("interface", CheckStatus::Fail, "Interface is down")],
+        });
+    }
+
+    let checks = vec![
+        check("interface", CheckStatus::Pass, "Interface is up"),
+        handshake_check(config_name),
+        gateway_check(&config),
+        external_ip_check(&config),
+        dns_check(&config),
+    ];
+
+    let light = if checks.iter().any(|c| c.status == CheckStatus::Fail) {
+        Light::Red
+    } else if checks.iter().any(|c| c.status == CheckStatus::Warn) {
+        Light::Yellow
+    } else {
+        Light::Green
+    };
+
+    Ok(TunnelVerdict {
+        config_name: config_name.to_string(),
+        light,
+        checks,
+    })
+}
+
+/// An address inside the peer's AllowedIPs to send traffic to. Catch-all routes are skipped since
+/// they say nothing about where the peer lives; a full-tunnel peer falls back to the gateway.
+fn handshake_target(config: &WgConfig, peer: &Peer) -> Option<IpAddr> {
+    let cidrs = Cidr::parse_list(&peer.allowed_ips).ok()?;
+    let first = cidrs.iter().find(|c| c.prefix > 0).map(|c| {
+        if c.is_host() {
+            return c.addr;
+        }
+        match c.network().addr {
+            IpAddr::V4(v4) => IpAddr::V4(Ipv4Addr::from(u32::from(v4) + 1)),
+            IpAddr::V6(v6) => IpAddr::V6(Ipv6Addr::from(u128::from(v6) + 1)),
+        }
+    });
+    first.or_else(|| gateway_address(config))
+}
+
+fn latest_handshake(config_name: &str, public_key: &str) -> Result<Option<u64>, WgError> {
+    Ok(get_peer_status(config_name)?
+        .into_iter()
+        .find(|s| s.public_key == public_key)
+        .and_then(|s| s.latest_handshake?.parse::<u64>().ok())
+        .filter(|&at| at > 0))
+}
+
+/// Provoke a handshake with one peer instead of waiting for ambient traffic: ping an address in
+/// its AllowedIPs or, when there is none, re-assert its endpoint with `wg set`. Waits up to
+/// `timeout_secs` for a handshake newer than the one before the attempt.
+pub fn trigger_handshake(config_name: &str, public_key: &str, timeout_secs: u64) -> Result<HandshakeAttempt, WgError> {
+    let config = parse_config(config_name)?;
+    let peer = config
+        .peers
+        .iter()
+        .find(|p| p.public_key == public_key)
+        .ok_or_else(|| WgError::NotFound(format!("No peer {} in {}", public_key, config_name)))?;
+    if !is_interface_up(config_name)? {
+        return Err(WgError::CommandFailed(format!("{} is down; bring it up first", config_name)));
+    }
+
+    let previous = latest_handshake(config_name, public_key)?;
+    let target = handshake_target(&config, peer);
+    let method = match target {
+        Some(address) => format!("ping {}", address),
+        None => {
+            let live = get_peer_status(config_name)?
+                .into_iter()
+                .find(|s| s.public_key == public_key)
+                .and_then(|s| s.endpoint);
+            let Some(endpoint) = live.or_else(|| peer.endpoint.clone()) else {
+                return Err(WgError::Refused(format!(
+                    "{} has neither an address to ping nor an endpoint; it can only wait for the peer",
+                    public_key
+                )));
+            };
+            let interface_name = get_real_interface_name(config_name)?;
+            let output = sudo_output(["wg", "set", &interface_name, "peer", public_key, "endpoint", &endpoint])?;
+            if !output.status.success() {
+                return Err(WgError::CommandFailed(String::from_utf8_lossy(&output.stderr).to_string()));
+            }
+            format!("re-assert endpoint {}", endpoint)
+        }
+    };
+
+    let started = Instant::now();
+    let timeout = Duration::from_secs(timeout_secs);
+    let mut latest = previous;
+    while started.elapsed() < timeout {
+        match target {
+            // ping itself waits up to 2 s for a reply
+            Some(ref address) => {
+                ping(address);
+            }
+            None => thread::sleep(Duration::from_millis(500)),
+        }
+        latest = latest_handshake(config_name, public_key)?;
+        if latest > previous {
+            break;
+        }
+    }
+
+    Ok(HandshakeAttempt {
+        public_key: public_key.to_string(),
+        method,
+        handshake: latest > previous,
+        previous_handshake: previous,
+        latest_handshake: latest,
+        waited_ms: started.elapsed().as_millis() as u64,
+    })
+}
+
+/// Send one UDP probe per interval to a closed port of `address` for `duration`. A reply or an
+/// ICMP port unreachable (reported as connection refused) both count as an answer.
+fn udp_probe(address: IpAddr, duration: Duration) -> Result<ProbeStats, WgError> {
+    let bind: SocketAddr = if address.is_ipv4() {
+        (Ipv4Addr::UNSPECIFIED, 0).into()
+    } else {
+        (Ipv6Addr::UNSPECIFIED, 0).into()
+    };
+    let socket = UdpSocket::bind(bind)?;
+    socket.connect((address, PROBE_PORT))?;
+
+    let mut sent = 0u32;
+    let mut rtts = Vec::new();
+    let mut buffer = [0u8; 64];
+    let started = Instant::now();
+    while started.elapsed() < duration {
+        let probe_started = Instant::now();
+        sent += 1;
+        if socket.send(b"wiredeck-probe").is_ok() {
+            socket.set_read_timeout(Some(PROBE_INTERVAL))?;
+            let answered = match socket.recv(&mut buffer) {
+                Ok(_) => true,
+                Err(e) => e.kind() == std::io::ErrorKind::ConnectionRefused,
+            };
+            if answered {
+                rtts.push(probe_started.elapsed().as_secs_f64() * 1000.0);
+            }
+        }
+        if let Some(rest) = PROBE_INTERVAL.checked_sub(probe_started.elapsed()) {
+            thread::sleep(rest);
+        }
+    }
+
+    let answered = rtts.len() as u32;
+    let jitter = (rtts.len() > 1)
+        .then(|| rtts.windows(2).map(|w| (w[1] - w[0]).abs()).sum::<f64>() / (rtts.len() - 1) as f64);
+    Ok(ProbeStats {
+        target: address.to_string(),
+        sent,
+        answered,
+        loss_percent: if sent == 0 { 0.0 } else { 100.0 * (sent - answered) as f64 / sent as f64 },
+        rtt_min_ms: rtts.iter().copied().reduce(f64::min),
+        rtt_avg_ms: (!rtts.is_empty()).then(|| rtts.iter().sum::<f64>() / rtts.len() as f64),
+        rtt_max_ms: rtts.iter().copied().reduce(f64::max),
+        jitter_ms: jitter,
+    })
+}
+
+fn judge(handshake_recent: bool, path: &ProbeStats, tunnel: Option<&ProbeStats>) -> (PathVerdict, String) {
+    let tunnel_answers = tunnel.is_some_and(|t| t.answered > 0);
+    match (handshake_recent, path.answered > 0) {
+        (true, true) if path.loss_percent > LOSSY_PERCENT => (
+            PathVerdict::LossyPath,
+            format!(
+                "The tunnel handshakes, but {:.0}% of probes to the endpoint were lost; the network path is the \
+                 problem, not the config.",
+                path.loss_percent
+            ),
+        ),
+        (true, true) => (PathVerdict::Healthy, "Recent handshake and a clean path to the endpoint.".to_string()),
+        (true, false) => (
+            PathVerdict::Inconclusive,
+            "The tunnel handshakes, but the endpoint doesn't answer probes (likely filtered), so loss can't be \
+             measured outside the tunnel."
+                .to_string(),
+        ),
+        (false, true) => (
+            PathVerdict::ConfigurationProblem,
+            "The endpoint host answers but there's no recent handshake: check the keys, the endpoint port and \
+             that the peer lists this host."
+                .to_string(),
+        ),
+        (false, false) if tunnel_answers => (
+            PathVerdict::Inconclusive,
+            "The tunnel address answers although the handshake is old; the endpoint filters probes.".to_string(),
+        ),
+        (false, false) => (
+            PathVerdict::Unreachable,
+            "Neither the endpoint nor the tunnel answers; the host may be offline or block UDP.".to_string(),
+        ),
+    }
+}
+
+/// Probe the path to a peer's endpoint for `duration_secs` while the tunnel runs, and the tunnel
+/// itself alongside, to tell a lossy network from a config that never handshakes. Probes go to a
+/// closed UDP port since WireGuard ignores anything it can't authenticate. For a full-tunnel
+/// config on Linux, the endpoint probes are themselves routed through the tunnel.
+pub fn probe_endpoint_quality(
+    config_name: &str,
+    public_key: &str,
+    duration_secs: u64,
+) -> Result<EndpointQuality, WgError> {
+    let config = parse_config(config_name)?;
+    let peer = config
+        .peers
+        .iter()
+        .find(|p| p.public_key == public_key)
+        .ok_or_else(|| WgError::NotFound(format!("No peer {} in {}", public_key, config_name)))?;
+    if !is_interface_up(config_name)? {
+        return Err(WgError::CommandFailed(format!("{} is down; bring it up first", config_name)));
+    }
+
+    let status = get_peer_status(config_name)?.into_iter().find(|s| s.public_key == public_key);
+    let handshake_recent = status
+        .as_ref()
+        .and_then(|s| s.latest_handshake.as_deref()?.parse::<u64>().ok())
+        .is_some_and(|h| h > 0 && now_secs().saturating_sub(h) < ONLINE_HANDSHAKE_SECS);
+
+    // Prefer the address wg is actually sending to over re-resolving the config's hostname
+    let live = status.and_then(|s| s.endpoint);
+    let endpoint = live
+        .or_else(|| peer.endpoint.clone())
+        .ok_or_else(|| WgError::NotFound(format!("endpoint for peer {}", public_key)))?;
+    let prefer_ipv6 = settings::load_settings()?.prefer_ipv6_endpoints;
+    let (address, _) = resolve_endpoint(&endpoint, AddressFamily::Auto, prefer_ipv6)?;
+
+    let duration = Duration::from_secs(duration_secs.clamp(1, MAX_PROBE_SECS));
+    let tunnel_target = handshake_target(&config, peer);
+    let (path, tunnel) = thread::scope(|scope| {
+        let tunnel = tunnel_target.map(|target| scope.spawn(move || udp_probe(target, duration)));
+        let path = udp_probe(address.ip(), duration);
+        let tunnel = tunnel.and_then(|t| t.join().ok()).transpose();
+        (path, tunnel)
+    });
+    let (path, tunnel) = (path?, tunnel?);
+
+    let (verdict, detail) = judge(handshake_recent, &path, tunnel.as_ref());
+    Ok(EndpointQuality {
+        public_key: public_key.to_string(),
+        endpoint,
+        handshake_recent,
+        path,
+        tunnel,
+        verdict,
+        detail,
+    })
+}
@@ -0,0 +1,430 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// A route wg-quick installed while bringing an interface up
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct AddedRoute {
+    pub destination: String,
+    /// Gateway for endpoint exception routes (macOS), otherwise the route points at the tunnel
+    pub gateway: Option<String>,
+    /// Policy routing table (Linux fwmark setups)
+    pub table: Option<String>,
+}
+
+/// Structured summary of what `wg-quick up` did
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct UpReport {
+    /// The real interface name (utunX on macOS)
+    pub interface_name: Option<String>,
+    pub routes_added: Vec<AddedRoute>,
+    /// DNS servers wg-quick configured, where its output reveals them
+    pub dns_set: Vec<String>,
+    /// Whether wg-quick handed DNS to resolvconf (servers are not echoed in that case)
+    pub dns_via_resolvconf: bool,
+    pub warnings: Vec<String>,
+    pub raw_output: String,
+}
+
+/// Parse combined stdout/stderr of `wg-quick up`.
+/// wg-quick echoes each command it runs prefixed with `[#]` and notes with `[+]`.
+pub fn parse_up_output(output: &str) -> UpReport {
+    let mut report = UpReport {
+        raw_output: output.to_string(),
+        ..Default::default()
+    };
+
+    for line in output.lines() {
+        let line = line.trim();
+
+        if let Some(note) = line.strip_prefix("[+]") {
+            // "[+] Interface for wg0 is utun4"
+            if let Some((_, name)) = note.trim().split_once(" is ") {
+                if note.trim().starts_with("Interface for") {
+                    report.interface_name = Some(name.trim().to_string());
+                }
+            }
+            continue;
+        }
+
+        let Some(command) = line.strip_prefix("[#]") else {
+            if line.to_ascii_lowercase().starts_with("warning") {
+                report.warnings.push(line.to_string());
+            }
+            continue;
+        };
+
+        let args: Vec<&str> = command.split_whitespace().collect();
+        match args.as_slice() {
+            // Linux: ip link add wg0 type wireguard
+            ["ip", "link", "add", name, "type", "wireguard", ..] if report.interface_name.is_none() => {
+                report.interface_name = Some(name.to_string());
+            }
+            // Linux: ip -4 route add 10.0.0.0/24 dev wg0 [table 51820]
+            ["ip", _, "route", "add", rest @ ..] | ["ip", "route", "add", rest @ ..] => {
+                if let Some(destination) = rest.first() {
+                    report.routes_added.push(AddedRoute {
+                        destination: destination.to_string(),
+                        gateway: value_after(rest, "via"),
+                        table: value_after(rest, "table"),
+                    });
+                }
+            }
+            // macOS: route -q -n add -inet 0.0.0.0/1 -interface utun4
+            ["route", rest @ ..] if rest.contains(&"add") => {
+                let after_add: Vec<&str> = rest
+                    .iter()
+                    .skip_while(|a| **a != "add")
+                    .skip(1)
+                    .copied()
+                    .filter(|a| *a != "-inet" && *a != "-inet6")
+                    .collect();
+                if let Some(destination) = after_add.first() {
+                    report.routes_added.push(AddedRoute {
+                        destination: destination.to_string(),
+                        gateway: value_after(&after_add, "-gateway"),
+                        table: None,
+                    });
+                }
+            }
+            // macOS: networksetup -setdnsservers Wi-Fi 1.1.1.1 8.8.8.8
+            ["networksetup", "-setdnsservers", _service, servers @ ..] => {
+                for server in servers {
+                    if *server != "Empty" && !report.dns_set.iter().any(|s| s == server) {
+                        report.dns_set.push(server.to_string());
+                    }
+                }
+            }
+            ["resolvconf", ..] => report.dns_via_resolvconf = true,
+            _ => {}
+        }
+    }
+
+    report
+}
+
+fn value_after(args: &[&str], flag: &str) -> Option<String> {
+    args.iter()
+        .position(|a| *a == flag)
+        .and_then(|i| args.get(i + 1))
+        .map(|v| v.to_string())
+}
+
+/// What made `wg-quick` fail, recognized from its stderr
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum WgQuickError {
+    /// `wg-quick: `wg0' already exists`
+    InterfaceExists { interface: Option<String> },
+    /// `RTNETLINK answers: File exists`: an address or route is already set, usually by another tunnel
+    RouteConflict,
+    /// `resolvconf: command not found`
+    ResolvconfMissing,
+    /// `Address already in use` when binding the ListenPort
+    ListenPortInUse,
+    /// A peer endpoint hostname didn't resolve
+    EndpointUnresolved,
+    /// `wg-quick: `wg0' is not a WireGuard interface` when taking a tunnel down
+    NotRunning,
+    /// The kernel has no WireGuard support and no userspace implementation was found
+    WireGuardUnsupported,
+    /// macOS ships bash 3; wg-quick needs bash 4 or newer
+    BashTooOld,
+    PermissionDenied,
+    /// Anything not recognized; the stderr is all there is
+    Other,
+}
+
+/// A wg-quick failure with a plain-language summary and what to do about it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WgQuickFailure {
+    pub error: WgQuickError,
+    pub summary: String,
+    pub remediation: Option<String>,
+    /// Raw stderr, for the details view and failure reports
+    pub stderr: String,
+}
+
+impl std::fmt::Display for WgQuickFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.remediation {
+            Some(ref remediation) => write!(f, "{} {}", self.summary, remediation),
+            None => write!(f, "{}", self.summary),
+        }
+    }
+}
+
+impl WgQuickError {
+    /// Stable snake_case name, matching the serialized `kind`
+    pub fn kind(&self) -> &'static str {
+        match self {
+            WgQuickError::InterfaceExists { .. } => "interface_exists",
+            WgQuickError::RouteConflict => "route_conflict",
+            WgQuickError::ResolvconfMissing => "resolvconf_missing",
+            WgQuickError::ListenPortInUse => "listen_port_in_use",
+            WgQuickError::EndpointUnresolved => "endpoint_unresolved",
+            WgQuickError::NotRunning => "not_running",
+            WgQuickError::WireGuardUnsupported => "wireguard_unsupported",
+            WgQuickError::BashTooOld => "bash_too_old",
+            WgQuickError::PermissionDenied => "permission_denied",
+            WgQuickError::Other => "other",
+        }
+    }
+}
+
+/// Interface name from wg-quick's `` `wg0' `` quoting
+fn quoted_interface(line: &str) -> Option<String> {
+    let start = line.find('`')? + 1;
+    let end = start + line[start..].find('\'')?;
+    Some(line[start..end].to_string())
+}
+
+fn classify(stderr: &str) -> WgQuickError {
+    let lower = stderr.to_lowercase();
+
+    if let Some(line) = stderr.lines().find(|l| l.contains("already exists")) {
+        return WgQuickError::InterfaceExists {
+            interface: quoted_interface(line),
+        };
+    }
+    if lower.contains("is not a wireguard interface") {
+        return WgQuickError::NotRunning;
+    }
+    let patterns: &[(&str, WgQuickError)] = &[
+        ("rtnetlink answers: file exists", WgQuickError::RouteConflict),
+        ("resolvconf: command not found", WgQuickError::ResolvconfMissing),
+        ("address already in use", WgQuickError::ListenPortInUse),
+        ("name or service not known", WgQuickError::EndpointUnresolved),
+        ("temporary failure in name resolution", WgQuickError::EndpointUnresolved),
+        ("nodename nor servname", WgQuickError::EndpointUnresolved),
+        ("protocol not supported", WgQuickError::WireGuardUnsupported),
+        ("unknown device type", WgQuickError::WireGuardUnsupported),
+        ("version mismatch: bash", WgQuickError::BashTooOld),
+        ("permission denied", WgQuickError::PermissionDenied),
+        ("must be run as root", WgQuickError::PermissionDenied),
+    ];
+    patterns
+        .iter()
+        .find(|(pattern, _)| lower.contains(pattern))
+        .map(|(_, error)| error.clone())
+        .unwrap_or(WgQuickError::Other)
+}
+
+/// Turn the stderr of a failed `wg-quick <action>` into a typed failure with a remediation hint
+pub fn parse_failure(action: &str, stderr: &str) -> WgQuickFailure {
+    let stderr = stderr.trim().to_string();
+    let error = classify(&stderr);
+
+    let (summary, remediation) = match &error {
+        WgQuickError::InterfaceExists { interface } => (
+            match interface {
+                Some(name) => format!("Interface {} is already up.", name),
+                None => "The interface is already up.".to_string(),
+            },
+            "Bring it down first, or restart the tunnel instead.",
+        ),
+        WgQuickError::RouteConflict => (
+            "An address or route of this tunnel is already in use on this machine.".to_string(),
+            "Another tunnel probably has the same Address or AllowedIPs; disconnect it or change this config.",
+        ),
+        WgQuickError::ResolvconfMissing => (
+            "wg-quick couldn't set DNS because resolvconf isn't installed.".to_string(),
+            "Install openresolv (or systemd-resolved's resolvconf), or remove DNS from the config.",
+        ),
+        WgQuickError::ListenPortInUse => (
+            "The ListenPort is already taken.".to_string(),
+            "Another tunnel or program uses it; change the port or turn on ListenPort randomization.",
+        ),
+        WgQuickError::EndpointUnresolved => (
+            "A peer endpoint hostname didn't resolve.".to_string(),
+            "Check the hostname and that this machine's DNS works.",
+        ),
+        WgQuickError::NotRunning => (
+            "The tunnel isn't up.".to_string(),
+            "Nothing to bring down; refresh the tunnel list.",
+        ),
+        WgQuickError::WireGuardUnsupported => (
+            "This system has no WireGuard support.".to_string(),
+            "Load the wireguard kernel module (modprobe wireguard) or install wireguard-go.",
+        ),
+        WgQuickError::BashTooOld => (
+            "wg-quick needs bash 4 or newer.".to_string(),
+            "Install a current bash, e.g. brew install bash, and make sure it comes first on the PATH.",
+        ),
+        WgQuickError::PermissionDenied => (
+            "wg-quick wasn't allowed to change the network configuration.".to_string(),
+            "Check that WireDeck can run wg-quick through sudo.",
+        ),
+        WgQuickError::Other => {
+            let last = stderr.lines().rev().find(|l| !l.trim().is_empty() && !l.starts_with("[#]"));
+            let summary = match last {
+                Some(last) => format!("wg-quick {} failed: {}", action, last.trim()),
+                None => format!("wg-quick {} failed.", action),
+            };
+            return WgQuickFailure {
+                error,
+                summary,
+                remediation: None,
+                stderr,
+            };
+        }
+    };
+
+    WgQuickFailure {
+        error,
+        summary,
+        remediation: Some(remediation.to_string()),
+        stderr,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn route(destination: &str, gateway: Option<&str>, table: Option<&str>) -> AddedRoute {
+        AddedRoute {
+            destination: destination.to_string(),
+            gateway: gateway.map(str::to_string),
+            table: table.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn parses_macos_up() {
+        let output = "\
+[#] wireguard-go utun
+[+] Interface for wg0 is utun4
+[#] wg setconf utun4 /dev/fd/63
+[#] ifconfig utun4 inet 10.0.0.2/24 10.0.0.2 alias
+[#] ifconfig utun4 up
+[#] route -q -n add -inet 10.0.0.0/24 -interface utun4
+[#] route -q -n add -inet 0.0.0.0/1 -interface utun4
+[#] route -q -n add -inet 128.0.0.0/1 -interface utun4
+[#] route -q -n add -inet 203.0.113.5 -gateway 192.168.1.1
+[#] networksetup -getdnsservers Wi-Fi
+[#] networksetup -setdnsservers Wi-Fi 1.1.1.1 9.9.9.9
+[+] Backgrounding route monitor
+";
+        let report = parse_up_output(output);
+        assert_eq!(report.interface_name.as_deref(), Some("utun4"));
+        assert_eq!(
+            report.routes_added,
+            vec![
+                route("10.0.0.0/24", None, None),
+                route("0.0.0.0/1", None, None),
+                route("128.0.0.0/1", None, None),
+                route("203.0.113.5", Some("192.168.1.1"), None),
+            ]
+        );
+        assert_eq!(report.dns_set, vec!["1.1.1.1", "9.9.9.9"]);
+        assert!(!report.dns_via_resolvconf);
+        assert_eq!(report.raw_output, output);
+    }
+
+    #[test]
+    fn parses_linux_full_tunnel_up() {
+        let output = "\
+[#] ip link add wg0 type wireguard
+[#] wg setconf wg0 /dev/fd/63
+[#] ip -4 address add 10.0.0.2/24 dev wg0
+[#] ip link set mtu 1420 up dev wg0
+[#] resolvconf -a wg0 -m 0 -x
+[#] wg set wg0 fwmark 51820
+[#] ip -6 route add ::/0 dev wg0 table 51820
+[#] ip -6 rule add not fwmark 51820 table 51820
+[#] ip -6 rule add table main suppress_prefixlength 0
+[#] ip -4 route add 0.0.0.0/0 dev wg0 table 51820
+[#] ip -4 rule add not fwmark 51820 table 51820
+[#] ip -4 rule add table main suppress_prefixlength 0
+[#] sysctl -q net.ipv4.conf.all.src_valid_mark=1
+[#] nft -f /dev/fd/63
+";
+        let report = parse_up_output(output);
+        assert_eq!(report.interface_name.as_deref(), Some("wg0"));
+        assert_eq!(
+            report.routes_added,
+            vec![route("::/0", None, Some("51820")), route("0.0.0.0/0", None, Some("51820"))]
+        );
+        assert!(report.dns_via_resolvconf);
+        assert!(report.dns_set.is_empty());
+    }
+
+    #[test]
+    fn parses_linux_split_tunnel_up() {
+        let output = "\
+[#] ip link add wg1 type wireguard
+[#] wg setconf wg1 /dev/fd/63
+[#] ip -4 address add 10.8.0.3/24 dev wg1
+[#] ip link set mtu 1420 up dev wg1
+[#] ip -4 route add 192.168.50.0/24 dev wg1
+Warning: AllowedIP has nonzero host part: 192.168.50.1/24
+";
+        let report = parse_up_output(output);
+        assert_eq!(report.interface_name.as_deref(), Some("wg1"));
+        assert_eq!(report.routes_added, vec![route("192.168.50.0/24", None, None)]);
+        assert_eq!(report.warnings, vec!["Warning: AllowedIP has nonzero host part: 192.168.50.1/24"]);
+    }
+
+    #[test]
+    fn recognizes_interface_exists() {
+        let failure = parse_failure("up", "wg-quick: `wg0' already exists\n");
+        assert_eq!(failure.error, WgQuickError::InterfaceExists { interface: Some("wg0".to_string()) });
+        assert_eq!(failure.summary, "Interface wg0 is already up.");
+        assert_eq!(failure.stderr, "wg-quick: `wg0' already exists");
+    }
+
+    #[test]
+    fn recognizes_route_conflict() {
+        let stderr = "\
+[#] ip link add wg0 type wireguard
+[#] wg setconf wg0 /dev/fd/63
+[#] ip -4 address add 10.0.0.2/24 dev wg0
+RTNETLINK answers: File exists
+[#] ip link delete dev wg0
+";
+        assert_eq!(parse_failure("up", stderr).error, WgQuickError::RouteConflict);
+    }
+
+    #[test]
+    fn recognizes_missing_resolvconf() {
+        let stderr = "\
+[#] ip link set mtu 1420 up dev wg0
+[#] resolvconf -a wg0 -m 0 -x
+/usr/bin/wg-quick: line 32: resolvconf: command not found
+[#] ip link delete dev wg0
+";
+        let failure = parse_failure("up", stderr);
+        assert_eq!(failure.error, WgQuickError::ResolvconfMissing);
+        assert!(failure.remediation.is_some());
+    }
+
+    #[test]
+    fn recognizes_macos_failures() {
+        let bash = parse_failure("up", "wg-quick: Version mismatch: bash 3 detected, when bash 4+ required\n");
+        assert_eq!(bash.error, WgQuickError::BashTooOld);
+
+        let stderr = "Name does not resolve: `vpn.example.com:51820'\n\
+                      nodename nor servname provided, or not known: `vpn.example.com:51820'\n\
+                      Configuration parsing error\n";
+        assert_eq!(parse_failure("up", stderr).error, WgQuickError::EndpointUnresolved);
+    }
+
+    #[test]
+    fn recognizes_down_when_not_running() {
+        let failure = parse_failure("down", "wg-quick: `wg0' is not a WireGuard interface\n");
+        assert_eq!(failure.error, WgQuickError::NotRunning);
+    }
+
+    #[test]
+    fn falls_back_to_last_message_line() {
+        let stderr = "\
+Line unrecognized: `Foo=bar'
+Configuration parsing error
+[#] ip link delete dev wg0
+";
+        let failure = parse_failure("up", stderr);
+        assert_eq!(failure.error, WgQuickError::Other);
+        assert_eq!(failure.summary, "wg-quick up failed: Configuration parsing error");
+        assert_eq!(failure.remediation, None);
+    }
+}
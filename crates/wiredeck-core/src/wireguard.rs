@@ -0,0 +1,1448 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use rand::rngs::OsRng;
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::ffi::{OsStr, OsString};
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use thiserror::Error;
+use zeroize::Zeroizing;
+
+use crate::backup;
+use crate::daemons;
+use crate::elevation::sudo_output;
+use crate::endpoint::format_endpoint;
+use crate::events;
+use crate::guardrails::check_config_limits;
+use crate::keys::{validate_config_keys, validate_key_format, InvalidKey};
+use crate::netinfo::EndpointInfo;
+use crate::privileged::record_change;
+use crate::selfservice::sync_selfservice_bundle;
+use crate::trace;
+use crate::wgquick::{parse_failure, parse_up_output, UpReport, WgQuickFailure};
+use crate::x25519;
+
+/// Where wg-quick looks for configs on Linux
+const LINUX_WIREGUARD_DIR: &str = "/etc/wireguard";
+
+/// Install directory of the WireGuard app on Windows; its tunnel service reads configs from `Data\Configurations`
+const WINDOWS_WIREGUARD_HOME: &str = r"C:\Program Files\WireGuard";
+
+/// Largest file read as a config. Reads stop here, so a stray huge file is never loaded whole.
+const MAX_READ_BYTES: u64 = 8 * 1024 * 1024;
+
+/// Errors returned by config handling and the wg/wg-quick backends
+#[derive(Error, Debug)]
+pub enum WgError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Parse error: {0}")]
+    Parse(String),
+    #[error("Command execution failed: {0}")]
+    CommandFailed(String),
+    #[error("Config not found: {0}")]
+    NotFound(String),
+    #[error("Refused: {0}")]
+    Refused(String),
+    #[error("Unauthorized: {0}")]
+    Unauthorized(String),
+    #[error("{0}")]
+    WgQuick(WgQuickFailure),
+    #[error("{0}")]
+    InvalidKey(InvalidKey),
+    #[error("Parse error: {0}")]
+    Syntax(ConfigSyntaxError),
+}
+
+impl WgError {
+    /// Stable snake_case name of the variant, for grouping and reporting errors
+    pub fn kind(&self) -> &'static str {
+        match self {
+            WgError::Io(_) => "io",
+            WgError::Parse(_) => "parse",
+            WgError::CommandFailed(_) => "command_failed",
+            WgError::NotFound(_) => "not_found",
+            WgError::Refused(_) => "refused",
+            WgError::Unauthorized(_) => "unauthorized",
+            WgError::WgQuick(_) => "wg_quick",
+            WgError::InvalidKey(_) => "invalid_key",
+            WgError::Syntax(_) => "syntax",
+        }
+    }
+}
+
+/// A `WgError` as the frontend gets it: the kind to branch on, the message to show and, for a
+/// bad key or unparsable text, which field or line to highlight
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ErrorDetail {
+    /// `WgError::kind`, e.g. `invalid_key`
+    pub kind: String,
+    /// `PrivateKey`, `PublicKey` or `PresharedKey` for an invalid key
+    pub field: Option<String>,
+    /// Name (or `#n` position) of the peer the field belongs to
+    pub peer: Option<String>,
+    /// 1-based line a config failed to parse at; the message says what was expected there
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl From<&WgError> for ErrorDetail {
+    fn from(error: &WgError) -> Self {
+        let (field, peer) = match error {
+            WgError::InvalidKey(key) => (Some(key.field.clone()), key.peer.clone()),
+            _ => (None, None),
+        };
+        let line = match error {
+            WgError::Syntax(syntax) => syntax.line,
+            _ => None,
+        };
+        ErrorDetail {
+            kind: error.kind().to_string(),
+            field,
+            peer,
+            line,
+            message: error.to_string(),
+        }
+    }
+}
+
+/// Where and why config text couldn't be parsed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConfigSyntaxError {
+    /// 1-based; `None` when the problem is the file as a whole, e.g. a missing `[Interface]`
+    pub line: Option<usize>,
+    /// The offending line as written
+    pub text: Option<String>,
+    /// What the parser expected instead
+    pub expected: String,
+}
+
+impl std::fmt::Display for ConfigSyntaxError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.line, &self.text) {
+            (Some(line), Some(text)) => write!(f, "line {}: expected {}, found '{}'", line, self.expected, text),
+            (Some(line), None) => write!(f, "line {}: expected {}", line, self.expected),
+            _ => write!(f, "expected {}", self.expected),
+        }
+    }
+}
+
+/// Comment lines of a section, kept verbatim (with `#`) so annotated configs survive a save
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct Comments {
+    /// Block above the section header; for peers without its last line, which is the peer name
+    pub before: Vec<String>,
+    /// Lines inside the section, keyed by the key they sit above
+    pub keys: HashMap<String, Vec<String>>,
+    /// Lines after the last key at the end of the file
+    pub after: Vec<String>,
+}
+
+impl Comments {
+    pub fn is_empty(&self) -> bool {
+        self.before.is_empty() && self.keys.is_empty() && self.after.is_empty()
+    }
+}
+
+/// The `[Interface]` section of a config
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Interface {
+    /// `None` for reference configs kept only as a peer directory; placeholders are kept as written
+    #[serde(default)]
+    pub private_key: Option<String>,
+    /// Whether `private_key` is a real key rather than missing or a placeholder, set when parsing
+    #[serde(default)]
+    pub has_private_key: bool,
+    /// One CIDR per entry, from every Address line and comma-separated value
+    pub address: Vec<String>,
+    /// `None` for client configs, which let the kernel pick a port
+    #[serde(default)]
+    pub listen_port: Option<u16>,
+    /// DNS servers; the DNS key's entries that are IP addresses
+    #[serde(default)]
+    pub dns: Vec<String>,
+    /// Search domains; the DNS key's other entries
+    #[serde(default)]
+    pub dns_search: Vec<String>,
+    #[serde(default)]
+    pub mtu: Option<u16>,
+    /// Routing table for the AllowedIPs routes: a table name or number, `off` or `auto`
+    #[serde(default)]
+    pub table: Option<String>,
+    /// Mark on outgoing packets, as written (e.g. `0x51820`, or `off`)
+    #[serde(default)]
+    pub fw_mark: Option<String>,
+    /// Hook commands, one entry per line; wg-quick runs them in order
+    #[serde(default)]
+    pub pre_up: Vec<String>,
+    #[serde(default)]
+    pub post_up: Vec<String>,
+    #[serde(default)]
+    pub pre_down: Vec<String>,
+    #[serde(default)]
+    pub post_down: Vec<String>,
+    /// `SaveConfig = true`: wg-quick writes the live state back over the file on down
+    #[serde(default)]
+    pub save_config: bool,
+    /// Keys WireDeck doesn't model (e.g. AmneziaWG's `Jc` or `Jmin`), in file order, written back as they were
+    #[serde(default)]
+    pub extra: Vec<(String, String)>,
+    #[serde(default)]
+    pub comments: Comments,
+}
+
+/// A `[Peer]` section of a config
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Peer {
+    pub public_key: String,
+    /// Extra symmetric key mixed into the handshake, from `wg genpsk`
+    #[serde(default)]
+    pub preshared_key: Option<String>,
+    pub allowed_ips: String,
+    pub persistent_keepalive: Option<u16>,
+    pub endpoint: Option<String>,
+    pub name: Option<String>, // From comment above peer
+    /// Keys WireDeck doesn't model, in file order
+    #[serde(default)]
+    pub extra: Vec<(String, String)>,
+    #[serde(default)]
+    pub comments: Comments,
+}
+
+/// A parsed WireGuard config file
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct WgConfig {
+    pub name: String,
+    pub path: PathBuf,
+    pub interface: Interface,
+    pub peers: Vec<Peer>,
+    /// Configuration management tool named in a header comment, e.g. `# Managed by Ansible`
+    #[serde(default)]
+    pub managed_by: Option<String>,
+    /// `content_hash` of the file as it was read; `None` for configs built in memory
+    #[serde(default)]
+    pub content_hash: Option<String>,
+}
+
+/// Live state of a peer as reported by `wg show dump`
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct PeerStatus {
+    pub public_key: String,
+    pub endpoint: Option<String>,
+    pub latest_handshake: Option<String>,
+    pub transfer_rx: Option<String>,
+    pub transfer_tx: Option<String>,
+    /// Reverse DNS / ASN of the endpoint, filled in by `netinfo::enrich_peer_statuses`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub endpoint_info: Option<EndpointInfo>,
+}
+
+/// Get WireGuard config directory for the active workspace, based on OS and architecture by default
+pub fn get_wireguard_dir() -> PathBuf {
+    // A workspace can bind its own config directory
+    if let Some(dir) = crate::workspace::active_workspace().config_dir {
+        return dir;
+    }
+
+    // Distribution packages put configs where wg-quick and wg-quick@ units look for them
+    if cfg!(target_os = "linux") {
+        return PathBuf::from(LINUX_WIREGUARD_DIR);
+    }
+    if cfg!(windows) {
+        return Path::new(WINDOWS_WIREGUARD_HOME).join("Data").join("Configurations");
+    }
+
+    // Check for Homebrew on Apple Silicon
+    let arm_path = PathBuf::from("/opt/homebrew/etc/wireguard");
+    if arm_path.exists() {
+        return arm_path;
+    }
+
+    // Check for Homebrew on Intel
+    let intel_path = PathBuf::from("/usr/local/etc/wireguard");
+    if intel_path.exists() {
+        return intel_path;
+    }
+
+    // Default to Intel path
+    intel_path
+}
+
+/// Whether a config file exists, asking through sudo when its directory is root-only
+/// (`/etc/wireguard` is 0700 on Linux)
+pub(crate) fn config_file_exists(path: &Path) -> bool {
+    match fs::metadata(path) {
+        Ok(meta) => meta.is_file(),
+        Err(e) if e.kind() == std::io::ErrorKind::PermissionDenied => {
+            sudo_output([OsStr::new("test"), OsStr::new("-f"), path.as_os_str()])
+                .map(|o| o.status.success())
+                .unwrap_or(false)
+        }
+        Err(_) => false,
+    }
+}
+
+/// File names in the config directory, listed through sudo when it's root-only
+fn config_dir_entries(wg_dir: &Path) -> Result<Vec<String>, WgError> {
+    match fs::read_dir(wg_dir) {
+        Ok(entries) => {
+            let mut names = Vec::new();
+            for entry in entries {
+                names.push(entry?.file_name().to_string_lossy().to_string());
+            }
+            Ok(names)
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::PermissionDenied => {
+            let output = sudo_output([OsStr::new("ls"), OsStr::new("-1A"), wg_dir.as_os_str()])?;
+            if !output.status.success() {
+                return Err(WgError::Io(e));
+            }
+            Ok(String::from_utf8_lossy(&output.stdout).lines().map(str::to_string).collect())
+        }
+        Err(e) => Err(WgError::Io(e)),
+    }
+}
+
+/// Check that a config name is usable as a wg-quick interface name
+pub fn validate_config_name(name: &str) -> Result<(), WgError> {
+    let valid = !name.is_empty()
+        && name.len() <= 15
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || "_=+.-".contains(c));
+
+    if valid {
+        Ok(())
+    } else {
+        Err(WgError::Parse(format!(
+            "Invalid config name '{}': use up to 15 letters, digits or _=+.-",
+            name
+        )))
+    }
+}
+
+/// List all WireGuard configuration files
+pub fn list_configs() -> Result<Vec<String>, WgError> {
+    let wg_dir = get_wireguard_dir();
+
+    if !wg_dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut configs = Vec::new();
+
+    for file_name in config_dir_entries(&wg_dir)? {
+        let path = Path::new(&file_name);
+
+        if path.extension().and_then(|s| s.to_str()) == Some("conf") {
+            if let Some(name) = path.file_stem().and_then(|s| s.to_str()) {
+                configs.push(name.to_string());
+            }
+        }
+    }
+
+    configs.sort();
+    Ok(configs)
+}
+
+/// Parse WireGuard configuration file
+pub fn parse_config(name: &str) -> Result<WgConfig, WgError> {
+    parse_config_with_encoding(name).map(|(config, _)| config)
+}
+
+/// Parse a config, along with what had to be done to read it as UTF-8 text, if anything
+pub(crate) fn parse_config_with_encoding(name: &str) -> Result<(WgConfig, Option<String>), WgError> {
+    let path = get_wireguard_dir().join(format!("{}.conf", name));
+    if !config_file_exists(&path) {
+        return Err(WgError::NotFound(name.to_string()));
+    }
+    let (content, encoding) = read_config_text_with_encoding(&path)?;
+    Ok((parse_config_content(name, &path, &content)?, encoding))
+}
+
+/// Read up to `MAX_READ_BYTES` of a file, through sudo when it's root-only
+fn read_capped(path: &Path) -> Result<Vec<u8>, WgError> {
+    let limit = MAX_READ_BYTES + 1;
+    let mut bytes = Vec::new();
+    match fs::File::open(path).and_then(|file| file.take(limit).read_to_end(&mut bytes)) {
+        Ok(_) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::PermissionDenied => {
+            let limit = limit.to_string();
+            let output = sudo_output([OsStr::new("head"), OsStr::new("-c"), OsStr::new(&limit), path.as_os_str()])?;
+            if !output.status.success() {
+                return Err(WgError::Io(e));
+            }
+            bytes = output.stdout;
+        }
+        Err(e) => return Err(WgError::Io(e)),
+    }
+
+    if bytes.len() as u64 > MAX_READ_BYTES {
+        return Err(WgError::Parse(format!(
+            "{} is over {} MiB, far too big for a WireGuard config",
+            path.display(),
+            MAX_READ_BYTES / (1024 * 1024)
+        )));
+    }
+    Ok(bytes)
+}
+
+/// Decode config bytes as text. UTF-16 is converted, a byte order mark dropped and invalid UTF-8
+/// replaced rather than failing the read; the note says which, for validation to warn about.
+fn decode_config_text(path: &Path, bytes: Vec<u8>) -> Result<(String, Option<String>), WgError> {
+    let utf16 = |bytes: &[u8], decode: fn([u8; 2]) -> u16| {
+        let units: Vec<u16> = bytes.chunks_exact(2).map(|pair| decode([pair[0], pair[1]])).collect();
+        String::from_utf16_lossy(&units)
+    };
+    let utf16_note = || Some("is saved as UTF-16; wg-quick only reads UTF-8".to_string());
+    if let Some(rest) = bytes.strip_prefix(b"\xff\xfe") {
+        return Ok((utf16(rest, u16::from_le_bytes), utf16_note()));
+    }
+    if let Some(rest) = bytes.strip_prefix(b"\xfe\xff") {
+        return Ok((utf16(rest, u16::from_be_bytes), utf16_note()));
+    }
+    // Text never has NUL bytes; this is an image, archive or the like with a .conf name
+    if bytes.contains(&0) {
+        return Err(WgError::Parse(format!("{} is a binary file, not a WireGuard config", path.display())));
+    }
+
+    let bom = bytes.starts_with(b"\xef\xbb\xbf");
+    let text = if bom { &bytes[3..] } else { &bytes[..] };
+    match std::str::from_utf8(text) {
+        Ok(content) if bom => Ok((
+            content.to_string(),
+            Some("starts with a byte order mark, which wg-quick can't read past".to_string()),
+        )),
+        Ok(content) => Ok((content.to_string(), None)),
+        Err(_) => Ok((
+            String::from_utf8_lossy(text).into_owned(),
+            Some("has bytes that aren't valid UTF-8; they read as \u{fffd}, and saving replaces them".to_string()),
+        )),
+    }
+}
+
+/// Read a config file as text, and what had to be done to decode it, if anything
+pub(crate) fn read_config_text_with_encoding(path: &Path) -> Result<(String, Option<String>), WgError> {
+    decode_config_text(path, read_capped(path)?)
+}
+
+/// Read a config file as text, through sudo when it's root-only
+pub(crate) fn read_config_text(path: &Path) -> Result<String, WgError> {
+    Ok(read_config_text_with_encoding(path)?.0)
+}
+
+/// Values of a comma-separated key, trimmed, without empty entries
+pub(crate) fn split_list(value: &str) -> Vec<String> {
+    value.split(',').map(str::trim).filter(|v| !v.is_empty()).map(str::to_string).collect()
+}
+
+/// Tool named by a "managed by" style header comment ("Managed by Ansible", "Ansible managed", ...)
+fn managed_by_marker(comment: &str) -> Option<String> {
+    let words: Vec<&str> = comment.split_whitespace().collect();
+    let is_managed = |word: &str| word.trim_matches(|c: char| !c.is_alphanumeric()).eq_ignore_ascii_case("managed");
+
+    let tool = if let Some(index) = words.windows(2).position(|w| is_managed(w[0]) && w[1].eq_ignore_ascii_case("by")) {
+        words.get(index + 2)
+    } else if words.len() >= 2 && is_managed(words[1]) {
+        words.first()
+    } else {
+        None
+    }?;
+
+    let tool = tool.trim_matches(|c: char| !c.is_alphanumeric());
+    (!tool.is_empty()).then(|| tool.to_string())
+}
+
+/// Keys the parser models, in the spelling `serialize_config` writes
+const KNOWN_KEYS: [&str; 17] = [
+    "PrivateKey",
+    "Address",
+    "ListenPort",
+    "DNS",
+    "MTU",
+    "Table",
+    "FwMark",
+    "PreUp",
+    "PostUp",
+    "PreDown",
+    "PostDown",
+    "SaveConfig",
+    "PublicKey",
+    "PresharedKey",
+    "AllowedIPs",
+    "PersistentKeepalive",
+    "Endpoint",
+];
+
+/// wg and wg-quick match key names regardless of case; a known key reads as its usual spelling
+/// and anything else is kept as written
+fn canonical_key(key: &str) -> &str {
+    KNOWN_KEYS.iter().find(|known| known.eq_ignore_ascii_case(key)).copied().unwrap_or(key)
+}
+
+/// Parse WireGuard config from string content. Like wg-quick, everything from a `#` to the end
+/// of the line is a comment and section and key names are matched regardless of case.
+/// A comment after a key or section header is kept as a comment on the line above it.
+pub fn parse_config_content(name: &str, path: &Path, content: &str) -> Result<WgConfig, WgError> {
+    let mut interface: Option<Interface> = None;
+    let mut interface_comments = Comments::default();
+    let mut peers: Vec<Peer> = Vec::new();
+    let mut current_section = "";
+    let mut current_peer: Option<Peer> = None;
+    let mut pending_comments: Vec<String> = Vec::new();
+    let mut managed_by: Option<String> = None;
+
+    for (index, raw) in content.lines().enumerate() {
+        let line = raw.trim();
+        let syntax = |expected: &str| {
+            WgError::Syntax(ConfigSyntaxError {
+                line: Some(index + 1),
+                text: Some(raw.trim_end().to_string()),
+                expected: expected.to_string(),
+            })
+        };
+
+        // Skip empty lines
+        if line.is_empty() {
+            continue;
+        }
+
+        // Collect comments until we know whether they belong to a key or the next section
+        if line.starts_with('#') {
+            // Only the header above the first section marks the whole file
+            if current_section.is_empty() && managed_by.is_none() {
+                managed_by = managed_by_marker(line.trim_start_matches('#').trim());
+            }
+            pending_comments.push(line.to_string());
+            continue;
+        }
+        let line = match line.split_once('#') {
+            Some((code, comment)) => {
+                pending_comments.push(format!("#{}", comment.trim_end()));
+                code.trim_end()
+            }
+            None => line,
+        };
+
+        // Handle sections
+        if line.starts_with('[') {
+            if !line.ends_with(']') {
+                return Err(syntax("a section header like [Peer]"));
+            }
+            // Save previous peer if exists
+            if let Some(peer) = current_peer.take() {
+                peers.push(peer);
+            }
+
+            let section = line[1..line.len() - 1].trim();
+            current_section = if section.eq_ignore_ascii_case("Interface") {
+                "Interface"
+            } else if section.eq_ignore_ascii_case("Peer") {
+                "Peer"
+            } else {
+                // wg rejects any other section as well
+                return Err(syntax("[Interface] or [Peer]"));
+            };
+
+            match current_section {
+                "Interface" => interface_comments.before = std::mem::take(&mut pending_comments),
+                _ => {
+                    // The comment right above a peer is its name
+                    let name = pending_comments
+                        .pop()
+                        .map(|c| c.trim_start_matches('#').trim().to_string());
+                    current_peer = Some(Peer {
+                        public_key: String::new(),
+                        preshared_key: None,
+                        allowed_ips: String::new(),
+                        persistent_keepalive: None,
+                        endpoint: None,
+                        name,
+                        extra: Vec::new(),
+                        comments: Comments {
+                            before: std::mem::take(&mut pending_comments),
+                            ..Comments::default()
+                        },
+                    });
+                }
+            }
+            continue;
+        }
+
+        // Parse key-value pairs
+        let Some((key, value)) = line.split_once('=') else {
+            return Err(syntax("Key = value"));
+        };
+        let key = canonical_key(key.trim());
+        let value = value.trim().to_string();
+        if key.is_empty() {
+            return Err(syntax("a key name before '='"));
+        }
+        if current_section.is_empty() {
+            return Err(syntax("an [Interface] or [Peer] header before the first key"));
+        }
+
+        if !pending_comments.is_empty() {
+            let comments = match current_section {
+                "Interface" => Some(&mut interface_comments),
+                "Peer" => current_peer.as_mut().map(|p| &mut p.comments),
+                _ => None,
+            };
+            if let Some(comments) = comments {
+                comments
+                    .keys
+                    .entry(key.to_string())
+                    .or_default()
+                    .append(&mut pending_comments);
+            }
+            pending_comments.clear();
+        }
+
+        match current_section {
+            "Interface" => {
+                if interface.is_none() {
+                    interface = Some(Interface {
+                        private_key: None,
+                        has_private_key: false,
+                        address: Vec::new(),
+                        listen_port: None,
+                        dns: Vec::new(),
+                        dns_search: Vec::new(),
+                        mtu: None,
+                        table: None,
+                        fw_mark: None,
+                        pre_up: Vec::new(),
+                        post_up: Vec::new(),
+                        pre_down: Vec::new(),
+                        post_down: Vec::new(),
+                        save_config: false,
+                        extra: Vec::new(),
+                        comments: Comments::default(),
+                    });
+                }
+
+                if let Some(ref mut iface) = interface {
+                    match key {
+                        "PrivateKey" => iface.private_key = Some(value).filter(|v| !v.is_empty()),
+                        "Address" => iface.address.extend(split_list(&value)),
+                        // 0 asks for a random port, the same as leaving the key out
+                        "ListenPort" => {
+                            let port: u16 = value.parse().map_err(|_| syntax("a port from 0 to 65535"))?;
+                            iface.listen_port = Some(port).filter(|&p| p != 0);
+                        }
+                        // wg-quick takes IP addresses as servers and anything else as a search domain
+                        "DNS" => {
+                            for entry in split_list(&value) {
+                                if entry.parse::<std::net::IpAddr>().is_ok() {
+                                    iface.dns.push(entry);
+                                } else {
+                                    iface.dns_search.push(entry);
+                                }
+                            }
+                        }
+                        "MTU" => iface.mtu = Some(value.parse().map_err(|_| syntax("an MTU in bytes"))?),
+                        "Table" => iface.table = Some(value),
+                        "FwMark" => iface.fw_mark = Some(value),
+                        "PreUp" => iface.pre_up.push(value),
+                        "PostUp" => iface.post_up.push(value),
+                        "PreDown" => iface.pre_down.push(value),
+                        "PostDown" => iface.post_down.push(value),
+                        "SaveConfig" => iface.save_config = value.eq_ignore_ascii_case("true"),
+                        _ => iface.extra.push((key.to_string(), value)),
+                    }
+                }
+            }
+            "Peer" => {
+                if let Some(ref mut peer) = current_peer {
+                    match key {
+                        "PublicKey" => peer.public_key = value,
+                        "PresharedKey" => peer.preshared_key = Some(value),
+                        "AllowedIPs" => peer.allowed_ips = value,
+                        "PersistentKeepalive" => {
+                            peer.persistent_keepalive = match value.as_str() {
+                                "off" => None,
+                                _ => Some(value.parse().map_err(|_| syntax("seconds between keepalives, or off"))?),
+                            }
+                        }
+                        "Endpoint" => peer.endpoint = Some(value),
+                        _ => peer.extra.push((key.to_string(), value)),
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // Comments at the end of the file stay with the last section
+    match current_peer.as_mut() {
+        Some(peer) => peer.comments.after = pending_comments,
+        None => interface_comments.after = pending_comments,
+    }
+
+    // Save last peer
+    if let Some(peer) = current_peer {
+        peers.push(peer);
+    }
+
+    let mut interface = interface.ok_or_else(|| {
+        WgError::Syntax(ConfigSyntaxError {
+            line: None,
+            text: None,
+            expected: "an [Interface] section with at least one key".to_string(),
+        })
+    })?;
+    interface.comments = interface_comments;
+    interface.has_private_key = interface
+        .private_key
+        .as_deref()
+        .is_some_and(|k| validate_key_format(k).is_ok());
+
+    Ok(WgConfig {
+        name: name.to_string(),
+        path: path.to_path_buf(),
+        interface,
+        peers,
+        managed_by,
+        content_hash: Some(content_hash(content)),
+    })
+}
+
+/// Where config text fails to parse, if it does, for pointing at the line in an editor
+pub fn check_config_syntax(content: &str) -> Option<ConfigSyntaxError> {
+    match parse_config_content("", Path::new(""), content) {
+        Err(WgError::Syntax(e)) => Some(e),
+        _ => None,
+    }
+}
+
+/// SHA-256 of config file text, hex-encoded; tells whether a file changed since it was read
+pub fn content_hash(content: &str) -> String {
+    Sha256::digest(content.as_bytes()).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Writes `Key = value` lines, each preceded by the comments that sat above it
+struct SectionWriter<'a> {
+    output: &'a mut String,
+    key_comments: HashMap<String, Vec<String>>,
+}
+
+impl<'a> SectionWriter<'a> {
+    fn new(output: &'a mut String, comments: &Comments) -> Self {
+        SectionWriter {
+            output,
+            key_comments: comments.keys.clone(),
+        }
+    }
+
+    fn comment_lines(&mut self, lines: &[String]) {
+        for line in lines {
+            self.output.push_str(line);
+            self.output.push('\n');
+        }
+    }
+
+    fn key(&mut self, key: &str, value: impl std::fmt::Display) {
+        if let Some(lines) = self.key_comments.remove(key) {
+            self.comment_lines(&lines);
+        }
+        self.output.push_str(&format!("{} = {}\n", key, value));
+    }
+
+    /// Keys the parser didn't recognize, as they were read
+    fn extra(&mut self, keys: &[(String, String)]) {
+        for (key, value) in keys {
+            self.key(key, value);
+        }
+    }
+
+    /// Comments of keys that are gone, then the section's trailing comments
+    fn finish(mut self, after: &[String]) {
+        let mut orphaned: Vec<_> = std::mem::take(&mut self.key_comments).into_iter().collect();
+        orphaned.sort();
+        for (_, lines) in orphaned {
+            self.comment_lines(&lines);
+        }
+        self.comment_lines(after);
+    }
+}
+
+/// Serialize WgConfig back to .conf format
+pub fn serialize_config(config: &WgConfig) -> String {
+    let mut output = String::new();
+    let interface = &config.interface;
+
+    if interface.comments.before.is_empty() {
+        if let Some(ref tool) = config.managed_by {
+            output.push_str(&format!("# Managed by {}\n\n", tool));
+        }
+    }
+
+    // Interface section
+    let mut section = SectionWriter::new(&mut output, &interface.comments);
+    section.comment_lines(&interface.comments.before);
+    section.output.push_str("[Interface]\n");
+    if let Some(ref private_key) = interface.private_key {
+        section.key("PrivateKey", private_key);
+    }
+    if !interface.address.is_empty() {
+        section.key("Address", interface.address.join(", "));
+    }
+    if let Some(port) = interface.listen_port {
+        section.key("ListenPort", port);
+    }
+
+    if !interface.dns.is_empty() || !interface.dns_search.is_empty() {
+        let entries: Vec<&str> = interface.dns.iter().chain(&interface.dns_search).map(String::as_str).collect();
+        section.key("DNS", entries.join(", "));
+    }
+
+    if let Some(mtu) = interface.mtu {
+        section.key("MTU", mtu);
+    }
+
+    if let Some(ref table) = interface.table {
+        section.key("Table", table);
+    }
+
+    if let Some(ref fw_mark) = interface.fw_mark {
+        section.key("FwMark", fw_mark);
+    }
+
+    for pre_up in &interface.pre_up {
+        section.key("PreUp", pre_up);
+    }
+
+    for post_up in &interface.post_up {
+        section.key("PostUp", post_up);
+    }
+
+    for pre_down in &interface.pre_down {
+        section.key("PreDown", pre_down);
+    }
+
+    for post_down in &interface.post_down {
+        section.key("PostDown", post_down);
+    }
+
+    if interface.save_config {
+        section.key("SaveConfig", "true");
+    }
+    section.extra(&interface.extra);
+    section.finish(&interface.comments.after);
+
+    // Peers
+    for peer in &config.peers {
+        output.push('\n');
+
+        let mut section = SectionWriter::new(&mut output, &peer.comments);
+        section.comment_lines(&peer.comments.before);
+        if let Some(ref name) = peer.name {
+            section.output.push_str(&format!("# {}\n", name));
+        }
+
+        section.output.push_str("[Peer]\n");
+        section.key("PublicKey", &peer.public_key);
+        if let Some(ref preshared_key) = peer.preshared_key {
+            section.key("PresharedKey", preshared_key);
+        }
+        section.key("AllowedIPs", &peer.allowed_ips);
+
+        if let Some(keepalive) = peer.persistent_keepalive {
+            section.key("PersistentKeepalive", keepalive);
+        }
+
+        if let Some(ref endpoint) = peer.endpoint {
+            section.key("Endpoint", format_endpoint(endpoint));
+        }
+        section.extra(&peer.extra);
+        section.finish(&peer.comments.after);
+    }
+
+    output
+}
+
+/// Every command of a hook, across its lines and the `; `-joined commands within a line
+pub fn hook_commands(hook: &[String]) -> impl Iterator<Item = &str> {
+    hook.iter().flat_map(|line| line.split("; "))
+}
+
+/// Append shell commands to a PostUp/PostDown hook, each on its own line
+pub fn add_hook_commands(hook: &mut Vec<String>, commands: &[String]) {
+    hook.extend(commands.iter().cloned());
+}
+
+/// Remove exactly matching commands from a PostUp/PostDown hook, dropping lines left empty
+pub fn remove_hook_commands(hook: &mut Vec<String>, commands: &[String]) {
+    hook.retain_mut(|line| {
+        let remaining: Vec<&str> = line.split("; ").filter(|part| !commands.iter().any(|c| c == part)).collect();
+        *line = remaining.join("; ");
+        !line.is_empty()
+    });
+}
+
+/// Refuse to change a config owned by a configuration management tool, unless the tunnel allows it
+pub fn check_managed(config: &WgConfig) -> Result<(), WgError> {
+    // A caller-supplied config may have lost the marker, so the file on disk counts too
+    let on_disk = || {
+        let content = read_config_text(&config.path).ok()?;
+        parse_config_content(&config.name, &config.path, &content).ok()?.managed_by
+    };
+    let Some(tool) = config.managed_by.clone().or_else(on_disk) else {
+        return Ok(());
+    };
+    if crate::tunnels::get_tunnel_settings(&config.name)?.allow_managed_edits {
+        return Ok(());
+    }
+    Err(WgError::Refused(format!(
+        "{} is managed by {}; change it there, or allow WireDeck edits for this tunnel",
+        config.name, tool
+    )))
+}
+
+/// The checks `save_config` makes before writing, for previewing whether a save would go through
+pub(crate) fn check_config_for_save(config: &WgConfig) -> Result<(), WgError> {
+    check_managed(config)?;
+    validate_config_keys(config)?;
+    check_config_limits(config)
+}
+
+/// Save configuration to file
+pub fn save_config(config: &WgConfig) -> Result<(), WgError> {
+    check_config_for_save(config)?;
+    let content = serialize_config(config);
+    write_config_file(&config.path, &config.name, &content)?;
+    sync_selfservice_bundle(config);
+    Ok(())
+}
+
+/// Hidden file beside `path` that new content is written to before replacing it
+fn staging_path(path: &Path) -> PathBuf {
+    let file_name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    path.with_file_name(format!(".{}.{}.tmp", file_name, std::process::id()))
+}
+
+/// Create a file only the owner can read and flush it to disk
+fn write_new_private(path: &Path, content: &str) -> std::io::Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options.open(path)?;
+    file.write_all(content.as_bytes())?;
+    file.sync_all()
+}
+
+/// Replace a file with mode 0600 content such that a crash leaves either the old or the new
+/// file, never a truncated one: the content goes to a staging file in the same directory, which
+/// is then renamed over the original
+fn write_private_file(path: &Path, content: &str) -> std::io::Result<()> {
+    let staging = staging_path(path);
+    let _ = fs::remove_file(&staging);
+    let result = write_new_private(&staging, content).and_then(|_| fs::rename(&staging, path));
+    if result.is_err() {
+        let _ = fs::remove_file(&staging);
+    }
+    // The rename itself is only durable once the directory is flushed too
+    #[cfg(unix)]
+    if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+        let _ = fs::File::open(dir).and_then(|d| d.sync_all());
+    }
+    result
+}
+
+/// Write raw config content to a path, keeping a backup of the previous file
+pub fn write_config_file(path: &Path, name: &str, content: &str) -> Result<(), WgError> {
+    // The old text is only needed for the change event, so don't ask sudo for it otherwise
+    let exists = config_file_exists(path);
+    let previous = if events::is_observed() && exists {
+        read_config_text(path).ok()
+    } else {
+        None
+    };
+
+    // With a backup destination the previous file goes there instead of beside the config
+    if exists && backup::get_backup_policy().destination.is_some() {
+        let previous = match fs::read(path) {
+            Ok(previous) => previous,
+            Err(e) if e.kind() == std::io::ErrorKind::PermissionDenied => {
+                let output = sudo_output([OsStr::new("cat"), path.as_os_str()])?;
+                if !output.status.success() {
+                    return Err(WgError::CommandFailed(String::from_utf8_lossy(&output.stderr).to_string()));
+                }
+                output.stdout
+            }
+            Err(e) => return Err(WgError::Io(e)),
+        };
+        backup::store_config_backup(name, &previous)?;
+    } else if exists {
+        // Create backup using sudo if needed
+        let backup_path = path.with_extension("conf.bak");
+
+        // Try direct copy first, fall back to sudo if permission denied
+        if let Err(e) = fs::copy(path, &backup_path) {
+            if e.kind() == std::io::ErrorKind::PermissionDenied {
+                sudo_output([OsStr::new("cp"), path.as_os_str(), backup_path.as_os_str()])?;
+            } else {
+                return Err(WgError::Io(e));
+            }
+        }
+    }
+
+    // Write new config using sudo if needed
+    match write_private_file(path, content) {
+        Ok(_) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::PermissionDenied => {
+            // Write to temp file first
+            let temp_path = backup::private_temp_dir()?.join(format!("wiredeck_{}.conf", name));
+            let _ = fs::remove_file(&temp_path);
+            write_new_private(&temp_path, content)?;
+
+            // Copied next to the config first, so the final step is a rename within its directory
+            let output = sudo_output([
+                OsStr::new("sh"),
+                OsStr::new("-c"),
+                OsStr::new(r#"install -m 600 "$1" "$2" && sync && mv -f "$2" "$3" || { rm -f "$2"; exit 1; }"#),
+                OsStr::new("sh"),
+                temp_path.as_os_str(),
+                staging_path(path).as_os_str(),
+                path.as_os_str(),
+            ]);
+            let _ = fs::remove_file(&temp_path);
+            let output = output?;
+
+            if !output.status.success() {
+                return Err(WgError::CommandFailed(
+                    String::from_utf8_lossy(&output.stderr).to_string()
+                ));
+            }
+        }
+        Err(e) => return Err(WgError::Io(e)),
+    }
+
+    events::publish_change(name, path, previous.as_deref(), content);
+    let _ = crate::identity::record_content(name, content);
+    Ok(())
+}
+
+/// Get status of all peers in a config
+pub fn get_peer_status(config_name: &str) -> Result<Vec<PeerStatus>, WgError> {
+    let interface_name = get_real_interface_name(config_name)?;
+
+    let output = sudo_output(["wg", "show", &interface_name, "dump"])?;
+
+    if !output.status.success() {
+        return Err(WgError::CommandFailed(
+            String::from_utf8_lossy(&output.stderr).to_string()
+        ));
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut statuses = Vec::new();
+
+    for (i, line) in stdout.lines().enumerate() {
+        if i == 0 {
+            continue; // Skip interface line
+        }
+
+        let parts: Vec<&str> = line.split('\t').collect();
+        if parts.len() >= 6 {
+            statuses.push(PeerStatus {
+                public_key: parts[0].to_string(),
+                endpoint: if parts[2].is_empty() { None } else { Some(parts[2].to_string()) },
+                latest_handshake: if parts[4] == "0" { None } else { Some(parts[4].to_string()) },
+                transfer_rx: Some(parts[5].to_string()),
+                transfer_tx: if parts.len() > 6 { Some(parts[6].to_string()) } else { None },
+                endpoint_info: None,
+            });
+        }
+    }
+
+    Ok(statuses)
+}
+
+/// Get the real interface name (handles macOS utun mapping; on Linux it is the config name)
+/// On macOS, wg-quick creates utunX interfaces and stores the mapping in /var/run/wireguard/{config}.name
+pub fn get_real_interface_name(config_name: &str) -> Result<String, WgError> {
+    let name_file = PathBuf::from(format!("/var/run/wireguard/{}.name", config_name));
+
+    if name_file.exists() {
+        // Try to read directly first
+        match fs::read_to_string(&name_file) {
+            Ok(content) => Ok(content.trim().to_string()),
+            Err(e) if e.kind() == std::io::ErrorKind::PermissionDenied => {
+                // Need sudo to read
+                let output = sudo_output([OsStr::new("cat"), name_file.as_os_str()])?;
+
+                if output.status.success() {
+                    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+                } else {
+                    // Fallback to config name
+                    Ok(config_name.to_string())
+                }
+            }
+            Err(_) => Ok(config_name.to_string()),
+        }
+    } else {
+        // Not running or on Linux - use config name directly
+        Ok(config_name.to_string())
+    }
+}
+
+/// Check if WireGuard interface is running
+pub fn is_interface_up(config_name: &str) -> Result<bool, WgError> {
+    if cfg!(windows) {
+        // Each running tunnel is a service; `sc query` reports its state
+        let output = trace::output(Command::new("sc").args(["query", &tunnel_service(config_name)]))?;
+        return Ok(output.status.success() && String::from_utf8_lossy(&output.stdout).contains("RUNNING"));
+    }
+    if cfg!(target_os = "linux") {
+        // The interface is named after the config and shows up in sysfs while it exists
+        if !Path::new("/sys/class/net").join(config_name).exists() {
+            return Ok(false);
+        }
+    } else {
+        // Check if the name file exists - reliable indicator on macOS
+        let name_file = PathBuf::from(format!("/var/run/wireguard/{}.name", config_name));
+        if !name_file.exists() {
+            return Ok(false);
+        }
+    }
+
+    let interface_name = get_real_interface_name(config_name)?;
+
+    let output = sudo_output(["wg", "show", &interface_name])?;
+
+    Ok(output.status.success())
+}
+
+/// The `wg-quick@` systemd unit that runs a config as a service on Linux
+fn systemd_unit(config_name: &str) -> String {
+    format!("wg-quick@{}.service", config_name)
+}
+
+/// Whether systemd reports the config's unit as `check` ("is-enabled", "is-active"); false where
+/// there's no systemd. Tunnels set up as units go through systemctl so systemd's view stays right.
+fn systemd_unit_is(config_name: &str, check: &str) -> bool {
+    if !cfg!(target_os = "linux") {
+        return false;
+    }
+    trace::output(Command::new("systemctl").args([check, "--quiet", &systemd_unit(config_name)]))
+        .map(|o| o.status.success())
+        .unwrap_or(false)
+}
+
+/// What wg-quick logged during the unit's last start or stop; systemctl itself only says it failed
+fn systemd_unit_log(config_name: &str) -> String {
+    let unit = systemd_unit(config_name);
+    sudo_output(["journalctl", "--unit", &unit, "--lines", "30", "--no-pager", "--output", "cat"])
+        .map(|o| String::from_utf8_lossy(&o.stdout).to_string())
+        .unwrap_or_default()
+}
+
+/// `wireguard.exe`, which installs and removes tunnel services on Windows
+fn wireguard_exe() -> PathBuf {
+    let installed = Path::new(WINDOWS_WIREGUARD_HOME).join("wireguard.exe");
+    if installed.exists() {
+        installed
+    } else {
+        PathBuf::from("wireguard.exe")
+    }
+}
+
+/// Windows service the WireGuard app runs a tunnel as
+fn tunnel_service(config_name: &str) -> String {
+    format!("WireGuardTunnel${}", config_name)
+}
+
+/// What brings a config's interface up and down on this system
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Backend {
+    WgQuick,
+    /// Linux configs set up as `wg-quick@` units
+    Systemd,
+    /// `wireguard.exe /installtunnelservice` on Windows
+    TunnelService,
+}
+
+impl Backend {
+    fn for_up(config_name: &str) -> Backend {
+        if cfg!(windows) {
+            Backend::TunnelService
+        } else if systemd_unit_is(config_name, "is-enabled") || systemd_unit_is(config_name, "is-active") {
+            // An enabled unit would start the tunnel at boot anyway; going through it keeps one owner
+            Backend::Systemd
+        } else {
+            Backend::WgQuick
+        }
+    }
+
+    fn for_down(config_name: &str) -> Backend {
+        if cfg!(windows) {
+            Backend::TunnelService
+        } else if systemd_unit_is(config_name, "is-active") {
+            // Stopping an inactive unit succeeds without touching an interface wg-quick brought up
+            Backend::Systemd
+        } else {
+            Backend::WgQuick
+        }
+    }
+
+    /// Program and arguments for bringing the interface up or down
+    fn argv(self, config_name: &str, up: bool) -> Vec<OsString> {
+        match self {
+            Backend::WgQuick => vec!["wg-quick".into(), if up { "up" } else { "down" }.into(), config_name.into()],
+            Backend::Systemd => vec![
+                "systemctl".into(),
+                if up { "start" } else { "stop" }.into(),
+                systemd_unit(config_name).into(),
+            ],
+            // Installing takes the config's path; uninstalling the tunnel's name
+            Backend::TunnelService if up => vec![
+                wireguard_exe().into(),
+                "/installtunnelservice".into(),
+                get_wireguard_dir().join(format!("{}.conf", config_name)).into(),
+            ],
+            Backend::TunnelService => {
+                vec![wireguard_exe().into(), "/uninstalltunnelservice".into(), config_name.into()]
+            }
+        }
+    }
+
+    /// The command as recorded in the privileged change log
+    fn command(self, config_name: &str, up: bool) -> String {
+        self.argv(config_name, up)
+            .iter()
+            .map(|arg| arg.to_string_lossy())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Bring the interface up or down, returning the output and wg-quick's log for parsing
+    fn run(self, config_name: &str, up: bool) -> Result<(Output, String), WgError> {
+        let output = sudo_output(self.argv(config_name, up))?;
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let log = match self {
+            // systemctl itself only says the unit failed
+            Backend::Systemd => format!("{}{}", systemd_unit_log(config_name), stderr),
+            // wg-quick logs the commands it runs on stderr
+            Backend::WgQuick | Backend::TunnelService => {
+                format!("{}{}", stderr, String::from_utf8_lossy(&output.stdout))
+            }
+        };
+        Ok((output, log))
+    }
+}
+
+/// Bring up WireGuard interface and report what wg-quick changed. Exclusive groups aren't
+/// looked at, so outside callers go through `tunnels::connect`; within the crate this is for
+/// bringing back a tunnel that was up a moment ago.
+pub(crate) fn bring_up(config_name: &str) -> Result<UpReport, WgError> {
+    daemons::refuse_if_managed(config_name, "bring it up")?;
+    // Catch keyless reference configs before wg-quick fails on them with a cryptic message
+    if let Ok(config) = parse_config(config_name) {
+        require_private_key(&config)?;
+    }
+    let backend = Backend::for_up(config_name);
+    let (output, log) = backend.run(config_name, true)?;
+
+    if !output.status.success() {
+        return Err(WgError::WgQuick(parse_failure("up", &log)));
+    }
+    record_change(
+        "interface_up",
+        Some(config_name),
+        vec![backend.command(config_name, true)],
+        vec![backend.command(config_name, false)],
+    );
+
+    let mut report = parse_up_output(&log);
+
+    // resolvconf reads servers from stdin, so take them from the config instead
+    if report.dns_via_resolvconf && report.dns_set.is_empty() {
+        if let Ok(config) = parse_config(config_name) {
+            report.dns_set = config.interface.dns;
+        }
+    }
+
+    if report.interface_name.is_none() {
+        report.interface_name = get_real_interface_name(config_name).ok();
+    }
+
+    Ok(report)
+}
+
+/// Bring down WireGuard interface
+pub fn bring_down(config_name: &str) -> Result<String, WgError> {
+    daemons::refuse_if_managed(config_name, "take it down")?;
+    let backend = Backend::for_down(config_name);
+    let (output, log) = backend.run(config_name, false)?;
+
+    if !output.status.success() {
+        return Err(WgError::WgQuick(parse_failure("down", &log)));
+    }
+    record_change(
+        "interface_down",
+        Some(config_name),
+        vec![backend.command(config_name, false)],
+        vec![backend.command(config_name, true)],
+    );
+
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// The interface's private key, or an error for reference configs that have none or a placeholder
+pub fn require_private_key(config: &WgConfig) -> Result<&str, WgError> {
+    match config.interface.private_key.as_deref() {
+        Some(key) if config.interface.has_private_key => Ok(key),
+        other => Err(WgError::Refused(format!(
+            "{} has {} private key, so it can't be used as a tunnel; add a PrivateKey first",
+            config.name,
+            if other.is_some() { "a placeholder instead of a" } else { "no" }
+        ))),
+    }
+}
+
+/// 32 bytes from the OS random source, or `None` if it can't be read
+fn os_random_key() -> Option<Zeroizing<[u8; 32]>> {
+    let mut key = Zeroizing::new([0u8; 32]);
+    OsRng.try_fill_bytes(key.as_mut()).ok()?;
+    Some(key)
+}
+
+/// Private key from `wg genkey`, for when the OS random source can't be read
+fn wg_genkey() -> Result<Zeroizing<[u8; 32]>, WgError> {
+    let output = trace::output(Command::new("wg").arg("genkey"))?;
+
+    if !output.status.success() {
+        return Err(WgError::CommandFailed(
+            "Failed to generate private key".to_string()
+        ));
+    }
+
+    decode_key(String::from_utf8_lossy(&output.stdout).trim())
+}
+
+fn decode_key(key: &str) -> Result<Zeroizing<[u8; 32]>, WgError> {
+    let key = Zeroizing::new(validate_key_format(key)?);
+    let bytes = Zeroizing::new(BASE64.decode(key.as_bytes()).map_err(|e| WgError::Parse(e.to_string()))?);
+    let mut decoded = Zeroizing::new([0u8; 32]);
+    decoded.copy_from_slice(&bytes);
+    Ok(decoded)
+}
+
+/// Generate WireGuard key pair. Keys are computed in-process, so they never pass through another
+/// program's stdin or argv.
+pub fn generate_keypair() -> Result<(String, String), WgError> {
+    let mut private_key = match os_random_key() {
+        Some(key) => key,
+        None => wg_genkey()?,
+    };
+    x25519::clamp(&mut private_key);
+    let public_key = x25519::x25519(&private_key, &x25519::BASE_POINT);
+
+    Ok((BASE64.encode(private_key.as_ref()), BASE64.encode(public_key)))
+}
+
+/// Generate a preshared key for a peer
+pub fn generate_preshared_key() -> Result<String, WgError> {
+    if let Some(key) = os_random_key() {
+        return Ok(BASE64.encode(key.as_ref()));
+    }
+    let output = trace::output(Command::new("wg").arg("genpsk"))?;
+
+    if !output.status.success() {
+        return Err(WgError::CommandFailed(
+            "Failed to generate preshared key".to_string()
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Get public key from private key
+pub fn get_public_key(private_key: &str) -> Result<String, WgError> {
+    let private_key = decode_key(private_key)?;
+    Ok(BASE64.encode(x25519::x25519(&private_key, &x25519::BASE_POINT)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PRIVATE_KEY: &str = "yAnz5TF+lXXJte14tji3zlMNq+hd2rYUIgJBgB3fBmk=";
+    const PUBLIC_KEY: &str = "xTIBA5rboUvnH4htodjb6e697QjLERt1NAB4mZqp8Dg=";
+
+    fn parse(content: &str) -> Result<WgConfig, WgError> {
+        parse_config_content("wg0", Path::new("/etc/wireguard/wg0.conf"), content)
+    }
+
+    #[test]
+    fn strips_inline_comments() {
+        let config = parse(&format!(
+            "[Interface]\nPrivateKey = {}\nListenPort = 51820 # server\n\n[Peer] # laptop\nPublicKey = {}\n\
+             AllowedIPs = 10.0.0.2/32 # one host\nPersistentKeepalive = 25#seconds\n",
+            PRIVATE_KEY, PUBLIC_KEY
+        ))
+        .unwrap();
+        assert_eq!(config.interface.listen_port, Some(51820));
+        assert_eq!(config.peers.len(), 1);
+        assert_eq!(config.peers[0].name.as_deref(), Some("laptop"));
+        assert_eq!(config.peers[0].allowed_ips, "10.0.0.2/32");
+        assert_eq!(config.peers[0].persistent_keepalive, Some(25));
+    }
+
+    #[test]
+    fn keeps_inline_comments_when_saving() {
+        let content = format!("[Interface]\nPrivateKey = {}\nListenPort = 51820 # server\n", PRIVATE_KEY);
+        let config = parse(&content).unwrap();
+        let saved = serialize_config(&config);
+        assert!(saved.contains("# server\nListenPort = 51820\n"), "{}", saved);
+        assert_eq!(parse(&saved).unwrap().interface.listen_port, Some(51820));
+    }
+
+    #[test]
+    fn matches_sections_and_keys_regardless_of_case() {
+        let config = parse(&format!(
+            "[interface]\nprivatekey = {}\nLISTENPORT = 51820\naddress = 10.0.0.1/24\n\n[PEER]\n\
+             publickey = {}\nallowedips = 10.0.0.2/32\n",
+            PRIVATE_KEY, PUBLIC_KEY
+        ))
+        .unwrap();
+        assert!(config.interface.has_private_key);
+        assert_eq!(config.interface.listen_port, Some(51820));
+        assert_eq!(config.interface.address, vec!["10.0.0.1/24"]);
+        assert!(config.interface.extra.is_empty());
+        assert_eq!(config.peers[0].public_key, PUBLIC_KEY);
+        assert_eq!(config.peers[0].allowed_ips, "10.0.0.2/32");
+        assert!(serialize_config(&config).starts_with("[Interface]\nPrivateKey = "));
+    }
+
+    #[test]
+    fn leaves_out_an_empty_address() {
+        let config = parse("[Interface]\nListenPort = 51820\n").unwrap();
+        assert!(!serialize_config(&config).contains("Address"));
+    }
+
+    #[test]
+    fn unknown_keys_keep_their_spelling() {
+        let config = parse("[Interface]\nJc = 4\n").unwrap();
+        assert_eq!(config.interface.extra, vec![("Jc".to_string(), "4".to_string())]);
+    }
+
+    #[test]
+    fn reports_syntax_errors_with_their_line() {
+        let error = check_config_syntax("[Interface]\nListenPort = 51820\n[Peers]\n").unwrap();
+        assert_eq!(error.line, Some(3));
+        assert_eq!(error.text.as_deref(), Some("[Peers]"));
+
+        let error = check_config_syntax("[Interface]\nListenPort = high # server\n").unwrap();
+        assert_eq!(error.line, Some(2));
+
+        assert_eq!(check_config_syntax("# only a comment\n").unwrap().line, None);
+    }
+}
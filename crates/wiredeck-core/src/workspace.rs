@@ -0,0 +1,170 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+use crate::store;
+use crate::wireguard::WgError;
+
+const WORKSPACES_FILE: &str = "workspaces.json";
+
+/// Workspace that always exists; its data lives directly in the app data directory
+pub const DEFAULT_WORKSPACE: &str = "default";
+
+/// A named, separate set of tunnels and WireDeck data
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Workspace {
+    pub name: String,
+    /// WireGuard config directory for this workspace; the system one when unset
+    #[serde(default)]
+    pub config_dir: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct WorkspaceIndex {
+    active: Option<String>,
+    workspaces: Vec<Workspace>,
+}
+
+fn load_index() -> WorkspaceIndex {
+    // A broken index must not lock the user out of their data; fall back to the default
+    store::load_global(WORKSPACES_FILE).unwrap_or_default()
+}
+
+fn default_workspace() -> Workspace {
+    Workspace {
+        name: DEFAULT_WORKSPACE.to_string(),
+        config_dir: None,
+    }
+}
+
+fn validate_workspace_name(name: &str) -> Result<(), WgError> {
+    let valid = !name.is_empty()
+        && name.len() <= 32
+        && name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || "_-".contains(c));
+
+    if valid {
+        Ok(())
+    } else {
+        Err(WgError::Parse(format!(
+            "Invalid workspace name '{}': use up to 32 lowercase letters, digits, _ or -",
+            name
+        )))
+    }
+}
+
+/// Directory holding a workspace's metadata, templates and other app data
+pub fn workspace_data_dir(name: &str) -> PathBuf {
+    let root = store::get_app_data_dir();
+    if name == DEFAULT_WORKSPACE {
+        root
+    } else {
+        root.join("workspaces").join(name)
+    }
+}
+
+/// All workspaces, the default one first
+pub fn list_workspaces() -> Vec<Workspace> {
+    let index = load_index();
+    let mut workspaces = vec![index
+        .workspaces
+        .iter()
+        .find(|w| w.name == DEFAULT_WORKSPACE)
+        .cloned()
+        .unwrap_or_else(default_workspace)];
+    workspaces.extend(index.workspaces.into_iter().filter(|w| w.name != DEFAULT_WORKSPACE));
+    workspaces
+}
+
+/// Name of the workspace currently in use
+pub fn active_workspace_name() -> String {
+    load_index()
+        .active
+        .unwrap_or_else(|| DEFAULT_WORKSPACE.to_string())
+}
+
+/// The workspace currently in use
+pub fn active_workspace() -> Workspace {
+    let name = active_workspace_name();
+    list_workspaces()
+        .into_iter()
+        .find(|w| w.name == name)
+        .unwrap_or_else(default_workspace)
+}
+
+/// Create a workspace, optionally bound to its own WireGuard config directory
+pub fn create_workspace(name: &str, config_dir: Option<PathBuf>) -> Result<Workspace, WgError> {
+    validate_workspace_name(name)?;
+
+    let mut index = load_index();
+    if name == DEFAULT_WORKSPACE || index.workspaces.iter().any(|w| w.name == name) {
+        return Err(WgError::Parse(format!("Workspace '{}' already exists", name)));
+    }
+
+    let workspace = Workspace {
+        name: name.to_string(),
+        config_dir,
+    };
+    index.workspaces.push(workspace.clone());
+    fs::create_dir_all(workspace_data_dir(name))?;
+    store::save_global(WORKSPACES_FILE, &index)?;
+
+    Ok(workspace)
+}
+
+/// Bind a workspace to a WireGuard config directory, or back to the system one
+pub fn set_workspace_config_dir(name: &str, config_dir: Option<PathBuf>) -> Result<Workspace, WgError> {
+    let mut index = load_index();
+
+    if name == DEFAULT_WORKSPACE && !index.workspaces.iter().any(|w| w.name == name) {
+        index.workspaces.push(default_workspace());
+    }
+
+    let workspace = index
+        .workspaces
+        .iter_mut()
+        .find(|w| w.name == name)
+        .ok_or_else(|| WgError::NotFound(format!("workspace {}", name)))?;
+    workspace.config_dir = config_dir;
+    let workspace = workspace.clone();
+
+    store::save_global(WORKSPACES_FILE, &index)?;
+    Ok(workspace)
+}
+
+/// Make another workspace the active one
+pub fn switch_workspace(name: &str) -> Result<Workspace, WgError> {
+    let workspace = list_workspaces()
+        .into_iter()
+        .find(|w| w.name == name)
+        .ok_or_else(|| WgError::NotFound(format!("workspace {}", name)))?;
+
+    let mut index = load_index();
+    index.active = Some(workspace.name.clone());
+    store::save_global(WORKSPACES_FILE, &index)?;
+
+    Ok(workspace)
+}
+
+/// Delete a workspace and its WireDeck data. Configs in a bound directory are left alone.
+pub fn delete_workspace(name: &str) -> Result<(), WgError> {
+    if name == DEFAULT_WORKSPACE {
+        return Err(WgError::Parse("The default workspace can't be deleted".to_string()));
+    }
+    if active_workspace_name() == name {
+        return Err(WgError::Parse("Switch to another workspace before deleting this one".to_string()));
+    }
+
+    let mut index = load_index();
+    let before = index.workspaces.len();
+    index.workspaces.retain(|w| w.name != name);
+    if index.workspaces.len() == before {
+        return Err(WgError::NotFound(format!("workspace {}", name)));
+    }
+
+    let dir = workspace_data_dir(name);
+    if dir.exists() {
+        fs::remove_dir_all(dir)?;
+    }
+    store::save_global(WORKSPACES_FILE, &index)
+}
@@ -0,0 +1,233 @@
+use zeroize::Zeroize;
+
+/// Field element mod 2^255 - 19 as sixteen 16-bit limbs, with room for carries
+type Fe = [i64; 16];
+
+/// (A - 2) / 4 for Curve25519, used in the ladder's doubling step
+const A24: Fe = [0xdb41, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+
+/// u-coordinate of the curve's base point
+pub const BASE_POINT: [u8; 32] = {
+    let mut point = [0u8; 32];
+    point[0] = 9;
+    point
+};
+
+fn carry(o: &mut Fe) {
+    for i in 0..16 {
+        o[i] += 1 << 16;
+        let c = o[i] >> 16;
+        if i < 15 {
+            o[i + 1] += c - 1;
+        } else {
+            o[0] += 38 * (c - 1);
+        }
+        o[i] -= c << 16;
+    }
+}
+
+/// Swap `p` and `q` when `bit` is 1, without branching on it
+fn swap(p: &mut Fe, q: &mut Fe, bit: i64) {
+    let mask = !(bit - 1);
+    for i in 0..16 {
+        let t = mask & (p[i] ^ q[i]);
+        p[i] ^= t;
+        q[i] ^= t;
+    }
+}
+
+fn add(a: &Fe, b: &Fe) -> Fe {
+    std::array::from_fn(|i| a[i] + b[i])
+}
+
+fn sub(a: &Fe, b: &Fe) -> Fe {
+    std::array::from_fn(|i| a[i] - b[i])
+}
+
+fn mul(a: &Fe, b: &Fe) -> Fe {
+    let mut t = [0i64; 31];
+    for i in 0..16 {
+        for j in 0..16 {
+            t[i + j] += a[i] * b[j];
+        }
+    }
+    // 2^256 = 38 mod p folds the high half back in
+    for i in 0..15 {
+        t[i] += 38 * t[i + 16];
+    }
+    let mut o: Fe = std::array::from_fn(|i| t[i]);
+    carry(&mut o);
+    carry(&mut o);
+    t.zeroize();
+    o
+}
+
+/// a^(p - 2), the inverse by Fermat's little theorem
+fn invert(a: &Fe) -> Fe {
+    let mut c = *a;
+    for bit in (0..=253).rev() {
+        c = mul(&c, &c);
+        if bit != 2 && bit != 4 {
+            c = mul(&c, a);
+        }
+    }
+    c
+}
+
+fn unpack(bytes: &[u8; 32]) -> Fe {
+    let mut o: Fe = std::array::from_fn(|i| bytes[2 * i] as i64 + ((bytes[2 * i + 1] as i64) << 8));
+    o[15] &= 0x7fff;
+    o
+}
+
+/// Canonical little-endian encoding, fully reduced mod p
+fn pack(n: &Fe) -> [u8; 32] {
+    let mut t = *n;
+    carry(&mut t);
+    carry(&mut t);
+    carry(&mut t);
+    for _ in 0..2 {
+        let mut m = [0i64; 16];
+        m[0] = t[0] - 0xffed;
+        for i in 1..15 {
+            m[i] = t[i] - 0xffff - ((m[i - 1] >> 16) & 1);
+            m[i - 1] &= 0xffff;
+        }
+        m[15] = t[15] - 0x7fff - ((m[14] >> 16) & 1);
+        let borrow = (m[15] >> 16) & 1;
+        m[14] &= 0xffff;
+        swap(&mut t, &mut m, 1 - borrow);
+    }
+    let mut o = [0u8; 32];
+    for i in 0..16 {
+        o[2 * i] = (t[i] & 0xff) as u8;
+        o[2 * i + 1] = (t[i] >> 8) as u8;
+    }
+    t.zeroize();
+    o
+}
+
+/// Clear and set the bits RFC 7748 fixes in every private key, as `wg genkey` does
+pub fn clamp(scalar: &mut [u8; 32]) {
+    scalar[0] &= 248;
+    scalar[31] = (scalar[31] & 127) | 64;
+}
+
+/// The X25519 function of RFC 7748: `scalar` times the point with u-coordinate `point`, in
+/// constant time. The scalar is clamped first.
+pub fn x25519(scalar: &[u8; 32], point: &[u8; 32]) -> [u8; 32] {
+    let mut z = *scalar;
+    clamp(&mut z);
+    let x = unpack(point);
+
+    // Montgomery ladder over (x2 : z2) = a / c and (x3 : z3) = b / d
+    let mut a: Fe = [0; 16];
+    let mut b = x;
+    let mut c: Fe = [0; 16];
+    let mut d: Fe = [0; 16];
+    a[0] = 1;
+    d[0] = 1;
+    for i in (0..=254).rev() {
+        let bit = ((z[i >> 3] >> (i & 7)) & 1) as i64;
+        swap(&mut a, &mut b, bit);
+        swap(&mut c, &mut d, bit);
+        let mut e = add(&a, &c);
+        a = sub(&a, &c);
+        c = add(&b, &d);
+        b = sub(&b, &d);
+        d = mul(&e, &e);
+        let f = mul(&a, &a);
+        a = mul(&c, &a);
+        c = mul(&b, &e);
+        e = add(&a, &c);
+        a = sub(&a, &c);
+        b = mul(&a, &a);
+        c = sub(&d, &f);
+        a = mul(&c, &A24);
+        a = add(&a, &d);
+        c = mul(&c, &a);
+        a = mul(&d, &f);
+        d = mul(&b, &x);
+        b = mul(&e, &e);
+        swap(&mut a, &mut b, bit);
+        swap(&mut c, &mut d, bit);
+    }
+    let out = pack(&mul(&a, &invert(&c)));
+    for fe in [&mut a, &mut b, &mut c, &mut d] {
+        fe.zeroize();
+    }
+    z.zeroize();
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bytes(hex: &str) -> [u8; 32] {
+        std::array::from_fn(|i| u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).unwrap())
+    }
+
+    /// RFC 7748 section 5.2
+    #[test]
+    fn rfc7748_vectors() {
+        let vectors = [
+            (
+                "a546e36bf0527c9d3b16154b82465edd62144c0ac1fc5a18506a2244ba449ac4",
+                "e6db6867583030db3594c1a424b15f7c726624ec26b3353b10a903a6d0ab1c4c",
+                "c3da55379de9c6908e94ea4df28d084f32eccf03491c71f754b4075577a28552",
+            ),
+            (
+                "4b66e9d4d1b4673c5ad22691957d6af5c11b6421e0ea01d42ca4169e7918ba0d",
+                "e5210f12786811d3f4b7959d0538ae2c31dbe7106fc03c3efc4cd549c715a493",
+                "95cbde9476e8907d7aade45cb4b873f88b595a68799fa152e6f8f7647aac7957",
+            ),
+        ];
+        for (scalar, point, expected) in vectors {
+            assert_eq!(x25519(&bytes(scalar), &bytes(point)), bytes(expected));
+        }
+    }
+
+    fn iterate(k: &mut [u8; 32], u: &mut [u8; 32], times: usize) {
+        for _ in 0..times {
+            let next = x25519(k, u);
+            *u = *k;
+            *k = next;
+        }
+    }
+
+    /// RFC 7748 section 5.2, after one and after 1,000 iterations
+    #[test]
+    fn rfc7748_iterated() {
+        let (mut k, mut u) = (BASE_POINT, BASE_POINT);
+        iterate(&mut k, &mut u, 1);
+        assert_eq!(k, bytes("422c8e7a6227d7bca1350b3e2bb7279f7897b87bb6854b783c60e80311ae3079"));
+        iterate(&mut k, &mut u, 999);
+        assert_eq!(k, bytes("684cf59ba83309552800ef566f2f4d3c1c3887c49360e3875f2eb94d99532c51"));
+    }
+
+    /// RFC 7748 section 5.2, after 1,000,000 iterations; too slow for every run, so it needs
+    /// `cargo test --release -- --ignored`
+    #[test]
+    #[ignore]
+    fn rfc7748_iterated_million() {
+        let (mut k, mut u) = (BASE_POINT, BASE_POINT);
+        iterate(&mut k, &mut u, 1_000_000);
+        assert_eq!(k, bytes("7c3911e0ab2586fd864497297e575e6f3bc601c0883c30df5f4dd2d24f665424"));
+    }
+
+    /// RFC 7748 section 6.1
+    #[test]
+    fn rfc7748_diffie_hellman() {
+        let alice = bytes("77076d0a7318a57d3c16c17251b26645df4c2f87ebc0992ab177fba51db92c2a");
+        let bob = bytes("5dab087e624a8a4b79e17f8b83800ee66f3bb1292618b6fd1c2f8b27ff88e0eb");
+        let alice_public = x25519(&alice, &BASE_POINT);
+        let bob_public = x25519(&bob, &BASE_POINT);
+        assert_eq!(alice_public, bytes("8520f0098930a754748b7ddcb43ef75a0dbf3a0d26381af4eba4a98eaa9b4e6a"));
+        assert_eq!(bob_public, bytes("de9edb7d7b7dc1b4d35b61c2ece435373f8343c85b78674dadfc7e146f882b4f"));
+
+        let shared = bytes("4a5d9d5ba4ce2de1728e3bf480350f25e07e21c947d19e3376f09b3c1e161742");
+        assert_eq!(x25519(&alice, &bob_public), shared);
+        assert_eq!(x25519(&bob, &alice_public), shared);
+    }
+}
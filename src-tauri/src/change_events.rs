@@ -0,0 +1,29 @@
+use std::sync::Arc;
+use tauri::{AppHandle, Emitter};
+use wiredeck_core::events::{self, ConfigChanged};
+use wiredeck_core::settings;
+
+use crate::subscriptions::log;
+
+/// Event every config mutation is delivered on
+const CONFIG_CHANGED_EVENT: &str = "config-changed";
+
+/// Forward config changes from the core to the frontend, and open the socket if it's enabled
+pub fn install(app: &AppHandle) {
+    let emitter = app.clone();
+    events::set_change_listener(Arc::new(move |change: &ConfigChanged| {
+        let _ = emitter.emit(CONFIG_CHANGED_EVENT, change);
+    }));
+
+    let enabled = settings::load_settings().map(|s| s.event_socket).unwrap_or(false);
+    apply_socket_setting(app, enabled);
+}
+
+/// Start or stop the event socket to match the setting
+pub fn apply_socket_setting(app: &AppHandle, enabled: bool) {
+    match events::set_event_socket(enabled) {
+        Ok(Some(path)) => log(app, format!("Publishing config changes on {}", path.display())),
+        Ok(None) => {}
+        Err(e) => log(app, format!("Could not open the event socket: {}", e)),
+    }
+}
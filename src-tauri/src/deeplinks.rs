@@ -0,0 +1,50 @@
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+use tauri_plugin_deep_link::DeepLinkExt;
+use wiredeck_core::confirm::Guarded;
+use wiredeck_core::deeplink::{self, DeepLinkOutcome};
+
+use crate::subscriptions::log;
+
+/// Sent to the frontend for every link, with the confirmation request if one is needed
+#[derive(Debug, Clone, Serialize)]
+struct DeepLinkEvent {
+    url: String,
+    outcome: Guarded<DeepLinkOutcome>,
+}
+
+pub fn handle(app: &AppHandle, url: String) {
+    match deeplink::run_deep_link(&url, None) {
+        Ok(outcome) => {
+            let _ = app.emit("deep-link", DeepLinkEvent { url, outcome });
+        }
+        Err(e) => {
+            log(app, format!("Ignoring deep link {}: {}", url, e));
+            let _ = app.emit("deep-link-failed", e.to_string());
+        }
+    }
+}
+
+/// Handle the link WireDeck was launched with and any opened while it runs
+pub fn register(app: &AppHandle) -> tauri::Result<()> {
+    // macOS registers the scheme from the bundle; elsewhere it has to be done at runtime
+    #[cfg(any(target_os = "linux", all(debug_assertions, windows)))]
+    app.deep_link()
+        .register_all()
+        .map_err(|e| tauri::Error::Anyhow(e.into()))?;
+
+    if let Ok(Some(urls)) = app.deep_link().get_current() {
+        for url in urls {
+            handle(app, url.to_string());
+        }
+    }
+
+    let handle_app = app.clone();
+    app.deep_link().on_open_url(move |event| {
+        for url in event.urls() {
+            handle(&handle_app, url.to_string());
+        }
+    });
+
+    Ok(())
+}
@@ -1,86 +1,497 @@
-mod wireguard;
+mod change_events;
+mod deeplinks;
+mod menubar;
+mod single_instance;
+mod subscriptions;
+mod subsystems;
+mod sudo_prompt;
+mod tasks;
+mod windows;
 
-use wireguard::*;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use subscriptions::Subscriptions;
+use subsystems::{Lifetime, SubsystemStatus, Subsystems};
+use tauri::{AppHandle, DragDropEvent, Emitter, Manager, State, Window, WindowEvent};
+use wiredeck_core::addressing::{self, AddressMap, AddressMatch, AddressPlan};
+use wiredeck_core::anonymize::{self, AnonymizedConfig};
+use wiredeck_core::api::{self, ApiToken, IssuedToken, TokenScope};
+use wiredeck_core::approuting::{self, PfAppRules};
+use wiredeck_core::backup::{self, BackupPolicy, ConfigBackup};
+use wiredeck_core::cidr::Cidr;
+use wiredeck_core::confirm::Guarded;
+use wiredeck_core::daemons::{self, ExternalManager, TakeOver};
+use wiredeck_core::deeplink::{self, DeepLinkOutcome};
+use wiredeck_core::deploy::{self, DeployRequest, DeployedHost};
+use wiredeck_core::diagnostics::Finding;
+use wiredeck_core::endpoint::{
+    self, AddressFamily, EndpointPreferences, HostReplacement, ReplaceScope, ResolvedEndpoint,
+};
+use wiredeck_core::failures::{self, FailureSummary};
+use wiredeck_core::firewall::{self, FirewallStatus};
+use wiredeck_core::guardrails;
+use wiredeck_core::guests::{self, ComposeExport};
+use wiredeck_core::i18n::{self, Language};
+use wiredeck_core::idempotency::{with_idempotency, with_idempotency_if};
+use wiredeck_core::identity::{self, ConfigIdentity};
+use wiredeck_core::import::{self, ImportResult};
+use wiredeck_core::instance::{self, Claim, Invocation};
+use wiredeck_core::keys;
+use wiredeck_core::locks::with_config_lock;
+use wiredeck_core::metadata::{self, AttachmentKind, AttachmentMatch, PeerMetadata};
+use wiredeck_core::metrics::{self, EndpointSpan, PeerEvent, StatsBaseline, StatsUsage};
+use wiredeck_core::migrations;
+use wiredeck_core::netinfo;
+use wiredeck_core::operations::{self, OperationSnapshot};
+use wiredeck_core::pdf::{self, PdfExportOptions};
+use wiredeck_core::peers::{self, DisabledPeer, DumpImport, KickedPeer, RosterImport};
+use wiredeck_core::platform::{self, PeerPlatform, Platform};
+use wiredeck_core::power::{self, PowerState};
+use wiredeck_core::preflight::{self, Preflight};
+use wiredeck_core::privileged::{self, PrivilegedChange};
+use wiredeck_core::qos::{self, RateLimit, RateLimitEntry, TcRules};
+use wiredeck_core::quarantine::{self, QuarantinedConfig};
+use wiredeck_core::reload::{self, ReloadOutcome};
+use wiredeck_core::remote::{self, RefreshOutcome, RemoteAuth, RemoteSubscription};
+use wiredeck_core::revoked::{self, RevokedKey};
+use wiredeck_core::sandbox::{self, SandboxReport};
+use wiredeck_core::saveconfig;
+use wiredeck_core::schedule::{self, PeerChangeKind, ScheduledPeerChange};
+use wiredeck_core::schema;
+use wiredeck_core::selfservice::{self, ApprovedUser, SelfServicePortal};
+use wiredeck_core::server::{self, ForwardingReport, NatApplyMode};
+use wiredeck_core::services::{self, EmbeddedService};
+use wiredeck_core::simulate::{self, Simulation};
+use wiredeck_core::settings::{self, AppSettings};
+use wiredeck_core::snippets::{self, RenderedSnippet, Snippet};
+use wiredeck_core::splitdns::{self, SplitDns, SplitDnsRule};
+use wiredeck_core::status::{self, StatusOverview, TunnelState};
+use wiredeck_core::tempaccess::{self, TempPeer};
+use wiredeck_core::topology::{self, GraphFormat};
+use wiredeck_core::trace::{self, Traced};
+use wiredeck_core::tune::{self, TuneMatrix, TuneReport};
+use wiredeck_core::tunnels::{self, Profile, TunnelActivation, TunnelSettings};
+use wiredeck_core::validation::{self, ConfigReport};
+use wiredeck_core::verify::{self, EndpointQuality, HandshakeAttempt, TunnelVerdict};
+use wiredeck_core::wireguard::*;
+use wiredeck_core::workspace::{self, Workspace};
 
 // WireGuard configuration management commands
 
+/// Record a failed command in the failure history and turn the error into the frontend's string
+fn failed(command: &'static str) -> impl Fn(WgError) -> String {
+    move |e| {
+        failures::record_failure(command, &e);
+        e.to_string()
+    }
+}
+
+/// `failed` for commands that hand the frontend an `ErrorDetail` instead of a string
+fn failed_detail(command: &'static str) -> impl Fn(WgError) -> ErrorDetail {
+    move |e| {
+        failures::record_failure(command, &e);
+        ErrorDetail::from(&e)
+    }
+}
+
 #[tauri::command]
 fn list_wireguard_configs() -> Result<Vec<String>, String> {
     list_configs().map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-fn load_wireguard_config(name: String) -> Result<WgConfig, String> {
-    parse_config(&name).map_err(|e| e.to_string())
+fn load_wireguard_config(name: String) -> Result<WgConfig, ErrorDetail> {
+    quarantine::load_config(&name).map_err(failed_detail("load_wireguard_config"))
+}
+
+#[tauri::command]
+fn reload_config_if_unchanged(
+    name: String,
+    known_hash: String,
+    unsaved: Option<WgConfig>,
+) -> Result<ReloadOutcome, String> {
+    reload::reload_config_if_unchanged(&name, &known_hash, unsaved.as_ref()).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn list_quarantined_configs() -> Result<Vec<QuarantinedConfig>, String> {
+    quarantine::list_quarantined_configs().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn open_in_raw_editor(config_name: String) -> Result<(), String> {
+    quarantine::open_in_raw_editor(&config_name).map_err(|e| e.to_string())
+}
+
+// May wait on the sudo password prompt, keep it off the main thread
+#[tauri::command(async)]
+fn restore_from_backup(config_name: String, backup: Option<String>) -> Result<WgConfig, String> {
+    with_config_lock(&config_name, || quarantine::restore_from_backup(&config_name, backup.map(PathBuf::from)))
+        .map_err(failed("restore_from_backup"))
+}
+
+#[tauri::command]
+fn list_operation_snapshots(config_name: Option<String>) -> Result<Vec<OperationSnapshot>, String> {
+    operations::list_operation_snapshots(config_name.as_deref()).map_err(|e| e.to_string())
+}
+
+// May wait on the sudo password prompt, keep it off the main thread
+#[tauri::command(async)]
+fn rollback_operation(op_id: String, confirmation_token: Option<String>) -> Result<Guarded<WgConfig>, String> {
+    operations::rollback_operation(&op_id, confirmation_token.as_deref()).map_err(failed("rollback_operation"))
+}
+
+// May wait on the sudo password prompt, keep it off the main thread
+#[tauri::command(async)]
+fn save_wireguard_config(config: WgConfig) -> Result<Traced<()>, ErrorDetail> {
+    trace::traced(|| with_config_lock(&config.name, || save_config(&config)))
+        .map_err(failed_detail("save_wireguard_config"))
+}
+
+#[tauri::command]
+fn check_save_config(config_name: String) -> Result<Option<Finding>, String> {
+    saveconfig::check_save_config(&config_name).map_err(|e| e.to_string())
+}
+
+// May wait on the sudo password prompt, keep it off the main thread
+#[tauri::command(async)]
+fn sync_live_state(config_name: String) -> Result<Traced<WgConfig>, String> {
+    trace::traced(|| saveconfig::sync_live_state(&config_name)).map_err(failed("sync_live_state"))
+}
+
+#[tauri::command]
+fn set_save_config(config_name: String, enabled: bool) -> Result<WgConfig, String> {
+    saveconfig::set_save_config(&config_name, enabled).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn add_peer(config_name: String, peer: Peer, idempotency_key: Option<String>) -> Result<WgConfig, ErrorDetail> {
+    let request = (&config_name, &peer);
+    with_idempotency(idempotency_key.as_deref(), "add_peer", &request, || {
+        peers::add_peer(&config_name, peer.clone())
+    })
+    .map_err(|e| ErrorDetail::from(&e))
+}
+
+#[tauri::command]
+fn update_peer(
+    config_name: String,
+    public_key: String,
+    updated_peer: Peer,
+    idempotency_key: Option<String>,
+) -> Result<WgConfig, ErrorDetail> {
+    let request = (&config_name, &public_key, &updated_peer);
+    with_idempotency(idempotency_key.as_deref(), "update_peer", &request, || {
+        peers::update_peer(&config_name, &public_key, updated_peer.clone())
+    })
+    .map_err(|e| ErrorDetail::from(&e))
+}
+
+#[tauri::command]
+fn delete_peer(
+    config_name: String,
+    public_key: String,
+    confirmation_token: Option<String>,
+    idempotency_key: Option<String>,
+) -> Result<Guarded<WgConfig>, String> {
+    // The confirmation round-trip reuses the key, so the token isn't part of the request
+    let request = (&config_name, &public_key);
+    with_idempotency_if(
+        idempotency_key.as_deref(),
+        "delete_peer",
+        &request,
+        || peers::delete_peer(&config_name, &public_key, confirmation_token.as_deref()),
+        |outcome| matches!(outcome, Guarded::Done { .. }),
+    )
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn disable_peer(config_name: String, public_key: String, idempotency_key: Option<String>) -> Result<WgConfig, String> {
+    let request = (&config_name, &public_key);
+    with_idempotency(idempotency_key.as_deref(), "disable_peer", &request, || {
+        peers::disable_peer(&config_name, &public_key)
+    })
+    .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-fn save_wireguard_config(config: WgConfig) -> Result<(), String> {
-    save_config(&config).map_err(|e| e.to_string())
+fn enable_peer(config_name: String, public_key: String, idempotency_key: Option<String>) -> Result<WgConfig, String> {
+    let request = (&config_name, &public_key);
+    with_idempotency(idempotency_key.as_deref(), "enable_peer", &request, || {
+        peers::enable_peer(&config_name, &public_key)
+    })
+    .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-fn add_peer(config_name: String, peer: Peer) -> Result<WgConfig, String> {
-    let mut config = parse_config(&config_name).map_err(|e| e.to_string())?;
-    config.peers.push(peer);
-    save_config(&config).map_err(|e| e.to_string())?;
-    Ok(config)
+fn list_disabled_peers(config_name: String) -> Result<Vec<DisabledPeer>, String> {
+    peers::list_disabled_peers(&config_name).map_err(|e| e.to_string())
+}
+
+// May wait on the sudo password prompt, keep it off the main thread
+#[tauri::command(async)]
+fn kick_peer(config_name: String, public_key: String) -> Result<KickedPeer, String> {
+    peers::kick_peer(&config_name, &public_key).map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-fn update_peer(config_name: String, public_key: String, updated_peer: Peer) -> Result<WgConfig, String> {
-    let mut config = parse_config(&config_name).map_err(|e| e.to_string())?;
+fn schedule_peer_change(
+    app: AppHandle,
+    config_name: String,
+    public_key: String,
+    kind: PeerChangeKind,
+    grace_secs: u64,
+    confirmation_token: Option<String>,
+) -> Result<Guarded<ScheduledPeerChange>, String> {
+    let token = confirmation_token.as_deref();
+    let outcome =
+        schedule::schedule_peer_change(&config_name, &public_key, kind, grace_secs, token).map_err(|e| e.to_string())?;
+    if let Guarded::Done { ref result } = outcome {
+        let _ = app.emit("peer-change-scheduled", result);
+    }
+    Ok(outcome)
+}
 
-    if let Some(peer) = config.peers.iter_mut().find(|p| p.public_key == public_key) {
-        *peer = updated_peer;
-        save_config(&config).map_err(|e| e.to_string())?;
-        Ok(config)
-    } else {
-        Err("Peer not found".to_string())
+// May wait on the sudo password prompt, keep it off the main thread
+#[tauri::command(async)]
+fn create_temp_peer(
+    app: AppHandle,
+    config_name: String,
+    ttl_secs: u64,
+    label: String,
+    endpoint: String,
+) -> Result<TempPeer, String> {
+    let temp = tempaccess::create_temp_peer(&config_name, ttl_secs, &label, &endpoint).map_err(|e| e.to_string())?;
+    let scheduled = schedule::list_scheduled_changes(Some(&config_name)).unwrap_or_default();
+    if let Some(change) = scheduled.iter().find(|c| c.id == temp.schedule_id) {
+        let _ = app.emit("peer-change-scheduled", change);
     }
+    Ok(temp)
+}
+
+#[tauri::command]
+fn list_scheduled_changes(config_name: Option<String>) -> Result<Vec<ScheduledPeerChange>, String> {
+    schedule::list_scheduled_changes(config_name.as_deref()).map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-fn delete_peer(config_name: String, public_key: String) -> Result<WgConfig, String> {
-    let mut config = parse_config(&config_name).map_err(|e| e.to_string())?;
-    config.peers.retain(|p| p.public_key != public_key);
-    save_config(&config).map_err(|e| e.to_string())?;
-    Ok(config)
+fn cancel_scheduled_change(app: AppHandle, id: String) -> Result<Option<ScheduledPeerChange>, String> {
+    let cancelled = schedule::cancel_scheduled_change(&id).map_err(|e| e.to_string())?;
+    if let Some(ref change) = cancelled {
+        let _ = app.emit("peer-change-cancelled", change);
+    }
+    Ok(cancelled)
 }
 
 #[tauri::command]
+fn import_peers_from_dump(
+    config_name: String,
+    dump_text: String,
+    idempotency_key: Option<String>,
+) -> Result<DumpImport, String> {
+    let request = (&config_name, &dump_text);
+    with_idempotency(idempotency_key.as_deref(), "import_peers_from_dump", &request, || {
+        peers::import_peers_from_dump(&config_name, &dump_text)
+    })
+    .map_err(|e| e.to_string())
+}
+
+// Reads every config once for the keys of our own interfaces, possibly through sudo
+#[tauri::command(async)]
+fn import_roster(
+    config_name: String,
+    roster_path: String,
+    idempotency_key: Option<String>,
+) -> Result<RosterImport, String> {
+    let request = (&config_name, &roster_path);
+    with_idempotency(idempotency_key.as_deref(), "import_roster", &request, || {
+        peers::import_roster(&config_name, Path::new(&roster_path))
+    })
+    .map_err(|e| e.to_string())
+}
+
+// Endpoint lookups can take a few seconds on first sight of an address
+#[tauri::command(async)]
 fn get_wireguard_status(config_name: String) -> Result<Vec<PeerStatus>, String> {
-    get_peer_status(&config_name).map_err(|e| e.to_string())
+    let mut statuses = get_peer_status(&config_name).map_err(failed("get_wireguard_status"))?;
+    netinfo::enrich_peer_statuses(&mut statuses).map_err(|e| e.to_string())?;
+    Ok(statuses)
 }
 
 #[tauri::command]
 fn check_interface_status(config_name: String) -> Result<bool, String> {
-    is_interface_up(&config_name).map_err(|e| e.to_string())
+    is_interface_up(&config_name).map_err(failed("check_interface_status"))
 }
 
 #[tauri::command]
-fn restart_wireguard(config_name: String) -> Result<String, String> {
-    restart_interface(&config_name).map_err(|e| e.to_string())
+fn get_tunnel_state(config_name: String) -> Result<TunnelState, String> {
+    status::get_tunnel_state(&config_name).map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-fn bring_interface_up(config_name: String) -> Result<String, String> {
-    bring_up(&config_name).map_err(|e| e.to_string())
+fn get_status_summary_text(config_name: String) -> Result<String, String> {
+    status::get_status_summary_text(&config_name).map_err(|e| e.to_string())
+}
+
+// Reads the state of every tunnel
+#[tauri::command(async)]
+fn get_status_overview() -> StatusOverview {
+    status::get_status_overview()
+}
+
+#[tauri::command]
+fn verify_tunnel(config_name: String) -> Result<Traced<TunnelVerdict>, String> {
+    trace::traced(|| verify::verify_tunnel(&config_name)).map_err(failed("verify_tunnel"))
+}
+
+// Pings and polls for several seconds
+#[tauri::command(async)]
+fn trigger_handshake(
+    config_name: String,
+    public_key: String,
+    timeout_secs: Option<u64>,
+) -> Result<HandshakeAttempt, String> {
+    let timeout = timeout_secs.unwrap_or(verify::DEFAULT_HANDSHAKE_WAIT_SECS);
+    verify::trigger_handshake(&config_name, &public_key, timeout).map_err(failed("trigger_handshake"))
+}
+
+// Probes for several seconds
+#[tauri::command(async)]
+fn probe_endpoint_quality(
+    config_name: String,
+    public_key: String,
+    duration_secs: Option<u64>,
+) -> Result<EndpointQuality, String> {
+    let duration = duration_secs.unwrap_or(verify::DEFAULT_PROBE_SECS);
+    verify::probe_endpoint_quality(&config_name, &public_key, duration).map_err(failed("probe_endpoint_quality"))
+}
+
+#[tauri::command]
+fn get_external_ip_via(interface: Option<String>) -> Result<String, String> {
+    verify::get_external_ip_via(interface.as_deref()).map_err(|e| e.to_string())
+}
+
+// Runs for minutes, keep it off the main thread
+#[tauri::command(async)]
+fn auto_tune(config_name: String, matrix: Option<TuneMatrix>, apply: bool) -> Result<TuneReport, String> {
+    tune::auto_tune(&config_name, matrix, apply).map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-fn bring_interface_down(config_name: String) -> Result<String, String> {
-    bring_down(&config_name).map_err(|e| e.to_string())
+fn get_tune_report(config_name: String) -> Result<Option<TuneReport>, String> {
+    tune::get_tune_report(&config_name).map_err(|e| e.to_string())
+}
+
+// May wait on the sudo password prompt, keep it off the main thread
+#[tauri::command(async)]
+fn restart_wireguard(config_name: String) -> Result<Traced<TunnelActivation>, String> {
+    trace::traced(|| tunnels::restart(&config_name)).map_err(failed("restart_wireguard"))
+}
+
+// Resolves endpoints and asks sudo, keep it off the main thread
+#[tauri::command(async)]
+fn preflight(config_name: String) -> Result<Preflight, String> {
+    preflight::preflight(&config_name).map_err(|e| e.to_string())
+}
+
+// May wait on the sudo password prompt, keep it off the main thread
+#[tauri::command(async)]
+fn bring_interface_up(config_name: String) -> Result<Traced<TunnelActivation>, String> {
+    trace::traced(|| tunnels::connect(&config_name)).map_err(failed("bring_interface_up"))
+}
+
+// May wait on the sudo password prompt, keep it off the main thread
+#[tauri::command(async)]
+fn bring_interface_down(
+    config_name: String,
+    confirmation_token: Option<String>,
+) -> Result<Traced<Guarded<String>>, String> {
+    trace::traced(|| tunnels::disconnect(&config_name, confirmation_token.as_deref()))
+        .map_err(failed("bring_interface_down"))
 }
 
 #[tauri::command]
 fn generate_wireguard_keypair() -> Result<(String, String), String> {
-    generate_keypair().map_err(|e| e.to_string())
+    generate_keypair().map_err(failed("generate_wireguard_keypair"))
+}
+
+#[tauri::command]
+fn generate_preshared_key() -> Result<String, String> {
+    wiredeck_core::wireguard::generate_preshared_key().map_err(failed("generate_preshared_key"))
 }
 
 #[tauri::command]
 fn derive_public_key(private_key: String) -> Result<String, String> {
-    get_public_key(&private_key).map_err(|e| e.to_string())
+    get_public_key(&private_key).map_err(failed("derive_public_key"))
+}
+
+#[tauri::command]
+fn normalize_key(key: String) -> String {
+    keys::normalize_key(&key)
+}
+
+#[tauri::command]
+fn validate_public_key(key: String, config_name: Option<String>) -> Result<String, String> {
+    let config = match config_name {
+        Some(name) => Some(parse_config(&name).map_err(|e| e.to_string())?),
+        None => None,
+    };
+    keys::validate_public_key(&key, config.as_ref()).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn check_peer_keys(config_name: String) -> Result<Vec<Finding>, String> {
+    let config = parse_config(&config_name).map_err(|e| e.to_string())?;
+    Ok(keys::check_config_keys(&config))
+}
+
+#[tauri::command]
+fn check_config_limits(config_name: String) -> Result<Vec<Finding>, String> {
+    let config = parse_config(&config_name).map_err(|e| e.to_string())?;
+    Ok(guardrails::limit_findings(&config))
+}
+
+#[tauri::command]
+fn check_config_syntax(content: String) -> Option<ConfigSyntaxError> {
+    wiredeck_core::wireguard::check_config_syntax(&content)
+}
+
+#[tauri::command]
+fn validate_wireguard_config(config: WgConfig) -> Vec<Finding> {
+    validation::validate_config(&config)
+}
+
+// Reads every config, possibly through sudo for unreadable ones
+#[tauri::command(async)]
+fn validate_all_configs() -> Result<Vec<ConfigReport>, String> {
+    validation::validate_all_configs().map_err(|e| e.to_string())
+}
+
+// Reads live peer status, which may wait on the sudo password prompt
+#[tauri::command(async)]
+fn simulate_change(config_name: String, proposed: WgConfig) -> Result<Simulation, String> {
+    simulate::simulate_change(&config_name, &proposed).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn list_revoked_keys() -> Result<Vec<RevokedKey>, String> {
+    revoked::list_revoked_keys().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn add_revoked_key(public_key: String, reason: Option<String>) -> Result<RevokedKey, String> {
+    revoked::add_revoked_key(&public_key, reason.as_deref()).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn set_revocation_list(path: Option<String>) -> Result<(), String> {
+    revoked::set_revocation_list(path.map(PathBuf::from)).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_api_schema() -> serde_json::Value {
+    schema::api_schema()
 }
 
 #[tauri::command]
@@ -88,25 +499,917 @@ fn get_wireguard_directory() -> String {
     get_wireguard_dir().to_string_lossy().to_string()
 }
 
+// App settings and peer metadata
+
+#[tauri::command]
+fn get_app_settings() -> Result<AppSettings, String> {
+    settings::load_settings().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn update_app_settings(app: AppHandle, settings: AppSettings) -> Result<AppSettings, String> {
+    settings::save_settings(&settings).map_err(|e| e.to_string())?;
+    change_events::apply_socket_setting(&app, settings.event_socket);
+    Ok(settings)
+}
+
+#[tauri::command]
+fn set_artifact_language(lang: Language) -> Result<(), String> {
+    i18n::set_artifact_language(lang).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_backup_policy() -> BackupPolicy {
+    backup::get_backup_policy()
+}
+
+#[tauri::command]
+fn set_backup_destination(path: Option<String>) -> Result<BackupPolicy, String> {
+    backup::set_backup_destination(path.map(PathBuf::from)).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn set_backup_retention(retention: usize) -> Result<BackupPolicy, String> {
+    backup::set_backup_retention(retention).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn list_config_backups(config_name: String) -> Result<Vec<ConfigBackup>, String> {
+    backup::list_config_backups(&config_name).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_config_id(config_name: String) -> Result<String, String> {
+    identity::config_id(&config_name).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn list_config_identities() -> Result<Vec<ConfigIdentity>, String> {
+    identity::list_config_identities().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_peer_metadata(config_name: String) -> Result<HashMap<String, PeerMetadata>, String> {
+    metadata::list_peer_metadata(&config_name).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn add_peer_attachment(
+    config_name: String,
+    public_key: String,
+    kind: AttachmentKind,
+    value: String,
+    label: Option<String>,
+) -> Result<PeerMetadata, String> {
+    metadata::add_peer_attachment(&config_name, &public_key, kind, &value, label.as_deref()).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn remove_peer_attachment(config_name: String, public_key: String, value: String) -> Result<PeerMetadata, String> {
+    metadata::remove_peer_attachment(&config_name, &public_key, &value).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn search_peer_attachments(query: String) -> Result<Vec<AttachmentMatch>, String> {
+    metadata::search_peer_attachments(&query).map_err(|e| e.to_string())
+}
+
+// Peer platforms
+
+#[tauri::command]
+fn list_peer_platforms(config_name: String) -> Result<Vec<PeerPlatform>, String> {
+    platform::list_peer_platforms(&config_name).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn set_peer_platform(
+    config_name: String,
+    public_key: String,
+    platform: Option<Platform>,
+    os: Option<String>,
+) -> Result<PeerMetadata, String> {
+    platform::set_peer_platform(&config_name, &public_key, platform, os).map_err(|e| e.to_string())
+}
+
+// External tunnel managers
+
+#[tauri::command]
+fn get_external_manager(config_name: String) -> Option<ExternalManager> {
+    daemons::get_external_manager(&config_name)
+}
+
+// Stops a launchd job and may bring the tunnel back up
+#[tauri::command(async)]
+fn take_over_management(config_name: String) -> Result<TakeOver, String> {
+    daemons::take_over_management(&config_name).map_err(|e| e.to_string())
+}
+
+// Topology diagram
+
+#[tauri::command]
+fn generate_topology_graph(format: GraphFormat) -> Result<String, String> {
+    topology::generate_topology_graph(format).map_err(|e| e.to_string())
+}
+
+// Endpoint address family preferences
+
+#[tauri::command]
+fn set_peer_endpoint_family(config_name: String, public_key: String, family: AddressFamily) -> Result<PeerMetadata, String> {
+    metadata::update_peer_metadata(&config_name, &public_key, |m| m.endpoint_family = family)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn resolve_peer_endpoint(config_name: String, public_key: String) -> Result<ResolvedEndpoint, String> {
+    endpoint::resolve_peer_endpoint(&config_name, &public_key).map_err(|e| e.to_string())
+}
+
+// May wait on the sudo password prompt, keep it off the main thread
+#[tauri::command(async)]
+fn apply_endpoint_preferences(config_name: String) -> Result<Traced<EndpointPreferences>, String> {
+    trace::traced(|| endpoint::apply_endpoint_preferences(&config_name)).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn replace_endpoint_host(
+    old_host: String,
+    new_host: String,
+    scope: ReplaceScope,
+    dry_run: bool,
+) -> Result<HostReplacement, String> {
+    endpoint::replace_endpoint_host(&old_host, &new_host, &scope, dry_run).map_err(|e| e.to_string())
+}
+
+// Per-peer bandwidth limits
+
+#[tauri::command]
+fn generate_peer_tc_rules(config_name: String, public_key: String, rate_limit: RateLimit) -> Result<TcRules, String> {
+    let config = parse_config(&config_name).map_err(|e| e.to_string())?;
+    let peer = config
+        .peers
+        .iter()
+        .find(|p| p.public_key == public_key)
+        .ok_or_else(|| "Peer not found".to_string())?;
+    qos::generate_tc_rules(&config, peer, &rate_limit).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn set_peer_rate_limit(
+    config_name: String,
+    public_key: String,
+    rate_limit: RateLimit,
+    install: bool,
+) -> Result<RateLimitEntry, String> {
+    qos::set_rate_limit(&config_name, &public_key, rate_limit, install).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn clear_peer_rate_limit(config_name: String, public_key: String) -> Result<(), String> {
+    qos::clear_rate_limit(&config_name, &public_key).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn list_peer_rate_limits(config_name: String) -> Result<Vec<RateLimitEntry>, String> {
+    qos::list_rate_limits(&config_name).map_err(|e| e.to_string())
+}
+
+// Split DNS
+
+#[tauri::command]
+fn get_split_dns(config_name: String) -> Result<Option<SplitDns>, String> {
+    splitdns::get_split_dns(&config_name).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn set_split_dns(config_name: String, rules: Vec<SplitDnsRule>) -> Result<Option<SplitDns>, String> {
+    splitdns::set_split_dns(&config_name, rules).map_err(|e| e.to_string())
+}
+
+// Per-application routing (macOS)
+
+#[tauri::command]
+fn generate_pf_app_rules(config_name: String, app_paths: Vec<String>) -> Result<PfAppRules, String> {
+    approuting::generate_pf_app_rules(&config_name, &app_paths).map_err(|e| e.to_string())
+}
+
+// May wait on the sudo password prompt, keep it off the main thread
+#[tauri::command(async)]
+fn install_pf_app_rules(config_name: String, app_paths: Vec<String>) -> Result<PfAppRules, String> {
+    approuting::install_pf_app_rules(&config_name, &app_paths).map_err(|e| e.to_string())
+}
+
+// May wait on the sudo password prompt, keep it off the main thread
+#[tauri::command(async)]
+fn remove_pf_app_rules(config_name: String) -> Result<(), String> {
+    approuting::remove_pf_app_rules(&config_name).map_err(|e| e.to_string())
+}
+
+// Remote config subscriptions
+
+#[tauri::command]
+fn subscribe_remote_config(
+    config_name: String,
+    url: String,
+    auth: Option<RemoteAuth>,
+    interval_secs: u64,
+) -> Result<WgConfig, String> {
+    remote::subscribe_remote_config(&config_name, &url, auth.unwrap_or_default(), interval_secs)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn unsubscribe_remote_config(config_name: String) -> Result<(), String> {
+    remote::unsubscribe_remote_config(&config_name).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn list_remote_subscriptions() -> Result<Vec<RemoteSubscription>, String> {
+    remote::list_subscriptions().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn refresh_remote_configs() -> Result<Vec<RefreshOutcome>, String> {
+    remote::refresh_subscriptions(true).map_err(|e| e.to_string())
+}
+
+// Tunnel dependencies, auto-connect and profiles
+
+#[tauri::command]
+fn get_tunnel_settings() -> Result<HashMap<String, TunnelSettings>, String> {
+    tunnels::list_tunnel_settings().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn set_tunnel_dependencies(config_name: String, depends_on: Vec<String>) -> Result<TunnelSettings, String> {
+    tunnels::set_tunnel_dependencies(&config_name, depends_on).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn set_tunnel_auto_connect(config_name: String, auto_connect: bool) -> Result<TunnelSettings, String> {
+    tunnels::set_auto_connect(&config_name, auto_connect).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn set_tunnel_exclusive_group(config_name: String, group: Option<String>) -> Result<TunnelSettings, String> {
+    tunnels::set_exclusive_group(&config_name, group).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn set_tunnel_allow_managed_edits(config_name: String, allow: bool) -> Result<TunnelSettings, String> {
+    tunnels::set_allow_managed_edits(&config_name, allow).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn set_tunnel_randomize_listen_port(config_name: String, randomize: bool) -> Result<TunnelSettings, String> {
+    tunnels::set_randomize_listen_port(&config_name, randomize).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn list_profiles() -> Result<Vec<Profile>, String> {
+    tunnels::list_profiles().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn save_profile(profile: Profile) -> Result<(), String> {
+    tunnels::save_profile(profile).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn delete_profile(name: String) -> Result<(), String> {
+    tunnels::delete_profile(&name).map_err(|e| e.to_string())
+}
+
+// May wait on the sudo password prompt, keep it off the main thread
+#[tauri::command(async)]
+fn activate_profile(name: String) -> Result<Traced<Vec<TunnelActivation>>, String> {
+    trace::traced(|| tunnels::activate_profile(&name)).map_err(failed("activate_profile"))
+}
+
+// PostUp/PostDown snippet library
+
+#[tauri::command]
+fn list_snippets() -> Result<Vec<Snippet>, String> {
+    snippets::list_snippets().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn save_snippet(snippet: Snippet) -> Result<(), String> {
+    snippets::save_snippet(snippet).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn delete_snippet(id: String) -> Result<(), String> {
+    snippets::delete_snippet(&id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn render_snippet(config_name: String, snippet_id: String, params: HashMap<String, String>) -> Result<RenderedSnippet, String> {
+    let config = parse_config(&config_name).map_err(|e| e.to_string())?;
+    snippets::render_snippet(&config, &snippet_id, params).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn apply_snippet(config_name: String, snippet_id: String, params: HashMap<String, String>) -> Result<WgConfig, String> {
+    snippets::apply_snippet(&config_name, &snippet_id, params).map_err(|e| e.to_string())
+}
+
+// Server setup checks
+
+#[tauri::command]
+fn check_forwarding(config_name: String) -> Result<ForwardingReport, String> {
+    server::check_forwarding(&config_name).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn check_listen_port(config_name: String) -> Result<Vec<Finding>, String> {
+    let config = parse_config(&config_name).map_err(|e| e.to_string())?;
+    Ok(server::listen_port_findings(&config))
+}
+
+#[tauri::command]
+fn get_firewall_status(config_name: Option<String>) -> Result<FirewallStatus, String> {
+    firewall::get_firewall_status(config_name.as_deref()).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn setup_server_nat(
+    config_name: String,
+    wan_interface: String,
+    mode: NatApplyMode,
+) -> Result<Traced<RenderedSnippet>, String> {
+    trace::traced(|| server::setup_server_nat(&config_name, &wan_interface, mode)).map_err(|e| e.to_string())
+}
+
+// Sudo password prompt
+
+#[tauri::command]
+fn answer_sudo_prompt(id: u64, password: Option<String>) -> Result<(), String> {
+    sudo_prompt::answer(id, password)
+}
+
+// Self-test
+
+// Waits on wg-quick and a handshake, and may wait on the sudo password prompt
+#[tauri::command(async)]
+fn create_sandbox_tunnel() -> SandboxReport {
+    sandbox::create_sandbox_tunnel()
+}
+
+// Embedded servers
+
+#[tauri::command]
+fn get_embedded_services_status() -> Vec<EmbeddedService> {
+    services::embedded_services_status()
+}
+
+// Failure history
+
+#[tauri::command]
+fn get_recent_failures() -> Result<Vec<FailureSummary>, String> {
+    failures::get_recent_failures().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn clear_failures() -> Result<(), String> {
+    failures::clear_failures().map_err(|e| e.to_string())
+}
+
+// Privileged change log
+
+#[tauri::command]
+fn list_privileged_changes() -> Result<Vec<PrivilegedChange>, String> {
+    privileged::list_privileged_changes().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_revert_script(id: u64) -> Result<String, String> {
+    privileged::get_revert_script(id).map_err(|e| e.to_string())
+}
+
+// May wait on the sudo password prompt, keep it off the main thread
+#[tauri::command(async)]
+fn revert_last_privileged_change() -> Result<Traced<PrivilegedChange>, String> {
+    trace::traced(privileged::revert_last_privileged_change).map_err(|e| e.to_string())
+}
+
+// Power state
+
+#[tauri::command]
+fn get_power_state() -> PowerState {
+    power::get_power_state()
+}
+
+// Remote API tokens
+
+#[tauri::command]
+fn list_api_tokens() -> Result<Vec<ApiToken>, String> {
+    api::list_tokens().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn issue_api_token(name: String, scope: TokenScope) -> Result<IssuedToken, String> {
+    api::issue_token(&name, scope).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn revoke_api_token(id: String) -> Result<(), String> {
+    api::revoke_token(&id).map_err(|e| e.to_string())
+}
+
+// Workspaces
+
+#[tauri::command]
+fn list_workspaces() -> Vec<Workspace> {
+    workspace::list_workspaces()
+}
+
+#[tauri::command]
+fn get_active_workspace() -> Workspace {
+    workspace::active_workspace()
+}
+
+#[tauri::command]
+fn create_workspace(name: String, config_dir: Option<String>) -> Result<Workspace, String> {
+    workspace::create_workspace(&name, config_dir.map(PathBuf::from)).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn set_workspace_config_dir(name: String, config_dir: Option<String>) -> Result<Workspace, String> {
+    workspace::set_workspace_config_dir(&name, config_dir.map(PathBuf::from)).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn switch_workspace(name: String) -> Result<Workspace, String> {
+    workspace::switch_workspace(&name).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn delete_workspace(name: String) -> Result<(), String> {
+    workspace::delete_workspace(&name).map_err(|e| e.to_string())
+}
+
+// Pushed state subscriptions
+
+#[tauri::command]
+fn subscribe(topic: String, window: Window, subscriptions: State<Subscriptions>) -> Result<usize, String> {
+    subscriptions::validate_topic(&topic)?;
+    Ok(subscriptions.subscribe(window.label(), &topic))
+}
+
+#[tauri::command]
+fn unsubscribe(topic: String, window: Window, subscriptions: State<Subscriptions>) -> usize {
+    subscriptions.unsubscribe(window.label(), &topic)
+}
+
+// wiredeck:// links
+
+#[tauri::command]
+fn run_deep_link(url: String, confirmation_token: Option<String>) -> Result<Guarded<DeepLinkOutcome>, String> {
+    deeplink::run_deep_link(&url, confirmation_token.as_deref()).map_err(|e| e.to_string())
+}
+
+// File import (drag and drop or file picker)
+
+#[tauri::command]
+fn import_config_files(paths: Vec<String>) -> Vec<ImportResult> {
+    paths.iter().flat_map(|p| import::import_path(Path::new(p))).collect()
+}
+
+#[tauri::command]
+fn import_config_as(path: String, entry: Option<String>, config_name: String, overwrite: bool) -> Result<WgConfig, String> {
+    import::import_config_as(Path::new(&path), entry.as_deref(), &config_name, overwrite).map_err(|e| e.to_string())
+}
+
+// Tunnel addressing
+
+#[tauri::command]
+fn generate_ula_prefix() -> String {
+    addressing::generate_ula_prefix().to_string()
+}
+
+#[tauri::command]
+fn plan_tunnel_addresses() -> Result<AddressPlan, String> {
+    addressing::plan_tunnel_addresses().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn create_wireguard_config(config_name: String, listen_port: Option<u16>) -> Result<WgConfig, String> {
+    addressing::create_wireguard_config(&config_name, listen_port).map_err(|e| e.to_string())
+}
+
+// Parses every config and derives each interface's public key
+#[tauri::command(async)]
+fn get_address_map() -> Result<AddressMap, String> {
+    addressing::get_address_map().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn who_has_ip(ip: String) -> Result<Vec<AddressMatch>, String> {
+    let ip = ip.trim().parse().map_err(|_| format!("Invalid IP address: {}", ip))?;
+    addressing::who_has_ip(ip).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn list_peers_in_subnet(cidr: String) -> Result<Vec<AddressMatch>, String> {
+    let subnet = Cidr::parse(&cidr).map_err(|e| e.to_string())?;
+    addressing::list_peers_in_subnet(subnet).map_err(|e| e.to_string())
+}
+
+// Printable export
+
+#[tauri::command]
+fn export_config_pdf(config_name: String, options: PdfExportOptions, output_path: String) -> Result<(), String> {
+    pdf::export_config_pdf(&config_name, &options, Path::new(&output_path)).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn anonymize_config(config_name: String) -> Result<AnonymizedConfig, String> {
+    anonymize::anonymize_config(&config_name).map_err(|e| e.to_string())
+}
+
+// Container and VM exports
+
+#[tauri::command]
+fn export_docker_compose(config_name: String) -> Result<ComposeExport, String> {
+    guests::export_docker_compose(&config_name).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn export_cloud_init(config_name: String) -> Result<String, String> {
+    guests::export_cloud_init(&config_name).map_err(|e| e.to_string())
+}
+
+// Self-service portal
+
+#[tauri::command]
+fn get_selfservice_portal(config_name: String) -> Result<Option<SelfServicePortal>, String> {
+    selfservice::get_selfservice_portal(&config_name).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn configure_selfservice_portal(
+    config_name: String,
+    output_dir: String,
+    endpoint: String,
+    allowed_ips: Option<String>,
+    title: Option<String>,
+) -> Result<SelfServicePortal, String> {
+    selfservice::configure_selfservice_portal(
+        &config_name,
+        PathBuf::from(output_dir),
+        &endpoint,
+        allowed_ips.as_deref(),
+        title.as_deref(),
+    )
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn approve_selfservice_user(
+    config_name: String,
+    public_key: String,
+    client_private_key: String,
+    name: String,
+    platform: Option<Platform>,
+) -> Result<ApprovedUser, String> {
+    selfservice::approve_selfservice_user(&config_name, &public_key, &client_private_key, &name, platform)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn revoke_selfservice_user(config_name: String, user_id: String) -> Result<(), String> {
+    selfservice::revoke_selfservice_user(&config_name, &user_id).map_err(|e| e.to_string())
+}
+
+// Key derivation takes a moment per user
+#[tauri::command(async)]
+fn generate_selfservice_bundle(config_name: String) -> Result<SelfServicePortal, String> {
+    selfservice::generate_selfservice_bundle(&config_name).map_err(|e| e.to_string())
+}
+
+// Exit node deployment
+
+// Waits on provider APIs for up to a few minutes, keep it off the main thread
+#[tauri::command(async)]
+fn deploy_exit_node(request: DeployRequest) -> Result<DeployedHost, String> {
+    deploy::deploy_exit_node(&request).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn list_deployed_hosts() -> Result<Vec<DeployedHost>, String> {
+    deploy::list_deployed_hosts().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn forget_deployed_host(id: String) -> Result<(), String> {
+    deploy::forget_deployed_host(&id).map_err(|e| e.to_string())
+}
+
+// Detached tunnel windows
+
+// Creating windows from a synchronous command deadlocks on Windows
+#[tauri::command(async)]
+fn open_tunnel_window(app: AppHandle, config_name: String) -> Result<String, String> {
+    windows::open_tunnel_window(&app, &config_name)
+}
+
+// Peer activity timeline
+
+#[tauri::command]
+fn get_peer_timeline(config_name: String, public_key: String) -> Result<Vec<PeerEvent>, String> {
+    metrics::get_peer_timeline(&config_name, &public_key).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_endpoint_history(config_name: String, public_key: String) -> Result<Vec<EndpointSpan>, String> {
+    metrics::get_endpoint_history(&config_name, &public_key).map_err(|e| e.to_string())
+}
+
+// May wait on the sudo password prompt, keep it off the main thread
+#[tauri::command(async)]
+fn mark_stats_baseline(config_name: String, public_key: String) -> Result<StatsBaseline, String> {
+    metrics::mark_stats_baseline(&config_name, &public_key).map_err(|e| e.to_string())
+}
+
+// May wait on the sudo password prompt, keep it off the main thread
+#[tauri::command(async)]
+fn get_stats_since_baseline(config_name: String, public_key: String) -> Result<Option<StatsUsage>, String> {
+    metrics::get_stats_since_baseline(&config_name, &public_key).map_err(|e| e.to_string())
+}
+
+/// Whether the app was started in safe mode
+struct SafeMode(bool);
+
+/// `WIREDECK_SAFE_MODE=1` or `--safe-mode` starts the app without auto-connect, remote refresh, the
+/// status monitor or any other background work, so a bad rule or script doesn't take connectivity
+/// down again on every launch
+fn safe_mode_requested() -> bool {
+    std::env::var("WIREDECK_SAFE_MODE").is_ok_and(|v| !v.is_empty() && v != "0")
+        || std::env::args().any(|arg| arg == "--safe-mode")
+}
+
+#[tauri::command]
+fn is_safe_mode(safe_mode: State<SafeMode>) -> bool {
+    safe_mode.0
+}
+
+#[tauri::command]
+fn get_subsystem_status(subsystems: State<Subsystems>) -> Vec<SubsystemStatus> {
+    subsystems.snapshot()
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    // A second launch hands its deep links and files to the running app and quits, so two
+    // instances never race on the same configs and app data
+    let (primary, claim_error) = match instance::claim_instance(&Invocation::current()) {
+        Ok(Claim::Primary(primary)) => (Some(primary), None),
+        Ok(Claim::Running { forwarded }) => {
+            if !forwarded {
+                eprintln!("WireDeck is already running");
+            }
+            return;
+        }
+        Err(e) => (None, Some(e.to_string())),
+    };
+    let safe_mode = safe_mode_requested();
+
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
+        .plugin(tauri_plugin_deep_link::init())
+        .manage(Subscriptions::default())
+        .manage(Subsystems::default())
+        .manage(SafeMode(safe_mode))
+        .on_window_event(|window, event| {
+            match event {
+                WindowEvent::Destroyed => window.state::<Subscriptions>().drop_window(window.label()),
+                WindowEvent::DragDrop(DragDropEvent::Drop { paths, .. }) => {
+                    tasks::import_dropped_files(window.clone(), paths.clone())
+                }
+                _ => {}
+            }
+        })
+        .setup(move |app| {
+            // Upgrade app data before anything reads it
+            match migrations::run_migrations() {
+                Ok(report) if report.from_version != report.to_version => subscriptions::log(
+                    app.handle(),
+                    format!("Migrated app data from v{} to v{}", report.from_version, report.to_version),
+                ),
+                Ok(_) => {}
+                Err(e) => subscriptions::log(app.handle(), format!("App data migration failed: {}", e)),
+            }
+            sudo_prompt::install(app.handle());
+
+            // Everything else starts on its own thread so the window shows at once, and a
+            // subsystem that fails is reported by get_subsystem_status instead of stopping launch
+            let app = app.handle();
+            subsystems::launch(app, "change_events", Lifetime::Once, |app| {
+                change_events::install(&app);
+                Ok(())
+            });
+            if safe_mode {
+                subscriptions::log(
+                    app,
+                    "Safe mode: skipping auto-connect, remote refresh, scheduled peer changes, the status monitor, \
+                     the config watcher and subscriptions",
+                );
+                for name in [
+                    "remote_refresh",
+                    "auto_connect",
+                    "status_monitor",
+                    "scheduled_changes",
+                    "config_watcher",
+                    "subscriptions",
+                ] {
+                    subsystems::skip(app, name, "safe mode");
+                }
+            } else {
+                subsystems::launch(app, "remote_refresh", Lifetime::Service, tasks::run_remote_refresh);
+                subsystems::launch(app, "auto_connect", Lifetime::Once, tasks::run_auto_connect);
+                subsystems::launch(app, "status_monitor", Lifetime::Service, tasks::run_status_monitor);
+                subsystems::launch(app, "scheduled_changes", Lifetime::Service, tasks::run_scheduled_changes);
+                subsystems::launch(app, "config_watcher", Lifetime::Service, tasks::run_config_watcher);
+                subsystems::launch(app, "subscriptions", Lifetime::Service, subscriptions::run_publisher);
+            }
+            subsystems::launch(app, "config_validation", Lifetime::Once, tasks::run_config_validation);
+            if cfg!(target_os = "macos") {
+                subsystems::launch(app, "menu_bar", Lifetime::Service, menubar::run);
+            } else {
+                subsystems::skip(app, "menu_bar", "macOS only");
+            }
+            // The deep link plugin is set up from the setup thread
+            subsystems::record(app, "deep_links", deeplinks::register(app).map_err(|e| e.to_string()));
+            match (primary, claim_error) {
+                (Some(primary), _) if cfg!(unix) => {
+                    subsystems::launch(app, "single_instance", Lifetime::Service, move |app| {
+                        single_instance::run(app, primary)
+                    });
+                }
+                (Some(_), _) => subsystems::skip(app, "single_instance", "forwarding launches needs Unix sockets"),
+                (None, error) => subsystems::record(app, "single_instance", Err(error.unwrap_or_default())),
+            }
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
+            is_safe_mode,
+            get_subsystem_status,
             list_wireguard_configs,
             load_wireguard_config,
+            reload_config_if_unchanged,
+            list_quarantined_configs,
+            open_in_raw_editor,
+            restore_from_backup,
+            list_operation_snapshots,
+            rollback_operation,
             save_wireguard_config,
+            check_save_config,
+            sync_live_state,
+            set_save_config,
             add_peer,
             update_peer,
             delete_peer,
+            disable_peer,
+            enable_peer,
+            list_disabled_peers,
+            kick_peer,
+            schedule_peer_change,
+            create_temp_peer,
+            list_scheduled_changes,
+            cancel_scheduled_change,
+            import_peers_from_dump,
+            import_roster,
             get_wireguard_status,
             check_interface_status,
+            get_tunnel_state,
+            get_status_summary_text,
+            get_status_overview,
+            verify_tunnel,
+            trigger_handshake,
+            probe_endpoint_quality,
+            get_external_ip_via,
+            auto_tune,
+            get_tune_report,
             restart_wireguard,
+            preflight,
             bring_interface_up,
             bring_interface_down,
             generate_wireguard_keypair,
+            generate_preshared_key,
             derive_public_key,
+            normalize_key,
+            validate_public_key,
+            check_peer_keys,
+            check_config_limits,
+            validate_all_configs,
+            validate_wireguard_config,
+            check_config_syntax,
+            simulate_change,
+            list_revoked_keys,
+            add_revoked_key,
+            set_revocation_list,
+            get_api_schema,
             get_wireguard_directory,
+            get_app_settings,
+            update_app_settings,
+            set_artifact_language,
+            get_backup_policy,
+            set_backup_destination,
+            set_backup_retention,
+            list_config_backups,
+            get_config_id,
+            list_config_identities,
+            get_peer_metadata,
+            add_peer_attachment,
+            remove_peer_attachment,
+            search_peer_attachments,
+            get_external_manager,
+            take_over_management,
+            generate_topology_graph,
+            list_peer_platforms,
+            set_peer_platform,
+            set_peer_endpoint_family,
+            resolve_peer_endpoint,
+            apply_endpoint_preferences,
+            replace_endpoint_host,
+            generate_peer_tc_rules,
+            set_peer_rate_limit,
+            clear_peer_rate_limit,
+            list_peer_rate_limits,
+            get_split_dns,
+            set_split_dns,
+            generate_pf_app_rules,
+            install_pf_app_rules,
+            remove_pf_app_rules,
+            subscribe_remote_config,
+            unsubscribe_remote_config,
+            list_remote_subscriptions,
+            refresh_remote_configs,
+            get_tunnel_settings,
+            set_tunnel_dependencies,
+            set_tunnel_auto_connect,
+            set_tunnel_exclusive_group,
+            set_tunnel_randomize_listen_port,
+            set_tunnel_allow_managed_edits,
+            list_profiles,
+            save_profile,
+            delete_profile,
+            activate_profile,
+            list_snippets,
+            save_snippet,
+            delete_snippet,
+            render_snippet,
+            apply_snippet,
+            check_forwarding,
+            check_listen_port,
+            get_firewall_status,
+            setup_server_nat,
+            get_peer_timeline,
+            get_endpoint_history,
+            mark_stats_baseline,
+            get_stats_since_baseline,
+            subscribe,
+            unsubscribe,
+            open_tunnel_window,
+            run_deep_link,
+            import_config_files,
+            import_config_as,
+            generate_ula_prefix,
+            plan_tunnel_addresses,
+            create_wireguard_config,
+            get_address_map,
+            who_has_ip,
+            list_peers_in_subnet,
+            export_config_pdf,
+            anonymize_config,
+            export_docker_compose,
+            export_cloud_init,
+            get_selfservice_portal,
+            configure_selfservice_portal,
+            approve_selfservice_user,
+            revoke_selfservice_user,
+            generate_selfservice_bundle,
+            deploy_exit_node,
+            list_deployed_hosts,
+            forget_deployed_host,
+            answer_sudo_prompt,
+            create_sandbox_tunnel,
+            get_embedded_services_status,
+            get_recent_failures,
+            clear_failures,
+            list_privileged_changes,
+            get_revert_script,
+            revert_last_privileged_change,
+            get_power_state,
+            list_api_tokens,
+            issue_api_token,
+            revoke_api_token,
+            list_workspaces,
+            get_active_workspace,
+            create_workspace,
+            set_workspace_config_dir,
+            switch_workspace,
+            delete_workspace,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
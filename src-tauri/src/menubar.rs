@@ -0,0 +1,198 @@
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::image::Image;
+use tauri::menu::{IconMenuItem, IsMenuItem, Menu, MenuItem, PredefinedMenuItem};
+use tauri::tray::{TrayIcon, TrayIconBuilder};
+use tauri::{AppHandle, Manager};
+use wiredeck_core::status::{self, AggregateState, Glance, TunnelGlance};
+
+use crate::subscriptions::log;
+use crate::windows;
+
+const TRAY_ID: &str = "menu-bar";
+
+/// How often the menu bar item re-reads tunnel state and traffic
+const MENU_BAR_TICK: Duration = Duration::from_secs(3);
+
+/// Menu item ids; tunnel items are `TUNNEL_ITEM_PREFIX` + config name
+const OPEN_ITEM: &str = "open";
+const TUNNEL_ITEM_PREFIX: &str = "tunnel:";
+
+/// Menu bar icons are drawn at 2x for Retina displays
+const ICON_SIZE: u32 = 36;
+const DOT_SIZE: u32 = 16;
+
+const GREEN: [u8; 3] = [0x34, 0xc7, 0x59];
+const ORANGE: [u8; 3] = [0xff, 0x9f, 0x0a];
+const GRAY: [u8; 3] = [0x8e, 0x8e, 0x93];
+
+fn aggregate_color(state: AggregateState) -> [u8; 3] {
+    match state {
+        AggregateState::AllUp => GREEN,
+        AggregateState::Partial => ORANGE,
+        AggregateState::Down => GRAY,
+    }
+}
+
+fn glance_color(glance: Glance) -> [u8; 3] {
+    match glance {
+        Glance::Healthy => GREEN,
+        Glance::Degraded => ORANGE,
+        Glance::Down => GRAY,
+    }
+}
+
+/// A filled circle with a softened edge; `ring` hollows it out to that fraction of the radius
+fn circle(size: u32, [r, g, b]: [u8; 3], ring: Option<f32>) -> Image<'static> {
+    let center = size as f32 / 2.0;
+    let radius = center - 1.0;
+    let mut rgba = Vec::with_capacity((size * size * 4) as usize);
+    for y in 0..size {
+        for x in 0..size {
+            let distance = ((x as f32 + 0.5 - center).powi(2) + (y as f32 + 0.5 - center).powi(2)).sqrt();
+            let mut alpha = (radius - distance + 0.5).clamp(0.0, 1.0);
+            if let Some(ring) = ring {
+                alpha *= (distance - radius * ring + 0.5).clamp(0.0, 1.0);
+            }
+            rgba.extend_from_slice(&[r, g, b, (alpha * 255.0) as u8]);
+        }
+    }
+    Image::new_owned(rgba, size, size)
+}
+
+/// Solid when everything is up, a ring while tunnels are down, so the state reads without color too
+fn aggregate_icon(state: AggregateState) -> Image<'static> {
+    let ring = match state {
+        AggregateState::AllUp => None,
+        AggregateState::Partial => Some(0.35),
+        AggregateState::Down => Some(0.6),
+    };
+    circle(ICON_SIZE, aggregate_color(state), ring)
+}
+
+/// Bytes per second, compact enough for the menu bar ("1.2M", "340K")
+fn format_rate(bytes_per_sec: f64) -> String {
+    const UNITS: [&str; 4] = ["B", "K", "M", "G"];
+    let mut value = bytes_per_sec;
+    let mut unit = 0;
+    while value >= 1000.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if value < 10.0 && unit > 0 {
+        format!("{:.1}{}", value, UNITS[unit])
+    } else {
+        format!("{:.0}{}", value, UNITS[unit])
+    }
+}
+
+fn build_menu(app: &AppHandle, tunnels: &[TunnelGlance]) -> tauri::Result<Menu<tauri::Wry>> {
+    let mut items: Vec<Box<dyn IsMenuItem<tauri::Wry>>> = Vec::new();
+    for tunnel in tunnels {
+        let state = match tunnel.glance {
+            Glance::Healthy => "connected",
+            Glance::Degraded => "degraded",
+            Glance::Down => "down",
+        };
+        items.push(Box::new(IconMenuItem::with_id(
+            app,
+            format!("{}{}", TUNNEL_ITEM_PREFIX, tunnel.config_name),
+            format!("{} — {}", tunnel.config_name, state),
+            true,
+            Some(circle(DOT_SIZE, glance_color(tunnel.glance), None)),
+            None::<&str>,
+        )?));
+    }
+    if tunnels.is_empty() {
+        items.push(Box::new(MenuItem::with_id(app, "none", "No tunnels", false, None::<&str>)?));
+    }
+    items.push(Box::new(PredefinedMenuItem::separator(app)?));
+    items.push(Box::new(MenuItem::with_id(app, OPEN_ITEM, "Open WireDeck", true, None::<&str>)?));
+    items.push(Box::new(PredefinedMenuItem::quit(app, Some("Quit WireDeck"))?));
+
+    let refs: Vec<&dyn IsMenuItem<tauri::Wry>> = items.iter().map(|item| item.as_ref()).collect();
+    Menu::with_items(app, &refs)
+}
+
+fn on_menu_event(app: &AppHandle, id: &str) {
+    if id == OPEN_ITEM {
+        if let Some(window) = app.get_webview_window("main") {
+            let _ = window.show();
+            let _ = window.set_focus();
+        }
+    } else if let Some(config_name) = id.strip_prefix(TUNNEL_ITEM_PREFIX) {
+        if let Err(e) = windows::open_tunnel_window(app, config_name) {
+            log(app, format!("Could not open {}: {}", config_name, e));
+        }
+    }
+}
+
+/// Keep the icon, per-tunnel menu and traffic readout in step with the backend's tunnel state
+fn update_forever(app: &AppHandle, tray: &TrayIcon) -> ! {
+    // What the icon and menu show; summaries change every tick and only go in the tooltip
+    let mut shown: (AggregateState, Vec<(String, Glance)>) = (AggregateState::Down, Vec::new());
+    let mut last_totals: Option<(Instant, u64, u64)> = None;
+
+    loop {
+        let overview = status::get_status_overview();
+        let now = Instant::now();
+
+        let current = (
+            overview.state,
+            overview.tunnels.iter().map(|t| (t.config_name.clone(), t.glance)).collect(),
+        );
+        if shown != current {
+            let _ = tray.set_icon(Some(aggregate_icon(overview.state)));
+            match build_menu(app, &overview.tunnels) {
+                Ok(menu) => {
+                    let _ = tray.set_menu(Some(menu));
+                }
+                Err(e) => log(app, format!("Could not build the menu bar menu: {}", e)),
+            }
+            shown = current;
+        }
+        let tooltip: Vec<String> = overview
+            .tunnels
+            .iter()
+            .map(|t| format!("{}: {}", t.config_name, t.summary))
+            .collect();
+        let tooltip = if tooltip.is_empty() { "No tunnels".to_string() } else { tooltip.join("\n") };
+        let _ = tray.set_tooltip(Some(tooltip));
+
+        // Counters reset when a tunnel restarts; skip that tick rather than show a bogus rate
+        let title = match last_totals {
+            Some((at, rx, tx)) if overview.rx_bytes >= rx && overview.tx_bytes >= tx => {
+                let secs = now.duration_since(at).as_secs_f64().max(1.0);
+                Some(format!(
+                    "↓{} ↑{}",
+                    format_rate((overview.rx_bytes - rx) as f64 / secs),
+                    format_rate((overview.tx_bytes - tx) as f64 / secs)
+                ))
+            }
+            _ => None,
+        };
+        if overview.state == AggregateState::Down {
+            let _ = tray.set_title(None::<&str>);
+        } else if let Some(title) = title {
+            let _ = tray.set_title(Some(title));
+        }
+        last_totals = Some((now, overview.rx_bytes, overview.tx_bytes));
+
+        thread::sleep(MENU_BAR_TICK);
+    }
+}
+
+/// Add the menu bar status item and keep it updated. Meant for macOS; other platforms keep to
+/// their windows.
+pub fn run(app: AppHandle) -> Result<(), String> {
+    // Reading state can wait on sudo, so the item starts out empty and the updater fills it in
+    let tray = TrayIconBuilder::with_id(TRAY_ID)
+        .icon(aggregate_icon(AggregateState::Down))
+        .icon_as_template(false)
+        .menu(&build_menu(&app, &[]).map_err(|e| e.to_string())?)
+        .show_menu_on_left_click(true)
+        .on_menu_event(|app, event| on_menu_event(app, event.id().as_ref()))
+        .build(&app)
+        .map_err(|e| e.to_string())?;
+    update_forever(&app, &tray)
+}
@@ -0,0 +1,43 @@
+use std::path::PathBuf;
+use tauri::{AppHandle, Emitter, Manager};
+use wiredeck_core::instance::{Invocation, PrimaryInstance};
+
+use crate::{deeplinks, tasks};
+
+/// A later launch handed its invocation over: bring the main window forward and do what it was
+/// launched for, the same way this instance would have at startup
+fn on_invocation(app: &AppHandle, invocation: Invocation) {
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.unminimize();
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+
+    let mut files: Vec<PathBuf> = Vec::new();
+    for arg in &invocation.args {
+        if arg.starts_with("wiredeck://") {
+            deeplinks::handle(app, arg.clone());
+        } else if !arg.starts_with('-') {
+            let path = match invocation.cwd {
+                Some(ref cwd) => cwd.join(arg),
+                None => PathBuf::from(arg),
+            };
+            if path.is_file() {
+                files.push(path);
+            }
+        }
+    }
+    if !files.is_empty() {
+        if let Some(window) = app.get_webview_window("main") {
+            tasks::import_dropped_files(window.as_ref().window(), files);
+        }
+    }
+    let _ = app.emit("second-instance", &invocation);
+}
+
+/// Act on every invocation forwarded by a later launch
+pub fn run(app: AppHandle, primary: PrimaryInstance) -> Result<(), String> {
+    primary
+        .serve(|invocation| on_invocation(&app, invocation))
+        .map_err(|e| e.to_string())
+}
@@ -0,0 +1,148 @@
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+use wiredeck_core::status;
+use wiredeck_core::wireguard::list_configs;
+
+/// How often active topics are re-evaluated
+const PUBLISH_TICK: Duration = Duration::from_secs(2);
+
+/// Event every topic update is delivered on
+const SUBSCRIPTION_EVENT: &str = "subscription";
+
+/// Topics the frontend can subscribe to; `status:<config>` is checked separately
+const STATIC_TOPICS: &[&str] = &["configs", "logs"];
+
+/// One pushed update for a topic
+#[derive(Debug, Clone, Serialize)]
+pub struct TopicUpdate {
+    pub topic: String,
+    pub data: Value,
+}
+
+/// Topic subscriptions shared by every window, reference-counted per window
+#[derive(Default)]
+pub struct Subscriptions {
+    /// topic -> window label -> subscription count
+    topics: Mutex<HashMap<String, HashMap<String, usize>>>,
+    /// Last payload pushed per topic, so unchanged state isn't re-sent
+    last_sent: Mutex<HashMap<String, Value>>,
+}
+
+impl Subscriptions {
+    /// Add a window's subscriber to a topic, returning how many the topic now has
+    pub fn subscribe(&self, window: &str, topic: &str) -> usize {
+        let mut topics = self.topics.lock().unwrap();
+        let windows = topics.entry(topic.to_string()).or_default();
+        *windows.entry(window.to_string()).or_insert(0) += 1;
+        windows.values().sum()
+    }
+
+    /// Drop a window's subscriber from a topic, returning how many the topic has left
+    pub fn unsubscribe(&self, window: &str, topic: &str) -> usize {
+        let mut topics = self.topics.lock().unwrap();
+        let Some(windows) = topics.get_mut(topic) else {
+            return 0;
+        };
+        if let Some(count) = windows.get_mut(window) {
+            *count -= 1;
+            if *count == 0 {
+                windows.remove(window);
+            }
+        }
+        let remaining = windows.values().sum();
+        if remaining == 0 {
+            topics.remove(topic);
+            self.last_sent.lock().unwrap().remove(topic);
+        }
+        remaining
+    }
+
+    /// Forget everything a closed window subscribed to
+    pub fn drop_window(&self, window: &str) {
+        let mut topics = self.topics.lock().unwrap();
+        let mut last_sent = self.last_sent.lock().unwrap();
+        topics.retain(|topic, windows| {
+            windows.remove(window);
+            if windows.is_empty() {
+                last_sent.remove(topic);
+            }
+            !windows.is_empty()
+        });
+    }
+
+    pub fn is_active(&self, topic: &str) -> bool {
+        self.topics.lock().unwrap().contains_key(topic)
+    }
+
+    fn active_topics(&self) -> Vec<String> {
+        self.topics.lock().unwrap().keys().cloned().collect()
+    }
+
+    /// Remember a payload, returning false if it's the same as last time
+    fn changed(&self, topic: &str, data: &Value) -> bool {
+        let mut last_sent = self.last_sent.lock().unwrap();
+        if last_sent.get(topic) == Some(data) {
+            return false;
+        }
+        last_sent.insert(topic.to_string(), data.clone());
+        true
+    }
+}
+
+/// Check that a topic is one the backend knows how to publish
+pub fn validate_topic(topic: &str) -> Result<(), String> {
+    if STATIC_TOPICS.contains(&topic) || topic.strip_prefix("status:").is_some_and(|name| !name.is_empty()) {
+        Ok(())
+    } else {
+        Err(format!("Unknown topic '{}'", topic))
+    }
+}
+
+fn emit(app: &AppHandle, topic: &str, data: Value) {
+    let _ = app.emit(
+        SUBSCRIPTION_EVENT,
+        TopicUpdate {
+            topic: topic.to_string(),
+            data,
+        },
+    );
+}
+
+/// Current payload of a polled topic
+fn topic_data(topic: &str) -> Option<Value> {
+    if topic == "configs" {
+        return list_configs().ok().and_then(|c| serde_json::to_value(c).ok());
+    }
+    let name = topic.strip_prefix("status:")?;
+    status::get_tunnel_state(name).ok().and_then(|s| serde_json::to_value(s).ok())
+}
+
+/// Log a backend message, pushing it to `logs` subscribers
+pub fn log(app: &AppHandle, message: impl Into<String>) {
+    let message = message.into();
+    eprintln!("{}", message);
+    if app.state::<Subscriptions>().is_active("logs") {
+        emit(app, "logs", Value::String(message));
+    }
+}
+
+/// Poll only the topics someone is subscribed to and push the ones that changed
+pub fn run_publisher(app: AppHandle) -> Result<(), String> {
+    loop {
+        let subscriptions = app.state::<Subscriptions>();
+        for topic in subscriptions.active_topics() {
+            if let Some(data) = topic_data(&topic) {
+                if subscriptions.changed(&topic, &data) {
+                    emit(&app, &topic, data);
+                }
+            }
+        }
+
+        thread::sleep(PUBLISH_TICK);
+    }
+}
@@ -0,0 +1,144 @@
+use serde::Serialize;
+use std::any::Any;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Instant;
+use tauri::{AppHandle, Emitter, Manager};
+use wiredeck_core::store::now_secs;
+
+use crate::subscriptions::log;
+
+/// Event every subsystem state change is delivered on
+const SUBSYSTEM_EVENT: &str = "subsystem-status";
+
+/// Where a subsystem is in its life
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum SubsystemState {
+    Starting,
+    /// A long-running service is up
+    Running,
+    /// A one-shot job completed
+    Finished,
+    Failed { error: String },
+    Skipped { reason: String },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SubsystemStatus {
+    pub name: String,
+    #[serde(flatten)]
+    pub state: SubsystemState,
+    pub started_at: Option<u64>,
+    /// How long a one-shot job or a failed service ran
+    pub duration_ms: Option<u64>,
+}
+
+/// Whether a subsystem runs until the app quits or does its work once
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lifetime {
+    Service,
+    Once,
+}
+
+/// Health of every subsystem started at launch, in launch order
+#[derive(Default)]
+pub struct Subsystems {
+    statuses: Mutex<Vec<SubsystemStatus>>,
+}
+
+impl Subsystems {
+    pub fn snapshot(&self) -> Vec<SubsystemStatus> {
+        self.statuses.lock().unwrap().clone()
+    }
+
+    fn update(&self, name: &str, f: impl FnOnce(&mut SubsystemStatus)) -> SubsystemStatus {
+        let mut statuses = self.statuses.lock().unwrap();
+        let index = match statuses.iter().position(|s| s.name == name) {
+            Some(index) => index,
+            None => {
+                statuses.push(SubsystemStatus {
+                    name: name.to_string(),
+                    state: SubsystemState::Starting,
+                    started_at: None,
+                    duration_ms: None,
+                });
+                statuses.len() - 1
+            }
+        };
+        f(&mut statuses[index]);
+        statuses[index].clone()
+    }
+}
+
+fn set_state(app: &AppHandle, name: &str, f: impl FnOnce(&mut SubsystemStatus)) {
+    let status = app.state::<Subsystems>().update(name, f);
+    if let SubsystemState::Failed { ref error } = status.state {
+        log(app, format!("Subsystem {} failed: {}", name, error));
+    }
+    let _ = app.emit(SUBSYSTEM_EVENT, &status);
+}
+
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    payload
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "panicked".to_string())
+}
+
+/// Run a subsystem on its own thread so launch doesn't wait for it. An error or panic marks only
+/// this subsystem failed; a service whose body returns counts as failed too, since it should run
+/// until the app quits.
+pub fn launch<F>(app: &AppHandle, name: &str, lifetime: Lifetime, body: F)
+where
+    F: FnOnce(AppHandle) -> Result<(), String> + Send + 'static,
+{
+    set_state(app, name, |s| s.state = SubsystemState::Starting);
+    let app = app.clone();
+    let name = name.to_string();
+    thread::spawn(move || {
+        let started = Instant::now();
+        set_state(&app, &name, |s| {
+            s.started_at = Some(now_secs());
+            if lifetime == Lifetime::Service {
+                s.state = SubsystemState::Running;
+            }
+        });
+
+        let result = panic::catch_unwind(AssertUnwindSafe(|| body(app.clone())))
+            .unwrap_or_else(|payload| Err(panic_message(payload)));
+        let state = match (result, lifetime) {
+            (Ok(()), Lifetime::Once) => SubsystemState::Finished,
+            (Ok(()), Lifetime::Service) => SubsystemState::Failed {
+                error: "stopped unexpectedly".to_string(),
+            },
+            (Err(error), _) => SubsystemState::Failed { error },
+        };
+        set_state(&app, &name, |s| {
+            s.state = state;
+            s.duration_ms = Some(started.elapsed().as_millis() as u64);
+        });
+    });
+}
+
+/// Record a subsystem that is deliberately not started
+pub fn skip(app: &AppHandle, name: &str, reason: &str) {
+    set_state(app, name, |s| {
+        s.state = SubsystemState::Skipped {
+            reason: reason.to_string(),
+        }
+    });
+}
+
+/// Record the outcome of a subsystem that has to start on the setup thread
+pub fn record(app: &AppHandle, name: &str, result: Result<(), String>) {
+    set_state(app, name, |s| {
+        s.started_at = Some(now_secs());
+        s.state = match result {
+            Ok(()) => SubsystemState::Running,
+            Err(error) => SubsystemState::Failed { error },
+        };
+    });
+}
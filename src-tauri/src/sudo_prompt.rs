@@ -0,0 +1,70 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{sync_channel, SyncSender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+use wiredeck_core::elevation::{self, Zeroizing};
+
+/// A prompt nobody answers is treated as cancelled after this long
+const ANSWER_TIMEOUT: Duration = Duration::from_secs(120);
+
+type Answer = Option<Zeroizing<String>>;
+
+static WAITING: Mutex<Option<HashMap<u64, SyncSender<Answer>>>> = Mutex::new(None);
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Sent to the frontend when sudo needs a password; answer with `answer_sudo_prompt`
+#[derive(Debug, Clone, Serialize)]
+struct PasswordRequest {
+    id: u64,
+    reason: String,
+}
+
+fn ask(app: &AppHandle, reason: &str) -> Answer {
+    // The answer arrives through an IPC call the main thread dispatches, so waiting there would hang
+    if thread::current().name() == Some("main") {
+        return None;
+    }
+
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    let (sender, receiver) = sync_channel(1);
+    WAITING.lock().unwrap().get_or_insert_with(HashMap::new).insert(id, sender);
+
+    let request = PasswordRequest {
+        id,
+        reason: reason.to_string(),
+    };
+    let answer = match app.emit("sudo-password-requested", request) {
+        Ok(()) => receiver.recv_timeout(ANSWER_TIMEOUT).ok().flatten(),
+        Err(_) => None,
+    };
+
+    if let Some(waiting) = WAITING.lock().unwrap().as_mut() {
+        waiting.remove(&id);
+    }
+    // Tell the frontend to close the dialog if it's still showing (timeout, or answered elsewhere)
+    let _ = app.emit("sudo-password-closed", id);
+    answer
+}
+
+/// Route sudo password requests from the core to the frontend
+pub fn install(app: &AppHandle) {
+    let app = app.clone();
+    elevation::set_password_prompt(Arc::new(move |reason: &str| ask(&app, reason)));
+}
+
+/// Deliver the user's answer to a waiting prompt; `None` cancels it
+pub fn answer(id: u64, password: Option<String>) -> Result<(), String> {
+    let sender = WAITING
+        .lock()
+        .unwrap()
+        .as_mut()
+        .and_then(|waiting| waiting.remove(&id))
+        .ok_or_else(|| format!("No password prompt #{} is waiting", id))?;
+    let _ = sender.send(password.map(Zeroizing::new));
+    Ok(())
+}
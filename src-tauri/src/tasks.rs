@@ -0,0 +1,168 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager, Window};
+use wiredeck_core::import;
+use wiredeck_core::metrics::{self, PollingPolicy};
+use wiredeck_core::power;
+use wiredeck_core::quarantine;
+use wiredeck_core::reload::ConfigWatcher;
+use wiredeck_core::remote::{self, RefreshOutcome};
+use wiredeck_core::schedule;
+use wiredeck_core::settings;
+use wiredeck_core::tunnels;
+use wiredeck_core::validation;
+use wiredeck_core::wireguard::{is_interface_up, list_configs};
+
+use crate::subscriptions::{log, Subscriptions};
+
+/// How often due remote subscriptions are looked for
+const REMOTE_REFRESH_TICK: Duration = Duration::from_secs(60);
+
+/// How often scheduled peer changes are checked for being due
+const SCHEDULE_TICK: Duration = Duration::from_secs(30);
+
+/// Base tick of the status monitor; each tunnel is sampled once its own interval is due
+const STATUS_MONITOR_TICK: Duration = Duration::from_secs(1);
+
+/// How often config files are checked for outside edits
+const CONFIG_WATCH_TICK: Duration = Duration::from_secs(2);
+
+/// How often the polling policy and power state are re-read
+const POLLING_POLICY_REFRESH: Duration = Duration::from_secs(30);
+
+/// Periodically refresh remote config subscriptions and tell the frontend about changes
+pub fn run_remote_refresh(app: AppHandle) -> Result<(), String> {
+    loop {
+        match remote::refresh_subscriptions(false) {
+            Ok(outcomes) => {
+                for outcome in outcomes {
+                    if !matches!(outcome, RefreshOutcome::Unchanged { .. }) {
+                        let _ = app.emit("remote-config-refreshed", &outcome);
+                    }
+                }
+            }
+            Err(e) => log(&app, format!("Remote config refresh failed: {}", e)),
+        }
+
+        thread::sleep(REMOTE_REFRESH_TICK);
+    }
+}
+
+/// Apply peer deletions and disables whose grace period is over
+pub fn run_scheduled_changes(app: AppHandle) -> Result<(), String> {
+    loop {
+        match schedule::run_due_changes() {
+            Ok(applied) => {
+                for change in applied {
+                    if let Some(ref error) = change.error {
+                        log(&app, format!("Scheduled change for {} failed: {}", change.change.config_name, error));
+                    }
+                    let _ = app.emit("peer-change-applied", &change);
+                }
+            }
+            Err(e) => log(&app, format!("Scheduled peer changes failed: {}", e)),
+        }
+
+        thread::sleep(SCHEDULE_TICK);
+    }
+}
+
+/// Validate every config once at launch so broken files are flagged in the tunnel list right away.
+/// Files that don't parse are quarantined rather than failing only when someone opens them.
+pub fn run_config_validation(app: AppHandle) -> Result<(), String> {
+    let reports = validation::validate_all_configs().map_err(|e| e.to_string())?;
+    let _ = app.emit("config-validation", &reports);
+    let quarantined = quarantine::sync_quarantine(&reports)
+        .map_err(|e| format!("Updating the config quarantine failed: {}", e))?;
+    if !quarantined.is_empty() {
+        let _ = app.emit("configs-quarantined", &quarantined);
+    }
+    Ok(())
+}
+
+/// Tell the frontend which config files changed on disk, so open editors can call
+/// `reload_config_if_unchanged` instead of overwriting outside edits on save
+pub fn run_config_watcher(app: AppHandle) -> Result<(), String> {
+    let mut watcher = ConfigWatcher::new();
+    loop {
+        thread::sleep(CONFIG_WATCH_TICK);
+        let changed = watcher.poll();
+        if !changed.is_empty() {
+            let _ = app.emit("config-files-changed", &changed);
+        }
+    }
+}
+
+/// Bring up auto-connect tunnels in the background so launch isn't blocked on wg-quick
+pub fn run_auto_connect(app: AppHandle) -> Result<(), String> {
+    match tunnels::auto_connect() {
+        Ok(activations) => {
+            let _ = app.emit("auto-connect-finished", &activations);
+            Ok(())
+        }
+        Err(e) => {
+            let _ = app.emit("auto-connect-failed", e.to_string());
+            Err(e.to_string())
+        }
+    }
+}
+
+/// Sample peers of running tunnels, recording timeline events and forwarding them to the frontend.
+/// Watched tunnels are sampled often; the rest back off, and everything slows down while throttled.
+pub fn run_status_monitor(app: AppHandle) -> Result<(), String> {
+    let mut last_sampled: HashMap<String, Instant> = HashMap::new();
+    let mut policy = PollingPolicy::default();
+    let mut power_state = power::get_power_state();
+    let mut policy_read: Option<Instant> = None;
+
+    loop {
+        if policy_read.is_none_or(|t| t.elapsed() >= POLLING_POLICY_REFRESH) {
+            policy = settings::load_settings().map(|s| s.status_polling).unwrap_or_default();
+            let current = power::get_power_state();
+            if current != power_state {
+                power_state = current;
+                let _ = app.emit("power-state-changed", power_state);
+            }
+            policy_read = Some(Instant::now());
+        }
+
+        let subscriptions = app.state::<Subscriptions>();
+        for config_name in list_configs().unwrap_or_default() {
+            let watched = subscriptions.is_active(&format!("status:{}", config_name));
+            let interval = policy.interval(watched, &power_state);
+            if last_sampled.get(&config_name).is_some_and(|t| t.elapsed() < interval) {
+                continue;
+            }
+            last_sampled.insert(config_name.clone(), Instant::now());
+
+            if !is_interface_up(&config_name).unwrap_or(false) {
+                continue;
+            }
+            match metrics::observe_peers(&config_name) {
+                Ok(notices) if !notices.is_empty() => {
+                    let _ = app.emit("peer-events", &notices);
+                }
+                Ok(_) => {}
+                Err(e) => log(&app, format!("Status monitor failed for {}: {}", config_name, e)),
+            }
+        }
+
+        thread::sleep(STATUS_MONITOR_TICK);
+    }
+}
+
+/// Import files dropped onto a window and report the results back to that window
+pub fn import_dropped_files(window: Window, paths: Vec<PathBuf>) {
+    thread::spawn(move || {
+        let results: Vec<_> = paths
+            .iter()
+            .filter(|p| import::is_importable(p))
+            .flat_map(|p| import::import_path(p))
+            .collect();
+        if !results.is_empty() {
+            let _ = window.emit("file-import-results", &results);
+        }
+    });
+}
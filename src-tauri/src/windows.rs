@@ -0,0 +1,45 @@
+use tauri::{AppHandle, Manager, WebviewUrl, WebviewWindowBuilder};
+use wiredeck_core::wireguard::{parse_config, validate_config_name};
+
+/// Window labels only allow `[A-Za-z0-9_-]`; config names may also contain `=+.`
+fn tunnel_window_label(config_name: &str) -> String {
+    let mut label = String::from("tunnel-");
+    for c in config_name.chars() {
+        if c.is_ascii_alphanumeric() || c == '_' {
+            label.push(c);
+        } else {
+            label.push_str(&format!("-{:02x}", c as u32));
+        }
+    }
+    label
+}
+
+/// Open (or focus) a detached dashboard window for one tunnel, returning its label
+pub fn open_tunnel_window(app: &AppHandle, config_name: &str) -> Result<String, String> {
+    validate_config_name(config_name).map_err(|e| e.to_string())?;
+    parse_config(config_name).map_err(|e| e.to_string())?;
+
+    let label = tunnel_window_label(config_name);
+    if let Some(window) = app.get_webview_window(&label) {
+        window.set_focus().map_err(|e| e.to_string())?;
+        return Ok(label);
+    }
+
+    // The frontend renders a single-tunnel dashboard when `tunnel` is in the query string
+    let query: String = config_name
+        .chars()
+        .map(|c| match c {
+            '+' => "%2B".to_string(),
+            '=' => "%3D".to_string(),
+            c => c.to_string(),
+        })
+        .collect();
+
+    WebviewWindowBuilder::new(app, &label, WebviewUrl::App(format!("index.html?tunnel={}", query).into()))
+        .title(format!("WireDeck — {}", config_name))
+        .inner_size(520.0, 640.0)
+        .build()
+        .map_err(|e| e.to_string())?;
+
+    Ok(label)
+}
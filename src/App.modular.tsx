@@ -1,7 +1,7 @@
 import { useState, useEffect } from "react";
 import { invoke } from "@tauri-apps/api/core";
 import { WgConfig, Peer, PeerStatus, Theme } from "./types";
-import { isPeerActive, getPeerStatus } from "./utils/helpers";
+import { isPeerActive, getPeerStatus, errorMessage } from "./utils/helpers";
 import { Sidebar } from "./components/Sidebar/Sidebar";
 import { ThemeToggle } from "./components/ThemeToggle";
 import { ErrorBanner } from "./components/ErrorBanner";
@@ -23,6 +23,7 @@ function App() {
   const [showAddPeer, setShowAddPeer] = useState<boolean>(false);
   const [selectedPeer, setSelectedPeer] = useState<string | null>(null);
   const [theme, setTheme] = useState<Theme>('system');
+  const [configDir, setConfigDir] = useState<string>("");
 
   // Load configs on mount
   useEffect(() => {
@@ -69,6 +70,9 @@ function App() {
       setLoading(true);
       const configList = await invoke<string[]>("list_wireguard_configs");
       setConfigs(configList);
+      if (configList.length === 0) {
+        setConfigDir(await invoke<string>("get_wireguard_directory"));
+      }
       if (configList.length > 0 && !activeConfig) {
         setActiveConfig(configList[0]);
       }
@@ -94,7 +98,7 @@ function App() {
 
       setError("");
     } catch (e) {
-      setError(`Failed to load config: ${e}`);
+      setError(`Failed to load config: ${errorMessage(e)}`);
     }
   }
 
@@ -151,7 +155,7 @@ function App() {
       }
       setError("");
     } catch (e) {
-      setError(`Failed to delete peer: ${e}`);
+      setError(`Failed to delete peer: ${errorMessage(e)}`);
     }
   }
 
@@ -178,7 +182,7 @@ function App() {
       setEditingPeer(null);
       setError("");
     } catch (e) {
-      setError(`Failed to save peer: ${e}`);
+      setError(`Failed to save peer: ${errorMessage(e)}`);
     }
   }
 
@@ -202,7 +206,7 @@ function App() {
         <div className="empty">
           <h2>No WireGuard configurations found</h2>
           <p>
-            Please create a WireGuard configuration file in <code>/opt/homebrew/etc/wireguard/</code> or <code>/usr/local/etc/wireguard/</code>
+            Please create a WireGuard configuration file in <code>{configDir || "the WireGuard config directory"}/</code>
           </p>
         </div>
       </div>
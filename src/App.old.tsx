@@ -1,6 +1,7 @@
 import { useState, useEffect } from "react";
 import { invoke } from "@tauri-apps/api/core";
 import "./App.css";
+import { errorMessage } from "./utils/helpers";
 
 interface WgInterface {
   private_key: string;
@@ -211,7 +212,7 @@ function App() {
       setShowAddPeer(false);
       setError("");
     } catch (e) {
-      setError(`Failed to save peer: ${e}`);
+      setError(`Failed to save peer: ${errorMessage(e)}`);
     } finally {
       setLoading(false);
     }
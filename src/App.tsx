@@ -1,8 +1,8 @@
 import { useState, useEffect } from "react";
 import { invoke } from "@tauri-apps/api/core";
 import { Settings, Play, Square, Plus, Trash2, Edit, Circle } from "lucide-react";
-import { WgConfig, Peer, PeerStatus, Theme } from "./types";
-import { isPeerActive, getPeerStatus } from "./utils/helpers";
+import { WgConfig, Peer, PeerStatus, Preflight, Theme } from "./types";
+import { isPeerActive, getPeerStatus, errorMessage } from "./utils/helpers";
 import { formatHandshake, formatBytes } from "./utils/formatters";
 import { Button } from "@/components/ui/button";
 import { Dialog, DialogContent, DialogDescription, DialogFooter, DialogHeader, DialogTitle } from "@/components/ui/dialog";
@@ -26,6 +26,8 @@ function App() {
   const [selectedPeer, setSelectedPeer] = useState<string | null>(null);
   const [theme, setTheme] = useState<Theme>('system');
   const [showSettings, setShowSettings] = useState<boolean>(false);
+  const [configDir, setConfigDir] = useState<string>("");
+  const [statusSummary, setStatusSummary] = useState<string>("");
 
   // Load configs on mount
   useEffect(() => {
@@ -72,6 +74,9 @@ function App() {
       setLoading(true);
       const configList = await invoke<string[]>("list_wireguard_configs");
       setConfigs(configList);
+      if (configList.length === 0) {
+        setConfigDir(await invoke<string>("get_wireguard_directory"));
+      }
       if (configList.length > 0 && !activeConfig) {
         setActiveConfig(configList[0]);
       }
@@ -93,11 +98,13 @@ function App() {
 
       if (status) {
         await loadStatus();
+      } else {
+        setStatusSummary(await invoke<string>("get_status_summary_text", { configName: name }));
       }
 
       setError("");
     } catch (e) {
-      setError(`Failed to load config: ${e}`);
+      setError(`Failed to load config: ${errorMessage(e)}`);
     }
   }
 
@@ -108,6 +115,7 @@ function App() {
         configName: activeConfig
       });
       setPeerStatuses(statuses);
+      setStatusSummary(await invoke<string>("get_status_summary_text", { configName: activeConfig }));
     } catch (e) {
       console.error("Failed to load status:", e);
     }
@@ -116,6 +124,12 @@ function App() {
   async function handleRestart() {
     if (!activeConfig) return;
     try {
+      const checklist = await invoke<Preflight>("preflight", { configName: activeConfig });
+      if (!checklist.ready) {
+        const failed = checklist.checks.filter(c => c.status === 'fail');
+        setError(`Not starting: ${failed.map(c => `${c.label}: ${c.detail ?? 'failed'}`).join('; ')}`);
+        return;
+      }
       await invoke("restart_interface", { name: activeConfig });
       setIsUp(true);
       setError("");
@@ -154,7 +168,7 @@ function App() {
       }
       setError("");
     } catch (e) {
-      setError(`Failed to delete peer: ${e}`);
+      setError(`Failed to delete peer: ${errorMessage(e)}`);
     }
   }
 
@@ -181,7 +195,7 @@ function App() {
       setEditingPeer(null);
       setError("");
     } catch (e) {
-      setError(`Failed to save peer: ${e}`);
+      setError(`Failed to save peer: ${errorMessage(e)}`);
     }
   }
 
@@ -195,6 +209,15 @@ function App() {
     }
   }
 
+  async function handleGeneratePresharedKey() {
+    try {
+      return await invoke<string>("generate_preshared_key");
+    } catch (e) {
+      setError(`Failed to generate preshared key: ${e}`);
+      return null;
+    }
+  }
+
   if (loading && !config) {
     return (
       <div className="flex h-screen items-center justify-center">
@@ -210,8 +233,7 @@ function App() {
           <h2 className="text-2xl font-bold">No WireGuard configurations found</h2>
           <p className="text-muted-foreground">
             Please create a WireGuard configuration file in{" "}
-            <code className="bg-muted px-2 py-1 rounded">/opt/homebrew/etc/wireguard/</code> or{" "}
-            <code className="bg-muted px-2 py-1 rounded">/usr/local/etc/wireguard/</code>
+            <code className="bg-muted px-2 py-1 rounded">{configDir || "the WireGuard config directory"}/</code>
           </p>
         </div>
       </div>
@@ -228,7 +250,12 @@ function App() {
         {/* Interface Header */}
         <div className="p-4 border-b">
           <div className="flex items-center gap-3 mb-3">
-            <div className={`w-2 h-2 rounded-full ${isUp ? 'bg-green-500 animate-pulse' : 'bg-gray-400'}`} />
+            <div
+              role="status"
+              aria-label={statusSummary}
+              title={statusSummary}
+              className={`w-2 h-2 rounded-full ${isUp ? 'bg-green-500 animate-pulse' : 'bg-gray-400'}`}
+            />
             {configs.length > 1 ? (
               <select
                 value={activeConfig}
@@ -262,15 +289,15 @@ function App() {
         <div className="p-4 space-y-2 text-sm border-b">
           <div className="flex justify-between">
             <span className="text-muted-foreground">Address</span>
-            <span className="font-mono">{config?.interface.address}</span>
+            <span className="font-mono">{config?.interface.address.join(', ')}</span>
           </div>
           <div className="flex justify-between">
             <span className="text-muted-foreground">Port</span>
-            <span className="font-mono">{config?.interface.listen_port}</span>
+            <span className="font-mono">{config?.interface.listen_port ?? 'Automatic'}</span>
           </div>
           <div className="flex justify-between">
             <span className="text-muted-foreground">Public Key</span>
-            <code className="text-xs">{config?.interface.private_key.substring(0, 16)}...</code>
+            <code className="text-xs">{config?.interface.has_private_key ? `${config.interface.private_key?.substring(0, 16)}...` : 'None'}</code>
           </div>
         </div>
 
@@ -458,6 +485,7 @@ function App() {
             setEditingPeer(null);
           }}
           onGenerateKeypair={handleGenerateKeypair}
+          onGeneratePresharedKey={handleGeneratePresharedKey}
         />
       )}
 
@@ -519,9 +547,10 @@ interface PeerEditorProps {
   onSave: (peer: Peer) => void;
   onCancel: () => void;
   onGenerateKeypair: () => Promise<{ privateKey: string; publicKey: string } | null>;
+  onGeneratePresharedKey: () => Promise<string | null>;
 }
 
-function PeerEditor({ peer, onSave, onCancel, onGenerateKeypair }: PeerEditorProps) {
+function PeerEditor({ peer, onSave, onCancel, onGenerateKeypair, onGeneratePresharedKey }: PeerEditorProps) {
   const [formData, setFormData] = useState<Peer>(
     peer || {
       public_key: "",
@@ -542,6 +571,13 @@ function PeerEditor({ peer, onSave, onCancel, onGenerateKeypair }: PeerEditorPro
     }
   }
 
+  async function handleGeneratePresharedKey() {
+    const key = await onGeneratePresharedKey();
+    if (key) {
+      setFormData({ ...formData, preshared_key: key });
+    }
+  }
+
   function handleSubmit(e: React.FormEvent) {
     e.preventDefault();
     onSave(formData);
@@ -599,6 +635,22 @@ function PeerEditor({ peer, onSave, onCancel, onGenerateKeypair }: PeerEditorPro
             </div>
           )}
 
+          <div className="space-y-2">
+            <Label htmlFor="preshared_key">Preshared Key (optional)</Label>
+            <div className="flex gap-2">
+              <Input
+                id="preshared_key"
+                value={formData.preshared_key || ""}
+                onChange={(e) => setFormData({ ...formData, preshared_key: e.target.value || undefined })}
+                placeholder="Set the same key on both ends"
+                className="flex-1 font-mono"
+              />
+              <Button type="button" onClick={handleGeneratePresharedKey} variant="outline">
+                Generate
+              </Button>
+            </div>
+          </div>
+
           <div className="space-y-2">
             <Label htmlFor="allowed_ips">Allowed IPs</Label>
             <Input
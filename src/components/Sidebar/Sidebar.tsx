@@ -49,15 +49,15 @@ export function Sidebar({
       <div className={styles.interfaceInfo}>
         <div className={styles.infoItem}>
           <span className={styles.infoLabel}>Address</span>
-          <span className={styles.infoValue}>{config.interface.address}</span>
+          <span className={styles.infoValue}>{config.interface.address.join(', ')}</span>
         </div>
         <div className={styles.infoItem}>
           <span className={styles.infoLabel}>Port</span>
-          <span className={styles.infoValue}>{config.interface.listen_port}</span>
+          <span className={styles.infoValue}>{config.interface.listen_port ?? 'Automatic'}</span>
         </div>
         <div className={styles.infoItem}>
           <span className={styles.infoLabel}>Public Key</span>
-          <code className={styles.infoValue}>{config.interface.private_key.substring(0, 16)}...</code>
+          <code className={styles.infoValue}>{config.interface.has_private_key ? `${config.interface.private_key?.substring(0, 16)}...` : 'None'}</code>
         </div>
       </div>
 
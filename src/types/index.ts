@@ -1,18 +1,37 @@
+export interface Comments {
+  before: string[];
+  keys: Record<string, string[]>;
+  after: string[];
+}
+
 export interface WgInterface {
-  private_key: string;
-  address: string;
-  listen_port: number;
-  dns?: string;
-  post_up?: string;
-  post_down?: string;
+  private_key?: string;
+  has_private_key: boolean;
+  address: string[];
+  listen_port?: number;
+  dns: string[];
+  dns_search: string[];
+  mtu?: number;
+  table?: string;
+  fw_mark?: string;
+  pre_up: string[];
+  post_up: string[];
+  pre_down: string[];
+  post_down: string[];
+  save_config?: boolean;
+  extra?: [string, string][];
+  comments?: Comments;
 }
 
 export interface Peer {
   public_key: string;
+  preshared_key?: string;
   allowed_ips: string;
   persistent_keepalive?: number;
   endpoint?: string;
   name?: string;
+  extra?: [string, string][];
+  comments?: Comments;
 }
 
 export interface WgConfig {
@@ -20,6 +39,8 @@ export interface WgConfig {
   path: string;
   interface: WgInterface;
   peers: Peer[];
+  managed_by?: string;
+  content_hash?: string;
 }
 
 export interface PeerStatus {
@@ -28,6 +49,549 @@ export interface PeerStatus {
   latest_handshake?: string;
   transfer_rx?: string;
   transfer_tx?: string;
+  endpoint_info?: EndpointInfo;
+}
+
+export interface EndpointInfo {
+  ip: string;
+  reverse_dns?: string;
+  asn?: number;
+  network_name?: string;
+  country?: string;
+}
+
+export type ManagerKind = 'brew_services' | 'launchd';
+
+export interface ExternalManager {
+  kind: ManagerKind;
+  name: string;
+  plist?: string;
+  active: boolean;
+}
+
+export interface TakeOver {
+  previous: ExternalManager;
+  interface_up: boolean;
+  brought_up: boolean;
+}
+
+export type TunnelState =
+  | { state: 'down'; external?: ExternalManager }
+  | { state: 'up_no_peers'; interface_name: string; external?: ExternalManager }
+  | { state: 'up_healthy'; interface_name: string; peers: PeerStatus[]; external?: ExternalManager }
+  | { state: 'up_degraded'; interface_name: string; peers: PeerStatus[]; reasons: string[]; external?: ExternalManager };
+
+export type Glance = 'healthy' | 'degraded' | 'down';
+
+export type AggregateState = 'all_up' | 'partial' | 'down';
+
+export interface TunnelGlance {
+  config_name: string;
+  glance: Glance;
+  summary: string;
+}
+
+export interface StatusOverview {
+  state: AggregateState;
+  tunnels: TunnelGlance[];
+  rx_bytes: number;
+  tx_bytes: number;
+}
+
+export type SubsystemState =
+  | { state: 'starting' }
+  | { state: 'running' }
+  | { state: 'finished' }
+  | { state: 'failed'; error: string }
+  | { state: 'skipped'; reason: string };
+
+export type SubsystemStatus = SubsystemState & {
+  name: string;
+  started_at?: number;
+  duration_ms?: number;
+};
+
+export interface TopicUpdate<T = unknown> {
+  topic: string;
+  data: T;
+}
+
+export type Guarded<T> =
+  | { status: 'done'; result: T }
+  | { status: 'requires_confirmation'; reason: string; token: string };
+
+export interface CommandTrace {
+  argv: string[];
+  exit_code?: number;
+  duration_ms: number;
+}
+
+export interface Traced<T> {
+  result: T;
+  trace?: CommandTrace[];
+}
+
+export type PeerEvent = { at: number } & (
+  | { kind: 'first_seen'; endpoint?: string }
+  | { kind: 'endpoint_changed'; from: string; to: string }
+  | { kind: 'went_offline'; last_handshake?: number }
+  | { kind: 'keepalive_restored'; offline_secs?: number }
+);
+
+export interface EndpointSpan {
+  endpoint: string;
+  first_seen: number;
+  last_seen: number;
+}
+
+export interface StatsBaseline {
+  marked_at: number;
+  rx_counter: number;
+  tx_counter: number;
+  carried_rx: number;
+  carried_tx: number;
+}
+
+export interface StatsUsage {
+  since: number;
+  rx_bytes: number;
+  tx_bytes: number;
+}
+
+export interface PowerState {
+  source: 'ac' | 'battery' | 'unknown';
+  low_power_mode: boolean;
+  battery_percent?: number;
+}
+
+export type TokenScope = 'read_only' | 'peer_management' | 'full';
+
+export interface ApiToken {
+  id: string;
+  name: string;
+  scope: TokenScope;
+  created_at: number;
+  last_used?: number;
+}
+
+export interface IssuedToken {
+  token: ApiToken;
+  secret: string;
+}
+
+export interface RosterImport {
+  config?: WgConfig;
+  added: string[];
+  updated: string[];
+  unchanged: string[];
+  skipped: [string, string][];
+  allocated: [string, string][];
+}
+
+export interface DisabledPeer {
+  config_name: string;
+  peer: Peer;
+  disabled_at: number;
 }
 
+export interface KickedPeer {
+  public_key: string;
+  endpoint?: string;
+}
+
+export type PeerChangeKind = 'delete' | 'disable';
+
+export interface ScheduledPeerChange {
+  id: string;
+  config_id: string;
+  config_name: string;
+  public_key: string;
+  peer_name?: string;
+  kind: PeerChangeKind;
+  scheduled_at: number;
+  effective_at: number;
+  live: boolean;
+}
+
+export interface TempPeer {
+  config_name: string;
+  public_key: string;
+  label: string;
+  address: string;
+  client_config: string;
+  qr_svg?: string;
+  live: boolean;
+  expires_at: number;
+  schedule_id: string;
+}
+
+export interface AppliedPeerChange {
+  change: ScheduledPeerChange;
+  error?: string;
+}
+
+export interface BackupPolicy {
+  destination?: string;
+  retention: number;
+}
+
+export interface ConfigBackup {
+  path: string;
+  created_at: number;
+  sequence: number;
+}
+
+export interface RevokedKey {
+  public_key: string;
+  reason?: string;
+}
+
+export interface Cidr {
+  addr: string;
+  prefix: number;
+}
+
+export interface AddressPlan {
+  ipv4: Cidr;
+  ipv6: Cidr;
+  ula_prefix: Cidr;
+  interface_address: string[];
+}
+
+export type AddressOwner = { kind: 'interface' } | { kind: 'peer'; public_key: string; name?: string };
+
+export interface AddressAssignment {
+  config: string;
+  address: Cidr;
+  owner: AddressOwner;
+}
+
+export interface SubnetUsage {
+  subnet: Cidr;
+  configs: string[];
+  assignments: AddressAssignment[];
+}
+
+export interface AddressConflict {
+  address: Cidr;
+  message: string;
+  assignments: AddressAssignment[];
+}
+
+export interface AddressMatch {
+  config: string;
+  owner: AddressOwner;
+  matched: Cidr;
+}
+
+export interface AddressMap {
+  subnets: SubnetUsage[];
+  conflicts: AddressConflict[];
+}
+
+export interface ComposeExport {
+  compose: string;
+  config_path: string;
+  config: string;
+}
+
+export interface SelfServiceUser {
+  id: string;
+  name: string;
+  public_key: string;
+  approved_at: number;
+}
+
+export interface ApprovedUser {
+  user: SelfServiceUser;
+  access_code: string;
+}
+
+export interface SelfServicePortal {
+  config_name: string;
+  output_dir: string;
+  endpoint: string;
+  allowed_ips: string;
+  title: string;
+  users: SelfServiceUser[];
+  last_generated?: number;
+}
+
+export type CloudProvider = 'hetzner' | 'digital_ocean';
+
+export interface DeployRequest {
+  provider: CloudProvider;
+  api_token: string;
+  config_name: string;
+  host_name?: string;
+  region?: string;
+  size?: string;
+}
+
+export interface DeployedHost {
+  id: string;
+  provider: CloudProvider;
+  server_id: number;
+  host_name: string;
+  config_name: string;
+  region: string;
+  ipv4?: string;
+  created_at: number;
+}
+
+export interface SandboxStep {
+  name: string;
+  ok: boolean;
+  detail?: string;
+}
+
+export interface SandboxReport {
+  passed: boolean;
+  steps: SandboxStep[];
+  duration_ms: number;
+}
+
+export interface Finding {
+  severity: 'info' | 'warning' | 'error';
+  message: string;
+  fix?: string;
+  field?: string;
+}
+
+export interface ConfigReport {
+  name: string;
+  valid: boolean;
+  error?: string;
+  syntax_error?: ConfigSyntaxError;
+  findings: Finding[];
+}
+
+export interface ErrorDetail {
+  kind: string;
+  field?: string;
+  peer?: string;
+  line?: number;
+  message: string;
+}
+
+export interface ConfigSyntaxError {
+  line?: number;
+  text?: string;
+  expected: string;
+}
+
+export interface QuarantinedConfig {
+  name: string;
+  path: string;
+  error: string;
+  detected_at: number;
+  backups: string[];
+  actions: ('open_in_raw_editor' | 'restore_from_backup')[];
+}
+
+export interface ReloadConflict {
+  disk: WgConfig;
+  interface_changes: string[];
+  peers: PeerDiff[];
+}
+
+export type ReloadOutcome =
+  | { status: 'unchanged' }
+  | { status: 'reloaded'; config: WgConfig }
+  | { status: 'conflict'; conflict: ReloadConflict };
+
+export interface OperationSnapshot {
+  id: string;
+  operation: string;
+  config_name: string;
+  taken_at: number;
+  rolled_back_at: number | null;
+}
+
+export type Platform = 'phone' | 'computer' | 'server' | 'router';
+
+export type GraphFormat = 'dot' | 'svg';
+
+export interface PeerPlatform {
+  public_key: string;
+  platform?: Platform;
+  os?: string;
+  source?: 'recorded' | 'detected';
+  reasons: string[];
+}
+
+export interface ConfigIdentity {
+  id: string;
+  name: string;
+  created_at: number;
+  content_sha256?: string;
+}
+
+export type AttachmentKind = 'url' | 'inventory_id';
+
+export interface Attachment {
+  kind: AttachmentKind;
+  value: string;
+  label?: string;
+  added_at: number;
+}
+
+export interface AttachmentMatch {
+  config_name: string;
+  public_key: string;
+  attachment: Attachment;
+}
+
+export interface HandshakeAttempt {
+  public_key: string;
+  method: string;
+  handshake: boolean;
+  previous_handshake?: number;
+  latest_handshake?: number;
+  waited_ms: number;
+}
+
+export interface ProbeStats {
+  target: string;
+  sent: number;
+  answered: number;
+  loss_percent: number;
+  rtt_min_ms?: number;
+  rtt_avg_ms?: number;
+  rtt_max_ms?: number;
+  jitter_ms?: number;
+}
+
+export type PathVerdict = 'healthy' | 'lossy_path' | 'configuration_problem' | 'unreachable' | 'inconclusive';
+
+export interface EndpointQuality {
+  public_key: string;
+  endpoint: string;
+  handshake_recent: boolean;
+  path: ProbeStats;
+  tunnel?: ProbeStats;
+  verdict: PathVerdict;
+  detail: string;
+}
+
+export interface PeerDiff {
+  public_key: string;
+  name?: string;
+  kind: 'added' | 'removed' | 'changed';
+  details: string[];
+}
+
+export interface DroppedConnection {
+  public_key: string;
+  name?: string;
+  reason: string;
+}
+
+export interface Simulation {
+  config_name: string;
+  interface_up: boolean;
+  requires_restart: boolean;
+  interface_changes: string[];
+  peers: PeerDiff[];
+  routes_added: string[];
+  routes_removed: string[];
+  dropped: DroppedConnection[];
+}
+
+export interface EmbeddedService {
+  name: string;
+  address: string;
+  port: number;
+  started_at: number;
+  uptime_secs: number;
+}
+
+export interface FailureSummary {
+  command: string;
+  kind: string;
+  message: string;
+  count: number;
+  first_at: number;
+  last_at: number;
+  environment: {
+    os: string;
+    path: string;
+    wg_found: boolean;
+    wg_quick_found: boolean;
+    sudo_noninteractive: boolean;
+    firewall?: 'macos_alf' | 'ufw' | 'firewalld';
+  };
+  hint?: string;
+}
+
+export type Language = 'en' | 'de' | 'fr' | 'es';
+
 export type Theme = 'light' | 'dark' | 'system';
+
+export interface ConfigChanged {
+  op: 'created' | 'updated';
+  config_name: string;
+  at: number;
+  affected_keys: string[];
+  summary: string[];
+}
+
+export interface SplitDnsRule {
+  domain: string;
+  servers: string[];
+}
+
+export type SplitDnsBackend = 'scutil' | 'systemd_resolved' | 'dnsmasq';
+
+export interface SplitDnsHooks {
+  post_up: string[];
+  post_down: string[];
+}
+
+export interface SplitDns {
+  rules: SplitDnsRule[];
+  backend: SplitDnsBackend;
+  hooks: SplitDnsHooks;
+}
+
+export interface AppTarget {
+  path: string;
+  bundle_id?: string;
+  executable: string;
+}
+
+export interface PfAppRules {
+  config_name: string;
+  anchor: string;
+  tag: string;
+  apps: AppTarget[];
+  rules: string[];
+  install: string[];
+  uninstall: string[];
+}
+
+export interface Invocation {
+  args: string[];
+  cwd?: string;
+}
+
+export interface PreflightCheck {
+  id: 'not_running' | 'keys' | 'listen_port' | 'subnets' | 'endpoints' | 'privileges';
+  label: string;
+  status: 'pass' | 'warn' | 'fail' | 'skipped';
+  detail?: string;
+  fix?: string;
+}
+
+export interface Preflight {
+  config_name: string;
+  ready: boolean;
+  checks: PreflightCheck[];
+}
+
+export interface AnonymizedConfig {
+  content: string;
+  keys: number;
+  addresses: number;
+  hostnames: number;
+}
@@ -1,4 +1,4 @@
-import { PeerStatus } from '../types';
+import { ErrorDetail, PeerStatus } from '../types';
 
 export function getPeerStatus(publicKey: string, peerStatuses: PeerStatus[]): PeerStatus | undefined {
   return peerStatuses.find(s => s.public_key === publicKey);
@@ -8,3 +8,10 @@ export function isPeerActive(publicKey: string, peerStatuses: PeerStatus[]): boo
   const status = getPeerStatus(publicKey, peerStatuses);
   return status?.latest_handshake !== undefined && status.latest_handshake !== "0";
 }
+
+export function errorMessage(error: unknown): string {
+  if (typeof error === 'object' && error !== null && 'message' in error) {
+    return (error as ErrorDetail).message;
+  }
+  return String(error);
+}